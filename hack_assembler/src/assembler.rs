@@ -1,7 +1,6 @@
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs;
 use std::io;
-use std::io::{BufRead, BufReader, Read, Seek};
 use std::path::PathBuf;
 
 use lazy_static::lazy_static;
@@ -25,10 +24,13 @@ struct Code<'b> {
 impl<'b> Code<'b> {
     fn new() -> Self {
         let mut dest = HashMap::new();
-        let dest_instruction_set = ["null", "M", "D", "DM", "A", "AM", "AD", "ADM"];
+        let dest_instruction_set = ["null", "M", "D", "MD", "A", "AM", "AD", "AMD"];
         for (index, instruction) in dest_instruction_set.iter().enumerate() {
             dest.insert(*instruction, format!("{index:03b}"));
         }
+        // Older programs spell the combined destinations DM and ADM.
+        dest.insert("DM", String::from("011"));
+        dest.insert("ADM", String::from("111"));
 
         let mut jump = HashMap::new();
         let jump_instruction_set = ["null", "JGT", "JEQ", "JGE", "JLT", "JNE", "JLE", "JMP"];
@@ -65,6 +67,13 @@ impl<'b> Code<'b> {
         comp.insert("D&M", "1000000");
         comp.insert("D|A", "0010101");
         comp.insert("D|M", "1010101");
+        // The course assembler also accepts the operands of +, & and | swapped.
+        comp.insert("A+D", "0000010");
+        comp.insert("M+D", "1000010");
+        comp.insert("A&D", "0000000");
+        comp.insert("M&D", "1000000");
+        comp.insert("A|D", "0010101");
+        comp.insert("M|D", "1010101");
 
         Code { dest, jump, comp }
     }
@@ -78,20 +87,26 @@ impl<'b> Code<'b> {
 /// C_INSTRUCTION for instructions of this format dest=comp;jump.
 pub struct Parser<'a> {
     symbol_table: &'a mut HashMap<String, u16>,
+    // The labels declared by the program, a subset of the symbol table.
+    labels: HashMap<String, u16>,
     // Variable address starts from 16 and it is incremented
     // by 1 whenever another variable is encountered.
     variable_address: u16,
     instruction_line: u16,
     c_instruction_set: Code<'a>,
+    // The binary code generated by the second pass.
+    binary: Vec<u16>,
 }
 
 impl<'a> Parser<'a> {
     fn new(table: &'a mut HashMap<String, u16>) -> Self {
         Parser {
             symbol_table: table,
+            labels: HashMap::new(),
             variable_address: 16,
             instruction_line: 0,
             c_instruction_set: Code::new(),
+            binary: Vec::new(),
         }
     }
 
@@ -104,11 +119,11 @@ impl<'a> Parser<'a> {
     /// an A_INSTRUCTION or C_INSTRUCTION is found, but does not change when whitespace,
     /// comments or label declaration is encountered.
     /// It adds a new entry to the symbol table for label declaration (L_INSTRUCTION),
-    /// associating the symbol with the current line number + 1 (this will be the ROM address
+    /// associating the symbol with the current line number (this will be the ROM address
     /// of the next instruction in the program). No binary code is generated.
     fn parse_labels(&mut self, raw_content: &str) {
-        let mut content = String::from(raw_content.replace(" ", ""));
-        if content == "" || content.starts_with("//") {
+        let mut content = strip_whitespace(raw_content);
+        if content.is_empty() || content.starts_with("//") {
             return;
         }
 
@@ -119,25 +134,25 @@ impl<'a> Parser<'a> {
         };
 
         // Handle L_INSTRUCTION
-        if content.starts_with("(") && content.ends_with(")") {
+        if content.starts_with('(') && content.ends_with(')') {
             let label = &content[1..content.len() - 1];
-            println!("{} L_INSTRUCTION: {label}", self.instruction_line + 1);
             self.symbol_table
-                .insert(label.to_string(), self.instruction_line + 1);
-        } else {
+                .insert(label.to_string(), self.instruction_line);
+            self.labels.insert(label.to_string(), self.instruction_line);
+        } else if !content.is_empty() {
             // Assumes the remaining instructions are C  and A INSTRUCTIONS.
-            self.instruction_line = self.instruction_line + 1;
+            self.instruction_line += 1;
         }
     }
 
     /// parse_instructions reads the entire assembly code again, it handles the
     /// A and C INSTRUCTIONS and generates the binary code that will be sent
     /// to the computer processor.
-    fn parse_instructions(&mut self, raw_content: &str) {
-        let mut content = String::from(raw_content.replace(" ", ""));
+    fn parse_instructions(&mut self, raw_content: &str) -> Result<(), String> {
+        let mut content = strip_whitespace(raw_content);
         // Ignore whitespace, comment and labels (L_INSTRUCTIONS)
-        if content == "" || content.starts_with("//") || content.starts_with("(") {
-            return;
+        if content.is_empty() || content.starts_with("//") || content.starts_with('(') {
+            return Ok(());
         }
 
         // Remove in-line comments "//"
@@ -146,121 +161,104 @@ impl<'a> Parser<'a> {
             None => content.as_str(),
         };
         content = refined_content.to_string();
+        if content.is_empty() {
+            return Ok(());
+        }
 
         // Assumes only A and C INSTRUCTIONS are left after the
         // the ignored contents above i.e. comments, whitespace and labels.
-        if content.starts_with("@") {
-            self.decode_a_instructions(content);
+        let code = if content.starts_with('@') {
+            self.decode_a_instructions(content)?
         } else {
             // Possibly C-INSTRUCTION or invalid content.
-            self.decode_c_instruction(content);
-        }
+            self.decode_c_instruction(content)?
+        };
+        self.binary.push(code);
 
-        self.instruction_line = self.instruction_line + 1;
+        self.instruction_line += 1;
+        Ok(())
     }
 
-    fn decode_a_instructions(&mut self, content: String) {
+    fn decode_a_instructions(&mut self, content: String) -> Result<u16, String> {
         // Handle A-instructions
         let a_instruction = &content[1..];
         if NUM_RE.is_match(a_instruction) {
-            let a_instruction_int: u16 = a_instruction.parse().unwrap();
-            println!(
-                "{} A-INSTRUCTION: {a_instruction} = {a_instruction_int:016b}",
-                self.instruction_line
-            );
-        } else {
-            match self.symbol_table.get(a_instruction) {
-                Some(value) => {
-                    println!("variable {a_instruction} already initialized");
-                    println!(
-                        "{} A-INSTRUCTION: {value} = {value:016b}",
-                        self.instruction_line
-                    );
-                }
-                None => {
-                    //TODO: You need to check if the new variable location is not SCREEN or KBD
-                    // Initialize the new variable and increase the variable address.
-                    self.symbol_table
-                        .insert(a_instruction.to_string(), self.variable_address);
-                    println!(
-                        "{} A-INSTRUCTION (symbol: new variable): {a_instruction}: {}",
-                        self.instruction_line, self.variable_address
-                    );
-                    let var = self.variable_address;
-                    println!(
-                        "{} A-INSTRUCTION: {var} = {var:016b}",
-                        self.instruction_line
-                    );
-
-                    self.variable_address += 1;
-                }
+            // The value takes the 15 lower bits, the first bit marks the A-instruction.
+            return a_instruction
+                .parse::<u16>()
+                .ok()
+                .filter(|value| *value <= 32767)
+                .ok_or_else(|| {
+                    format!("error: invalid address {a_instruction}, expected a number between 0 - 32767")
+                });
+        }
+
+        match self.symbol_table.get(a_instruction) {
+            Some(value) => Ok(*value),
+            None => {
+                //TODO: You need to check if the new variable location is not SCREEN or KBD
+                // Initialize the new variable and increase the variable address.
+                let var = self.variable_address;
+                self.symbol_table.insert(a_instruction.to_string(), var);
+                self.variable_address += 1;
+                Ok(var)
             }
         }
     }
 
-    fn decode_c_instruction(&self, instruction: String) {
+    fn decode_c_instruction(&self, instruction: String) -> Result<u16, String> {
         let mut dest_instruction = "000";
         let mut content = instruction;
-        if content.contains("=") && RE.is_match(content.as_str()) {
+        if content.contains('=') && RE.is_match(content.as_str()) {
             // Cut the dest part of content.
-            match content.split_once("=") {
-                Some((dest, remaining_substr)) => {
-                    dest_instruction = match self.c_instruction_set.dest.get(dest) {
-                        Some(code) => code,
-                        None => {
-                            println!("error: invalid dest {dest} instruction provided!");
-                            return;
-                        }
-                    };
-                    content = remaining_substr.to_string();
-                }
-                None => {}
-            };
+            if let Some((dest, remaining_substr)) = content.split_once('=') {
+                dest_instruction = match self.c_instruction_set.dest.get(dest) {
+                    Some(code) => code,
+                    None => {
+                        return Err(format!("error: invalid dest {dest} instruction provided!"))
+                    }
+                };
+                content = remaining_substr.to_string();
+            }
         }
 
-        let mut comp_instruction = "NOT_SET";
+        let comp_instruction;
         let mut jump_instruction = "000"; // null value, when omitted.
-        if content.contains(";") {
-            match content.split_once(";") {
-                Some((comp, jump)) => {
-                    comp_instruction = match self.c_instruction_set.comp.get(comp) {
-                        Some(code) => code,
-                        None => {
-                            println!("error: invalid comp {comp} instruction provided!");
-                            return;
-                        }
-                    };
-
-                    jump_instruction = match self.c_instruction_set.jump.get(jump) {
-                        Some(code) => code,
-                        None => {
-                            println!("error: invalid jump {jump} instruction provided!");
-                            return;
-                        }
-                    };
-                }
-                None => {}
+        if let Some((comp, jump)) = content.split_once(';') {
+            comp_instruction = match self.c_instruction_set.comp.get(comp) {
+                Some(code) => code,
+                None => return Err(format!("error: invalid comp {comp} instruction provided!")),
+            };
+
+            jump_instruction = match self.c_instruction_set.jump.get(jump) {
+                Some(code) => code,
+                None => return Err(format!("error: invalid jump {jump} instruction provided!")),
             };
         } else {
             // Assumes content will be comp if none of the dest and jump conditions match.
             comp_instruction = match self.c_instruction_set.comp.get(content.as_str()) {
-                Some(code) => *code,
+                Some(code) => code,
                 None => {
-                    println!("error: invalid comp {content} instruction provided!");
-                    return;
+                    return Err(format!(
+                        "error: invalid comp {content} instruction provided!"
+                    ))
                 }
             };
         }
 
         // Create the final format for C_INSTRUCTIONS:
         // 111 + comp_instruction + dest_instruction + jump_instruction
-        println!(
-            "{} C_INSTRUCTION  (111{comp_instruction}{dest_instruction}{jump_instruction})",
-            self.instruction_line
-        );
+        let code = format!("111{comp_instruction}{dest_instruction}{jump_instruction}");
+        Ok(u16::from_str_radix(&code, 2).expect("C_INSTRUCTION fields are binary"))
     }
 }
 
+/// strip_whitespace removes all the spaces and tabs of a line, e.g. the
+/// indentation of generated code.
+fn strip_whitespace(raw_content: &str) -> String {
+    raw_content.split_whitespace().collect()
+}
+
 /// Pre-created labels in the symbol table.
 const SP: (&str, u8) = ("SP", 0);
 const LCL: (&str, u8) = ("LCL", 1);
@@ -286,6 +284,10 @@ enum PreCreatedLabel {
 pub struct Assembler {
     // HashMap<symbol, address>
     symbol_table: HashMap<String, u16>,
+    // HashMap<label, ROM address>, filled by assemble().
+    labels: HashMap<String, u16>,
+    // Source line number of the instruction at each ROM address.
+    source_lines: Vec<usize>,
     /// The path to the .asm file to read.
     pub(crate) path: PathBuf,
 }
@@ -295,6 +297,8 @@ impl Assembler {
     pub fn new(file_path: PathBuf) -> Self {
         Assembler {
             symbol_table: HashMap::new(),
+            labels: HashMap::new(),
+            source_lines: Vec::new(),
             path: file_path,
         }
     }
@@ -307,7 +311,7 @@ impl Assembler {
             self.symbol_table.insert(format!("R{address}"), address);
         }
 
-        let special_labels = vec![
+        let special_labels = [
             PreCreatedLabel::Special(SP),
             PreCreatedLabel::Special(LCL),
             PreCreatedLabel::Special(ARG),
@@ -330,37 +334,55 @@ impl Assembler {
         }
     }
 
-    pub fn read_file(&mut self) -> io::Result<()> {
-        let f = File::open(&self.path)?;
-        let mut reader = BufReader::new(f);
+    /// read_file assembles the .asm file at the path of the Assembler,
+    /// it returns the binary code, one 16-bit word per instruction.
+    pub fn read_file(&mut self) -> io::Result<Vec<u16>> {
+        let content = fs::read_to_string(&self.path)?;
+        self.assemble(&content)
+    }
+
+    /// assemble translates a whole assembly program to binary code. Errors
+    /// are reported with the line number of the invalid instruction.
+    pub fn assemble(&mut self, content: &str) -> io::Result<Vec<u16>> {
         let mut parser = Parser::new(&mut self.symbol_table);
 
         // First pass.
-        for line in reader.by_ref().lines() {
-            match line {
-                Ok(content) => {
-                    parser.parse_labels(content.as_str());
-                }
-                Err(error) => println!("error reading file content: {error}"),
-            }
-        }
-
-        if let Err(result) = reader.rewind() {
-            return Err(result);
+        for line in content.lines() {
+            parser.parse_labels(line);
         }
 
         // Second pass.
         parser.reset_instruction_line();
-        for line in reader.by_ref().lines() {
-            match line {
-                Ok(content) => {
-                    parser.parse_instructions(content.as_str());
-                }
-                Err(error) => println!("error reading file content {error}"),
+        let mut source_lines = Vec::new();
+        for (line_number, line) in content.lines().enumerate() {
+            if let Err(err) = parser.parse_instructions(line) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {err}", line_number + 1),
+                ));
             }
+            source_lines.resize(parser.binary.len(), line_number + 1);
         }
 
-        println!("SYMBOL_TABLE: {:?}", self.symbol_table);
-        Ok(())
+        self.labels = parser.labels;
+        self.source_lines = source_lines;
+        Ok(parser.binary)
+    }
+
+    /// symbol_table maps every symbol known after assemble() to its value:
+    /// the predefined symbols, the labels and the variables.
+    pub fn symbol_table(&self) -> &HashMap<String, u16> {
+        &self.symbol_table
+    }
+
+    /// labels maps the labels declared by the program to their ROM address.
+    pub fn labels(&self) -> &HashMap<String, u16> {
+        &self.labels
+    }
+
+    /// source_lines gives the line number, from 1, of the instruction at
+    /// each ROM address of the last assembled program.
+    pub fn source_lines(&self) -> &[usize] {
+        &self.source_lines
     }
 }
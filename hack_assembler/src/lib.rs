@@ -0,0 +1,2 @@
+//! Two-pass assembler translating Hack assembly code into Hack binary code.
+pub mod assembler;
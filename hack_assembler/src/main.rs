@@ -1,6 +1,6 @@
 use clap::Parser;
 
-mod assembler;
+use hack_assembler::assembler;
 
 /// Args reads the command line arguments.
 #[derive(Parser, Debug)]
@@ -13,23 +13,26 @@ fn main() {
     let args = Args::parse();
 
     if args.path.exists() {
-        match args.path.extension() {
-            None => {}
-            Some(ext) => {
-                if ext != "asm" {
-                    println!("invalid file format provided {:?}, expected .asm", ext);
-                    return;
-                }
-            }
+        if let Some(ext) = args.path.extension().filter(|ext| *ext != "asm") {
+            println!("invalid file format provided {:?}, expected .asm", ext);
+            return;
         }
 
-        let mut asmbler = assembler::Assembler::new( args.path );
+        let mut asmbler = assembler::Assembler::new(args.path.clone());
         asmbler.initialize();
 
-        match asmbler.read_file() {
-            Ok(_) => { println!("done!")}
-            Err(err) => {println!("err: {err}")}
+        // Write the binary code next to the input file, one instruction per line.
+        let binary = asmbler.read_file().and_then(|binary| {
+            let code: String = binary.iter().map(|word| format!("{word:016b}\n")).collect();
+            std::fs::write(args.path.with_extension("hack"), code)
+        });
+        match binary {
+            Ok(_) => {
+                println!("done!")
+            }
+            Err(err) => {
+                println!("err: {err}")
+            }
         }
     }
-
 }
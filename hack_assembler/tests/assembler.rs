@@ -0,0 +1,67 @@
+//! The assembler on the programs of projects/06 and on single instructions.
+use std::path::{Path, PathBuf};
+
+use hack_assembler::assembler::Assembler;
+
+fn assemble(content: &str) -> std::io::Result<Vec<u16>> {
+    let mut assembler = Assembler::new(PathBuf::from("Test.asm"));
+    assembler.initialize();
+    assembler.assemble(content)
+}
+
+fn assemble_file(path: &Path) -> Vec<u16> {
+    let mut assembler = Assembler::new(path.to_path_buf());
+    assembler.initialize();
+    assembler
+        .read_file()
+        .unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+}
+
+#[test]
+fn assembles_symbols_like_addresses() {
+    let projects = Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/06");
+    for (directory, name) in [("max", "Max"), ("rect", "Rect"), ("pong", "Pong")] {
+        let directory = projects.join(directory);
+        let symbolic = assemble_file(&directory.join(format!("{}.asm", name)));
+        let plain = assemble_file(&directory.join(format!("{}L.asm", name)));
+        assert!(!symbolic.is_empty(), "{}", name);
+        assert_eq!(symbolic, plain, "{}", name);
+    }
+}
+
+#[test]
+fn encodes_instructions() {
+    let cases = [
+        ("@21", 0b0000_0000_0001_0101),
+        ("@SCREEN", 0b0100_0000_0000_0000),
+        ("MD=D+1", 0b1110_0111_1101_1000),
+        ("AMD=M-1", 0b1111_1100_1011_1000),
+        ("D=A+D", 0b1110_0000_1001_0000),
+        ("D=D+A", 0b1110_0000_1001_0000),
+        ("D=M&D", 0b1111_0000_0001_0000),
+        ("D=D&M", 0b1111_0000_0001_0000),
+        ("0;JMP", 0b1110_1010_1000_0111),
+        ("D;JLE", 0b1110_0011_0000_0110),
+    ];
+    for (instruction, word) in cases {
+        assert_eq!(
+            assemble(instruction).unwrap(),
+            [word],
+            "{} is {:016b}",
+            instruction,
+            word
+        );
+    }
+}
+
+#[test]
+fn reports_the_line_of_errors() {
+    for (content, line) in [
+        ("@1\nD=A\nD=Q\n", "line 3"),
+        ("// comment\n\nAD=M;JXX\n", "line 3"),
+        ("@1\nXY=D\n", "line 2"),
+    ] {
+        let err = assemble(content).expect_err(content);
+        assert!(err.to_string().starts_with(line), "{:?}: {}", content, err);
+    }
+}
@@ -0,0 +1,260 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Size in words of the instruction memory (ROM32K).
+pub const ROM_SIZE: usize = 32768;
+/// Size in words of the data memory, addressed by the 15 lower bits of A.
+pub const RAM_SIZE: usize = 32768;
+/// Base address of the screen memory map, 8K words for 256 rows of 512 pixels.
+pub const SCREEN: usize = 16384;
+/// Address of the keyboard memory map.
+pub const KBD: usize = 24576;
+
+/// State of the CPU after a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    /// The program is stuck in a loop that can't change any state,
+    /// like the `(END) @END 0;JMP` at the end of most programs.
+    Halted,
+}
+
+/// Loop is a backward jump seen by the halt detection, with the state of the
+/// CPU when it was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Loop {
+    target: u16,
+    a: u16,
+    d: u16,
+    writes: u64,
+    keyboard_reads: u64,
+}
+
+/// Cpu emulates the Hack computer: the CPU with its A, D and PC registers,
+/// the instruction memory and the data memory with the screen and keyboard
+/// memory maps. Every call to step() is one clock cycle.
+pub struct Cpu {
+    pub a: u16,
+    pub d: u16,
+    pub pc: u16,
+    rom: Vec<u16>,
+    ram: Vec<u16>,
+    cycles: u64,
+    // Counters for the halt detection, a loop that neither writes to the
+    // RAM nor reads the keyboard can never end.
+    writes: u64,
+    keyboard_reads: u64,
+    last_loop: Option<Loop>,
+}
+
+impl Cpu {
+    /// Creates a new Cpu with the program loaded in the ROM, all registers
+    /// and the RAM are set to 0.
+    pub fn new(program: &[u16]) -> io::Result<Self> {
+        if program.len() > ROM_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "program has {} instructions, the ROM holds {}",
+                    program.len(),
+                    ROM_SIZE
+                ),
+            ));
+        }
+
+        let mut rom = vec![0; ROM_SIZE];
+        rom[..program.len()].copy_from_slice(program);
+        Ok(Cpu {
+            a: 0,
+            d: 0,
+            pc: 0,
+            rom,
+            ram: vec![0; RAM_SIZE],
+            cycles: 0,
+            writes: 0,
+            keyboard_reads: 0,
+            last_loop: None,
+        })
+    }
+
+    /// reset sets the PC to 0, the RAM and the other registers keep their values
+    /// like when the reset bit of the Hack computer is set.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.last_loop = None;
+    }
+
+    /// Number of cycles run since the CPU was created.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn rom(&self) -> &[u16] {
+        &self.rom
+    }
+
+    pub fn ram(&self) -> &[u16] {
+        &self.ram
+    }
+
+    /// ram_mut gives write access to the RAM, e.g. to set up the inputs of a
+    /// program or to press a key by writing to KBD.
+    pub fn ram_mut(&mut self) -> &mut [u16] {
+        // Changes made from outside may release a halted loop.
+        self.last_loop = None;
+        &mut self.ram
+    }
+
+    pub fn peek(&self, address: usize) -> u16 {
+        self.ram[address]
+    }
+
+    pub fn poke(&mut self, address: usize, value: u16) {
+        self.ram_mut()[address] = value;
+    }
+
+    /// step executes the instruction at PC.
+    ///
+    /// An A-instruction (0vvv vvvv vvvv vvvv) loads the value in A, a
+    /// C-instruction (111a cccc ccdd djjj) computes comp with the ALU, stores
+    /// it in the dest registers and jumps to A if the jump condition holds.
+    pub fn step(&mut self) -> Status {
+        let instruction = self.rom[self.pc as usize & (ROM_SIZE - 1)];
+        let pc = self.pc;
+        self.cycles += 1;
+
+        if instruction & 0x8000 == 0 {
+            self.a = instruction;
+            self.pc = pc.wrapping_add(1) & 0x7FFF;
+            return Status::Running;
+        }
+
+        let address = self.a as usize & (RAM_SIZE - 1);
+        let uses_m = instruction & 0x1000 != 0;
+        let y = if uses_m {
+            if address == KBD {
+                self.keyboard_reads += 1;
+            }
+            self.ram[address]
+        } else {
+            self.a
+        };
+        let out = alu(self.d, y, (instruction >> 6) & 0x3F);
+
+        // dest bits d1 d2 d3 are A, D and M, M is written at the old address.
+        if instruction & 0x0008 != 0 {
+            self.ram[address] = out;
+            self.writes += 1;
+        }
+        let jump_target = self.a;
+        if instruction & 0x0020 != 0 {
+            self.a = out;
+        }
+        if instruction & 0x0010 != 0 {
+            self.d = out;
+        }
+
+        // jump bits j1 j2 j3 are out < 0, out = 0 and out > 0.
+        let negative = out & 0x8000 != 0;
+        let zero = out == 0;
+        let jump = (instruction & 0x4 != 0 && negative)
+            || (instruction & 0x2 != 0 && zero)
+            || (instruction & 0x1 != 0 && !negative && !zero);
+
+        if !jump {
+            self.pc = pc.wrapping_add(1) & 0x7FFF;
+            return Status::Running;
+        }
+
+        self.pc = jump_target & 0x7FFF;
+        if self.pc > pc {
+            return Status::Running;
+        }
+
+        // A backward jump closes a loop, if nothing changed since the last
+        // time the same jump was taken the program can never leave it.
+        let current = Loop {
+            target: self.pc,
+            a: self.a,
+            d: self.d,
+            writes: self.writes,
+            keyboard_reads: self.keyboard_reads,
+        };
+        if self.last_loop == Some(current) {
+            return Status::Halted;
+        }
+        self.last_loop = Some(current);
+        Status::Running
+    }
+
+    /// run executes up to max_cycles cycles, it stops early when the program
+    /// halts. It returns the number of cycles run and the final status.
+    pub fn run(&mut self, max_cycles: u64) -> (u64, Status) {
+        for cycle in 0..max_cycles {
+            if self.step() == Status::Halted {
+                return (cycle + 1, Status::Halted);
+            }
+        }
+        (max_cycles, Status::Running)
+    }
+}
+
+/// alu computes the Hack ALU output for the control bits zx nx zy ny f no.
+fn alu(x: u16, y: u16, control: u16) -> u16 {
+    let zx = control & 0b100000 != 0;
+    let nx = control & 0b010000 != 0;
+    let zy = control & 0b001000 != 0;
+    let ny = control & 0b000100 != 0;
+    let f = control & 0b000010 != 0;
+    let no = control & 0b000001 != 0;
+
+    let mut x = if zx { 0 } else { x };
+    if nx {
+        x = !x;
+    }
+    let mut y = if zy { 0 } else { y };
+    if ny {
+        y = !y;
+    }
+    let out = if f { x.wrapping_add(y) } else { x & y };
+    if no {
+        !out
+    } else {
+        out
+    }
+}
+
+/// parse_hack reads a program in the Hack machine language, one 16-bit binary
+/// instruction per line. Blank lines are ignored.
+pub fn parse_hack(content: &str) -> io::Result<Vec<u16>> {
+    let mut program = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let valid = line.len() == 16 && line.chars().all(|c| c == '0' || c == '1');
+        let instruction = u16::from_str_radix(line, 2).ok().filter(|_| valid);
+        match instruction {
+            Some(instruction) => program.push(instruction),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "line {}: invalid instruction {:?}, expected 16 binary digits",
+                        line_number + 1,
+                        line
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(program)
+}
+
+/// load_hack reads a `.hack` file, see parse_hack.
+pub fn load_hack(path: &Path) -> io::Result<Vec<u16>> {
+    parse_hack(&fs::read_to_string(path)?)
+}
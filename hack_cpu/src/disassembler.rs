@@ -0,0 +1,67 @@
+/// comp_mnemonic gives the mnemonic of the a bit and the six c bits of a
+/// C-instruction, None for the codes the assembler has no mnemonic for.
+fn comp_mnemonic(comp: u16) -> Option<&'static str> {
+    let mnemonic = match comp {
+        0b0101010 => "0",
+        0b0111111 => "1",
+        0b0111010 => "-1",
+        0b0001100 => "D",
+        0b0110000 => "A",
+        0b1110000 => "M",
+        0b0001101 => "!D",
+        0b0110001 => "!A",
+        0b1110001 => "!M",
+        0b0001111 => "-D",
+        0b0110011 => "-A",
+        0b1110011 => "-M",
+        0b0011111 => "D+1",
+        0b0110111 => "A+1",
+        0b1110111 => "M+1",
+        0b0001110 => "D-1",
+        0b0110010 => "A-1",
+        0b1110010 => "M-1",
+        0b0000010 => "D+A",
+        0b1000010 => "D+M",
+        0b0010011 => "D-A",
+        0b1010011 => "D-M",
+        0b0000111 => "A-D",
+        0b1000111 => "M-D",
+        0b0000000 => "D&A",
+        0b1000000 => "D&M",
+        0b0010101 => "D|A",
+        0b1010101 => "D|M",
+        _ => return None,
+    };
+    Some(mnemonic)
+}
+
+const DEST: [&str; 8] = ["", "M", "D", "MD", "A", "AM", "AD", "AMD"];
+const JUMP: [&str; 8] = ["", "JGT", "JEQ", "JGE", "JLT", "JNE", "JLE", "JMP"];
+
+/// disassemble translates an instruction back to the Hack assembly
+/// language, e.g. `@17` or `MD=M+1` or `D;JGT`. A C-instruction with a comp
+/// field unknown to the assembler is shown as its binary code.
+pub fn disassemble(instruction: u16) -> String {
+    if instruction & 0x8000 == 0 {
+        return format!("@{}", instruction);
+    }
+
+    let comp = match comp_mnemonic((instruction >> 6) & 0x7F) {
+        Some(comp) => comp,
+        None => return format!("{:016b}", instruction),
+    };
+    let dest = DEST[(instruction as usize >> 3) & 0x7];
+    let jump = JUMP[instruction as usize & 0x7];
+
+    let mut text = String::new();
+    if !dest.is_empty() {
+        text.push_str(dest);
+        text.push('=');
+    }
+    text.push_str(comp);
+    if !jump.is_empty() {
+        text.push(';');
+        text.push_str(jump);
+    }
+    text
+}
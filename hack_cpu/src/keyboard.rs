@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cpu::{Cpu, Status, KBD};
+
+/// Codes of the special keys of the Hack character set, the other keys
+/// use their ASCII code.
+const KEY_NAMES: [(&str, u16); 17] = [
+    ("NONE", 0),
+    ("SPACE", 32),
+    ("NEWLINE", 128),
+    ("ENTER", 128),
+    ("BACKSPACE", 129),
+    ("LEFT", 130),
+    ("UP", 131),
+    ("RIGHT", 132),
+    ("DOWN", 133),
+    ("HOME", 134),
+    ("END", 135),
+    ("PAGEUP", 136),
+    ("PAGEDOWN", 137),
+    ("INSERT", 138),
+    ("DELETE", 139),
+    ("ESC", 140),
+    ("ESCAPE", 140),
+];
+/// Code of F1, F2 to F12 follow it.
+const F1: u16 = 141;
+
+/// KeyEvent sets the key held down once the CPU has run a number of
+/// cycles, the key stays down until the next event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub cycle: u64,
+    pub key: u16,
+}
+
+/// Keyboard feeds scripted key codes into the KBD memory map.
+///
+/// A script has one event per line, the cycle and the key, e.g. `1000 a`
+/// then `5000 NONE` to release it. A key is a single character, a name
+/// like ENTER, LEFT, ESC or F1, or a decimal code. `//` starts a comment.
+#[derive(Debug, Clone, Default)]
+pub struct Keyboard {
+    events: Vec<KeyEvent>,
+    next: usize,
+}
+
+/// parse_key reads a key of a keyboard script.
+fn parse_key(key: &str) -> Option<u16> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_graphic().then_some(c as u16);
+    }
+
+    let name = key.to_ascii_uppercase();
+    if let Some((_, code)) = KEY_NAMES.iter().find(|(known, _)| *known == name) {
+        return Some(*code);
+    }
+    match name.strip_prefix('F').map(str::parse::<u16>) {
+        Some(Ok(number @ 1..=12)) => Some(F1 + number - 1),
+        _ => key.parse::<u16>().ok().filter(|code| *code <= 0x7FFF),
+    }
+}
+
+impl Keyboard {
+    /// parse reads a keyboard script, the events are sorted by cycle.
+    pub fn parse(script: &str) -> io::Result<Self> {
+        let invalid = |line: usize, message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line + 1, message),
+            )
+        };
+
+        let mut events = Vec::new();
+        for (line_number, line) in script.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or_default();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (cycle, key) = match fields[..] {
+                [] => continue,
+                [cycle, key] => (cycle, key),
+                _ => {
+                    return Err(invalid(
+                        line_number,
+                        format!("expected CYCLE KEY, got {:?}", line.trim()),
+                    ))
+                }
+            };
+
+            let cycle = cycle
+                .parse()
+                .map_err(|_| invalid(line_number, format!("invalid cycle {:?}", cycle)))?;
+            let key = parse_key(key)
+                .ok_or_else(|| invalid(line_number, format!("unknown key {:?}", key)))?;
+            events.push(KeyEvent { cycle, key });
+        }
+
+        // Events at the same cycle keep their order, the last one wins.
+        events.sort_by_key(|event| event.cycle);
+        Ok(Keyboard { events, next: 0 })
+    }
+
+    /// load reads a keyboard script file, see parse.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn events(&self) -> &[KeyEvent] {
+        &self.events
+    }
+
+    /// update writes to KBD the key of the events due at the current cycle
+    /// of the CPU.
+    pub fn update(&mut self, cpu: &mut Cpu) {
+        while let Some(event) = self.events.get(self.next) {
+            if event.cycle > cpu.cycles() {
+                break;
+            }
+            cpu.poke(KBD, event.key);
+            self.next += 1;
+        }
+    }
+
+    /// run executes up to max_cycles cycles like Cpu::run, pressing the
+    /// keys of the script on the way.
+    pub fn run(&mut self, cpu: &mut Cpu, max_cycles: u64) -> (u64, Status) {
+        for cycle in 0..max_cycles {
+            self.update(cpu);
+            if cpu.step() == Status::Halted {
+                return (cycle + 1, Status::Halted);
+            }
+        }
+        (max_cycles, Status::Running)
+    }
+}
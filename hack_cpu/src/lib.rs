@@ -0,0 +1,12 @@
+//! Headless emulator of the Hack computer, it executes the binary code
+//! produced by the hack_assembler.
+
+mod cpu;
+mod disassembler;
+mod keyboard;
+mod screen;
+
+pub use cpu::{load_hack, parse_hack, Cpu, Status, KBD, RAM_SIZE, ROM_SIZE, SCREEN};
+pub use disassembler::disassemble;
+pub use keyboard::{KeyEvent, Keyboard};
+pub use screen::{Screen, SCREEN_HEIGHT, SCREEN_WIDTH};
@@ -0,0 +1,144 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use hack_cpu::{Cpu, Keyboard, Screen, Status, RAM_SIZE};
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The hack machine language program (.hack) to run.
+    path: PathBuf,
+
+    /// Maximum number of cycles to run, the program stops earlier when it halts.
+    #[arg(short, long, default_value_t = 1_000_000)]
+    cycles: u64,
+
+    /// Sets a RAM word before running, as ADDRESS=VALUE (e.g. 0=256). Can be repeated.
+    #[arg(short, long, value_parser = parse_assignment)]
+    set: Vec<(usize, i16)>,
+
+    /// RAM range to print after running, as START-END or a single ADDRESS. Can be repeated.
+    #[arg(short, long, value_parser = parse_range)]
+    dump: Vec<RangeInclusive<usize>>,
+
+    /// Keyboard script feeding KBD, one `CYCLE KEY` event per line (e.g. `1000 a`, `2000 NONE`).
+    #[arg(short, long)]
+    keys: Option<PathBuf>,
+
+    /// Writes the screen to a .png or .pbm image after running.
+    #[arg(long)]
+    screen: Option<PathBuf>,
+
+    /// Prints the screen as ASCII art after running.
+    #[arg(long)]
+    ascii: bool,
+}
+
+fn parse_address(value: &str) -> Result<usize, String> {
+    let address: usize = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid address {value:?}"))?;
+    if address >= RAM_SIZE {
+        return Err(format!("address {address} is outside of the RAM"));
+    }
+    Ok(address)
+}
+
+fn parse_assignment(value: &str) -> Result<(usize, i16), String> {
+    let (address, word) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected ADDRESS=VALUE, got {value:?}"))?;
+    let word = word
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value {word:?}"))?;
+    Ok((parse_address(address)?, word))
+}
+
+fn parse_range(value: &str) -> Result<RangeInclusive<usize>, String> {
+    match value.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse_address(start)?, parse_address(end)?);
+            if start > end {
+                return Err(format!("range {value:?} ends before it starts"));
+            }
+            Ok(start..=end)
+        }
+        None => {
+            let address = parse_address(value)?;
+            Ok(address..=address)
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let program = match hack_cpu::load_hack(&args.path) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("err: {}: {err}", args.path.display());
+            std::process::exit(1);
+        }
+    };
+    let mut cpu = match Cpu::new(&program) {
+        Ok(cpu) => cpu,
+        Err(err) => {
+            eprintln!("err: {}: {err}", args.path.display());
+            std::process::exit(1);
+        }
+    };
+
+    for (address, value) in &args.set {
+        cpu.poke(*address, *value as u16);
+    }
+
+    let mut keyboard = match &args.keys {
+        Some(path) => match Keyboard::load(path) {
+            Ok(keyboard) => keyboard,
+            Err(err) => {
+                eprintln!("err: {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => Keyboard::default(),
+    };
+
+    let (cycles, status) = keyboard.run(&mut cpu, args.cycles);
+    match status {
+        Status::Halted => println!("halted after {cycles} cycles"),
+        Status::Running => println!("stopped after {cycles} cycles"),
+    }
+    println!(
+        "A = {}, D = {}, PC = {}",
+        cpu.a as i16, cpu.d as i16, cpu.pc
+    );
+
+    for range in &args.dump {
+        for address in range.clone() {
+            println!("RAM[{address}] = {}", cpu.peek(address) as i16);
+        }
+    }
+
+    let screen = Screen::new(cpu.ram());
+    if args.ascii {
+        print!("{}", screen.to_ascii());
+    }
+    if let Some(path) = &args.screen {
+        let image = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => screen.to_png(),
+            Some("pbm") => screen.to_pbm(),
+            _ => {
+                eprintln!("err: {}: expected a .png or .pbm file", path.display());
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = std::fs::write(path, image) {
+            eprintln!("err: {}: {err}", path.display());
+            std::process::exit(1);
+        }
+    }
+}
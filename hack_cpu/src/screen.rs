@@ -0,0 +1,149 @@
+use crate::cpu::SCREEN;
+
+/// Width of the screen in pixels.
+pub const SCREEN_WIDTH: usize = 512;
+/// Height of the screen in pixels.
+pub const SCREEN_HEIGHT: usize = 256;
+/// Words of a row of the screen memory map, 16 pixels per word.
+const ROW_WORDS: usize = SCREEN_WIDTH / 16;
+
+/// Screen reads the 512x256 bitmap out of the screen memory map, the pixel
+/// (x, y) is the bit x % 16 of RAM[SCREEN + 32 * y + x / 16], 1 is black.
+pub struct Screen<'a> {
+    ram: &'a [u16],
+}
+
+impl<'a> Screen<'a> {
+    /// Creates a Screen over the RAM of a Cpu.
+    pub fn new(ram: &'a [u16]) -> Self {
+        Screen { ram }
+    }
+
+    /// pixel tells if the pixel at column x and row y is black.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let word = self.ram[SCREEN + y * ROW_WORDS + x / 16];
+        word >> (x % 16) & 1 != 0
+    }
+
+    /// to_pbm encodes the screen as a binary PBM (P4) image.
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut image = format!("P4\n{} {}\n", SCREEN_WIDTH, SCREEN_HEIGHT).into_bytes();
+        for y in 0..SCREEN_HEIGHT {
+            // PBM packs the leftmost pixel in the highest bit, 1 is black.
+            image.extend(self.row_bytes(y));
+        }
+        image
+    }
+
+    /// to_png encodes the screen as a 1-bit grayscale PNG image.
+    pub fn to_png(&self) -> Vec<u8> {
+        // Every row starts with its filter type, 0 is none. In a grayscale
+        // PNG 0 is black, the bits of the PBM rows are inverted.
+        let mut pixels = Vec::with_capacity(SCREEN_HEIGHT * (SCREEN_WIDTH / 8 + 1));
+        for y in 0..SCREEN_HEIGHT {
+            pixels.push(0);
+            pixels.extend(self.row_bytes(y).map(|byte| !byte));
+        }
+
+        let mut header = Vec::new();
+        header.extend((SCREEN_WIDTH as u32).to_be_bytes());
+        header.extend((SCREEN_HEIGHT as u32).to_be_bytes());
+        // Bit depth 1, color type 0 (grayscale), default compression,
+        // filter method and no interlace.
+        header.extend([1, 0, 0, 0, 0]);
+
+        let mut image = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut image, b"IHDR", &header);
+        write_chunk(&mut image, b"IDAT", &zlib_stored(&pixels));
+        write_chunk(&mut image, b"IEND", &[]);
+        image
+    }
+
+    /// to_ascii draws the screen with characters, each one stands for a
+    /// block of 4x8 pixels and is darker the more of them are black.
+    pub fn to_ascii(&self) -> String {
+        const SHADES: &[u8] = b" .:-=+*#%@";
+        const BLOCK_WIDTH: usize = 4;
+        const BLOCK_HEIGHT: usize = 8;
+
+        let mut art = String::new();
+        for row in 0..SCREEN_HEIGHT / BLOCK_HEIGHT {
+            let mut line = String::new();
+            for column in 0..SCREEN_WIDTH / BLOCK_WIDTH {
+                let black = (0..BLOCK_HEIGHT)
+                    .flat_map(|dy| (0..BLOCK_WIDTH).map(move |dx| (dx, dy)))
+                    .filter(|(dx, dy)| {
+                        self.pixel(column * BLOCK_WIDTH + dx, row * BLOCK_HEIGHT + dy)
+                    })
+                    .count();
+                let shade = (black * (SHADES.len() - 1)).div_ceil(BLOCK_WIDTH * BLOCK_HEIGHT);
+                line.push(SHADES[shade] as char);
+            }
+            art.push_str(line.trim_end());
+            art.push('\n');
+        }
+        art
+    }
+
+    /// row_bytes packs the pixels of a row, the leftmost in the highest bit.
+    fn row_bytes(&self, y: usize) -> impl Iterator<Item = u8> + '_ {
+        let row = &self.ram[SCREEN + y * ROW_WORDS..SCREEN + (y + 1) * ROW_WORDS];
+        row.iter().flat_map(|word| {
+            let word = word.reverse_bits();
+            [(word >> 8) as u8, word as u8]
+        })
+    }
+}
+
+/// write_chunk appends a PNG chunk: its length, type, data and CRC.
+fn write_chunk(image: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    image.extend((data.len() as u32).to_be_bytes());
+    let start = image.len();
+    image.extend(kind);
+    image.extend(data);
+    let crc = crc32(&image[start..]);
+    image.extend(crc.to_be_bytes());
+}
+
+/// zlib_stored wraps data in a zlib stream of uncompressed deflate blocks,
+/// a screen is small enough to not need compression.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const BLOCK: usize = 0xFFFF;
+
+    let mut stream = vec![0x78, 0x01];
+    let blocks = data.chunks(BLOCK).collect::<Vec<_>>();
+    for (index, block) in blocks.iter().enumerate() {
+        // BFINAL marks the last block, BTYPE 00 is stored.
+        stream.push((index + 1 == blocks.len()) as u8);
+        let length = block.len() as u16;
+        stream.extend(length.to_le_bytes());
+        stream.extend((!length).to_le_bytes());
+        stream.extend(*block);
+    }
+    if blocks.is_empty() {
+        stream.extend([1, 0, 0, 0xFF, 0xFF]);
+    }
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
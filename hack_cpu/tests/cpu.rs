@@ -0,0 +1,222 @@
+//! The CPU on single instructions and on the programs of project 06.
+use std::io::ErrorKind;
+use std::path::Path;
+
+use hack_cpu::{load_hack, parse_hack, Cpu, Status, ROM_SIZE, SCREEN};
+
+/// program loads a program of projects/06 assembled by the course, Max
+/// sets R2 to max(R0, R1) and Rect draws R0 rows of 16 black pixels.
+fn program(name: &str) -> Vec<u16> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects/computer-chip")
+        .join(format!("{}.hack", name));
+    load_hack(&path).unwrap()
+}
+
+/// c_instruction encodes a C-instruction from its a+comp, dest and jump bits.
+fn c_instruction(comp: u16, dest: u16, jump: u16) -> u16 {
+    0xE000 | comp << 6 | dest << 3 | jump
+}
+
+#[test]
+fn computes_every_comp() {
+    let (d, a, m) = (17i16, 5i16, -3i16);
+    let comps: [(&str, u16, i16); 28] = [
+        ("0", 0b0101010, 0),
+        ("1", 0b0111111, 1),
+        ("-1", 0b0111010, -1),
+        ("D", 0b0001100, d),
+        ("A", 0b0110000, a),
+        ("!D", 0b0001101, !d),
+        ("!A", 0b0110001, !a),
+        ("-D", 0b0001111, -d),
+        ("-A", 0b0110011, -a),
+        ("D+1", 0b0011111, d + 1),
+        ("A+1", 0b0110111, a + 1),
+        ("D-1", 0b0001110, d - 1),
+        ("A-1", 0b0110010, a - 1),
+        ("D+A", 0b0000010, d + a),
+        ("D-A", 0b0010011, d - a),
+        ("A-D", 0b0000111, a - d),
+        ("D&A", 0b0000000, d & a),
+        ("D|A", 0b0010101, d | a),
+        ("M", 0b1110000, m),
+        ("!M", 0b1110001, !m),
+        ("-M", 0b1110011, -m),
+        ("M+1", 0b1110111, m + 1),
+        ("M-1", 0b1110010, m - 1),
+        ("D+M", 0b1000010, d + m),
+        ("D-M", 0b1010011, d - m),
+        ("M-D", 0b1000111, m - d),
+        ("D&M", 0b1000000, d & m),
+        ("D|M", 0b1010101, d | m),
+    ];
+    for (name, comp, expected) in comps {
+        // D=comp
+        let mut cpu = Cpu::new(&[c_instruction(comp, 0b010, 0)]).unwrap();
+        cpu.a = a as u16;
+        cpu.d = d as u16;
+        cpu.poke(a as usize, m as u16);
+        assert_eq!(cpu.step(), Status::Running, "{}", name);
+        assert_eq!(cpu.d as i16, expected, "{}", name);
+        assert_eq!((cpu.a, cpu.pc), (a as u16, 1), "{}", name);
+    }
+}
+
+#[test]
+fn writes_m_at_the_address_before_the_instruction() {
+    // AM=M+1 then MD=A
+    let program = [
+        c_instruction(0b1110111, 0b101, 0),
+        c_instruction(0b0110000, 0b011, 0),
+    ];
+    let mut cpu = Cpu::new(&program).unwrap();
+    cpu.a = 10;
+    cpu.poke(10, 41);
+    cpu.step();
+    assert_eq!((cpu.a, cpu.peek(10), cpu.peek(42)), (42, 42, 0));
+    cpu.step();
+    assert_eq!((cpu.d, cpu.peek(42)), (42, 42));
+}
+
+#[test]
+fn jumps_to_a_before_the_instruction() {
+    // A=A+1;JMP
+    let mut cpu = Cpu::new(&[c_instruction(0b0110111, 0b100, 0b111)]).unwrap();
+    cpu.a = 7;
+    cpu.step();
+    assert_eq!((cpu.a, cpu.pc), (8, 7));
+}
+
+#[test]
+fn jumps_on_every_condition() {
+    let conditions = ["JGT", "JEQ", "JGE", "JLT", "JNE", "JLE", "JMP"];
+    for (jump, name) in (1..=7).zip(conditions) {
+        for (comp, out) in [(0b0111010, -1i16), (0b0101010, 0), (0b0111111, 1)] {
+            let expected = match name {
+                "JGT" => out > 0,
+                "JEQ" => out == 0,
+                "JGE" => out >= 0,
+                "JLT" => out < 0,
+                "JNE" => out != 0,
+                "JLE" => out <= 0,
+                _ => true,
+            };
+            let mut cpu = Cpu::new(&[c_instruction(comp, 0, jump)]).unwrap();
+            cpu.a = 100;
+            cpu.step();
+            let pc = if expected { 100 } else { 1 };
+            assert_eq!(cpu.pc, pc, "{} on {}", name, out);
+        }
+    }
+}
+
+#[test]
+fn halts_in_loops_that_change_nothing() {
+    let mut cpu = Cpu::new(&program("Max")).unwrap();
+    cpu.poke(0, 3);
+    cpu.poke(1, 5);
+    // 14 cycles to reach the END loop at 14, the loop runs twice to be detected.
+    assert_eq!(cpu.run(1000), (16, Status::Halted));
+    assert_eq!((cpu.peek(2), cpu.pc, cpu.cycles()), (5, 14, 16));
+
+    // R0 counts down from 3 to 0, each iteration of the loop at 4 writes R0.
+    let program = "\
+0000000000000011
+1110110000010000
+0000000000000000
+1110001100001000
+0000000000000000
+1111110010011000
+0000000000000100
+1110001100000001
+0000000000001000
+1110101010000111
+";
+    let mut cpu = Cpu::new(&parse_hack(program).unwrap()).unwrap();
+    assert_eq!(cpu.run(1000), (20, Status::Halted));
+    assert_eq!((cpu.peek(0), cpu.pc), (0, 8));
+}
+
+#[test]
+fn waits_in_loops_that_read_the_keyboard() {
+    // (LOOP) @KBD D=M @LOOP D;JEQ
+    let program = [
+        0x6000,
+        c_instruction(0b1110000, 0b010, 0),
+        0,
+        c_instruction(0b0001100, 0, 0b010),
+    ];
+    let mut cpu = Cpu::new(&program).unwrap();
+    assert_eq!(cpu.run(1000), (1000, Status::Running));
+    // A key leaves the loop.
+    cpu.poke(0x6000, 65);
+    assert_eq!(cpu.run(4), (4, Status::Running));
+    assert_eq!((cpu.d, cpu.pc), (65, 4));
+}
+
+#[test]
+fn runs_the_programs_of_project_06() {
+    for (r0, r1, max) in [(3, 5, 5), (5, 3, 5), (-4, -7, -4), (0, 0, 0)] {
+        let mut cpu = Cpu::new(&program("Max")).unwrap();
+        cpu.poke(0, r0 as u16);
+        cpu.poke(1, r1 as u16);
+        assert_eq!(cpu.run(1000).1, Status::Halted);
+        assert_eq!(cpu.peek(2) as i16, max, "max({}, {})", r0, r1);
+    }
+
+    let mut cpu = Cpu::new(&program("Rect")).unwrap();
+    cpu.poke(0, 4);
+    assert_eq!(cpu.run(10_000).1, Status::Halted);
+    let rows: Vec<u16> = (0..6).map(|row| cpu.peek(SCREEN + row * 32)).collect();
+    assert_eq!(rows, [0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0, 0]);
+    assert_eq!(cpu.peek(SCREEN + 1), 0);
+}
+
+#[test]
+fn reports_invalid_programs() {
+    let err = parse_hack("0000000000000001\n\n101\n").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        "line 3: invalid instruction \"101\", expected 16 binary digits"
+    );
+    let err = parse_hack("000000000000000x").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "line 1: invalid instruction \"000000000000000x\", expected 16 binary digits"
+    );
+    assert_eq!(parse_hack("\n  0000000000000001  \n").unwrap(), [1]);
+
+    let err = load_hack(Path::new("missing.hack")).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let err = Cpu::new(&vec![0; ROM_SIZE + 1]).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "program has 32769 instructions, the ROM holds 32768"
+    );
+}
+
+#[test]
+fn dumps_the_ranges_of_the_command_line() {
+    let max = Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/computer-chip/Max.hack");
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_hack_cpu"))
+            .args(args)
+            .arg(&max)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["-s", "0=3", "-s", "1=5", "-d", "1-2", "-d", "0"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let dump: Vec<&str> = stdout.lines().skip(2).collect();
+    assert_eq!(dump, ["RAM[1] = 5", "RAM[2] = 5", "RAM[0] = 3"]);
+
+    let output = run(&["-d", "5-2"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("invalid value '5-2' for '--dump <DUMP>': range \"5-2\" ends before it starts"));
+}
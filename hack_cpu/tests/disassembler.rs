@@ -0,0 +1,28 @@
+//! Instructions disassembled back to the Hack assembly language.
+use hack_cpu::disassemble;
+
+#[test]
+fn disassembles_instructions() {
+    let instructions = [
+        (0b0000_0000_0001_0001, "@17"),
+        (0b0111_1111_1111_1111, "@32767"),
+        (0b1110_1010_1000_0111, "0;JMP"),
+        (0b1111_1101_1101_1000, "MD=M+1"),
+        (0b1110_0011_0000_0001, "D;JGT"),
+        (0b1110_0000_1001_0000, "D=D+A"),
+        (0b1111_0000_0000_1000, "M=D&M"),
+        (0b1110_1101_1111_1111, "AMD=A+1;JMP"),
+        (0b1111_0101_0111_0110, "AD=D|M;JLE"),
+        (0b1110_1110_1000_0000, "-1"),
+    ];
+    for (instruction, text) in instructions {
+        assert_eq!(disassemble(instruction), text);
+    }
+}
+
+#[test]
+fn shows_unknown_comps_in_binary() {
+    // The a bit with a comp of A only, and a comp the ALU table lacks.
+    assert_eq!(disassemble(0b1111_0011_1101_0000), "1111001111010000");
+    assert_eq!(disassemble(0b1110_1111_0101_0000), "1110111101010000");
+}
@@ -0,0 +1,87 @@
+//! Keyboard scripts and the keys they press on the CPU.
+use hack_cpu::{Cpu, KeyEvent, Keyboard, Status, KBD};
+
+fn keys(script: &str) -> Vec<(u64, u16)> {
+    Keyboard::parse(script)
+        .unwrap()
+        .events()
+        .iter()
+        .map(|event| (event.cycle, event.key))
+        .collect()
+}
+
+fn error(script: &str) -> String {
+    Keyboard::parse(script).unwrap_err().to_string()
+}
+
+#[test]
+fn parses_keys() {
+    let script = "\
+// Presses a, then ENTER.
+100 a
+200 NONE   // release
+
+300 enter
+400 SPACE
+500 F1
+600 f12
+700 Esc
+800 100
+900 7
+";
+    assert_eq!(
+        keys(script),
+        [
+            (100, 97),
+            (200, 0),
+            (300, 128),
+            (400, 32),
+            (500, 141),
+            (600, 152),
+            (700, 140),
+            (800, 100),
+            (900, 55)
+        ]
+    );
+}
+
+#[test]
+fn sorts_events_by_cycle() {
+    assert_eq!(
+        keys("300 c\n100 a\n300 d\n200 b\n"),
+        [(100, 97), (200, 98), (300, 99), (300, 100)]
+    );
+}
+
+#[test]
+fn reports_parse_errors() {
+    assert_eq!(
+        error("100 a\n200\n"),
+        "line 2: expected CYCLE KEY, got \"200\""
+    );
+    assert_eq!(
+        error("100 a b // c\n"),
+        "line 1: expected CYCLE KEY, got \"100 a b\""
+    );
+    assert_eq!(error("-5 a\n"), "line 1: invalid cycle \"-5\"");
+    assert_eq!(error("10 F13\n"), "line 1: unknown key \"F13\"");
+    assert_eq!(error("10 32768\n"), "line 1: unknown key \"32768\"");
+    assert_eq!(error("\n\n10 é\n"), "line 3: unknown key \"é\"");
+}
+
+#[test]
+fn presses_keys_on_their_cycle() {
+    // (LOOP) @KBD D=M @R0 M=D @LOOP 0;JMP
+    let program = [0x6000, 0xFC10, 0, 0xE308, 0, 0xEA87];
+    let mut keyboard = Keyboard::parse("12 x\n24 NONE\n").unwrap();
+    let mut cpu = Cpu::new(&program).unwrap();
+
+    assert_eq!(keyboard.run(&mut cpu, 12), (12, Status::Running));
+    assert_eq!((cpu.peek(KBD), cpu.peek(0)), (0, 0));
+    assert_eq!(keyboard.run(&mut cpu, 6), (6, Status::Running));
+    assert_eq!((cpu.peek(KBD), cpu.peek(0)), (120, 120));
+    // The key is released, the loop reads KBD so it never halts.
+    assert_eq!(keyboard.run(&mut cpu, 100), (100, Status::Running));
+    assert_eq!((cpu.peek(KBD), cpu.peek(0)), (0, 0));
+    assert_eq!(keyboard.events()[1], KeyEvent { cycle: 24, key: 0 });
+}
@@ -0,0 +1,96 @@
+//! The images of the screen memory map.
+use hack_cpu::{Screen, RAM_SIZE, SCREEN, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// ram has the top left and bottom right pixels black, and a black block
+/// of 4x8 pixels at the column 16 of the row 8.
+fn ram() -> Vec<u16> {
+    let mut ram = vec![0; RAM_SIZE];
+    ram[SCREEN] = 0x0001;
+    ram[SCREEN + 32 * 255 + 31] = 0x8000;
+    for y in 8..16 {
+        ram[SCREEN + 32 * y + 1] = 0x000F;
+    }
+    ram
+}
+
+/// chunk reads the PNG chunk at offset: its type, its data and its CRC.
+fn chunk(image: &[u8], offset: usize) -> (&[u8], &[u8], u32) {
+    let length = u32::from_be_bytes(image[offset..offset + 4].try_into().unwrap()) as usize;
+    let kind = &image[offset + 4..offset + 8];
+    let data = &image[offset + 8..offset + 8 + length];
+    let end = offset + 8 + length;
+    let crc = u32::from_be_bytes(image[end..end + 4].try_into().unwrap());
+    (kind, data, crc)
+}
+
+#[test]
+fn reads_pixels() {
+    let ram = ram();
+    let screen = Screen::new(&ram);
+    assert!(screen.pixel(0, 0));
+    assert!(!screen.pixel(1, 0));
+    assert!(screen.pixel(SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1));
+    assert!(!screen.pixel(SCREEN_WIDTH - 2, SCREEN_HEIGHT - 1));
+    assert!(screen.pixel(19, 15) && !screen.pixel(20, 15) && !screen.pixel(19, 16));
+}
+
+#[test]
+fn encodes_pbm() {
+    let ram = ram();
+    let image = Screen::new(&ram).to_pbm();
+    let header = b"P4\n512 256\n";
+    assert_eq!(&image[..header.len()], header);
+
+    let rows = &image[header.len()..];
+    assert_eq!(rows.len(), 256 * 64);
+    // The leftmost pixel is the highest bit.
+    assert_eq!((rows[0], rows[1]), (0x80, 0));
+    assert_eq!((rows[8 * 64 + 2], rows[8 * 64 + 3]), (0xF0, 0));
+    assert_eq!(rows[rows.len() - 1], 0x01);
+    assert_eq!(rows.iter().filter(|byte| **byte != 0).count(), 10);
+}
+
+#[test]
+fn encodes_png() {
+    let ram = ram();
+    let image = Screen::new(&ram).to_png();
+    assert_eq!(&image[..8], b"\x89PNG\r\n\x1a\n");
+
+    let (kind, header, crc) = chunk(&image, 8);
+    assert_eq!(kind, b"IHDR");
+    assert_eq!(header, [0, 0, 2, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0]);
+    assert_eq!(crc, 0xEDEB_F3CA);
+
+    // One row is a filter byte then 64 bytes of pixels, 0 is black.
+    let (kind, stream, crc) = chunk(&image, 33);
+    assert_eq!(kind, b"IDAT");
+    assert_eq!(crc, 0xDFDA_572C);
+    assert_eq!(stream.len(), 2 + 5 + 256 * 65 + 4);
+    // zlib header, then a single final stored block of 16640 bytes.
+    assert_eq!(stream[..7], [0x78, 0x01, 1, 0x00, 0x41, 0xFF, 0xBE]);
+    let pixels = &stream[7..stream.len() - 4];
+    assert_eq!(pixels[..3], [0, 0x7F, 0xFF]);
+    assert_eq!(pixels[pixels.len() - 2..], [0xFF, 0xFE]);
+    assert!(pixels.chunks(65).all(|row| row[0] == 0));
+    let adler = u32::from_be_bytes(stream[stream.len() - 4..].try_into().unwrap());
+    assert_eq!(adler, 0x8E48_BBB1);
+
+    let (kind, end, crc) = chunk(&image, 33 + 12 + stream.len());
+    assert_eq!((kind, end, crc), (&b"IEND"[..], &[][..], 0xAE42_6082));
+    assert_eq!(image.len(), 33 + 12 + stream.len() + 12);
+}
+
+#[test]
+fn draws_ascii() {
+    let ram = ram();
+    let art = Screen::new(&ram).to_ascii();
+    let lines: Vec<&str> = art.lines().collect();
+    assert_eq!(lines.len(), 32);
+    // A block of 4x8 pixels with one black pixel is the lightest shade.
+    assert_eq!(lines[0], ".");
+    assert_eq!(lines[1], "    @");
+    assert!(lines[2..31].iter().all(|line| line.is_empty()));
+    assert_eq!(lines[31], format!("{}.", " ".repeat(127)));
+
+    assert_eq!(Screen::new(&vec![0; RAM_SIZE]).to_ascii(), "\n".repeat(32));
+}
@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use hack_cpu::{Cpu, Status};
+
+use crate::program::Program;
+
+/// Why a run stopped, shown in the status line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(u16),
+    /// A watched RAM word changed, with its old and new values.
+    Watchpoint(usize, u16, u16),
+    Halted,
+    /// The cycle limit of continue was reached.
+    Limit(u64),
+}
+
+/// Watchpoint is a watched RAM word, with the name it was given and its
+/// value when last seen.
+pub struct Watchpoint {
+    pub name: String,
+    pub value: u16,
+}
+
+/// Debugger runs a program on the CPU emulator and stops it at the
+/// breakpoints on ROM addresses and the watchpoints on RAM words.
+pub struct Debugger {
+    pub program: Program,
+    pub cpu: Cpu,
+    pub breakpoints: BTreeSet<u16>,
+    /// Watched RAM address -> watchpoint.
+    pub watchpoints: BTreeMap<usize, Watchpoint>,
+    pub halted: bool,
+    /// Maximum number of cycles of a continue.
+    limit: u64,
+}
+
+impl Debugger {
+    pub fn new(program: Program, limit: u64) -> Result<Self, String> {
+        let cpu = Cpu::new(&program.code).map_err(|err| err.to_string())?;
+        Ok(Debugger {
+            program,
+            cpu,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            halted: false,
+            limit,
+        })
+    }
+
+    /// reset restarts the program with the registers and the RAM cleared,
+    /// the breakpoints and watchpoints are kept.
+    pub fn reset(&mut self) -> Result<(), String> {
+        self.cpu = Cpu::new(&self.program.code).map_err(|err| err.to_string())?;
+        self.halted = false;
+        for (address, watchpoint) in self.watchpoints.iter_mut() {
+            watchpoint.value = self.cpu.peek(*address);
+        }
+        Ok(())
+    }
+
+    pub fn add_breakpoint(&mut self, location: &str) -> Result<u16, String> {
+        let address = self.program.rom_address(location)?;
+        self.breakpoints.insert(address);
+        Ok(address)
+    }
+
+    pub fn remove_breakpoint(&mut self, location: &str) -> Result<u16, String> {
+        let address = self.program.rom_address(location)?;
+        if !self.breakpoints.remove(&address) {
+            return Err(format!("No breakpoint at {}", address));
+        }
+        Ok(address)
+    }
+
+    pub fn add_watchpoint(&mut self, location: &str) -> Result<usize, String> {
+        let address = self.program.ram_address(location)?;
+        let watchpoint = Watchpoint {
+            name: location.to_string(),
+            value: self.cpu.peek(address),
+        };
+        self.watchpoints.insert(address, watchpoint);
+        Ok(address)
+    }
+
+    pub fn remove_watchpoint(&mut self, location: &str) -> Result<usize, String> {
+        let address = self.program.ram_address(location)?;
+        if self.watchpoints.remove(&address).is_none() {
+            return Err(format!("No watchpoint on RAM[{}]", address));
+        }
+        Ok(address)
+    }
+
+    /// step_once executes one instruction, it returns the watchpoint hit by
+    /// it or Halted.
+    fn step_once(&mut self) -> Option<Stop> {
+        if self.cpu.step() == Status::Halted {
+            self.halted = true;
+            return Some(Stop::Halted);
+        }
+
+        let mut hit = None;
+        for (address, watchpoint) in self.watchpoints.iter_mut() {
+            let current = self.cpu.peek(*address);
+            if current != watchpoint.value {
+                hit.get_or_insert(Stop::Watchpoint(*address, watchpoint.value, current));
+                watchpoint.value = current;
+            }
+        }
+        hit
+    }
+
+    /// step executes up to count instructions, it stops early on a
+    /// watchpoint or when the program halts. Breakpoints don't stop it.
+    pub fn step(&mut self, count: u64) -> Stop {
+        for _ in 0..count {
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+        }
+        Stop::Stepped
+    }
+
+    /// run executes instructions until the PC reaches a breakpoint, a
+    /// watchpoint is hit, the program halts or the cycle limit is reached.
+    /// The instruction at the starting PC is executed even if it has a
+    /// breakpoint, so that continue moves past it.
+    pub fn run(&mut self) -> Stop {
+        for cycle in 0..self.limit {
+            if cycle > 0 && self.breakpoints.contains(&self.cpu.pc) {
+                return Stop::Breakpoint(self.cpu.pc);
+            }
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+        }
+        Stop::Limit(self.limit)
+    }
+}
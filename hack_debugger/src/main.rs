@@ -0,0 +1,252 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use clap::Parser;
+
+mod debugger;
+mod program;
+mod view;
+
+use debugger::{Debugger, Stop};
+use program::Program;
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The program to debug, a .asm or a .hack file.
+    path: PathBuf,
+
+    /// Sets a breakpoint on a ROM address or a label. Can be repeated.
+    #[arg(short, long = "break")]
+    breakpoints: Vec<String>,
+
+    /// Sets a watchpoint on a RAM address or a symbol like SP, LCL or R13. Can be repeated.
+    #[arg(short, long = "watch")]
+    watchpoints: Vec<String>,
+
+    /// Maximum number of cycles run by a continue.
+    #[arg(short, long, default_value_t = 10_000_000)]
+    limit: u64,
+}
+
+const HELP: &str = "\
+commands:
+  s, step [N]         execute N instructions (1), breakpoints don't stop it
+  c, continue         run until a breakpoint, a watchpoint or the program halts
+  b, break LOC        set a breakpoint on a ROM address or a label
+  d, delete LOC       remove a breakpoint
+  w, watch ADDR       set a watchpoint on a RAM address or a symbol (SP, LCL, R13)
+  u, unwatch ADDR     remove a watchpoint
+  p, print ADDR [N]   print N RAM words (1) from an address or a symbol
+  set ADDR VALUE      write a value to a RAM word
+  i, info             list the breakpoints and the watchpoints
+  r, reset            restart the program with the RAM cleared
+  h, help             show this help
+  q, quit             leave the debugger
+an empty line repeats the last step or continue.";
+
+/// Result of a command, what the status line shows below the screen.
+enum Reply {
+    Message(String),
+    Quit,
+}
+
+fn describe(debugger: &Debugger, stop: Stop) -> String {
+    match stop {
+        Stop::Stepped => String::new(),
+        Stop::Breakpoint(address) => match debugger.program.labels_at(address).first() {
+            Some(label) => format!("breakpoint at {} ({})", address, label),
+            None => format!("breakpoint at {}", address),
+        },
+        Stop::Watchpoint(address, old, new) => format!(
+            "watchpoint {} ({}): {} -> {}",
+            debugger.watchpoints[&address].name, address, old as i16, new as i16
+        ),
+        Stop::Halted => String::from("the program halted"),
+        Stop::Limit(cycles) => format!("stopped after {} cycles", cycles),
+    }
+}
+
+fn parse_value(value: &str) -> Result<u16, String> {
+    value
+        .parse::<i16>()
+        .map(|value| value as u16)
+        .or_else(|_| value.parse::<u16>())
+        .map_err(|_| format!("Invalid value: {}", value))
+}
+
+fn info(debugger: &Debugger) -> String {
+    let breakpoints: Vec<String> = debugger
+        .breakpoints
+        .iter()
+        .map(
+            |address| match debugger.program.labels_at(*address).first() {
+                Some(label) => format!("{} ({})", address, label),
+                None => address.to_string(),
+            },
+        )
+        .collect();
+    let watchpoints: Vec<String> = debugger
+        .watchpoints
+        .iter()
+        .map(|(address, watchpoint)| format!("{} ({})", watchpoint.name, address))
+        .collect();
+    format!(
+        "breakpoints: {}\nwatchpoints: {}",
+        breakpoints.join(", "),
+        watchpoints.join(", ")
+    )
+}
+
+/// execute runs a command line of the debugger.
+fn execute(debugger: &mut Debugger, line: &str) -> Result<Reply, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let argument = |index: usize| {
+        words
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("{} expects an argument, see help", words[0]))
+    };
+
+    let message = match words[0] {
+        "s" | "step" => {
+            let count = match words.get(1) {
+                Some(count) => count
+                    .parse()
+                    .map_err(|_| format!("Invalid count: {}", count))?,
+                None => 1,
+            };
+            let stop = debugger.step(count);
+            describe(debugger, stop)
+        }
+        "c" | "continue" => {
+            let stop = debugger.run();
+            describe(debugger, stop)
+        }
+        "b" | "break" => {
+            let address = debugger.add_breakpoint(argument(1)?)?;
+            format!("breakpoint at {}", address)
+        }
+        "d" | "delete" => {
+            let address = debugger.remove_breakpoint(argument(1)?)?;
+            format!("removed the breakpoint at {}", address)
+        }
+        "w" | "watch" => {
+            let address = debugger.add_watchpoint(argument(1)?)?;
+            format!("watchpoint on RAM[{}]", address)
+        }
+        "u" | "unwatch" => {
+            let address = debugger.remove_watchpoint(argument(1)?)?;
+            format!("removed the watchpoint on RAM[{}]", address)
+        }
+        "p" | "print" => {
+            let address = debugger.program.ram_address(argument(1)?)?;
+            let count: usize = match words.get(2) {
+                Some(count) => count
+                    .parse()
+                    .map_err(|_| format!("Invalid count: {}", count))?,
+                None => 1,
+            };
+            // At most the whole RAM, a huge count must not overflow the end.
+            let count = count.min(hack_cpu::RAM_SIZE);
+            let end = address.saturating_add(count).min(hack_cpu::RAM_SIZE);
+            (address..end)
+                .map(|address| format!("RAM[{}] = {}", address, debugger.cpu.peek(address) as i16))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        "set" => {
+            let address = debugger.program.ram_address(argument(1)?)?;
+            let value = parse_value(argument(2)?)?;
+            debugger.cpu.poke(address, value);
+            format!("RAM[{}] = {}", address, value as i16)
+        }
+        "i" | "info" => info(debugger),
+        "r" | "reset" => {
+            debugger.reset()?;
+            String::from("restarted the program")
+        }
+        "h" | "help" => String::from(HELP),
+        "q" | "quit" => return Ok(Reply::Quit),
+        command => return Err(format!("Unknown command: {}, see help", command)),
+    };
+    Ok(Reply::Message(message))
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let program = match Program::load(&args.path) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("err: {}: {}", args.path.display(), err);
+            std::process::exit(1);
+        }
+    };
+    let mut debugger = match Debugger::new(program, args.limit) {
+        Ok(debugger) => debugger,
+        Err(err) => {
+            eprintln!("err: {}: {}", args.path.display(), err);
+            std::process::exit(1);
+        }
+    };
+    for location in &args.breakpoints {
+        if let Err(err) = debugger.add_breakpoint(location) {
+            eprintln!("err: {}", err);
+            std::process::exit(1);
+        }
+    }
+    for location in &args.watchpoints {
+        if let Err(err) = debugger.add_watchpoint(location) {
+            eprintln!("err: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    // Redraw in place on a terminal, a piped session reads like a log.
+    let terminal = io::stdout().is_terminal();
+    let mut stdout = io::stdout();
+    let mut message = String::from("type help for the commands");
+    let mut last_command = String::new();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if terminal {
+            print!("\x1b[2J\x1b[H");
+        }
+        print!("{}", view::render(&debugger));
+        if !message.is_empty() {
+            println!("\n{}", message);
+        }
+        print!("(hdb) ");
+        let _ = stdout.flush();
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        // Echo the commands of a piped session.
+        if !io::stdin().is_terminal() {
+            println!("{}", line);
+        }
+
+        let line = if line.trim().is_empty() {
+            last_command.clone()
+        } else {
+            line
+        };
+        if line.trim().is_empty() {
+            message.clear();
+            continue;
+        }
+
+        message = match execute(&mut debugger, &line) {
+            Ok(Reply::Message(message)) => message,
+            Ok(Reply::Quit) => break,
+            Err(err) => format!("err: {}", err),
+        };
+        let command = line.split_whitespace().next().unwrap_or_default();
+        if ["s", "step", "c", "continue"].contains(&command) {
+            last_command = line;
+        }
+    }
+}
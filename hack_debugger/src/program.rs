@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use hack_assembler::assembler::Assembler;
+use hack_cpu::{RAM_SIZE, ROM_SIZE};
+
+/// Program is the code being debugged with what is known of its source.
+/// A `.asm` program has its source lines, labels and variables, a `.hack`
+/// program only has the predefined symbols.
+pub struct Program {
+    pub code: Vec<u16>,
+    /// Lines of the `.asm` source, empty for a `.hack` program.
+    source: Vec<String>,
+    /// Source line number, from 1, of the instruction at each ROM address.
+    source_lines: Vec<usize>,
+    /// Label name -> ROM address.
+    labels: HashMap<String, u16>,
+    /// Symbol name -> value, the predefined symbols and the variables.
+    symbols: HashMap<String, u16>,
+}
+
+impl Program {
+    /// load assembles a `.asm` file or reads a `.hack` file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut assembler = Assembler::new(path.to_path_buf());
+        assembler.initialize();
+
+        if path.extension().is_some_and(|ext| ext == "hack") {
+            return Ok(Program {
+                code: hack_cpu::load_hack(path)?,
+                source: Vec::new(),
+                source_lines: Vec::new(),
+                labels: HashMap::new(),
+                symbols: assembler.symbol_table().clone(),
+            });
+        }
+
+        let content = fs::read_to_string(path)?;
+        let code = assembler.assemble(&content)?;
+        let labels = assembler.labels().clone();
+        let symbols = assembler
+            .symbol_table()
+            .iter()
+            .filter(|(name, _)| !labels.contains_key(*name))
+            .map(|(name, value)| (name.clone(), *value))
+            .collect();
+        Ok(Program {
+            code,
+            source: content.lines().map(String::from).collect(),
+            source_lines: assembler.source_lines().to_vec(),
+            labels,
+            symbols,
+        })
+    }
+
+    /// rom_address resolves a breakpoint location, a ROM address or a label.
+    pub fn rom_address(&self, location: &str) -> Result<u16, String> {
+        if let Some(address) = self.labels.get(location) {
+            return Ok(*address);
+        }
+        match location.parse::<usize>() {
+            Ok(address) if address < ROM_SIZE => Ok(address as u16),
+            Ok(_) => Err(format!("{} is outside of the ROM", location)),
+            Err(_) => Err(format!("Unknown label: {}", location)),
+        }
+    }
+
+    /// ram_address resolves a RAM address or a symbol like SP, R13 or a
+    /// variable of the program.
+    pub fn ram_address(&self, location: &str) -> Result<usize, String> {
+        if let Some(address) = self.symbols.get(location) {
+            return Ok(*address as usize);
+        }
+        match location.parse::<usize>() {
+            Ok(address) if address < RAM_SIZE => Ok(address),
+            Ok(_) => Err(format!("{} is outside of the RAM", location)),
+            Err(_) => Err(format!("Unknown symbol: {}", location)),
+        }
+    }
+
+    /// labels_at lists the labels declared at a ROM address, sorted.
+    pub fn labels_at(&self, address: u16) -> Vec<&str> {
+        let mut labels: Vec<&str> = self
+            .labels
+            .iter()
+            .filter(|(_, value)| **value == address)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        labels.sort_unstable();
+        labels
+    }
+
+    /// listing is the assembly of the instruction at a ROM address. The
+    /// A-instructions keep the symbol of the source with its value, e.g.
+    /// `@LOOP  // 10`.
+    pub fn listing(&self, address: u16) -> String {
+        let instruction = self.code.get(address as usize).copied().unwrap_or(0);
+        let text = hack_cpu::disassemble(instruction);
+        if instruction & 0x8000 != 0 {
+            return text;
+        }
+
+        let symbol = self
+            .source_lines
+            .get(address as usize)
+            .and_then(|line| self.source.get(line - 1))
+            .map(|line| line.split("//").next().unwrap_or_default().trim())
+            .and_then(|line| line.strip_prefix('@'))
+            .map(str::trim)
+            .filter(|symbol| symbol.parse::<u16>().is_err());
+        match symbol {
+            Some(symbol) => format!("@{:<15} // {}", symbol, instruction),
+            None => text,
+        }
+    }
+}
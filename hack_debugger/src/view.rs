@@ -0,0 +1,129 @@
+use std::fmt::Write;
+
+use hack_cpu::{RAM_SIZE, ROM_SIZE};
+
+use crate::debugger::Debugger;
+
+/// Base of the stack of the VM programs.
+const STACK: usize = 256;
+/// Rows of the code and stack panes.
+const ROWS: usize = 18;
+/// Width of the code pane, the stack pane is drawn on its right.
+const CODE_WIDTH: usize = 46;
+
+/// render draws the state of the debugger: the registers, the code around
+/// PC, the stack from 256 up to SP and the watched RAM words.
+pub fn render(debugger: &Debugger) -> String {
+    let cpu = &debugger.cpu;
+    let mut screen = String::new();
+
+    let m = cpu.peek(cpu.a as usize & (RAM_SIZE - 1));
+    let _ = writeln!(
+        screen,
+        " A = {:<6}  D = {:<6}  PC = {:<5}  M = {:<6}  cycles = {}{}",
+        cpu.a as i16,
+        cpu.d as i16,
+        cpu.pc,
+        m as i16,
+        cpu.cycles(),
+        if debugger.halted { "  (halted)" } else { "" }
+    );
+    let _ = writeln!(screen);
+
+    let code = code_pane(debugger);
+    let stack = stack_pane(debugger);
+    let _ = writeln!(screen, " {:<width$}stack", "code", width = CODE_WIDTH);
+    for row in 0..ROWS {
+        let left = code.get(row).map_or("", String::as_str);
+        let right = stack.get(row).map_or("", String::as_str);
+        let line = format!("{:<width$}{}", left, right, width = CODE_WIDTH + 1);
+        let _ = writeln!(screen, "{}", line.trim_end());
+    }
+
+    if !debugger.watchpoints.is_empty() {
+        let _ = writeln!(screen, "\n watch");
+        for (address, watchpoint) in &debugger.watchpoints {
+            let name = format!("{} ({})", watchpoint.name, address);
+            let _ = writeln!(screen, "   {:<12} = {}", name, cpu.peek(*address) as i16);
+        }
+    }
+    screen
+}
+
+/// code_pane lists the instructions around PC with their labels, the
+/// current instruction is marked with `>` and breakpoints with `*`.
+fn code_pane(debugger: &Debugger) -> Vec<String> {
+    let pc = debugger.cpu.pc as usize;
+    let end = debugger.program.code.len().max(pc + 1).min(ROM_SIZE);
+
+    let mut rows = Vec::new();
+    let mut current = 0;
+    let first = pc.saturating_sub(ROWS / 3);
+    for address in first..end {
+        for label in debugger.program.labels_at(address as u16) {
+            rows.push(format!("        ({})", label));
+        }
+        let marker = match (
+            address == pc,
+            debugger.breakpoints.contains(&(address as u16)),
+        ) {
+            (true, true) => ">*",
+            (true, false) => "> ",
+            (false, true) => " *",
+            (false, false) => "  ",
+        };
+        if address == pc {
+            current = rows.len();
+        }
+        let text = debugger.program.listing(address as u16);
+        rows.push(format!(" {} {:>5}  {}", marker, address, text));
+        if rows.len() >= 2 * ROWS {
+            break;
+        }
+    }
+
+    // Keep a few rows above the current instruction, labels included.
+    let start = current.saturating_sub(ROWS / 3);
+    rows.into_iter().skip(start).take(ROWS).collect()
+}
+
+/// stack_pane shows the pointers and the top of the stack, the words from
+/// 256 up to SP with the ones LCL, ARG, THIS and THAT point to marked.
+fn stack_pane(debugger: &Debugger) -> Vec<String> {
+    let cpu = &debugger.cpu;
+    let mut rows = Vec::new();
+    for (name, address) in [("SP", 0), ("LCL", 1), ("ARG", 2), ("THIS", 3), ("THAT", 4)] {
+        rows.push(format!("{:<5}{:>6}", name, cpu.peek(address) as i16));
+    }
+    rows.push(String::new());
+
+    let sp = cpu.peek(0) as usize;
+    if !(STACK..RAM_SIZE).contains(&sp) {
+        rows.push(String::from("(SP is outside of the stack)"));
+        return rows;
+    }
+
+    let room = ROWS - rows.len();
+    let first = (sp + 1).saturating_sub(room).max(STACK);
+    for address in first..=sp {
+        let mut pointers: Vec<&str> = [("LCL", 1), ("ARG", 2), ("THIS", 3), ("THAT", 4)]
+            .iter()
+            .filter(|(_, pointer)| cpu.peek(*pointer) as usize == address)
+            .map(|(name, _)| *name)
+            .collect();
+        if address == sp {
+            pointers.insert(0, "SP");
+        }
+        let value = if address == sp {
+            String::new()
+        } else {
+            (cpu.peek(address) as i16).to_string()
+        };
+        let mut row = format!("{:>5}  {:>6}", address, value);
+        if !pointers.is_empty() {
+            let _ = write!(row, "  <- {}", pointers.join(", "));
+        }
+        rows.push(row);
+    }
+    rows
+}
@@ -0,0 +1,166 @@
+//! Debugging sessions of projects/06/max/Max.asm, the commands are piped
+//! to the binary and echoed in its output.
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const MAX: &str = "../projects/06/max/Max.asm";
+
+/// Screen is what the debugger shows after a command.
+struct Screen {
+    /// The line of the registers at the top of the view.
+    registers: String,
+    /// The status line below the view.
+    message: String,
+}
+
+/// debug runs a session and returns the screens after each command, the
+/// first one is shown before any command.
+fn debug(args: &[&str], commands: &str) -> Vec<Screen> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hack_debugger"))
+        .args(args)
+        .arg(MAX)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let screens: Vec<&str> = stdout.split("(hdb) ").collect();
+    assert_eq!(screens.len(), commands.lines().count() + 2, "{}", stdout);
+    screens[..screens.len() - 1]
+        .iter()
+        .map(|screen| {
+            let registers = screen.lines().find(|line| line.starts_with(" A = "));
+            Screen {
+                registers: registers.unwrap().split_whitespace().collect(),
+                message: match screen.trim_end().rsplit_once("\n\n") {
+                    Some((_, message)) if !message.starts_with(' ') => message.to_string(),
+                    _ => String::new(),
+                },
+            }
+        })
+        .collect()
+}
+
+fn messages(screens: &[Screen]) -> Vec<&str> {
+    screens
+        .iter()
+        .map(|screen| screen.message.as_str())
+        .collect()
+}
+
+#[test]
+fn breaks_on_labels() {
+    let commands = "\
+set R0 9
+set R1 -7
+break OUTPUT_FIRST
+c
+step 4
+p R2
+";
+    let screens = debug(&[], commands);
+    assert_eq!(
+        messages(&screens),
+        [
+            "type help for the commands",
+            "RAM[0] = 9",
+            "RAM[1] = -7",
+            "breakpoint at 10",
+            "breakpoint at 10 (OUTPUT_FIRST)",
+            "",
+            "RAM[2] = 9",
+        ]
+    );
+    assert_eq!(screens[4].registers, "A=10D=16PC=10M=0cycles=6");
+    assert_eq!(screens[5].registers, "A=2D=9PC=14M=9cycles=10");
+}
+
+#[test]
+fn watches_until_the_program_halts() {
+    let commands = "\
+set R0 3
+set R1 7
+c
+c
+p R0 3
+reset
+p R2
+";
+    let screens = debug(&["-w", "R2"], commands);
+    assert_eq!(
+        messages(&screens),
+        [
+            "type help for the commands",
+            "RAM[0] = 3",
+            "RAM[1] = 7",
+            "watchpoint R2 (2): 0 -> 7",
+            "the program halted",
+            "RAM[0] = 3\nRAM[1] = 7\nRAM[2] = 7",
+            "restarted the program",
+            "RAM[2] = 0",
+        ]
+    );
+    assert_eq!(screens[3].registers, "A=2D=7PC=14M=7cycles=12");
+    assert_eq!(screens[4].registers, "A=14D=7PC=14M=0cycles=16(halted)");
+    assert_eq!(screens[6].registers, "A=0D=0PC=0M=0cycles=0");
+}
+
+#[test]
+fn repeats_steps_on_empty_lines() {
+    let screens = debug(&["-b", "2"], "step 2\n\n\ninfo\nc\n");
+    let registers: Vec<&str> = screens[1..4]
+        .iter()
+        .map(|screen| screen.registers.as_str())
+        .collect();
+    assert_eq!(
+        registers,
+        [
+            "A=0D=0PC=2M=0cycles=2",
+            "A=1D=0PC=4M=0cycles=4",
+            "A=10D=0PC=6M=0cycles=6"
+        ]
+    );
+    assert_eq!(screens[4].message, "breakpoints: 2\nwatchpoints:");
+    assert_eq!(screens[5].message, "the program halted");
+}
+
+#[test]
+fn reports_errors() {
+    let commands = "\
+break NOWHERE
+frob
+p
+step x
+set R0 70000
+";
+    let screens = debug(&[], commands);
+    assert_eq!(
+        messages(&screens[1..]),
+        [
+            "err: Unknown label: NOWHERE",
+            "err: Unknown command: frob, see help",
+            "err: p expects an argument, see help",
+            "err: Invalid count: x",
+            "err: Invalid value: 70000",
+        ]
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hack_debugger"))
+        .args(["-b", "NOWHERE", MAX])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "err: Unknown label: NOWHERE\n"
+    );
+}
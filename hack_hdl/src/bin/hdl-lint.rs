@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use hack_hdl::{Linter, Severity};
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The chips (.hdl) to check, or directories searched for them, e.g. projects/*-chips.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Exits with an error on warnings too.
+    #[arg(short = 'D', long)]
+    deny_warnings: bool,
+}
+
+/// hdl_files lists the `.hdl` files of a directory and its subdirectories,
+/// sorted by path.
+fn hdl_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(directory).map_err(|err| format!("{}: {}", directory.display(), err))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            hdl_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "hdl") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut files = Vec::new();
+    for path in &args.paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+        } else if let Err(err) = hdl_files(path, &mut files) {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    }
+
+    // Chips of the same directory share a linter, their parts are resolved once.
+    let (mut errors, mut warnings) = (0, 0);
+    let mut linter: Option<(PathBuf, Linter)> = None;
+    for file in &files {
+        let directory = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let linter = match &mut linter {
+            Some((known, linter)) if *known == directory => linter,
+            _ => {
+                let created = Linter::new(&directory);
+                &mut linter.insert((directory, created)).1
+            }
+        };
+
+        let diagnostics = match linter.lint(file) {
+            Ok(diagnostics) => diagnostics,
+            Err(err) => {
+                eprintln!("err: {err}");
+                std::process::exit(1);
+            }
+        };
+        for diagnostic in diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            println!("{}:{}", file.display(), diagnostic);
+        }
+    }
+
+    println!(
+        "{} chips checked: {} errors, {} warnings",
+        files.len(),
+        errors,
+        warnings
+    );
+    if errors > 0 || (args.deny_warnings && warnings > 0) {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use hack_hdl::{Analyzer, Library, Stats};
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The chip (.hdl) to flatten down to Nand and DFF.
+    path: PathBuf,
+
+    /// Another directory of chips used as parts, searched after the directory of the chip and before the sibling `*-chips` directories. Can be repeated.
+    #[arg(short = 'L', long = "lib")]
+    libraries: Vec<PathBuf>,
+}
+
+/// builtin_parts lists the builtin parts that are not flattened, e.g.
+/// `RAM16K x1, Screen x1`.
+fn builtin_parts(stats: &Stats) -> String {
+    let parts: Vec<String> = stats
+        .builtin
+        .iter()
+        .map(|(chip, count)| format!("{} x{}", chip, count))
+        .collect();
+    parts.join(", ")
+}
+
+/// chip_directories finds the directories of the other projects of chips,
+/// the `*-chips` directories next to the chip or to one of its parents and
+/// their subdirectories, e.g. `memory-chips/a`. The directory of the chip
+/// itself is left out.
+fn chip_directories(directory: &Path) -> Vec<PathBuf> {
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+    let directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf());
+    let subdirectories = |parent: &Path| -> Vec<PathBuf> {
+        let mut directories: Vec<PathBuf> = fs::read_dir(parent)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        directories.sort();
+        directories
+    };
+    let is_chips = |path: &Path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with("-chips"))
+    };
+
+    for parent in directory.ancestors().skip(1) {
+        let projects: Vec<PathBuf> = subdirectories(parent)
+            .into_iter()
+            .filter(|path| is_chips(path))
+            .collect();
+        if projects.is_empty() {
+            continue;
+        }
+        return projects
+            .into_iter()
+            .flat_map(|project| {
+                let mut directories = vec![project.clone()];
+                directories.extend(subdirectories(&project));
+                directories
+            })
+            .filter(|path| *path != directory)
+            .collect();
+    }
+    Vec::new()
+}
+
+fn report(args: &Args) -> Result<(), String> {
+    let directory = args.path.parent().unwrap_or(Path::new("."));
+    let mut library = Library::new(directory);
+    for other in args.libraries.iter().chain(&chip_directories(directory)) {
+        library.add_directory(other);
+    }
+    let chip = Library::load(&args.path)?;
+
+    let mut analyzer = Analyzer::new(library);
+    let stats = analyzer.analyze(&chip)?;
+    let breakdown = analyzer.breakdown(&chip)?;
+
+    println!("{}", chip.name());
+    println!("  Nand   {:>8}", stats.nand);
+    println!("  DFF    {:>8}", stats.dff);
+    println!(
+        "  depth  {:>8} Nand on the longest combinational path",
+        stats.depth()
+    );
+    if !stats.builtin.is_empty() {
+        println!(
+            "  builtin parts not flattened, counted as no Nand and no depth: {}",
+            builtin_parts(&stats)
+        );
+    }
+
+    if !breakdown.is_empty() {
+        println!();
+        println!(
+            "  {:<12} {:>5} {:>8} {:>8} {:>6}",
+            "part", "count", "Nand", "DFF", "depth"
+        );
+        for part in &breakdown {
+            println!(
+                "  {:<12} {:>5} {:>8} {:>8} {:>6}",
+                part.chip,
+                part.count,
+                part.stats.nand * part.count,
+                part.stats.dff * part.count,
+                part.stats.depth()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(err) = report(&args) {
+        eprintln!("err: {err}");
+        std::process::exit(1);
+    }
+}
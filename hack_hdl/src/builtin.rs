@@ -0,0 +1,344 @@
+/// Builtin is a chip implemented in Rust. Nand and DFF are the primitives
+/// every chip is built from, the others stand in for the chips of the
+/// course when no `.hdl` file implements them, like the builtin chips of
+/// the Java hardware simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Nand,
+    Dff,
+    Not,
+    And,
+    Or,
+    Xor,
+    Mux,
+    DMux,
+    Not16,
+    And16,
+    Or16,
+    Mux16,
+    Or8Way,
+    Mux4Way16,
+    Mux8Way16,
+    DMux4Way,
+    DMux8Way,
+    HalfAdder,
+    FullAdder,
+    Add16,
+    Inc16,
+    Alu,
+    Bit,
+    Register,
+    ARegister,
+    DRegister,
+    Pc,
+    /// RAM8 to RAM16K, with the width of the address.
+    Ram(u16),
+    Screen,
+    Keyboard,
+    Rom32K,
+}
+
+/// Pins is a list of pin names with their width.
+type Pins = &'static [(&'static str, u16)];
+
+const A_B: Pins = &[("a", 1), ("b", 1)];
+const A_B_16: Pins = &[("a", 16), ("b", 16)];
+const OUT: Pins = &[("out", 1)];
+const OUT_16: Pins = &[("out", 16)];
+const REGISTER: Pins = &[("in", 16), ("load", 1)];
+const SUM_CARRY: Pins = &[("sum", 1), ("carry", 1)];
+
+const ALL: [Builtin; 35] = [
+    Builtin::Nand,
+    Builtin::Dff,
+    Builtin::Not,
+    Builtin::And,
+    Builtin::Or,
+    Builtin::Xor,
+    Builtin::Mux,
+    Builtin::DMux,
+    Builtin::Not16,
+    Builtin::And16,
+    Builtin::Or16,
+    Builtin::Mux16,
+    Builtin::Or8Way,
+    Builtin::Mux4Way16,
+    Builtin::Mux8Way16,
+    Builtin::DMux4Way,
+    Builtin::DMux8Way,
+    Builtin::HalfAdder,
+    Builtin::FullAdder,
+    Builtin::Add16,
+    Builtin::Inc16,
+    Builtin::Alu,
+    Builtin::Bit,
+    Builtin::Register,
+    Builtin::ARegister,
+    Builtin::DRegister,
+    Builtin::Pc,
+    Builtin::Ram(3),
+    Builtin::Ram(6),
+    Builtin::Ram(9),
+    Builtin::Ram(12),
+    Builtin::Ram(14),
+    Builtin::Screen,
+    Builtin::Keyboard,
+    Builtin::Rom32K,
+];
+
+impl Builtin {
+    /// find returns the builtin chip with the name.
+    pub fn find(name: &str) -> Option<Self> {
+        ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Nand => "Nand",
+            Builtin::Dff => "DFF",
+            Builtin::Not => "Not",
+            Builtin::And => "And",
+            Builtin::Or => "Or",
+            Builtin::Xor => "Xor",
+            Builtin::Mux => "Mux",
+            Builtin::DMux => "DMux",
+            Builtin::Not16 => "Not16",
+            Builtin::And16 => "And16",
+            Builtin::Or16 => "Or16",
+            Builtin::Mux16 => "Mux16",
+            Builtin::Or8Way => "Or8Way",
+            Builtin::Mux4Way16 => "Mux4Way16",
+            Builtin::Mux8Way16 => "Mux8Way16",
+            Builtin::DMux4Way => "DMux4Way",
+            Builtin::DMux8Way => "DMux8Way",
+            Builtin::HalfAdder => "HalfAdder",
+            Builtin::FullAdder => "FullAdder",
+            Builtin::Add16 => "Add16",
+            Builtin::Inc16 => "Inc16",
+            Builtin::Alu => "ALU",
+            Builtin::Bit => "Bit",
+            Builtin::Register => "Register",
+            Builtin::ARegister => "ARegister",
+            Builtin::DRegister => "DRegister",
+            Builtin::Pc => "PC",
+            Builtin::Ram(3) => "RAM8",
+            Builtin::Ram(6) => "RAM64",
+            Builtin::Ram(9) => "RAM512",
+            Builtin::Ram(12) => "RAM4K",
+            Builtin::Ram(_) => "RAM16K",
+            Builtin::Screen => "Screen",
+            Builtin::Keyboard => "Keyboard",
+            Builtin::Rom32K => "ROM32K",
+        }
+    }
+
+    pub fn inputs(self) -> Pins {
+        match self {
+            Builtin::Nand | Builtin::And | Builtin::Or | Builtin::Xor => A_B,
+            Builtin::Dff | Builtin::Not => &[("in", 1)],
+            Builtin::Mux => &[("a", 1), ("b", 1), ("sel", 1)],
+            Builtin::DMux => &[("in", 1), ("sel", 1)],
+            Builtin::Not16 | Builtin::Inc16 => &[("in", 16)],
+            Builtin::And16 | Builtin::Or16 | Builtin::Add16 => A_B_16,
+            Builtin::Mux16 => &[("a", 16), ("b", 16), ("sel", 1)],
+            Builtin::Or8Way => &[("in", 8)],
+            Builtin::Mux4Way16 => &[("a", 16), ("b", 16), ("c", 16), ("d", 16), ("sel", 2)],
+            Builtin::Mux8Way16 => &[
+                ("a", 16),
+                ("b", 16),
+                ("c", 16),
+                ("d", 16),
+                ("e", 16),
+                ("f", 16),
+                ("g", 16),
+                ("h", 16),
+                ("sel", 3),
+            ],
+            Builtin::DMux4Way => &[("in", 1), ("sel", 2)],
+            Builtin::DMux8Way => &[("in", 1), ("sel", 3)],
+            Builtin::HalfAdder => A_B,
+            Builtin::FullAdder => &[("a", 1), ("b", 1), ("c", 1)],
+            Builtin::Alu => &[
+                ("x", 16),
+                ("y", 16),
+                ("zx", 1),
+                ("nx", 1),
+                ("zy", 1),
+                ("ny", 1),
+                ("f", 1),
+                ("no", 1),
+            ],
+            Builtin::Bit => &[("in", 1), ("load", 1)],
+            Builtin::Register | Builtin::ARegister | Builtin::DRegister => REGISTER,
+            Builtin::Pc => &[("in", 16), ("load", 1), ("inc", 1), ("reset", 1)],
+            Builtin::Ram(3) => &[("in", 16), ("load", 1), ("address", 3)],
+            Builtin::Ram(6) => &[("in", 16), ("load", 1), ("address", 6)],
+            Builtin::Ram(9) => &[("in", 16), ("load", 1), ("address", 9)],
+            Builtin::Ram(12) => &[("in", 16), ("load", 1), ("address", 12)],
+            Builtin::Ram(_) => &[("in", 16), ("load", 1), ("address", 14)],
+            Builtin::Screen => &[("in", 16), ("load", 1), ("address", 13)],
+            Builtin::Keyboard => &[],
+            Builtin::Rom32K => &[("address", 15)],
+        }
+    }
+
+    pub fn outputs(self) -> Pins {
+        match self {
+            Builtin::DMux => A_B,
+            Builtin::DMux4Way => &[("a", 1), ("b", 1), ("c", 1), ("d", 1)],
+            Builtin::DMux8Way => &[
+                ("a", 1),
+                ("b", 1),
+                ("c", 1),
+                ("d", 1),
+                ("e", 1),
+                ("f", 1),
+                ("g", 1),
+                ("h", 1),
+            ],
+            Builtin::HalfAdder | Builtin::FullAdder => SUM_CARRY,
+            Builtin::Alu => &[("out", 16), ("zr", 1), ("ng", 1)],
+            Builtin::Nand
+            | Builtin::Dff
+            | Builtin::Not
+            | Builtin::And
+            | Builtin::Or
+            | Builtin::Xor
+            | Builtin::Mux
+            | Builtin::Or8Way
+            | Builtin::Bit => OUT,
+            _ => OUT_16,
+        }
+    }
+
+    /// is_clocked tells if an input is only sampled on the clock, the
+    /// outputs don't depend on it until the next cycle.
+    pub fn is_clocked(self, input: &str) -> bool {
+        match self {
+            Builtin::Dff => input == "in",
+            Builtin::Bit
+            | Builtin::Register
+            | Builtin::ARegister
+            | Builtin::DRegister
+            | Builtin::Ram(_)
+            | Builtin::Screen => input == "in" || input == "load",
+            Builtin::Pc => true,
+            _ => false,
+        }
+    }
+
+    /// memory_size is the number of 16-bit words of state of the chip.
+    pub fn memory_size(self) -> usize {
+        match self {
+            Builtin::Dff
+            | Builtin::Bit
+            | Builtin::Register
+            | Builtin::ARegister
+            | Builtin::DRegister
+            | Builtin::Pc
+            | Builtin::Keyboard => 1,
+            Builtin::Ram(address_width) => 1 << address_width,
+            Builtin::Screen => 8192,
+            Builtin::Rom32K => 32768,
+            _ => 0,
+        }
+    }
+
+    /// eval computes the outputs from the inputs and the memory, the values
+    /// are in the order of inputs() and outputs().
+    pub fn eval(self, inputs: &[u16], memory: &[u16], outputs: &mut [u16]) {
+        let bit = |value: bool| value as u16;
+        let input = |index: usize| inputs[index];
+        match self {
+            Builtin::Nand => outputs[0] = bit(input(0) & input(1) == 0),
+            Builtin::Not => outputs[0] = bit(input(0) == 0),
+            Builtin::And => outputs[0] = input(0) & input(1),
+            Builtin::Or => outputs[0] = input(0) | input(1),
+            Builtin::Xor => outputs[0] = input(0) ^ input(1),
+            Builtin::Mux | Builtin::Mux16 => {
+                outputs[0] = if input(2) == 0 { input(0) } else { input(1) }
+            }
+            Builtin::DMux | Builtin::DMux4Way | Builtin::DMux8Way => {
+                for (index, output) in outputs.iter_mut().enumerate() {
+                    *output = input(0) * bit(index == input(1) as usize);
+                }
+            }
+            Builtin::Not16 => outputs[0] = !input(0),
+            Builtin::And16 => outputs[0] = input(0) & input(1),
+            Builtin::Or16 => outputs[0] = input(0) | input(1),
+            Builtin::Or8Way => outputs[0] = bit(input(0) != 0),
+            Builtin::Mux4Way16 => outputs[0] = input(input(4) as usize),
+            Builtin::Mux8Way16 => outputs[0] = input(input(8) as usize),
+            Builtin::HalfAdder | Builtin::FullAdder => {
+                let sum: u16 = inputs.iter().sum();
+                outputs[0] = sum & 1;
+                outputs[1] = sum >> 1;
+            }
+            Builtin::Add16 => outputs[0] = input(0).wrapping_add(input(1)),
+            Builtin::Inc16 => outputs[0] = input(0).wrapping_add(1),
+            Builtin::Alu => {
+                let mut x = if input(2) != 0 { 0 } else { input(0) };
+                if input(3) != 0 {
+                    x = !x;
+                }
+                let mut y = if input(4) != 0 { 0 } else { input(1) };
+                if input(5) != 0 {
+                    y = !y;
+                }
+                let mut out = if input(6) != 0 {
+                    x.wrapping_add(y)
+                } else {
+                    x & y
+                };
+                if input(7) != 0 {
+                    out = !out;
+                }
+                outputs[0] = out;
+                outputs[1] = bit(out == 0);
+                outputs[2] = bit(out & 0x8000 != 0);
+            }
+            Builtin::Dff
+            | Builtin::Bit
+            | Builtin::Register
+            | Builtin::ARegister
+            | Builtin::DRegister
+            | Builtin::Pc
+            | Builtin::Keyboard => outputs[0] = memory[0],
+            Builtin::Ram(_) | Builtin::Screen => outputs[0] = memory[input(2) as usize],
+            Builtin::Rom32K => outputs[0] = memory[input(0) as usize],
+        }
+    }
+
+    /// clock samples the inputs when the clock goes up, it returns the
+    /// write to the memory. The outputs only show it once re-evaluated.
+    pub fn clock(self, inputs: &[u16], memory: &[u16]) -> Option<(usize, u16)> {
+        let input = |index: usize| inputs[index];
+        match self {
+            Builtin::Dff => Some((0, input(0))),
+            Builtin::Bit | Builtin::Register | Builtin::ARegister | Builtin::DRegister => {
+                (input(1) != 0).then_some((0, input(0)))
+            }
+            Builtin::Pc => {
+                let next = if input(3) != 0 {
+                    0
+                } else if input(1) != 0 {
+                    input(0)
+                } else if input(2) != 0 {
+                    memory[0].wrapping_add(1)
+                } else {
+                    return None;
+                };
+                Some((0, next))
+            }
+            Builtin::Ram(_) | Builtin::Screen => {
+                (input(1) != 0).then_some((input(2) as usize, input(0)))
+            }
+            _ => None,
+        }
+    }
+
+    /// is_clocked_chip tells if the chip has state that changes on the clock.
+    pub fn is_clocked_chip(self) -> bool {
+        self.inputs().iter().any(|(name, _)| self.is_clocked(name))
+    }
+}
@@ -0,0 +1,15 @@
+//! Parser and gate-level simulator of the HDL of the Hack hardware, the
+//! chips are flattened into builtin parts like Nand and DFF.
+
+mod builtin;
+mod library;
+mod lint;
+pub mod parser;
+mod simulator;
+mod stats;
+
+pub use builtin::Builtin;
+pub use library::{Chip, Library};
+pub use lint::{Diagnostic, Linter, Severity};
+pub use simulator::{Pin, PinKind, Simulator};
+pub use stats::{Analyzer, PartStats, Stats};
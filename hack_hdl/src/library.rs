@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::builtin::Builtin;
+use crate::parser::{self, Body, ChipDef};
+
+/// Chip is a resolved chip, defined by an `.hdl` file or builtin.
+#[derive(Debug, Clone)]
+pub enum Chip {
+    Hdl(Rc<ChipDef>),
+    Builtin(Builtin),
+}
+
+impl Chip {
+    pub fn name(&self) -> &str {
+        match self {
+            Chip::Hdl(def) => &def.name,
+            Chip::Builtin(builtin) => builtin.name(),
+        }
+    }
+
+    /// inputs lists the input pins with their width.
+    pub fn inputs(&self) -> Vec<(&str, u16)> {
+        match self {
+            Chip::Hdl(def) => def
+                .inputs
+                .iter()
+                .map(|pin| (pin.name.as_str(), pin.width))
+                .collect(),
+            Chip::Builtin(builtin) => builtin.inputs().to_vec(),
+        }
+    }
+
+    /// outputs lists the output pins with their width.
+    pub fn outputs(&self) -> Vec<(&str, u16)> {
+        match self {
+            Chip::Hdl(def) => def
+                .outputs
+                .iter()
+                .map(|pin| (pin.name.as_str(), pin.width))
+                .collect(),
+            Chip::Builtin(builtin) => builtin.outputs().to_vec(),
+        }
+    }
+}
+
+/// Library resolves the chips used as parts. Like the hardware simulator of
+/// the course it looks for `Name.hdl` in the directory of the chip being
+/// loaded first, then in the added directories, and falls back to the
+/// builtin chips.
+pub struct Library {
+    directory: PathBuf,
+    others: Vec<PathBuf>,
+    chips: HashMap<String, Chip>,
+    files: HashMap<String, PathBuf>,
+}
+
+impl Library {
+    pub fn new(directory: &Path) -> Self {
+        Library {
+            directory: directory.to_path_buf(),
+            others: Vec::new(),
+            chips: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// add_directory searches another directory for the chips that are not
+    /// in the directory of the library, e.g. the elementary chips.
+    pub fn add_directory(&mut self, directory: &Path) {
+        self.others.push(directory.to_path_buf());
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// load parses an `.hdl` file, a BUILTIN definition resolves to the
+    /// builtin chip of the same name.
+    pub fn load(path: &Path) -> Result<Chip, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let def = parser::parse(&content).map_err(|err| format!("{}:{}", path.display(), err))?;
+        if path
+            .file_stem()
+            .is_some_and(|stem| stem != def.name.as_str())
+        {
+            return Err(format!(
+                "{}:{}: chip {} must be defined in {}.hdl",
+                path.display(),
+                def.span,
+                def.name,
+                def.name
+            ));
+        }
+        match &def.body {
+            Body::Builtin { name, .. } => Builtin::find(name)
+                .map(Chip::Builtin)
+                .ok_or_else(|| format!("{}: unknown builtin chip {}", path.display(), name)),
+            Body::Parts(_) => Ok(Chip::Hdl(Rc::new(def))),
+        }
+    }
+
+    /// file is the `.hdl` file a chip was resolved from, a chip not resolved
+    /// yet is assumed to be in the directory of the library.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.files
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| self.directory.join(format!("{}.hdl", name)))
+    }
+
+    /// resolve returns the chip of a part, see Library.
+    pub fn resolve(&mut self, name: &str) -> Result<Chip, String> {
+        if let Some(chip) = self.chips.get(name) {
+            return Ok(chip.clone());
+        }
+
+        let file = format!("{}.hdl", name);
+        let path = self.directory.join(&file);
+        let found = std::iter::once(&self.directory)
+            .chain(&self.others)
+            .map(|directory| directory.join(&file))
+            .find(|path| path.is_file());
+        let chip = if let Some(found) = found {
+            let chip = Self::load(&found)?;
+            self.files.insert(name.to_string(), found);
+            chip
+        } else {
+            Builtin::find(name).map(Chip::Builtin).ok_or_else(|| {
+                format!(
+                    "Chip {} not found: no {} and no builtin chip",
+                    name,
+                    path.display()
+                )
+            })?
+        };
+        self.chips.insert(name.to_string(), chip.clone());
+        Ok(chip)
+    }
+}
@@ -0,0 +1,653 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::builtin::Builtin;
+use crate::library::{Chip, Library};
+use crate::parser::{self, Body, ChipDef, Part, Span, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A mistake the simulator refuses.
+    Error,
+    /// A chip that simulates but is likely wrong.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Diagnostic is an issue found in an `.hdl` file, displayed like
+/// `7:13: error: undefined pin x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(span: Span, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            span,
+            message,
+        }
+    }
+
+    fn warning(span: Span, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.span, self.severity, self.message)
+    }
+}
+
+/// InternalPin is a pin connecting the parts of a chip, declared by the
+/// part output that drives it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InternalPin {
+    pub name: String,
+    pub width: u16,
+    pub span: Span,
+}
+
+/// check_parts checks the connections of the parts of a chip, chips holds
+/// the chip of every part, None when it can't be resolved. It returns the
+/// internal pins with the issues found.
+pub(crate) fn check_parts(
+    def: &ChipDef,
+    parts: &[Part],
+    chips: &[Option<Chip>],
+) -> (Vec<InternalPin>, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let input_width = |name: &str| {
+        def.inputs
+            .iter()
+            .find(|pin| pin.name == name)
+            .map(|pin| pin.width)
+    };
+    let output_width = |name: &str| {
+        def.outputs
+            .iter()
+            .find(|pin| pin.name == name)
+            .map(|pin| pin.width)
+    };
+
+    // The width of an internal pin is the width of the part output that
+    // drives it. The pins connected to unknown chips can't be checked.
+    let mut internal: Vec<InternalPin> = Vec::new();
+    let mut unchecked = HashSet::new();
+    for (part, chip) in parts.iter().zip(chips) {
+        for connection in &part.connections {
+            let Value::Pin(value) = &connection.value else {
+                continue;
+            };
+            let Some(chip) = chip else {
+                unchecked.insert(value.name.as_str());
+                continue;
+            };
+            if input_width(&value.name).is_some() || output_width(&value.name).is_some() {
+                continue;
+            }
+            let output = chip
+                .outputs()
+                .into_iter()
+                .find(|(name, _)| *name == connection.pin.name);
+            let Some((_, width)) = output else {
+                continue;
+            };
+            if value.range.is_some() {
+                diagnostics.push(Diagnostic::error(
+                    value.span,
+                    format!("internal pin {} can't be subscripted", value.name),
+                ));
+                continue;
+            }
+
+            let width = connection.pin.width().unwrap_or(width);
+            match internal.iter().find(|pin| pin.name == value.name) {
+                Some(pin) if pin.width != width => diagnostics.push(Diagnostic::error(
+                    value.span,
+                    format!(
+                        "internal pin {} is {} bits here and {} bits at {}",
+                        value.name, width, pin.width, pin.span
+                    ),
+                )),
+                Some(_) => {}
+                None => internal.push(InternalPin {
+                    name: value.name.clone(),
+                    width,
+                    span: value.span,
+                }),
+            }
+        }
+    }
+    let pin_width = |name: &str| {
+        input_width(name).or(output_width(name)).or_else(|| {
+            internal
+                .iter()
+                .find(|pin| pin.name == name)
+                .map(|pin| pin.width)
+        })
+    };
+
+    // Bits of the outputs and internal pins driven by a part, and the
+    // internal pins read by one.
+    let mut driven: HashMap<&str, Vec<bool>> = HashMap::new();
+    let mut read = HashSet::new();
+    for (part, chip) in parts.iter().zip(chips) {
+        let Some(chip) = chip else {
+            continue;
+        };
+        let inputs = chip.inputs();
+        let outputs = chip.outputs();
+        let mut connected: HashMap<&str, Vec<bool>> = inputs
+            .iter()
+            .map(|(name, width)| (*name, vec![false; *width as usize]))
+            .collect();
+
+        for connection in &part.connections {
+            let pin = &connection.pin;
+            let input = inputs.iter().find(|(name, _)| *name == pin.name);
+            let output = outputs.iter().find(|(name, _)| *name == pin.name);
+            let (drives, width) = match (input, output) {
+                (Some((_, width)), _) => (false, *width),
+                (_, Some((_, width))) => (true, *width),
+                _ => {
+                    diagnostics.push(Diagnostic::error(
+                        pin.span,
+                        format!("{} has no pin {}", part.chip, pin.name),
+                    ));
+                    continue;
+                }
+            };
+            let (first, last) = pin.range.unwrap_or((0, width - 1));
+            if last >= width {
+                diagnostics.push(Diagnostic::error(
+                    pin.span,
+                    format!("{} is out of the {} bits of {}", pin, width, pin.name),
+                ));
+                continue;
+            }
+
+            if !drives {
+                let seen = connected.get_mut(pin.name.as_str()).expect("input pin");
+                if seen[first as usize..=last as usize].contains(&true) {
+                    diagnostics.push(Diagnostic::error(
+                        pin.span,
+                        format!("{} is connected twice", pin),
+                    ));
+                    continue;
+                }
+                seen[first as usize..=last as usize].fill(true);
+            }
+
+            let value = match &connection.value {
+                Value::Constant(_, span) if drives => {
+                    diagnostics.push(Diagnostic::error(
+                        *span,
+                        format!("output pin {} can't be connected to a constant", pin),
+                    ));
+                    continue;
+                }
+                Value::Constant(..) => continue,
+                Value::Pin(value) => value,
+            };
+
+            let Some(value_width) = pin_width(&value.name) else {
+                if !unchecked.contains(value.name.as_str()) {
+                    diagnostics.push(Diagnostic::error(
+                        value.span,
+                        format!("undefined pin {}", value.name),
+                    ));
+                }
+                continue;
+            };
+            let is_internal =
+                input_width(&value.name).is_none() && output_width(&value.name).is_none();
+            if is_internal && value.range.is_some() && !drives {
+                diagnostics.push(Diagnostic::error(
+                    value.span,
+                    format!("internal pin {} can't be subscripted", value.name),
+                ));
+                read.insert(value.name.as_str());
+                continue;
+            }
+            let (value_first, value_last) = value.range.unwrap_or((0, value_width - 1));
+            if value_last >= value_width {
+                diagnostics.push(Diagnostic::error(
+                    value.span,
+                    format!(
+                        "{} is out of the {} bits of {}",
+                        value, value_width, value.name
+                    ),
+                ));
+                continue;
+            }
+            // The pins are connected anyway, a mismatch is reported once
+            // and not as unconnected pins too.
+            if value_last - value_first != last - first {
+                diagnostics.push(Diagnostic::error(
+                    pin.span,
+                    format!(
+                        "width mismatch: {}.{} is {} bits, {} {} is {} bits",
+                        part.chip,
+                        pin,
+                        last - first + 1,
+                        if is_internal {
+                            "internal pin"
+                        } else {
+                            "chip pin"
+                        },
+                        value,
+                        value_last - value_first + 1
+                    ),
+                ));
+            }
+
+            if !drives {
+                if output_width(&value.name).is_some() {
+                    diagnostics.push(Diagnostic::error(
+                        value.span,
+                        format!("output pin {} can't be read inside the chip", value.name),
+                    ));
+                }
+                read.insert(value.name.as_str());
+            } else if input_width(&value.name).is_some() {
+                diagnostics.push(Diagnostic::error(
+                    value.span,
+                    format!("input pin {} can't be driven by a part", value.name),
+                ));
+            } else {
+                let bits = driven
+                    .entry(value.name.as_str())
+                    .or_insert_with(|| vec![false; value_width as usize]);
+                let bits = &mut bits[value_first as usize..=value_last as usize];
+                if bits.contains(&true) {
+                    diagnostics.push(Diagnostic::error(
+                        value.span,
+                        format!("{} has more than one driver", value),
+                    ));
+                }
+                bits.fill(true);
+            }
+        }
+    }
+
+    for pin in &def.outputs {
+        // The pins connected to unknown chips may be driven by them.
+        if unchecked.contains(pin.name.as_str()) {
+            continue;
+        }
+        let bits = driven.get(pin.name.as_str());
+        let undriven: Vec<usize> = (0..pin.width as usize)
+            .filter(|bit| !bits.is_some_and(|bits| bits[*bit]))
+            .collect();
+        let message = match undriven[..] {
+            [] => continue,
+            _ if undriven.len() == pin.width as usize => {
+                format!("output pin {} is not connected", pin.name)
+            }
+            [bit] => format!("bit {} of output pin {} is not driven", bit, pin.name),
+            _ => format!(
+                "bits {} of output pin {} are not driven",
+                bit_ranges(&undriven),
+                pin.name
+            ),
+        };
+        diagnostics.push(Diagnostic::warning(pin.span, message));
+    }
+    for pin in &internal {
+        if !read.contains(pin.name.as_str()) {
+            diagnostics.push(Diagnostic::warning(
+                pin.span,
+                format!("internal pin {} is not used", pin.name),
+            ));
+        }
+    }
+
+    (internal, diagnostics)
+}
+
+/// bit_ranges writes a sorted list of bits as ranges, e.g. `0..3, 8`.
+fn bit_ranges(bits: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for bit in bits {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == *bit => *last = *bit,
+            _ => ranges.push((*bit, *bit)),
+        }
+    }
+    let ranges: Vec<String> = ranges
+        .iter()
+        .map(|(first, last)| match first == last {
+            true => first.to_string(),
+            false => format!("{}..{}", first, last),
+        })
+        .collect();
+    ranges.join(", ")
+}
+
+/// Summary tells for every output of a chip the inputs it depends on
+/// without a clocked part in between.
+type Summary = HashMap<String, HashSet<String>>;
+
+/// Node of the dependency graph of a chip, an output of one of its parts.
+type Node = (usize, String);
+
+/// Graph links every part output to the part outputs it feeds through
+/// an internal pin, the edges are labeled with the pin.
+struct Graph {
+    edges: HashMap<Node, Vec<(Node, String)>>,
+    /// The part outputs depending on the value of each pin.
+    inputs: HashMap<String, Vec<Node>>,
+    /// The chip outputs driven by each part output.
+    outputs: HashMap<Node, Vec<String>>,
+}
+
+/// Linter checks the chips of a directory, their parts are resolved like
+/// the simulator does, see Library.
+pub struct Linter {
+    library: Library,
+    summaries: HashMap<String, Summary>,
+    /// Chips whose summary is being computed, to stop at a chip that
+    /// contains itself.
+    stack: Vec<String>,
+}
+
+impl Linter {
+    pub fn new(directory: &Path) -> Self {
+        Linter {
+            library: Library::new(directory),
+            summaries: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// lint checks an `.hdl` file of the directory, the diagnostics are
+    /// sorted by position.
+    pub fn lint(&mut self, path: &Path) -> Result<Vec<Diagnostic>, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let def = match parser::parse(&content) {
+            Ok(def) => def,
+            Err(err) => return Ok(vec![Diagnostic::error(err.span, err.message)]),
+        };
+
+        let mut diagnostics = Vec::new();
+        if path
+            .file_stem()
+            .is_some_and(|stem| stem != def.name.as_str())
+        {
+            diagnostics.push(Diagnostic::error(
+                def.span,
+                format!("chip {} must be defined in {}.hdl", def.name, def.name),
+            ));
+        }
+        let parts = match &def.body {
+            Body::Parts(parts) => parts,
+            Body::Builtin { name, .. } => {
+                if Builtin::find(name).is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        def.span,
+                        format!("unknown builtin chip {}", name),
+                    ));
+                }
+                return Ok(diagnostics);
+            }
+        };
+
+        let mut chips = Vec::new();
+        for part in parts {
+            let chip = if part.chip == def.name {
+                Err(format!("{} contains itself", part.chip))
+            } else {
+                self.library.resolve(&part.chip)
+            };
+            match chip {
+                Ok(chip) => chips.push(Some(chip)),
+                Err(err) => {
+                    diagnostics.push(Diagnostic::error(part.span, err));
+                    chips.push(None);
+                }
+            }
+        }
+
+        let (_, issues) = check_parts(&def, parts, &chips);
+        diagnostics.extend(issues);
+        self.stack.push(def.name.clone());
+        let graph = self.graph(parts, &chips);
+        self.stack.pop();
+        diagnostics.extend(find_loops(&graph, parts));
+
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.span, diagnostic.severity));
+        Ok(diagnostics)
+    }
+
+    /// summary finds the combinational paths from the inputs to the
+    /// outputs of a chip.
+    fn summary(&mut self, chip: &Chip) -> Summary {
+        let def = match chip {
+            Chip::Builtin(builtin) => {
+                let inputs: HashSet<String> = builtin
+                    .inputs()
+                    .iter()
+                    .filter(|(name, _)| !builtin.is_clocked(name))
+                    .map(|(name, _)| name.to_string())
+                    .collect();
+                return builtin
+                    .outputs()
+                    .iter()
+                    .map(|(name, _)| (name.to_string(), inputs.clone()))
+                    .collect();
+            }
+            Chip::Hdl(def) => def,
+        };
+        if let Some(summary) = self.summaries.get(&def.name) {
+            return summary.clone();
+        }
+        if self.stack.contains(&def.name) {
+            return Summary::new();
+        }
+        let Body::Parts(parts) = &def.body else {
+            return Summary::new();
+        };
+
+        let chips: Vec<Option<Chip>> = parts
+            .iter()
+            .map(|part| self.library.resolve(&part.chip).ok())
+            .collect();
+        self.stack.push(def.name.clone());
+        let graph = self.graph(parts, &chips);
+        self.stack.pop();
+
+        let mut summary = Summary::new();
+        for input in &def.inputs {
+            let Some(starts) = graph.inputs.get(&input.name) else {
+                continue;
+            };
+            let mut seen: HashSet<&Node> = HashSet::new();
+            let mut pending: Vec<&Node> = starts.iter().collect();
+            while let Some(node) = pending.pop() {
+                if !seen.insert(node) {
+                    continue;
+                }
+                for output in graph.outputs.get(node).into_iter().flatten() {
+                    summary
+                        .entry(output.clone())
+                        .or_default()
+                        .insert(input.name.clone());
+                }
+                pending.extend(
+                    graph
+                        .edges
+                        .get(node)
+                        .into_iter()
+                        .flatten()
+                        .map(|(next, _)| next),
+                );
+            }
+        }
+        self.summaries.insert(def.name.clone(), summary.clone());
+        summary
+    }
+
+    /// graph links the outputs of the parts of a chip, see Graph.
+    fn graph(&mut self, parts: &[Part], chips: &[Option<Chip>]) -> Graph {
+        let summaries: Vec<Option<Summary>> = chips
+            .iter()
+            .map(|chip| chip.as_ref().map(|chip| self.summary(chip)))
+            .collect();
+
+        // The part outputs depending on the value of each pin read by a part.
+        let mut readers: HashMap<&str, Vec<Node>> = HashMap::new();
+        for (index, (part, summary)) in parts.iter().zip(&summaries).enumerate() {
+            let Some(summary) = summary else {
+                continue;
+            };
+            for connection in &part.connections {
+                let Value::Pin(value) = &connection.value else {
+                    continue;
+                };
+                for (output, inputs) in summary {
+                    if inputs.contains(&connection.pin.name) {
+                        readers
+                            .entry(value.name.as_str())
+                            .or_default()
+                            .push((index, output.clone()));
+                    }
+                }
+            }
+        }
+
+        let mut graph = Graph {
+            edges: HashMap::new(),
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+        };
+        for (index, (part, chip)) in parts.iter().zip(chips).enumerate() {
+            let Some(chip) = chip else {
+                continue;
+            };
+            let outputs = chip.outputs();
+            for connection in &part.connections {
+                let Value::Pin(value) = &connection.value else {
+                    continue;
+                };
+                if !outputs.iter().any(|(name, _)| *name == connection.pin.name) {
+                    continue;
+                }
+                let node = (index, connection.pin.name.clone());
+                graph
+                    .outputs
+                    .entry(node.clone())
+                    .or_default()
+                    .push(value.name.clone());
+                for reader in readers.get(value.name.as_str()).into_iter().flatten() {
+                    graph
+                        .edges
+                        .entry(node.clone())
+                        .or_default()
+                        .push((reader.clone(), value.name.clone()));
+                }
+            }
+        }
+        for (name, nodes) in readers {
+            graph.inputs.insert(name.to_string(), nodes);
+        }
+        graph
+    }
+}
+
+/// find_loops reports the combinational loops of a chip, one per group of
+/// part outputs that depend on each other.
+fn find_loops(graph: &Graph, parts: &[Part]) -> Vec<Diagnostic> {
+    let mut nodes: Vec<&Node> = graph.edges.keys().collect();
+    nodes.sort();
+
+    let mut diagnostics = Vec::new();
+    let mut reported: HashSet<&Node> = HashSet::new();
+    for start in nodes {
+        if reported.contains(start) {
+            continue;
+        }
+        let Some(cycle) = shortest_cycle(graph, start) else {
+            continue;
+        };
+
+        let mut message = format!("combinational loop: {}", parts[start.0].chip);
+        for (node, pin) in &cycle {
+            message.push_str(&format!(" -> {} -> {}", pin, parts[node.0].chip));
+        }
+        // Every node reaching back to start is part of the same loop.
+        reported.insert(start);
+        for node in graph.edges.keys() {
+            if shortest_path(graph, start, node).is_some()
+                && shortest_path(graph, node, start).is_some()
+            {
+                reported.insert(node);
+            }
+        }
+        diagnostics.push(Diagnostic::error(parts[start.0].span, message));
+    }
+    diagnostics
+}
+
+/// shortest_cycle finds the shortest path from a node back to itself.
+fn shortest_cycle(graph: &Graph, start: &Node) -> Option<Vec<(Node, String)>> {
+    graph
+        .edges
+        .get(start)
+        .into_iter()
+        .flatten()
+        .filter_map(|(next, pin)| {
+            let mut path = vec![(next.clone(), pin.clone())];
+            if next != start {
+                path.extend(shortest_path(graph, next, start)?);
+            }
+            Some(path)
+        })
+        .min_by_key(Vec::len)
+}
+
+/// shortest_path finds the edges from a node to another, by breadth first
+/// search.
+fn shortest_path(graph: &Graph, from: &Node, to: &Node) -> Option<Vec<(Node, String)>> {
+    let mut previous: HashMap<&Node, (&Node, &str)> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        if node == to {
+            let mut path = Vec::new();
+            let mut node = to;
+            while node != from {
+                let (before, pin) = previous[node];
+                path.push((node.clone(), pin.to_string()));
+                node = before;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (next, pin) in graph.edges.get(node).into_iter().flatten() {
+            if next != from && !previous.contains_key(next) {
+                previous.insert(next, (node, pin));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use hack_hdl::{PinKind, Simulator};
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The chip (.hdl) to simulate, its parts are looked up in the same directory.
+    path: PathBuf,
+
+    /// Sets an input pin, as PIN=VALUE (e.g. sel=1). Can be repeated.
+    #[arg(short, long, value_parser = parse_assignment)]
+    set: Vec<(String, u16)>,
+
+    /// Number of clock cycles to run after setting the inputs.
+    #[arg(short, long, default_value_t = 0)]
+    ticks: u64,
+
+    /// Prints the internal pins too.
+    #[arg(short, long)]
+    internal: bool,
+}
+
+fn parse_assignment(value: &str) -> Result<(String, u16), String> {
+    let (pin, number) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected PIN=VALUE, got {value:?}"))?;
+    let number = number.trim();
+    let parsed = match number.strip_prefix("%B") {
+        Some(binary) => u16::from_str_radix(binary, 2).ok(),
+        None => number
+            .parse::<u16>()
+            .ok()
+            .or_else(|| number.parse::<i16>().ok().map(|value| value as u16)),
+    };
+    let number = parsed.ok_or_else(|| format!("invalid value {number:?}"))?;
+    Ok((pin.trim().to_string(), number))
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut simulator = match Simulator::load(&args.path) {
+        Ok(simulator) => simulator,
+        Err(err) => {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    for (pin, value) in &args.set {
+        if let Err(err) = simulator.set(pin, *value) {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    }
+    simulator.eval();
+    for _ in 0..args.ticks {
+        simulator.tick();
+        simulator.tock();
+    }
+
+    for pin in simulator.pins() {
+        if pin.kind == PinKind::Internal && !args.internal {
+            continue;
+        }
+        let value = simulator.get(&pin.name).expect("pin of the chip");
+        let kind = match pin.kind {
+            PinKind::Input => "in",
+            PinKind::Output => "out",
+            PinKind::Internal => "internal",
+        };
+        println!(
+            "{:<8} {:<12} {:0width$b} ({})",
+            kind,
+            pin.name,
+            value,
+            value as i16,
+            width = pin.width as usize
+        );
+    }
+}
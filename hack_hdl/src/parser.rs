@@ -0,0 +1,451 @@
+use std::fmt;
+
+/// Span is the position of a token in an `.hdl` file, from line 1 column 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// ParseError is a syntax error of an `.hdl` file, displayed with its
+/// position, e.g. `12:5: expected ';', got "Not"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+/// PinDecl declares an input or output pin of a chip, `a` or `in[16]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinDecl {
+    pub name: String,
+    pub width: u16,
+    pub span: Span,
+}
+
+/// PinRef names a pin or some of its bits: `a`, `a[3]` or `a[0..7]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinRef {
+    pub name: String,
+    /// The first and last bit, None for the whole pin.
+    pub range: Option<(u16, u16)>,
+    pub span: Span,
+}
+
+impl PinRef {
+    /// width is the number of bits of a sub-bus, None for the whole pin.
+    pub fn width(&self) -> Option<u16> {
+        self.range.map(|(first, last)| last - first + 1)
+    }
+}
+
+impl fmt::Display for PinRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.range {
+            None => write!(f, "{}", self.name),
+            Some((first, last)) if first == last => write!(f, "{}[{}]", self.name, first),
+            Some((first, last)) => write!(f, "{}[{}..{}]", self.name, first, last),
+        }
+    }
+}
+
+/// Value is the right side of a connection, a pin of the chip being
+/// defined or a constant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Pin(PinRef),
+    Constant(bool, Span),
+}
+
+/// Connection connects a pin of a part to a value, `a=x` or `b[0..7]=false`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub pin: PinRef,
+    pub value: Value,
+}
+
+/// Part is an instance of a chip in the PARTS section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    pub chip: String,
+    pub connections: Vec<Connection>,
+    pub span: Span,
+}
+
+/// Body is the implementation of a chip, its parts or a builtin chip
+/// with the inputs it samples on the clock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    Parts(Vec<Part>),
+    Builtin { name: String, clocked: Vec<String> },
+}
+
+/// ChipDef is the definition of a chip read from an `.hdl` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChipDef {
+    pub name: String,
+    pub inputs: Vec<PinDecl>,
+    pub outputs: Vec<PinDecl>,
+    pub body: Body,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Identifier(String),
+    Number(u16),
+    Symbol(char),
+    /// The `..` of a sub-bus.
+    Range,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Identifier(name) => write!(f, "{}", name),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+            Token::Range => write!(f, ".."),
+        }
+    }
+}
+
+/// tokenize splits an `.hdl` file in tokens, the `//`, `/* */` and
+/// `/** */` comments are skipped.
+fn tokenize(content: &str) -> Result<Vec<(Token, Span)>, ParseError> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    let mut position = 0;
+
+    while position < chars.len() {
+        let c = chars[position];
+        let span = Span { line, column };
+        let start = position;
+
+        if c == '/' && chars.get(position + 1) == Some(&'/') {
+            while position < chars.len() && chars[position] != '\n' {
+                position += 1;
+            }
+        } else if c == '/' && chars.get(position + 1) == Some(&'*') {
+            position += 2;
+            while position < chars.len()
+                && !(chars[position] == '*' && chars.get(position + 1) == Some(&'/'))
+            {
+                position += 1;
+            }
+            if position >= chars.len() {
+                return Err(ParseError {
+                    span,
+                    message: String::from("unterminated comment"),
+                });
+            }
+            position += 2;
+        } else if c.is_whitespace() {
+            position += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while position < chars.len()
+                && (chars[position].is_ascii_alphanumeric() || chars[position] == '_')
+            {
+                position += 1;
+            }
+            let name: String = chars[start..position].iter().collect();
+            tokens.push((Token::Identifier(name), span));
+        } else if c.is_ascii_digit() {
+            while position < chars.len() && chars[position].is_ascii_digit() {
+                position += 1;
+            }
+            let digits: String = chars[start..position].iter().collect();
+            let number = digits.parse().map_err(|_| ParseError {
+                span,
+                message: format!("number out of range: {}", digits),
+            })?;
+            tokens.push((Token::Number(number), span));
+        } else if c == '.' && chars.get(position + 1) == Some(&'.') {
+            position += 2;
+            tokens.push((Token::Range, span));
+        } else if "{}()[],;=:".contains(c) {
+            position += 1;
+            tokens.push((Token::Symbol(c), span));
+        } else {
+            return Err(ParseError {
+                span,
+                message: format!("unexpected character {:?}", c),
+            });
+        }
+
+        for c in &chars[start..position] {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parser reads a chip definition from the tokens of an `.hdl` file.
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    position: usize,
+    /// Span of the end of the file, for the errors of a truncated file.
+    end: Span,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(_, span)| *span)
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, ParseError> {
+        let got = match self.peek() {
+            Some(token) => format!("{:?}", token.to_string()),
+            None => String::from("the end of the file"),
+        };
+        Err(ParseError {
+            span: self.span(),
+            message: format!("expected {}, got {}", expected, got),
+        })
+    }
+
+    fn next_is(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(name)) if name == keyword)
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), ParseError> {
+        if !self.next_is(symbol) {
+            return self.error(&format!("'{}'", symbol));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if !self.next_is_keyword(keyword) {
+            return self.error(keyword);
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<(String, Span), ParseError> {
+        match self.tokens.get(self.position) {
+            Some((Token::Identifier(name), span)) => {
+                let result = (name.clone(), *span);
+                self.position += 1;
+                Ok(result)
+            }
+            _ => self.error(expected),
+        }
+    }
+
+    fn number(&mut self) -> Result<u16, ParseError> {
+        match self.peek() {
+            Some(Token::Number(number)) => {
+                let number = *number;
+                self.position += 1;
+                Ok(number)
+            }
+            _ => self.error("a number"),
+        }
+    }
+
+    /// chip reads `CHIP Name { IN ...; OUT ...; PARTS: ... }`.
+    fn chip(&mut self) -> Result<ChipDef, ParseError> {
+        let span = self.span();
+        self.expect_keyword("CHIP")?;
+        let (name, _) = self.identifier("the chip name")?;
+        self.expect('{')?;
+
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        loop {
+            if self.next_is_keyword("IN") {
+                self.position += 1;
+                inputs.extend(self.pin_decls()?);
+            } else if self.next_is_keyword("OUT") {
+                self.position += 1;
+                outputs.extend(self.pin_decls()?);
+            } else {
+                break;
+            }
+        }
+
+        let body = if self.next_is_keyword("BUILTIN") {
+            self.builtin()?
+        } else if self.next_is_keyword("PARTS") {
+            self.position += 1;
+            self.expect(':')?;
+            let mut parts = Vec::new();
+            while !self.next_is('}') && self.peek().is_some() {
+                parts.push(self.part()?);
+            }
+            Body::Parts(parts)
+        } else {
+            return self.error("IN, OUT, PARTS: or BUILTIN");
+        };
+        self.expect('}')?;
+
+        if self.peek().is_some() {
+            return self.error("the end of the file");
+        }
+        Ok(ChipDef {
+            name,
+            inputs,
+            outputs,
+            body,
+            span,
+        })
+    }
+
+    /// pin_decls reads `a, b[16], c;`.
+    fn pin_decls(&mut self) -> Result<Vec<PinDecl>, ParseError> {
+        let mut pins = Vec::new();
+        loop {
+            let (name, span) = self.identifier("a pin name")?;
+            let width = if self.next_is('[') {
+                self.position += 1;
+                let width = self.number()?;
+                self.expect(']')?;
+                if !(1..=16).contains(&width) {
+                    return Err(ParseError {
+                        span,
+                        message: format!("width of {} must be 1 to 16", name),
+                    });
+                }
+                width
+            } else {
+                1
+            };
+            pins.push(PinDecl { name, width, span });
+
+            if self.next_is(';') {
+                self.position += 1;
+                return Ok(pins);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    /// builtin reads `BUILTIN Name; CLOCKED a, b;`.
+    fn builtin(&mut self) -> Result<Body, ParseError> {
+        self.expect_keyword("BUILTIN")?;
+        let (name, _) = self.identifier("the builtin chip name")?;
+        self.expect(';')?;
+
+        let mut clocked = Vec::new();
+        if self.next_is_keyword("CLOCKED") {
+            self.position += 1;
+            loop {
+                clocked.push(self.identifier("a pin name")?.0);
+                if self.next_is(';') {
+                    self.position += 1;
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(Body::Builtin { name, clocked })
+    }
+
+    /// part reads `Chip (a=x, b[0..7]=y[8..15], c=true);`.
+    fn part(&mut self) -> Result<Part, ParseError> {
+        let (chip, span) = self.identifier("a part")?;
+        self.expect('(')?;
+        let mut connections = Vec::new();
+        while !self.next_is(')') {
+            let pin = self.pin_ref()?;
+            self.expect('=')?;
+            let value = match self.peek() {
+                Some(Token::Identifier(name)) if name == "true" || name == "false" => {
+                    let value = Value::Constant(name == "true", self.span());
+                    self.position += 1;
+                    value
+                }
+                Some(Token::Identifier(_)) => Value::Pin(self.pin_ref()?),
+                _ => return self.error("a pin, true or false"),
+            };
+            connections.push(Connection { pin, value });
+
+            if !self.next_is(')') {
+                self.expect(',')?;
+            }
+        }
+        self.expect(')')?;
+        self.expect(';')?;
+        Ok(Part {
+            chip,
+            connections,
+            span,
+        })
+    }
+
+    /// pin_ref reads `a`, `a[3]` or `a[0..7]`.
+    fn pin_ref(&mut self) -> Result<PinRef, ParseError> {
+        let (name, span) = self.identifier("a pin name")?;
+        let range = if self.next_is('[') {
+            self.position += 1;
+            let first = self.number()?;
+            let last = if self.peek() == Some(&Token::Range) {
+                self.position += 1;
+                self.number()?
+            } else {
+                first
+            };
+            self.expect(']')?;
+            if first > last || last > 15 {
+                return Err(ParseError {
+                    span,
+                    message: format!("invalid sub-bus {}[{}..{}]", name, first, last),
+                });
+            }
+            Some((first, last))
+        } else {
+            None
+        };
+        Ok(PinRef { name, range, span })
+    }
+}
+
+/// parse reads the chip definition of an `.hdl` file.
+pub fn parse(content: &str) -> Result<ChipDef, ParseError> {
+    let tokens = tokenize(content)?;
+    let lines = content.lines().count().max(1);
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: Span {
+            line: lines,
+            column: content
+                .lines()
+                .last()
+                .map_or(0, |line| line.chars().count())
+                + 1,
+        },
+    };
+    parser.chip()
+}
@@ -0,0 +1,478 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::builtin::Builtin;
+use crate::library::{Chip, Library};
+use crate::lint::{check_parts, Severity};
+use crate::parser::{Body, ChipDef, Span, Value};
+
+/// Net is a wire of the flattened circuit, it carries one bit.
+type Net = usize;
+
+/// The nets of the constants false and true.
+const FALSE: Net = 0;
+const TRUE: Net = 1;
+
+/// PinKind tells where a pin of the simulated chip comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinKind {
+    Input,
+    Output,
+    /// A pin connecting the parts of the chip.
+    Internal,
+}
+
+/// Pin is a pin of the simulated chip with the nets of its bits, the bit 0
+/// first.
+#[derive(Debug, Clone)]
+pub struct Pin {
+    pub name: String,
+    pub width: u16,
+    pub kind: PinKind,
+    nets: Vec<Net>,
+}
+
+/// Component is an instance of a builtin chip in the flattened circuit,
+/// the chips defined in `.hdl` files are replaced by their parts.
+#[derive(Debug, Clone)]
+struct Component {
+    builtin: Builtin,
+    /// Chips from the top one down to this part, e.g. `PC.Register.Bit`.
+    path: String,
+    inputs: Vec<Vec<Net>>,
+    outputs: Vec<Vec<Net>>,
+    memory: Vec<u16>,
+}
+
+/// Builder flattens a chip and its parts into components connected by
+/// nets. Connecting two pins merges their nets, in a union-find.
+struct Builder<'a> {
+    library: &'a mut Library,
+    parent: Vec<Net>,
+    components: Vec<Component>,
+    /// Chips being instantiated, to catch a chip that contains itself.
+    stack: Vec<String>,
+}
+
+impl Builder<'_> {
+    fn new_nets(&mut self, width: u16) -> Vec<Net> {
+        let first = self.parent.len();
+        self.parent.extend(first..first + width as usize);
+        (first..first + width as usize).collect()
+    }
+
+    fn root(&mut self, mut net: Net) -> Net {
+        while self.parent[net] != net {
+            self.parent[net] = self.parent[self.parent[net]];
+            net = self.parent[net];
+        }
+        net
+    }
+
+    fn union(&mut self, a: Net, b: Net) {
+        let (a, b) = (self.root(a), self.root(b));
+        // The constants stay roots so that their nets keep their index.
+        if a < b {
+            self.parent[b] = a;
+        } else {
+            self.parent[a] = b;
+        }
+    }
+
+    /// instantiate adds a chip to the circuit, pins holds the nets of its
+    /// input and output pins. It returns the nets of the internal pins.
+    fn instantiate(
+        &mut self,
+        chip: &Chip,
+        path: &str,
+        pins: HashMap<String, Vec<Net>>,
+    ) -> Result<HashMap<String, Vec<Net>>, String> {
+        match chip {
+            Chip::Builtin(builtin) => {
+                let nets = |list: &[(&str, u16)]| -> Vec<Vec<Net>> {
+                    list.iter().map(|(name, _)| pins[*name].clone()).collect()
+                };
+                let component = Component {
+                    builtin: *builtin,
+                    path: path.to_string(),
+                    inputs: nets(builtin.inputs()),
+                    outputs: nets(builtin.outputs()),
+                    memory: vec![0; builtin.memory_size()],
+                };
+                self.components.push(component);
+                Ok(HashMap::new())
+            }
+            Chip::Hdl(def) => {
+                self.stack.push(def.name.clone());
+                let internal = self.instantiate_parts(def, path, pins);
+                self.stack.pop();
+                internal
+            }
+        }
+    }
+
+    fn instantiate_parts(
+        &mut self,
+        def: &ChipDef,
+        path: &str,
+        mut pins: HashMap<String, Vec<Net>>,
+    ) -> Result<HashMap<String, Vec<Net>>, String> {
+        let file = self.library.file(&def.name);
+        let error =
+            |span: Span, message: String| format!("{}:{}: {}", file.display(), span, message);
+        let parts = match &def.body {
+            Body::Parts(parts) => parts,
+            Body::Builtin { .. } => return Ok(HashMap::new()),
+        };
+
+        let mut chips = Vec::new();
+        for part in parts {
+            if self.stack.contains(&part.chip) {
+                return Err(error(part.span, format!("{} contains itself", part.chip)));
+            }
+            let chip = self
+                .library
+                .resolve(&part.chip)
+                .map_err(|err| error(part.span, err))?;
+            chips.push(Some(chip));
+        }
+        let (internal, diagnostics) = check_parts(def, parts, &chips);
+        if let Some(diagnostic) = diagnostics
+            .into_iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            return Err(error(diagnostic.span, diagnostic.message));
+        }
+
+        let mut internal_nets = HashMap::new();
+        for pin in internal {
+            let nets = self.new_nets(pin.width);
+            internal_nets.insert(pin.name.clone(), nets.clone());
+            pins.insert(pin.name, nets);
+        }
+
+        // The connections are checked, they only merge nets.
+        for (part, chip) in parts.iter().zip(chips.into_iter().flatten()) {
+            let mut part_pins: HashMap<String, Vec<Net>> = HashMap::new();
+            let mut connected = HashSet::new();
+            for (name, width) in chip.inputs().into_iter().chain(chip.outputs()) {
+                let nets = self.new_nets(width);
+                part_pins.insert(name.to_string(), nets);
+            }
+
+            for connection in &part.connections {
+                let nets = &part_pins[&connection.pin.name];
+                let (first, last) = connection.pin.range.unwrap_or((0, nets.len() as u16 - 1));
+                let bits = nets[first as usize..=last as usize].to_vec();
+                connected.extend(bits.iter().copied());
+
+                let values = match &connection.value {
+                    Value::Constant(value, _) => {
+                        let constant = if *value { TRUE } else { FALSE };
+                        vec![constant; bits.len()]
+                    }
+                    Value::Pin(value) => {
+                        let nets = &pins[&value.name];
+                        let (first, last) = value.range.unwrap_or((0, nets.len() as u16 - 1));
+                        nets[first as usize..=last as usize].to_vec()
+                    }
+                };
+                for (net, value) in bits.into_iter().zip(values) {
+                    self.union(net, value);
+                }
+            }
+
+            // Inputs left unconnected are false.
+            for (name, _) in chip.inputs() {
+                for net in part_pins[name].clone() {
+                    if !connected.contains(&net) {
+                        self.union(net, FALSE);
+                    }
+                }
+            }
+
+            let part_path = format!("{}.{}", path, part.chip);
+            self.instantiate(&chip, &part_path, part_pins)?;
+        }
+        Ok(internal_nets)
+    }
+}
+
+/// Simulator evaluates a chip flattened into builtin components. The
+/// combinational components are evaluated in dependency order, the
+/// clocked ones store their inputs on tick and show them on tock.
+pub struct Simulator {
+    name: String,
+    pins: Vec<Pin>,
+    nets: Vec<bool>,
+    components: Vec<Component>,
+    /// Components in evaluation order, every one after its inputs.
+    order: Vec<usize>,
+    clocked: Vec<usize>,
+}
+
+impl Simulator {
+    /// load reads a chip from an `.hdl` file, its parts are resolved in the
+    /// same directory, see Library.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let directory = path.parent().unwrap_or(Path::new("."));
+        let mut library = Library::new(directory);
+        let chip = Library::load(path)?;
+        Self::new(&chip, &mut library)
+    }
+
+    /// Creates a Simulator of the chip with its parts from the library.
+    pub fn new(chip: &Chip, library: &mut Library) -> Result<Self, String> {
+        let mut builder = Builder {
+            library,
+            parent: vec![FALSE, TRUE],
+            components: Vec::new(),
+            stack: Vec::new(),
+        };
+
+        let mut pins = Vec::new();
+        let mut pin_nets = HashMap::new();
+        let declared = chip
+            .inputs()
+            .into_iter()
+            .map(|pin| (pin, PinKind::Input))
+            .chain(chip.outputs().into_iter().map(|pin| (pin, PinKind::Output)));
+        for ((name, width), kind) in declared {
+            let nets = builder.new_nets(width);
+            pin_nets.insert(name.to_string(), nets.clone());
+            pins.push(Pin {
+                name: name.to_string(),
+                width,
+                kind,
+                nets,
+            });
+        }
+
+        let internal = builder.instantiate(chip, chip.name(), pin_nets)?;
+        let mut internal: Vec<(String, Vec<Net>)> = internal.into_iter().collect();
+        internal.sort();
+        for (name, nets) in internal {
+            pins.push(Pin {
+                name,
+                width: nets.len() as u16,
+                kind: PinKind::Internal,
+                nets,
+            });
+        }
+
+        // Number the merged nets densely, FALSE and TRUE keep 0 and 1.
+        let mut index = HashMap::new();
+        let mut dense = |builder: &mut Builder, net: Net| {
+            let root = builder.root(net);
+            let next = index.len();
+            *index.entry(root).or_insert(next)
+        };
+        dense(&mut builder, FALSE);
+        dense(&mut builder, TRUE);
+        for pin in pins.iter_mut() {
+            for net in pin.nets.iter_mut() {
+                *net = dense(&mut builder, *net);
+            }
+        }
+        let mut components = std::mem::take(&mut builder.components);
+        for component in components.iter_mut() {
+            for net in component
+                .inputs
+                .iter_mut()
+                .chain(component.outputs.iter_mut())
+                .flatten()
+            {
+                *net = dense(&mut builder, *net);
+            }
+        }
+
+        let mut nets = vec![false; index.len()];
+        nets[TRUE] = true;
+        let order = evaluation_order(&components, nets.len())?;
+        let clocked = (0..components.len())
+            .filter(|index| components[*index].builtin.is_clocked_chip())
+            .collect();
+
+        let mut simulator = Simulator {
+            name: chip.name().to_string(),
+            pins,
+            nets,
+            components,
+            order,
+            clocked,
+        };
+        simulator.eval();
+        Ok(simulator)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// pins lists the input, output and internal pins of the chip.
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    fn pin(&self, name: &str) -> Result<&Pin, String> {
+        self.pins
+            .iter()
+            .find(|pin| pin.name == name)
+            .ok_or_else(|| format!("{} has no pin {}", self.name, name))
+    }
+
+    /// get reads the value of a pin, the bit 0 is the lowest.
+    pub fn get(&self, name: &str) -> Result<u16, String> {
+        let pin = self.pin(name)?;
+        Ok(pin.nets.iter().enumerate().fold(0, |value, (bit, net)| {
+            value | (self.nets[*net] as u16) << bit
+        }))
+    }
+
+    /// set changes the value of an input pin, the outputs are only updated
+    /// by the next eval, tick or tock.
+    pub fn set(&mut self, name: &str, value: u16) -> Result<(), String> {
+        let pin = self.pin(name)?;
+        if pin.kind != PinKind::Input {
+            return Err(format!("{} is not an input pin of {}", name, self.name));
+        }
+        let nets = pin.nets.clone();
+        for (bit, net) in nets.into_iter().enumerate() {
+            // An input connected straight to a constant inside the chip keeps it.
+            if net > TRUE {
+                self.nets[net] = value >> bit & 1 != 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// is_clocked tells if the chip has parts that change on the clock.
+    pub fn is_clocked(&self) -> bool {
+        !self.clocked.is_empty()
+    }
+
+    /// eval propagates the inputs through the combinational parts.
+    pub fn eval(&mut self) {
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for index in 0..self.order.len() {
+            let component = &self.components[self.order[index]];
+            read_pins(&self.nets, &component.inputs, &mut inputs);
+            outputs.clear();
+            outputs.resize(component.outputs.len(), 0);
+            component
+                .builtin
+                .eval(&inputs, &component.memory, &mut outputs);
+            for (nets, value) in component.outputs.iter().zip(&outputs) {
+                for (bit, net) in nets.iter().enumerate() {
+                    self.nets[*net] = value >> bit & 1 != 0;
+                }
+            }
+        }
+    }
+
+    /// tick is the rising edge of the clock, the clocked parts store their
+    /// inputs like the builtin chips of the course do, e.g. `DRegister[]`
+    /// changes, but the outputs don't change until tock.
+    pub fn tick(&mut self) {
+        self.eval();
+        let mut inputs = Vec::new();
+        for index in &self.clocked {
+            let component = &mut self.components[*index];
+            read_pins(&self.nets, &component.inputs, &mut inputs);
+            if let Some((address, value)) = component.builtin.clock(&inputs, &component.memory) {
+                component.memory[address] = value;
+            }
+        }
+    }
+
+    /// tock is the falling edge of the clock, the outputs are updated with
+    /// what the clocked parts stored on tick.
+    pub fn tock(&mut self) {
+        self.eval();
+    }
+
+    /// memory gives the state of the first part made of the builtin chip,
+    /// e.g. the words of a RAM16K or the value of a DRegister.
+    pub fn memory(&self, chip: &str) -> Option<&[u16]> {
+        self.components
+            .iter()
+            .find(|component| component.builtin.name() == chip)
+            .map(|component| component.memory.as_slice())
+    }
+
+    /// memory_mut gives write access to the state of a builtin part, see
+    /// memory. The outputs are updated by the next eval.
+    pub fn memory_mut(&mut self, chip: &str) -> Option<&mut [u16]> {
+        self.components
+            .iter_mut()
+            .find(|component| component.builtin.name() == chip)
+            .map(|component| component.memory.as_mut_slice())
+    }
+}
+
+/// read_pins packs the bits of the nets of each pin in a value.
+fn read_pins(nets: &[bool], pins: &[Vec<Net>], values: &mut Vec<u16>) {
+    values.clear();
+    values.extend(pins.iter().map(|pin| {
+        pin.iter()
+            .enumerate()
+            .fold(0, |value, (bit, net)| value | (nets[*net] as u16) << bit)
+    }));
+}
+
+/// evaluation_order sorts the components so that each one comes after the
+/// ones driving its combinational inputs, a clocked input doesn't count as
+/// it only matters on the next cycle. A loop is an error.
+fn evaluation_order(components: &[Component], net_count: usize) -> Result<Vec<usize>, String> {
+    let mut driver = vec![None; net_count];
+    for (index, component) in components.iter().enumerate() {
+        for net in component.outputs.iter().flatten() {
+            driver[*net] = Some(index);
+        }
+    }
+
+    // dependents[d] lists the components reading an output of d.
+    let mut dependents = vec![Vec::new(); components.len()];
+    let mut waiting = vec![0; components.len()];
+    for (index, component) in components.iter().enumerate() {
+        let builtin = component.builtin;
+        let mut sources: Vec<usize> = builtin
+            .inputs()
+            .iter()
+            .zip(&component.inputs)
+            .filter(|((name, _), _)| !builtin.is_clocked(name))
+            .flat_map(|(_, nets)| nets.iter().filter_map(|net| driver[*net]))
+            .collect();
+        sources.sort_unstable();
+        sources.dedup();
+        waiting[index] = sources.len();
+        for source in sources {
+            dependents[source].push(index);
+        }
+    }
+
+    let mut order: Vec<usize> = (0..components.len())
+        .filter(|index| waiting[*index] == 0)
+        .collect();
+    let mut next = 0;
+    while next < order.len() {
+        let index = order[next];
+        next += 1;
+        for dependent in &dependents[index] {
+            waiting[*dependent] -= 1;
+            if waiting[*dependent] == 0 {
+                order.push(*dependent);
+            }
+        }
+    }
+
+    if order.len() < components.len() {
+        let looping = (0..components.len())
+            .find(|index| waiting[*index] > 0)
+            .map(|index| components[index].path.as_str())
+            .unwrap_or_default();
+        return Err(format!("combinational loop through {}", looping));
+    }
+    Ok(order)
+}
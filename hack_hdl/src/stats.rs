@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use crate::builtin::Builtin;
+use crate::library::{Chip, Library};
+use crate::lint::{check_parts, Severity};
+use crate::parser::{Body, ChipDef, Part, Span, Value};
+
+/// Timing holds the depth, in Nand gates, of the combinational paths of a
+/// chip between its pins and its state, the DFFs. Paths are followed pin
+/// by pin, not bit by bit.
+#[derive(Debug, Clone, Default)]
+struct Timing {
+    /// Depth from an input to an output, by output then input.
+    through: HashMap<(String, String), u32>,
+    /// Depth from the state to an output.
+    from_state: HashMap<String, u32>,
+    /// Depth from an input to the state.
+    to_state: HashMap<String, u32>,
+    /// Depth of the paths from the state back to the state.
+    inside: Option<u32>,
+}
+
+/// Stats of a chip flattened down to Nand and DFF.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub nand: u64,
+    pub dff: u64,
+    /// Builtin parts without a Nand implementation, e.g. RAM16K or
+    /// Screen, by chip. Their paths have no depth.
+    pub builtin: BTreeMap<String, u64>,
+    timing: Timing,
+}
+
+impl Stats {
+    /// depth is the number of Nand gates on the longest combinational path,
+    /// from an input or a DFF to an output or a DFF.
+    pub fn depth(&self) -> u32 {
+        let timing = &self.timing;
+        timing
+            .through
+            .values()
+            .chain(timing.from_state.values())
+            .chain(timing.to_state.values())
+            .chain(&timing.inside)
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn add(&mut self, other: &Stats) {
+        self.nand += other.nand;
+        self.dff += other.dff;
+        for (chip, count) in &other.builtin {
+            *self.builtin.entry(chip.clone()).or_default() += count;
+        }
+    }
+}
+
+/// PartStats is the share of the parts made of one chip in the stats of
+/// the chip using them.
+#[derive(Debug, Clone)]
+pub struct PartStats {
+    pub chip: String,
+    pub count: u64,
+    /// Stats of one of the parts.
+    pub stats: Rc<Stats>,
+}
+
+/// Analyzer computes the stats of chips and their parts, the parts are
+/// resolved by a Library and computed once.
+pub struct Analyzer {
+    library: Library,
+    stats: HashMap<String, Rc<Stats>>,
+    /// Chips being analyzed, to stop at a chip that contains itself.
+    stack: Vec<String>,
+}
+
+/// Arrival is the depth of the paths reaching an output of a part.
+#[derive(Debug, Clone, Default)]
+struct Arrival {
+    from_inputs: HashMap<String, u32>,
+    from_state: Option<u32>,
+}
+
+fn raise<K: Eq + std::hash::Hash>(map: &mut HashMap<K, u32>, key: K, depth: u32) {
+    let entry = map.entry(key).or_insert(depth);
+    *entry = (*entry).max(depth);
+}
+
+fn raise_option(value: &mut Option<u32>, depth: u32) {
+    *value = Some(value.map_or(depth, |value| value.max(depth)));
+}
+
+impl Analyzer {
+    pub fn new(library: Library) -> Self {
+        Analyzer {
+            library,
+            stats: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// analyze computes the stats of a chip.
+    pub fn analyze(&mut self, chip: &Chip) -> Result<Rc<Stats>, String> {
+        let def = match chip {
+            Chip::Builtin(builtin) => return Ok(Rc::new(builtin_stats(*builtin))),
+            Chip::Hdl(def) => def,
+        };
+        if let Some(stats) = self.stats.get(&def.name) {
+            return Ok(stats.clone());
+        }
+
+        self.stack.push(def.name.clone());
+        let stats = self.analyze_parts(def);
+        self.stack.pop();
+        let stats = Rc::new(stats?.0);
+        self.stats.insert(def.name.clone(), stats.clone());
+        Ok(stats)
+    }
+
+    /// breakdown lists the parts of a chip grouped by chip, in the order
+    /// they first appear.
+    pub fn breakdown(&mut self, chip: &Chip) -> Result<Vec<PartStats>, String> {
+        match chip {
+            Chip::Builtin(_) => Ok(Vec::new()),
+            Chip::Hdl(def) => {
+                self.stack.push(def.name.clone());
+                let parts = self.analyze_parts(def);
+                self.stack.pop();
+                Ok(parts?.1)
+            }
+        }
+    }
+
+    fn analyze_parts(&mut self, def: &ChipDef) -> Result<(Stats, Vec<PartStats>), String> {
+        let file = self.library.file(&def.name);
+        let error =
+            |span: Span, message: String| format!("{}:{}: {}", file.display(), span, message);
+        let parts = match &def.body {
+            Body::Parts(parts) => parts,
+            Body::Builtin { .. } => return Ok((Stats::default(), Vec::new())),
+        };
+
+        let mut chips = Vec::new();
+        let mut part_stats = Vec::new();
+        for part in parts {
+            if self.stack.contains(&part.chip) {
+                return Err(error(part.span, format!("{} contains itself", part.chip)));
+            }
+            let chip = self
+                .library
+                .resolve(&part.chip)
+                .map_err(|err| error(part.span, err))?;
+            part_stats.push(self.analyze(&chip)?);
+            chips.push(Some(chip));
+        }
+        let (_, diagnostics) = check_parts(def, parts, &chips);
+        if let Some(diagnostic) = diagnostics
+            .into_iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            return Err(error(diagnostic.span, diagnostic.message));
+        }
+
+        let mut stats = Stats::default();
+        let mut breakdown: Vec<PartStats> = Vec::new();
+        for (part, part_stats) in parts.iter().zip(&part_stats) {
+            stats.add(part_stats);
+            match breakdown.iter_mut().find(|entry| entry.chip == part.chip) {
+                Some(entry) => entry.count += 1,
+                None => breakdown.push(PartStats {
+                    chip: part.chip.clone(),
+                    count: 1,
+                    stats: part_stats.clone(),
+                }),
+            }
+        }
+
+        // The part output driving each internal pin, and the ones driving
+        // the outputs of the chip.
+        let is_input = |name: &str| def.inputs.iter().any(|pin| pin.name == name);
+        let is_output = |name: &str| def.outputs.iter().any(|pin| pin.name == name);
+        let mut drivers: HashMap<&str, (usize, &str)> = HashMap::new();
+        let mut output_drivers: Vec<(&str, (usize, &str))> = Vec::new();
+        for (index, (part, chip)) in parts.iter().zip(chips.iter().flatten()).enumerate() {
+            let outputs = chip.outputs();
+            for connection in &part.connections {
+                let pin = connection.pin.name.as_str();
+                let Value::Pin(value) = &connection.value else {
+                    continue;
+                };
+                if !outputs.iter().any(|(name, _)| *name == pin) {
+                    continue;
+                }
+                if is_output(&value.name) {
+                    output_drivers.push((&value.name, (index, pin)));
+                } else {
+                    drivers.insert(&value.name, (index, pin));
+                }
+            }
+        }
+
+        let mut paths = Paths {
+            def,
+            parts,
+            stats: &part_stats,
+            drivers: &drivers,
+            arrivals: HashMap::new(),
+            visiting: Vec::new(),
+        };
+        let timing = &mut stats.timing;
+        for (output, node) in &output_drivers {
+            let arrival = paths
+                .arrival(*node)
+                .map_err(|span| error(span, String::from("combinational loop")))?;
+            for (input, depth) in &arrival.from_inputs {
+                raise(
+                    &mut timing.through,
+                    (output.to_string(), input.clone()),
+                    *depth,
+                );
+            }
+            if let Some(depth) = arrival.from_state {
+                raise(&mut timing.from_state, output.to_string(), depth);
+            }
+        }
+        for (index, part) in parts.iter().enumerate() {
+            if let Some(inside) = part_stats[index].timing.inside {
+                raise_option(&mut timing.inside, inside);
+            }
+            for connection in &part.connections {
+                let Value::Pin(value) = &connection.value else {
+                    continue;
+                };
+                let Some(depth) = part_stats[index].timing.to_state.get(&connection.pin.name)
+                else {
+                    continue;
+                };
+                if is_input(&value.name) {
+                    raise(&mut timing.to_state, value.name.clone(), *depth);
+                    continue;
+                }
+                let Some(node) = drivers.get(value.name.as_str()) else {
+                    continue;
+                };
+                let arrival = paths
+                    .arrival(*node)
+                    .map_err(|span| error(span, String::from("combinational loop")))?;
+                for (input, from_input) in &arrival.from_inputs {
+                    raise(&mut timing.to_state, input.clone(), from_input + depth);
+                }
+                if let Some(from_state) = arrival.from_state {
+                    raise_option(&mut timing.inside, from_state + depth);
+                }
+            }
+        }
+        Ok((stats, breakdown))
+    }
+}
+
+/// Paths computes the arrivals at the part outputs of a chip, each once.
+struct Paths<'a> {
+    def: &'a ChipDef,
+    parts: &'a [Part],
+    stats: &'a [Rc<Stats>],
+    drivers: &'a HashMap<&'a str, (usize, &'a str)>,
+    arrivals: HashMap<(usize, &'a str), Rc<Arrival>>,
+    /// Part outputs whose arrival is being computed, to find loops.
+    visiting: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Paths<'a> {
+    /// arrival finds the paths reaching an output of a part, a loop fails
+    /// with the span of a part in it.
+    fn arrival(&mut self, node: (usize, &'a str)) -> Result<Rc<Arrival>, Span> {
+        if let Some(arrival) = self.arrivals.get(&node) {
+            return Ok(arrival.clone());
+        }
+        let (index, output) = node;
+        let part = &self.parts[index];
+        if self.visiting.contains(&node) {
+            return Err(part.span);
+        }
+        self.visiting.push(node);
+
+        let timing = &self.stats[index].timing;
+        let mut arrival = Arrival {
+            from_inputs: HashMap::new(),
+            from_state: timing.from_state.get(output).copied(),
+        };
+        for connection in &part.connections {
+            let Value::Pin(value) = &connection.value else {
+                continue;
+            };
+            let key = (output.to_string(), connection.pin.name.clone());
+            let Some(depth) = timing.through.get(&key) else {
+                continue;
+            };
+            if self.def.inputs.iter().any(|pin| pin.name == value.name) {
+                raise(&mut arrival.from_inputs, value.name.clone(), *depth);
+                continue;
+            }
+            let Some(source) = self.drivers.get(value.name.as_str()) else {
+                continue;
+            };
+            let before = self.arrival(*source)?;
+            for (input, from_input) in &before.from_inputs {
+                raise(&mut arrival.from_inputs, input.clone(), from_input + depth);
+            }
+            if let Some(from_state) = before.from_state {
+                raise_option(&mut arrival.from_state, from_state + depth);
+            }
+        }
+
+        self.visiting.pop();
+        let arrival = Rc::new(arrival);
+        self.arrivals.insert(node, arrival.clone());
+        Ok(arrival)
+    }
+}
+
+/// builtin_stats are the stats of a builtin part. Nand and DFF are the
+/// primitives, the other builtin chips are counted as they are.
+fn builtin_stats(builtin: Builtin) -> Stats {
+    let mut stats = Stats::default();
+    let timing = &mut stats.timing;
+    let (depth, clocked) = match builtin {
+        Builtin::Nand => {
+            stats.nand = 1;
+            (1, false)
+        }
+        Builtin::Dff => {
+            stats.dff = 1;
+            (0, true)
+        }
+        _ => {
+            stats.builtin.insert(builtin.name().to_string(), 1);
+            (0, builtin.is_clocked_chip())
+        }
+    };
+
+    for (output, _) in builtin.outputs() {
+        for (input, _) in builtin.inputs() {
+            if builtin.is_clocked(input) {
+                timing.to_state.insert(input.to_string(), 0);
+            } else {
+                let key = (output.to_string(), input.to_string());
+                timing.through.insert(key, depth);
+            }
+        }
+        if clocked {
+            timing.from_state.insert(output.to_string(), 0);
+        }
+    }
+    stats
+}
@@ -0,0 +1,114 @@
+//! The linter on small chips written to a temporary directory.
+use std::fs;
+use std::path::PathBuf;
+
+use hack_hdl::{Diagnostic, Linter, Severity};
+
+/// check writes a chip to its own directory and lints it.
+fn check(name: &str, hdl: &str) -> Vec<Diagnostic> {
+    let directory: PathBuf =
+        std::env::temp_dir().join(format!("hack_hdl-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join(format!("{}.hdl", name));
+    fs::write(&path, hdl).unwrap();
+
+    let diagnostics = Linter::new(&directory).lint(&path).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    diagnostics
+}
+
+/// lint returns the errors of a chip as displayed.
+fn lint(name: &str, hdl: &str) -> Vec<String> {
+    check(name, hdl)
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+#[test]
+fn accepts_a_correct_chip() {
+    let hdl = "CHIP Xor {
+    IN a, b;
+    OUT out;
+    PARTS:
+    Nand(a=a, b=b, out=nand);
+    Or(a=a, b=b, out=or);
+    And(a=nand, b=or, out=out);
+}
+";
+    assert!(lint("Xor", hdl).is_empty());
+}
+
+#[test]
+fn reports_a_double_driver() {
+    let hdl = "CHIP Twice {
+    IN a, b;
+    OUT out;
+    PARTS:
+    Not(in=a, out=x);
+    Not(in=b, out=x);
+    And(a=x, b=b, out=out);
+}
+";
+    assert_eq!(
+        lint("Twice", hdl),
+        ["6:19: error: x has more than one driver"]
+    );
+}
+
+#[test]
+fn reports_a_width_mismatch() {
+    let hdl = "CHIP Narrow {
+    IN a[16];
+    OUT out[8];
+    PARTS:
+    Not16(in=a, out=out);
+}
+";
+    assert_eq!(
+        lint("Narrow", hdl),
+        ["5:17: error: width mismatch: Not16.out is 16 bits, chip pin out is 8 bits"]
+    );
+
+    // The mismatched pins are still connected.
+    let hdl = "CHIP Narrow {
+    IN a[16];
+    OUT out1[16], out2[8];
+    PARTS:
+    Not16(in=a, out=out1, out=out2);
+    And16(a=a, b=wide, out=narrow);
+    Not16(in=a, out=wide);
+    Or8Way(in=narrow, out=unused);
+}
+";
+    let diagnostics: Vec<String> = check("Narrow", hdl)
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            "5:27: error: width mismatch: Not16.out is 16 bits, chip pin out2 is 8 bits",
+            "8:12: error: width mismatch: Or8Way.in is 8 bits, internal pin narrow is 16 bits",
+            "8:27: warning: internal pin unused is not used",
+        ]
+    );
+}
+
+#[test]
+fn reports_a_combinational_loop() {
+    let hdl = "CHIP Latch {
+    IN set;
+    OUT out;
+    PARTS:
+    Or(a=set, b=back, out=or);
+    Not(in=or, out=inverted);
+    Not(in=inverted, out=back, out=out);
+}
+";
+    assert_eq!(
+        lint("Latch", hdl),
+        ["5:5: error: combinational loop: Or -> or -> Not -> inverted -> Not -> back -> Or"]
+    );
+}
@@ -0,0 +1,78 @@
+//! hdl-stats on the chips of the projects, the parts of a chip are found in
+//! the other `*-chips` directories.
+use std::fs;
+use std::process::Command;
+
+/// stats runs hdl-stats and returns the Nand, DFF and depth it reports.
+fn stats(args: &[&str]) -> (u64, u64, u64) {
+    let output = Command::new(env!("CARGO_BIN_EXE_hdl-stats"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let value = |name: &str| -> u64 {
+        let line = stdout
+            .lines()
+            .find(|line| line.trim_start().starts_with(name))
+            .unwrap();
+        line.split_whitespace().nth(1).unwrap().parse().unwrap()
+    };
+    (value("Nand"), value("DFF"), value("depth"))
+}
+
+#[test]
+fn flattens_the_elementary_chips() {
+    assert_eq!(
+        stats(&["../projects/elementary-chips/Mux16.hdl"]),
+        (448, 0, 9)
+    );
+}
+
+#[test]
+fn finds_the_parts_in_the_other_projects() {
+    let alu = (3225, 0, 117);
+    assert_eq!(stats(&["../projects/combinational-chips/ALU.hdl"]), alu);
+    assert_eq!(
+        stats(&[
+            "../projects/combinational-chips/ALU.hdl",
+            "-L",
+            "../projects/elementary-chips",
+        ]),
+        alu
+    );
+    // Register is in memory-chips/a and Mux8Way16 in elementary-chips.
+    assert_eq!(
+        stats(&["../projects/memory-chips/b/RAM512.hdl"]),
+        (460859, 8192, 81)
+    );
+}
+
+#[test]
+fn fails_on_a_missing_chip() {
+    let directory = std::env::temp_dir().join(format!("hack_hdl-stats-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("Twice.hdl");
+    fs::write(
+        &path,
+        "CHIP Twice {\n    IN in;\n    OUT out;\n    PARTS:\n    Missing(in=in, out=out);\n}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hdl-stats"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("err: ") && stderr.contains("Chip Missing not found"),
+        "{}",
+        stderr
+    );
+}
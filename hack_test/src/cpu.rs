@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use hack_assembler::assembler::Assembler;
+use hack_cpu::{Cpu, RAM_SIZE, ROM_SIZE};
+
+use crate::runner::Simulator;
+use crate::script::Value;
+
+/// CpuSimulator runs the scripts of CPU-level programs on the hack_cpu
+/// emulator. Programs are loaded from `.hack` files, `.asm` files are
+/// assembled first.
+///
+/// Variables are `A`, `D`, `PC`, `time`, `RAM[address]` and `ROM[address]`,
+/// the only command is `ticktock`, one clock cycle.
+#[derive(Default)]
+pub struct CpuSimulator {
+    cpu: Option<Cpu>,
+}
+
+impl CpuSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn cpu(&self) -> Result<&Cpu, String> {
+        self.cpu
+            .as_ref()
+            .ok_or_else(|| String::from("No program loaded"))
+    }
+
+    fn cpu_mut(&mut self) -> Result<&mut Cpu, String> {
+        self.cpu
+            .as_mut()
+            .ok_or_else(|| String::from("No program loaded"))
+    }
+}
+
+/// indexed splits a variable like `RAM[16]` into its name and index.
+fn indexed(name: &str) -> Option<(&str, usize)> {
+    let (memory, index) = name.strip_suffix(']')?.split_once('[')?;
+    Some((memory, index.parse().ok()?))
+}
+
+impl Simulator for CpuSimulator {
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let program = if path.extension().is_some_and(|ext| ext == "asm") {
+            let mut assembler = Assembler::new(path.to_path_buf());
+            assembler.initialize();
+            assembler.read_file()
+        } else {
+            hack_cpu::load_hack(path)
+        };
+        let cpu = program
+            .and_then(|program| Cpu::new(&program))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        self.cpu = Some(cpu);
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Value, String> {
+        let cpu = self.cpu()?;
+        let word = match (name, indexed(name)) {
+            ("A", _) => cpu.a,
+            ("D", _) => cpu.d,
+            ("PC", _) => cpu.pc,
+            ("time", _) => return Ok(Value::Number(cpu.cycles() as i64)),
+            (_, Some(("RAM", address))) if address < RAM_SIZE => cpu.peek(address),
+            (_, Some(("ROM", address))) if address < ROM_SIZE => cpu.rom()[address],
+            _ => return Err(format!("Unknown variable: {}", name)),
+        };
+        Ok(Value::Number(word as i16 as i64))
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+        if !(i16::MIN as i64..=u16::MAX as i64).contains(&value) {
+            return Err(format!("Value out of the 16-bit range: {}", value));
+        }
+        let word = value as u16;
+        let cpu = self.cpu_mut()?;
+        match (name, indexed(name)) {
+            ("A", _) => cpu.a = word,
+            ("D", _) => cpu.d = word,
+            ("PC", _) => cpu.pc = word & 0x7FFF,
+            (_, Some(("RAM", address))) if address < RAM_SIZE => cpu.poke(address, word),
+            _ => return Err(format!("Unknown variable: {}", name)),
+        }
+        Ok(())
+    }
+
+    fn simulate(&mut self, command: &str) -> Result<(), String> {
+        match command {
+            "ticktock" => {
+                self.cpu_mut()?.step();
+                Ok(())
+            }
+            _ => Err(format!("Unknown command: {}", command)),
+        }
+    }
+}
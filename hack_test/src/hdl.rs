@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use hack_hdl::Simulator as Chip;
+
+use crate::runner::Simulator;
+use crate::script::Value;
+
+/// HdlSimulator runs the scripts of chips on the hack_hdl simulator.
+///
+/// Variables are the pins of the chip, `time` and the state of its builtin
+/// parts, e.g. `DRegister[]`, `PC[]` or `RAM16K[3]`. The commands are
+/// `eval`, `tick` and `tock`, the two halves of a clock cycle, and
+/// `ROM32K load FILE.hack` to load a program in the ROM32K part.
+#[derive(Default)]
+pub struct HdlSimulator {
+    chip: Option<Chip>,
+    // Programs loaded in the ROM32K are relative to the chip.
+    directory: PathBuf,
+    // Clock cycles done, the output shows N+ between tick and tock.
+    time: u64,
+    ticked: bool,
+}
+
+impl HdlSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn chip(&self) -> Result<&Chip, String> {
+        self.chip
+            .as_ref()
+            .ok_or_else(|| String::from("No chip loaded"))
+    }
+
+    fn chip_mut(&mut self) -> Result<&mut Chip, String> {
+        self.chip
+            .as_mut()
+            .ok_or_else(|| String::from("No chip loaded"))
+    }
+}
+
+/// part_state splits a variable like `RAM16K[3]` or `DRegister[]` into
+/// the builtin part and the word of its state.
+fn part_state(name: &str) -> Option<(&str, usize)> {
+    let (part, index) = name.strip_suffix(']')?.split_once('[')?;
+    match index {
+        "" => Some((part, 0)),
+        _ => Some((part, index.parse().ok()?)),
+    }
+}
+
+impl Simulator for HdlSimulator {
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        self.chip = Some(Chip::load(path)?);
+        self.directory = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        self.time = 0;
+        self.ticked = false;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Value, String> {
+        if name == "time" {
+            let suffix = if self.ticked { "+" } else { "" };
+            return Ok(Value::Text(format!("{}{}", self.time, suffix)));
+        }
+
+        let chip = self.chip()?;
+        if let Some((part, index)) = part_state(name) {
+            let word = chip
+                .memory(part)
+                .and_then(|memory| memory.get(index))
+                .ok_or_else(|| format!("Unknown variable: {}", name))?;
+            return Ok(Value::Number(*word as i16 as i64));
+        }
+
+        // Buses narrower than a word are unsigned, e.g. an address.
+        let pin = chip
+            .pins()
+            .iter()
+            .find(|pin| pin.name == name)
+            .ok_or_else(|| format!("Unknown variable: {}", name))?;
+        let value = chip.get(name)?;
+        if pin.width == 16 {
+            Ok(Value::Number(value as i16 as i64))
+        } else {
+            Ok(Value::Number(value as i64))
+        }
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+        if !(i16::MIN as i64..=u16::MAX as i64).contains(&value) {
+            return Err(format!("Value out of the 16-bit range: {}", value));
+        }
+        let word = value as u16;
+        let chip = self.chip_mut()?;
+        match part_state(name) {
+            Some((part, index)) => {
+                let stored = chip
+                    .memory_mut(part)
+                    .and_then(|memory| memory.get_mut(index))
+                    .ok_or_else(|| format!("Unknown variable: {}", name))?;
+                *stored = word;
+                Ok(())
+            }
+            None => chip.set(name, word),
+        }
+    }
+
+    fn simulate(&mut self, command: &str) -> Result<(), String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words[..] {
+            ["eval"] => self.chip_mut()?.eval(),
+            ["tick"] => {
+                self.chip_mut()?.tick();
+                self.ticked = true;
+            }
+            ["tock"] => {
+                self.chip_mut()?.tock();
+                self.time += 1;
+                self.ticked = false;
+            }
+            ["ROM32K", "load", file] => {
+                let path = self.directory.join(file);
+                let program = hack_cpu::load_hack(&path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                let chip = self.chip_mut()?;
+                let rom = chip
+                    .memory_mut("ROM32K")
+                    .ok_or_else(|| String::from("The chip has no ROM32K part"))?;
+                if program.len() > rom.len() {
+                    return Err(format!("{}: program too large", path.display()));
+                }
+                rom.fill(0);
+                rom[..program.len()].copy_from_slice(&program);
+                chip.eval();
+            }
+            _ => return Err(format!("Unknown command: {}", command)),
+        }
+        Ok(())
+    }
+}
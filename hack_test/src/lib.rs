@@ -0,0 +1,13 @@
+//! Runner for the test scripts of the course (`.tst`), it writes the `.out`
+//! file of a script and compares it to the expected `.cmp` file.
+
+mod cpu;
+mod hdl;
+mod runner;
+pub mod script;
+mod vm;
+
+pub use cpu::CpuSimulator;
+pub use hdl::HdlSimulator;
+pub use runner::{Report, Runner, Simulator};
+pub use vm::VmSimulator;
@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use hack_test::script::{self, Command, Statement};
+use hack_test::{CpuSimulator, HdlSimulator, Report, Runner, VmSimulator};
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The test scripts (.tst) to run.
+    #[arg(required = true)]
+    scripts: Vec<PathBuf>,
+}
+
+/// loaded_file is the argument of the first load command, it tells which
+/// simulator the script is written for.
+fn loaded_file(statements: &[Statement]) -> Option<Option<&str>> {
+    statements
+        .iter()
+        .find_map(|statement| match &statement.command {
+            Command::Load(file) => Some(file.as_deref()),
+            Command::Repeat(_, body) | Command::While(_, body) => loaded_file(body),
+            _ => None,
+        })
+}
+
+fn run_script(path: &Path) -> Result<Report, String> {
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let statements = script::parse(&content)?;
+    let directory = path
+        .parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    // A load without a file loads the VM files of the script directory.
+    let extension = match loaded_file(&statements) {
+        Some(Some(file)) => Path::new(file).extension().and_then(|ext| ext.to_str()),
+        Some(None) => Some("vm"),
+        None => None,
+    };
+    match extension {
+        Some("asm" | "hack") => Runner::new(CpuSimulator::new(), directory).run(&statements),
+        Some("vm") => Runner::new(VmSimulator::new(), directory).run(&statements),
+        Some("hdl") => Runner::new(HdlSimulator::new(), directory).run(&statements),
+        Some(ext) => Err(format!("Unsupported program type: .{}", ext)),
+        None => Err(String::from("The script doesn't load a program")),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut failed = false;
+
+    for path in &args.scripts {
+        match run_script(path) {
+            Ok(Report {
+                compared: Some(lines),
+                ..
+            }) => println!(
+                "{}: comparison ended successfully ({} lines)",
+                path.display(),
+                lines
+            ),
+            Ok(Report {
+                output: Some(output),
+                ..
+            }) => println!("{}: output written to {}", path.display(), output.display()),
+            Ok(_) => println!("{}: end of script", path.display()),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
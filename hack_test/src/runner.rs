@@ -0,0 +1,203 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::script::{self, Column, Command, Statement, Value};
+
+/// Simulator is the machine a test script drives: the CPU emulator, the VM
+/// emulator or the hardware simulator.
+pub trait Simulator {
+    /// load loads the program or chip at path, it is the directory of the
+    /// script when the load command has no argument.
+    fn load(&mut self, path: &Path) -> Result<(), String>;
+
+    /// get reads a variable of an output-list or a while condition.
+    fn get(&self, name: &str) -> Result<Value, String>;
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String>;
+
+    /// simulate runs a simulator specific command, e.g. ticktock.
+    fn simulate(&mut self, command: &str) -> Result<(), String>;
+}
+
+/// Report of a script that ran to the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The output file written by the script, if any.
+    pub output: Option<PathBuf>,
+    /// Number of output lines checked against the compare file, None
+    /// when the script has no compare-to command.
+    pub compared: Option<usize>,
+}
+
+/// Runner executes a test script, writes its output file and compares
+/// every output line to the compare file as it is written.
+pub struct Runner<S: Simulator> {
+    simulator: S,
+    // Files named in the script are relative to its directory.
+    directory: PathBuf,
+    columns: Vec<Column>,
+    output_path: Option<PathBuf>,
+    output: Option<BufWriter<File>>,
+    compare: Option<Vec<String>>,
+    // Number of lines written to the output file.
+    lines: usize,
+}
+
+impl<S: Simulator> Runner<S> {
+    pub fn new(simulator: S, directory: &Path) -> Self {
+        Runner {
+            simulator,
+            directory: directory.to_path_buf(),
+            columns: Vec::new(),
+            output_path: None,
+            output: None,
+            compare: None,
+            lines: 0,
+        }
+    }
+
+    pub fn simulator(&self) -> &S {
+        &self.simulator
+    }
+
+    /// run executes the statements of a parsed script. It stops at the
+    /// first error or output line that differs from the compare file.
+    pub fn run(&mut self, statements: &[Statement]) -> Result<Report, String> {
+        self.execute(statements)?;
+        if let Some(output) = &mut self.output {
+            output.flush().map_err(|err| err.to_string())?;
+        }
+
+        Ok(Report {
+            output: self.output_path.clone(),
+            compared: self.compare.as_ref().map(|_| self.lines),
+        })
+    }
+
+    fn execute(&mut self, statements: &[Statement]) -> Result<(), String> {
+        for statement in statements {
+            self.execute_command(&statement.command)
+                .map_err(|err| format!("line {}: {}", statement.line, err))?;
+        }
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: &Command) -> Result<(), String> {
+        match command {
+            Command::Load(file) => {
+                let path = match file {
+                    Some(file) => self.directory.join(file),
+                    None => self.directory.clone(),
+                };
+                self.simulator.load(&path)?;
+            }
+            Command::OutputFile(file) => {
+                let path = self.directory.join(file);
+                let output =
+                    File::create(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+                self.output = Some(BufWriter::new(output));
+                self.output_path = Some(path);
+                self.lines = 0;
+            }
+            Command::CompareTo(file) => {
+                let path = self.directory.join(file);
+                let content = fs::read_to_string(&path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                self.compare = Some(content.lines().map(String::from).collect());
+            }
+            Command::OutputList(columns) => {
+                self.columns = columns.clone();
+                let header: Vec<String> = columns.iter().map(Column::header).collect();
+                self.write_line(&header)?;
+            }
+            Command::Set(name, value) => self.simulator.set(name, *value)?,
+            Command::Output => {
+                let cells = self
+                    .columns
+                    .iter()
+                    .map(|column| Ok(column.cell(&self.simulator.get(&column.name)?)))
+                    .collect::<Result<Vec<_>, String>>()?;
+                self.write_line(&cells)?;
+            }
+            Command::Echo(text) => println!("{}", text),
+            Command::ClearEcho => {}
+            Command::Repeat(Some(count), body) => {
+                for _ in 0..*count {
+                    self.execute(body)?;
+                }
+            }
+            Command::Repeat(None, body) => loop {
+                self.execute(body)?;
+            },
+            Command::While(condition, body) => {
+                while condition.compare.holds(
+                    self.number(&condition.left)?,
+                    self.number(&condition.right)?,
+                ) {
+                    self.execute(body)?;
+                }
+            }
+            Command::Simulate(command) => self.simulator.simulate(command)?,
+        }
+        Ok(())
+    }
+
+    /// number reads a number literal or the value of a variable.
+    fn number(&self, operand: &str) -> Result<i64, String> {
+        if let Ok(number) = script::parse_number(operand) {
+            return Ok(number);
+        }
+        match self.simulator.get(operand)? {
+            Value::Number(number) => Ok(number),
+            Value::Text(text) => Err(format!("{} is not a number: {}", operand, text)),
+        }
+    }
+
+    /// write_line writes a row of the output file and checks it against
+    /// the same line of the compare file.
+    fn write_line(&mut self, cells: &[String]) -> Result<(), String> {
+        let line = format!("|{}|", cells.join("|"));
+        let Some(output) = &mut self.output else {
+            return Err(String::from("No output file"));
+        };
+        writeln!(output, "{}", line).map_err(|err| err.to_string())?;
+        self.lines += 1;
+
+        if let Some(compare) = &self.compare {
+            let expected = compare.get(self.lines - 1).map(String::as_str);
+            if !expected.is_some_and(|expected| matches(expected, &line)) {
+                // Name the first column that differs when the cells line up.
+                let column = expected
+                    .and_then(|expected| {
+                        let expected = expected.strip_prefix('|')?.strip_suffix('|')?;
+                        let index = expected
+                            .split('|')
+                            .zip(cells)
+                            .position(|(expected, cell)| !matches(expected, cell))?;
+                        self.columns.get(index)
+                    })
+                    .map(|column| format!(", column {}", column.name))
+                    .unwrap_or_default();
+                return Err(format!(
+                    "Comparison failure at line {}{}\n  expected: {}\n  actual:   {}",
+                    self.lines,
+                    column,
+                    expected.unwrap_or("<end of file>"),
+                    line
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// matches compares a line or cell to the compare file, where a `*`
+/// matches any character, e.g. the outM of a CPU that doesn't write.
+fn matches(expected: &str, actual: &str) -> bool {
+    expected.chars().count() == actual.chars().count()
+        && expected
+            .chars()
+            .zip(actual.chars())
+            .all(|(expected, actual)| expected == '*' || expected == actual)
+}
@@ -0,0 +1,414 @@
+use std::fmt;
+
+/// Format of an output-list column, written `name%Fl.w.r` where F is the
+/// format letter, w the width of the value and l, r the spaces around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Binary,
+    Decimal,
+    Hex,
+    String,
+}
+
+/// Column is one variable of an output-list command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub format: Format,
+    pub pad_left: usize,
+    pub width: usize,
+    pub pad_right: usize,
+}
+
+impl Column {
+    /// parse reads a column like `RAM[0]%D2.6.2`, a name without format
+    /// is printed in binary like the course tools do.
+    fn parse(column: &str) -> Result<Self, String> {
+        let Some((name, format)) = column.split_once('%') else {
+            return Ok(Column {
+                name: column.to_string(),
+                format: Format::Binary,
+                pad_left: 1,
+                width: 16,
+                pad_right: 1,
+            });
+        };
+
+        let invalid = || format!("Invalid output format: {}", column);
+        let mut chars = format.chars();
+        let format = match chars.next() {
+            Some('B') => Format::Binary,
+            Some('D') => Format::Decimal,
+            Some('X') => Format::Hex,
+            Some('S') => Format::String,
+            _ => return Err(invalid()),
+        };
+        let sizes: Vec<usize> = chars
+            .as_str()
+            .split('.')
+            .map(|size| size.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let [pad_left, width, pad_right] = sizes[..] else {
+            return Err(invalid());
+        };
+
+        Ok(Column {
+            name: name.to_string(),
+            format,
+            pad_left,
+            width,
+            pad_right,
+        })
+    }
+
+    /// header is the name centered in the column, cut if it doesn't fit.
+    pub fn header(&self) -> String {
+        let size = self.pad_left + self.width + self.pad_right;
+        let name: String = self.name.chars().take(size).collect();
+        let left = (size - name.chars().count()) / 2;
+        format!("{:left$}{:<right$}", "", name, right = size - left)
+    }
+
+    /// cell formats a value of the column. Numbers are 16-bit words, binary
+    /// and hex values keep the lower bits that fit in the width.
+    pub fn cell(&self, value: &Value) -> String {
+        let text = match (self.format, value) {
+            (Format::String, value) => {
+                format!("{:<width$}", value.to_string(), width = self.width)
+            }
+            (_, Value::Text(text)) => format!("{:>width$}", text, width = self.width),
+            (Format::Decimal, Value::Number(number)) => {
+                format!("{:>width$}", number, width = self.width)
+            }
+            (Format::Binary, Value::Number(number)) => {
+                let digits = format!("{:016b}", *number as u16);
+                last_chars(&digits, self.width)
+            }
+            (Format::Hex, Value::Number(number)) => {
+                let digits = format!("{:04x}", *number as u16);
+                last_chars(&digits, self.width)
+            }
+        };
+        format!(
+            "{:left$}{}{:right$}",
+            "",
+            text,
+            "",
+            left = self.pad_left,
+            right = self.pad_right
+        )
+    }
+}
+
+/// last_chars keeps the width last digits, padded with zeros if needed.
+fn last_chars(digits: &str, width: usize) -> String {
+    if digits.len() >= width {
+        digits[digits.len() - width..].to_string()
+    } else {
+        format!("{:0>width$}", digits, width = width)
+    }
+}
+
+/// Value of a variable read by an output-list column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Number(i64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// parse_number reads a number of a set command or a while condition,
+/// decimal by default or with a %B, %X or %D prefix.
+pub fn parse_number(value: &str) -> Result<i64, String> {
+    let (radix, digits) = match value.get(..2) {
+        Some("%B") => (2, &value[2..]),
+        Some("%X") => (16, &value[2..]),
+        Some("%D") => (10, &value[2..]),
+        _ => (10, value),
+    };
+    let number =
+        i64::from_str_radix(digits, radix).map_err(|_| format!("Invalid number: {}", value))?;
+    // Binary and hex literals are the bits of a 16-bit word, e.g. %XFFFF is -1.
+    if radix != 10 && number > i16::MAX as i64 && number <= u16::MAX as i64 {
+        return Ok(number as u16 as i16 as i64);
+    }
+    Ok(number)
+}
+
+/// Comparison operator of a while condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl Compare {
+    // Longer operators first, so that "<=" is not read as "<".
+    const OPERATORS: [(&'static str, Compare); 6] = [
+        ("<>", Compare::Ne),
+        ("<=", Compare::Le),
+        (">=", Compare::Ge),
+        ("=", Compare::Eq),
+        ("<", Compare::Lt),
+        (">", Compare::Gt),
+    ];
+
+    pub fn holds(self, left: i64, right: i64) -> bool {
+        match self {
+            Compare::Eq => left == right,
+            Compare::Ne => left != right,
+            Compare::Lt => left < right,
+            Compare::Gt => left > right,
+            Compare::Le => left <= right,
+            Compare::Ge => left >= right,
+        }
+    }
+}
+
+/// Condition of a while loop, both sides are a variable or a number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub left: String,
+    pub compare: Compare,
+    pub right: String,
+}
+
+impl Condition {
+    fn parse(words: &[String]) -> Result<Self, String> {
+        let text = words.concat();
+        for (operator, compare) in Compare::OPERATORS {
+            if let Some((left, right)) = text.split_once(operator) {
+                if !left.is_empty() && !right.is_empty() {
+                    return Ok(Condition {
+                        left: left.to_string(),
+                        compare,
+                        right: right.to_string(),
+                    });
+                }
+            }
+        }
+        Err(format!("Invalid while condition: {}", words.join(" ")))
+    }
+}
+
+/// Command is a single command of a test script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Loads the program or chip to test, relative to the script.
+    Load(Option<String>),
+    OutputFile(String),
+    CompareTo(String),
+    OutputList(Vec<Column>),
+    Set(String, i64),
+    /// Writes the values of the output-list variables.
+    Output,
+    Echo(String),
+    ClearEcho,
+    /// Runs repeat times, forever when None.
+    Repeat(Option<u64>, Vec<Statement>),
+    While(Condition, Vec<Statement>),
+    /// A simulator specific command with its arguments, e.g. ticktock,
+    /// tick, tock, eval, vmstep or `ROM32K load Max.hack`.
+    Simulate(String),
+}
+
+/// Statement is a command with the script line it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub line: usize,
+    pub command: Command,
+}
+
+/// Token of a test script with its line number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Open,
+    Close,
+    /// The `,`, `;` or `!` ending a command.
+    End,
+}
+
+fn tokenize(script: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = script.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|c| *c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let start = line;
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => return Err(format!("line {}: unterminated comment", start)),
+                    }
+                }
+            }
+            '{' => tokens.push((line, Token::Open)),
+            '}' => tokens.push((line, Token::Close)),
+            ',' | ';' | '!' => tokens.push((line, Token::End)),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(format!("line {}: unterminated string", line))
+                        }
+                        Some(c) => text.push(c),
+                    }
+                }
+                tokens.push((line, Token::Text(text)));
+            }
+            c => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| {
+                    !c.is_whitespace() && !matches!(c, '{' | '}' | ',' | ';' | '!' | '"')
+                }) {
+                    word.push(c);
+                }
+                tokens.push((line, Token::Word(word)));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// parse reads a test script into its list of statements.
+pub fn parse(script: &str) -> Result<Vec<Statement>, String> {
+    let tokens = tokenize(script)?;
+    let mut position = 0;
+    let statements = parse_block(&tokens, &mut position)?;
+    match tokens.get(position) {
+        Some((line, _)) => Err(format!("line {}: unexpected }}", line)),
+        None => Ok(statements),
+    }
+}
+
+/// parse_block reads statements until the closing brace of the block or
+/// the end of the script.
+fn parse_block(tokens: &[(usize, Token)], position: &mut usize) -> Result<Vec<Statement>, String> {
+    let mut statements = Vec::new();
+
+    while let Some((line, token)) = tokens.get(*position) {
+        let line = *line;
+        let name = match token {
+            Token::Close => break,
+            Token::End => {
+                *position += 1;
+                continue;
+            }
+            Token::Word(name) => name.as_str(),
+            Token::Text(_) | Token::Open => {
+                return Err(format!("line {}: expected a command", line));
+            }
+        };
+        *position += 1;
+
+        // Arguments run up to the end of the command or the opening brace.
+        let mut words = Vec::new();
+        let mut text = None;
+        while let Some((_, token)) = tokens.get(*position) {
+            match token {
+                Token::Word(word) => words.push(word.clone()),
+                Token::Text(value) => text = Some(value.clone()),
+                _ => break,
+            }
+            *position += 1;
+        }
+        let located = |err: String| format!("line {}: {}", line, err);
+
+        let command = match name {
+            "repeat" | "while" => {
+                if !matches!(tokens.get(*position), Some((_, Token::Open))) {
+                    return Err(located(format!("expected {{ after {}", name)));
+                }
+                *position += 1;
+                let body = parse_block(tokens, position)?;
+                if !matches!(tokens.get(*position), Some((_, Token::Close))) {
+                    return Err(located(format!("missing }} of {}", name)));
+                }
+                *position += 1;
+
+                if name == "while" {
+                    Command::While(Condition::parse(&words).map_err(located)?, body)
+                } else {
+                    let count =
+                        match words.as_slice() {
+                            [] => None,
+                            [count] => Some(count.parse().map_err(|_| {
+                                located(format!("Invalid repeat count: {}", count))
+                            })?),
+                            _ => return Err(located(String::from("Too many repeat arguments"))),
+                        };
+                    Command::Repeat(count, body)
+                }
+            }
+            "load" => Command::Load(words.first().cloned()),
+            "output-file" | "compare-to" => {
+                let [file] = words.as_slice() else {
+                    return Err(located(format!("{} expects a file name", name)));
+                };
+                if name == "output-file" {
+                    Command::OutputFile(file.clone())
+                } else {
+                    Command::CompareTo(file.clone())
+                }
+            }
+            "output-list" => Command::OutputList(
+                words
+                    .iter()
+                    .map(|word| Column::parse(word))
+                    .collect::<Result<_, _>>()
+                    .map_err(located)?,
+            ),
+            "set" => {
+                let [variable, value] = words.as_slice() else {
+                    return Err(located(String::from("set expects a variable and a value")));
+                };
+                Command::Set(variable.clone(), parse_number(value).map_err(located)?)
+            }
+            "output" => Command::Output,
+            "echo" => Command::Echo(text.unwrap_or_else(|| words.join(" "))),
+            "clear-echo" => Command::ClearEcho,
+            _ if text.is_none() => {
+                let mut command = name.to_string();
+                for word in &words {
+                    command.push(' ');
+                    command.push_str(word);
+                }
+                Command::Simulate(command)
+            }
+            _ => return Err(located(format!("Unknown command: {}", name))),
+        };
+        statements.push(Statement { line, command });
+    }
+
+    Ok(statements)
+}
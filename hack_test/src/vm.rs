@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use vm_emulator::{Vm, RAM_SIZE};
+use vm_translator::parser::Segment;
+
+use crate::runner::Simulator;
+use crate::script::Value;
+
+/// VmSimulator runs the scripts of VM programs on the vm_emulator, a
+/// program is a `.vm` file or a directory of them.
+///
+/// Variables are the pointers `sp`, `local`, `argument`, `this` and `that`,
+/// the segment words like `local[2]` or `temp[0]`, `RAM[address]` and
+/// `currentFunction`. The only command is `vmstep`, one VM command.
+#[derive(Default)]
+pub struct VmSimulator {
+    vm: Option<Vm>,
+}
+
+impl VmSimulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn vm(&self) -> Result<&Vm, String> {
+        self.vm
+            .as_ref()
+            .ok_or_else(|| String::from("No program loaded"))
+    }
+
+    fn vm_mut(&mut self) -> Result<&mut Vm, String> {
+        self.vm
+            .as_mut()
+            .ok_or_else(|| String::from("No program loaded"))
+    }
+}
+
+/// address is the RAM address of a variable, see VmSimulator.
+fn address(vm: &Vm, name: &str) -> Result<usize, String> {
+    let pointer = match name {
+        "sp" => Some(0),
+        "local" => Some(1),
+        "argument" => Some(2),
+        "this" => Some(3),
+        "that" => Some(4),
+        _ => None,
+    };
+    if let Some(address) = pointer {
+        return Ok(address);
+    }
+
+    let unknown = || format!("Unknown variable: {}", name);
+    let (memory, index) = name
+        .strip_suffix(']')
+        .and_then(|name| name.split_once('['))
+        .ok_or_else(unknown)?;
+    let index: u16 = index.parse().map_err(|_| unknown())?;
+    let segment = match memory {
+        "RAM" if (index as usize) < RAM_SIZE => return Ok(index as usize),
+        "local" => Segment::Local,
+        "argument" => Segment::Argument,
+        "this" => Segment::This,
+        "that" => Segment::That,
+        "temp" if index <= Segment::Temp.max_index() => Segment::Temp,
+        "pointer" if index <= Segment::Pointer.max_index() => Segment::Pointer,
+        _ => return Err(unknown()),
+    };
+    vm.address(segment, index)
+        .ok_or_else(|| format!("{} is outside of the RAM", name))
+}
+
+impl Simulator for VmSimulator {
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let mut vm = Vm::load(path)?;
+        // Like the VM emulator of the course, a program with Sys.init
+        // starts with the stack at 256, scripts may still set it.
+        let _ = vm.bootstrap();
+        self.vm = Some(vm);
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Value, String> {
+        let vm = self.vm()?;
+        if name == "currentFunction" {
+            return Ok(Value::Text(vm.current_function().to_string()));
+        }
+        Ok(Value::Number(vm.peek(address(vm, name)?) as i16 as i64))
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+        if !(i16::MIN as i64..=u16::MAX as i64).contains(&value) {
+            return Err(format!("Value out of the 16-bit range: {}", value));
+        }
+        let vm = self.vm_mut()?;
+        let address = address(vm, name)?;
+        vm.poke(address, value as u16);
+        Ok(())
+    }
+
+    fn simulate(&mut self, command: &str) -> Result<(), String> {
+        match command {
+            "vmstep" => self.vm_mut()?.step().map(|_| ()),
+            _ => Err(format!("Unknown command: {}", command)),
+        }
+    }
+}
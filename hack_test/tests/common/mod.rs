@@ -0,0 +1,59 @@
+//! Runs the hack_test binary on copies of the course projects, the scripts
+//! write their .out files next to them.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// project is the path of a directory of projects/.
+pub fn project(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects")
+        .join(path)
+}
+
+/// copy copies the files of a directory to a new directory in the temporary
+/// directory of the system, named after the test.
+pub fn copy(directory: &Path, test: &str) -> PathBuf {
+    let copy = std::env::temp_dir().join(format!("hack_test-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&copy);
+    fs::create_dir_all(&copy).unwrap();
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() {
+            fs::copy(&path, copy.join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    copy
+}
+
+/// scripts lists the .tst files of a directory.
+pub fn scripts(directory: &Path) -> Vec<PathBuf> {
+    let mut scripts: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tst"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// hack_test runs the scripts, it asserts that each of them compares
+/// successfully with its .cmp file.
+pub fn hack_test(scripts: &[PathBuf]) {
+    assert!(!scripts.is_empty());
+    let output = Command::new(env!("CARGO_BIN_EXE_hack_test"))
+        .args(scripts)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for script in scripts {
+        let success = format!("{}: comparison ended successfully", script.display());
+        assert!(stdout.contains(&success), "{}", stdout);
+    }
+}
@@ -0,0 +1,12 @@
+//! The scripts of projects/hack-asm on the CPU emulator, they load the
+//! .asm programs through the assembler.
+mod common;
+
+use common::{copy, hack_test, project, scripts};
+
+#[test]
+fn passes_the_mult_script() {
+    let copy = copy(&project("hack-asm/mult"), "mult");
+    hack_test(&scripts(&copy));
+    std::fs::remove_dir_all(copy).unwrap();
+}
@@ -0,0 +1,13 @@
+//! The scripts of the chips of the course on the HDL simulator.
+mod common;
+
+use common::{copy, hack_test, project, scripts};
+
+#[test]
+fn passes_the_scripts_of_the_chips() {
+    for directory in ["elementary-chips", "combinational-chips"] {
+        let copy = copy(&project(directory), directory);
+        hack_test(&scripts(&copy));
+        std::fs::remove_dir_all(copy).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+//! The scripts of projects/07 on the VM emulator, and on the CPU emulator
+//! with the code of the vm_translator.
+mod common;
+
+use std::fs::File;
+
+use vm_translator::{parse_file, translate, Options};
+
+use common::{copy, hack_test, project, scripts};
+
+const TESTS: [&str; 5] = [
+    "StackArithmetic/SimpleAdd",
+    "StackArithmetic/StackTest",
+    "MemoryAccess/BasicTest",
+    "MemoryAccess/PointerTest",
+    "MemoryAccess/StaticTest",
+];
+
+#[test]
+fn passes_the_scripts_of_projects07() {
+    for test in TESTS {
+        let name = test.rsplit('/').next().unwrap();
+        let directory = copy(&project("07").join(test), name);
+        let file = parse_file(&directory.join(format!("{}.vm", name))).unwrap();
+
+        for (compact, optimize) in [(false, false), (false, true), (true, false), (true, true)] {
+            let options = Options {
+                compact,
+                optimize,
+                bootstrap: false,
+                comments: true,
+            };
+            let asm = File::create(directory.join(format!("{}.asm", name))).unwrap();
+            translate(std::slice::from_ref(&file), asm, &options).unwrap();
+            hack_test(&scripts(&directory));
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
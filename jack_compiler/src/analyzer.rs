@@ -0,0 +1,542 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::{
+    BinaryOp, Class, ClassVarKind, Expression, KeywordConstant, Statement, Subroutine,
+    SubroutineCall, SubroutineKind, Term, Type, UnaryOp,
+};
+use crate::error::Span;
+use crate::os;
+use crate::symbols::{Kind, SymbolTable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A mistake the compiler refuses.
+    Error,
+    /// A program that compiles but is likely wrong.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Diagnostic is an issue found in a `.jack` file, displayed like
+/// `7:13: error: undefined variable x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(span: Span, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            span,
+            message,
+        }
+    }
+
+    fn warning(span: Span, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.span, self.severity, self.message)
+    }
+}
+
+/// Signature is the declaration of a subroutine, what a call needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub kind: SubroutineKind,
+    /// The return type, None for void.
+    pub return_type: Option<Type>,
+    pub parameters: Vec<Type>,
+    pub span: Span,
+}
+
+/// ClassInfo is the API of a class, its subroutines by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassInfo {
+    pub subroutines: HashMap<String, Signature>,
+    /// Whether the class is one of the OS, not of the program.
+    pub os: bool,
+}
+
+impl ClassInfo {
+    fn new(class: &Class, os: bool) -> Self {
+        let mut subroutines = HashMap::new();
+        for subroutine in &class.subroutines {
+            subroutines
+                .entry(subroutine.name.name.clone())
+                .or_insert_with(|| Signature {
+                    kind: subroutine.kind,
+                    return_type: subroutine.return_type.clone(),
+                    parameters: subroutine
+                        .parameters
+                        .iter()
+                        .map(|parameter| parameter.ty.clone())
+                        .collect(),
+                    span: subroutine.name.span,
+                });
+        }
+        ClassInfo { subroutines, os }
+    }
+}
+
+/// Analyzer checks the classes of a program against each other and the OS
+/// API: the identifiers, the calls and the returns of their subroutines.
+pub struct Analyzer {
+    classes: HashMap<String, ClassInfo>,
+}
+
+impl Analyzer {
+    /// new knows the classes of a program and the OS ones, a class of the
+    /// program replaces the OS class of the same name.
+    pub fn new(classes: &[Class]) -> Self {
+        let mut known = HashMap::new();
+        for class in os::classes() {
+            known.insert(class.name.name.clone(), ClassInfo::new(&class, true));
+        }
+        for class in classes {
+            known.insert(class.name.name.clone(), ClassInfo::new(class, false));
+        }
+        Analyzer { classes: known }
+    }
+
+    pub fn class(&self, name: &str) -> Option<&ClassInfo> {
+        self.classes.get(name)
+    }
+
+    /// check finds the issues of a class, sorted by position.
+    pub fn check(&self, class: &Class) -> Vec<Diagnostic> {
+        let mut checker = Checker {
+            analyzer: self,
+            class,
+            symbols: SymbolTable::default(),
+            subroutine: None,
+            used: HashSet::new(),
+            diagnostics: Vec::new(),
+        };
+        checker.class();
+        let mut diagnostics = checker.diagnostics;
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span);
+        diagnostics
+    }
+}
+
+/// Checker walks a class with the variables in scope.
+struct Checker<'a> {
+    analyzer: &'a Analyzer,
+    class: &'a Class,
+    symbols: SymbolTable,
+    subroutine: Option<&'a Subroutine>,
+    /// Declarations of the variables read or written, by span.
+    used: HashSet<Span>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// assignable tells whether a value of a type can be stored in a variable
+/// of another type. Jack is loosely typed: the primitive types mix, and an
+/// int or an Array is an address of any object.
+fn assignable(expected: &Type, actual: &Type) -> bool {
+    match (expected, actual) {
+        (Type::Class(expected), Type::Class(actual)) => {
+            expected == actual || expected == "Array" || actual == "Array"
+        }
+        (Type::Class(_), Type::Int) | (Type::Int, Type::Class(_)) => true,
+        (Type::Class(_), _) | (_, Type::Class(_)) => false,
+        _ => true,
+    }
+}
+
+/// returns tells whether statements return on every path.
+fn returns(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Return { .. } => true,
+        Statement::If {
+            then,
+            otherwise: Some(otherwise),
+            ..
+        } => returns(then) && returns(otherwise),
+        _ => false,
+    })
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic::error(span, message));
+    }
+
+    fn warning(&mut self, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic::warning(span, message));
+    }
+
+    fn in_function(&self) -> bool {
+        self.subroutine
+            .is_some_and(|subroutine| subroutine.kind == SubroutineKind::Function)
+    }
+
+    fn check_type(&mut self, ty: &Type, span: Span) {
+        if let Type::Class(name) = ty {
+            if self.analyzer.class(name).is_none() {
+                self.error(span, format!("undefined class {}", name));
+            }
+        }
+    }
+
+    fn class(&mut self) {
+        let class = self.class;
+        for variable in &class.variables {
+            self.check_type(&variable.ty, variable.type_span);
+            let kind = match variable.kind {
+                ClassVarKind::Static => Kind::Static,
+                ClassVarKind::Field => Kind::Field,
+            };
+            for name in &variable.names {
+                if let Err(err) = self.symbols.define(name, &variable.ty, kind) {
+                    self.error(err.span, err.message);
+                }
+            }
+        }
+
+        let mut declared: HashMap<&str, Span> = HashMap::new();
+        for subroutine in &class.subroutines {
+            let name = subroutine.name.name.as_str();
+            if let Some(previous) = declared.get(name) {
+                let message = format!("{} is already declared at {}", name, previous);
+                self.error(subroutine.name.span, message);
+            } else {
+                declared.insert(name, subroutine.name.span);
+            }
+            self.subroutine(subroutine);
+        }
+    }
+
+    fn subroutine(&mut self, subroutine: &'a Subroutine) {
+        self.symbols.start_subroutine();
+        self.subroutine = Some(subroutine);
+        let name = &subroutine.name.name;
+
+        match (&subroutine.return_type, subroutine.kind) {
+            (Some(Type::Class(ty)), SubroutineKind::Constructor) if *ty == self.class.name.name => {
+            }
+            (_, SubroutineKind::Constructor) => {
+                let message = format!("constructor {} must return {}", name, self.class.name.name);
+                self.error(subroutine.type_span, message);
+            }
+            (Some(ty), _) => self.check_type(ty, subroutine.type_span),
+            (None, _) => {}
+        }
+
+        for parameter in &subroutine.parameters {
+            self.check_type(&parameter.ty, parameter.type_span);
+            if let Err(err) = self
+                .symbols
+                .define(&parameter.name, &parameter.ty, Kind::Argument)
+            {
+                self.error(err.span, err.message);
+            }
+        }
+        let mut locals = Vec::new();
+        for local in &subroutine.locals {
+            self.check_type(&local.ty, local.type_span);
+            for variable in &local.names {
+                match self.symbols.define(variable, &local.ty, Kind::Local) {
+                    Ok(()) => locals.push(variable),
+                    Err(err) => self.error(err.span, err.message),
+                }
+            }
+        }
+
+        self.statements(&subroutine.statements);
+        if !returns(&subroutine.statements) {
+            let message = format!("missing return at the end of {}", name);
+            self.warning(subroutine.end, message);
+        }
+        for variable in locals {
+            if !self.used.contains(&variable.span) {
+                let message = format!("unused variable {}", variable.name);
+                self.warning(variable.span, message);
+            }
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for (index, statement) in statements.iter().enumerate() {
+            if index > 0 && matches!(statements[index - 1], Statement::Return { .. }) {
+                self.warning(statement.span(), String::from("unreachable statement"));
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let {
+                target,
+                index,
+                value,
+                ..
+            } => {
+                let target_type = self.variable(&target.name, target.span);
+                if let Some(index) = index {
+                    self.expression(index);
+                }
+                let value_type = self.expression(value);
+                if let (Some(expected), Some(actual), None) = (target_type, value_type, index) {
+                    if !assignable(&expected, &actual) {
+                        let message = format!(
+                            "{} is {}, it cannot be assigned {}",
+                            target.name, expected, actual
+                        );
+                        self.warning(value.span(), message);
+                    }
+                }
+            }
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                self.expression(condition);
+                self.statements(then);
+                if let Some(otherwise) = otherwise {
+                    self.statements(otherwise);
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.expression(condition);
+                self.statements(body);
+            }
+            Statement::Do { call, .. } => {
+                self.call(call);
+            }
+            Statement::Return { value, span } => self.return_statement(value.as_ref(), *span),
+        }
+    }
+
+    fn return_statement(&mut self, value: Option<&Expression>, span: Span) {
+        let Some(subroutine) = self.subroutine else {
+            return;
+        };
+        let name = &subroutine.name.name;
+        let actual = value.and_then(|value| self.expression(value));
+        match (&subroutine.return_type, value) {
+            (Some(expected), None) => {
+                let message = format!("{} must return a value of type {}", name, expected);
+                self.error(span, message);
+            }
+            (None, Some(value)) => {
+                let message = format!("{} is void, it cannot return a value", name);
+                self.error(value.span(), message);
+            }
+            (Some(expected), Some(value)) => {
+                let is_this = matches!(value.first, Term::Keyword(KeywordConstant::This, _))
+                    && value.rest.is_empty();
+                if subroutine.kind == SubroutineKind::Constructor && !is_this {
+                    let message = format!("constructor {} should return this", name);
+                    self.warning(value.span(), message);
+                } else if let Some(actual) = actual.filter(|actual| !assignable(expected, actual)) {
+                    let message = format!("{} returns {}, not {}", name, expected, actual);
+                    self.warning(value.span(), message);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// variable is the type of a variable, after checking it is declared
+    /// and usable in the subroutine.
+    fn variable(&mut self, name: &str, span: Span) -> Option<Type> {
+        let Some(symbol) = self.symbols.get(name) else {
+            self.error(span, format!("undefined variable {}", name));
+            return None;
+        };
+        let (ty, kind) = (symbol.ty.clone(), symbol.kind);
+        self.used.insert(symbol.span);
+        if kind == Kind::Field && self.in_function() {
+            self.error(span, format!("field {} used in a function", name));
+        }
+        Some(ty)
+    }
+
+    /// expression checks an expression and finds its type, None when it is
+    /// unknown or null.
+    fn expression(&mut self, expression: &Expression) -> Option<Type> {
+        let mut ty = self.term(&expression.first);
+        for (op, _, term) in &expression.rest {
+            let right = self.term(term);
+            ty = match op {
+                BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Eq => Some(Type::Boolean),
+                BinaryOp::And | BinaryOp::Or
+                    if ty == Some(Type::Boolean) && right == Some(Type::Boolean) =>
+                {
+                    Some(Type::Boolean)
+                }
+                _ => Some(Type::Int),
+            };
+        }
+        ty
+    }
+
+    fn term(&mut self, term: &Term) -> Option<Type> {
+        match term {
+            Term::Integer(..) => Some(Type::Int),
+            Term::String(..) => Some(Type::Class(String::from("String"))),
+            Term::Keyword(constant, span) => match constant {
+                KeywordConstant::True | KeywordConstant::False => Some(Type::Boolean),
+                KeywordConstant::Null => None,
+                KeywordConstant::This => {
+                    if self.in_function() {
+                        self.error(*span, String::from("this used in a function"));
+                    }
+                    Some(Type::Class(self.class.name.name.clone()))
+                }
+            },
+            Term::Variable(name) => self.variable(&name.name, name.span),
+            Term::Index(name, index) => {
+                self.variable(&name.name, name.span);
+                self.expression(index);
+                None
+            }
+            Term::Call(call) => {
+                let (ty, void) = self.call(call);
+                if void {
+                    let message = format!("{} returns void, it has no value", call.name.name);
+                    self.warning(call.name.span, message);
+                }
+                ty
+            }
+            Term::Paren(expression, _) => self.expression(expression),
+            Term::Unary(op, term, _) => {
+                let ty = self.term(term);
+                match op {
+                    UnaryOp::Not if ty == Some(Type::Boolean) => ty,
+                    _ => Some(Type::Int),
+                }
+            }
+        }
+    }
+
+    /// call checks a subroutine call, it returns the type of its value and
+    /// whether the subroutine is void.
+    fn call(&mut self, call: &SubroutineCall) -> (Option<Type>, bool) {
+        let name = &call.name.name;
+        // The class of the subroutine, and whether it is called on an object.
+        let (class, object) = match &call.receiver {
+            None => {
+                let class = self.class.name.name.clone();
+                (class, true)
+            }
+            Some(receiver) => match self.symbols.get(&receiver.name) {
+                Some(_) => match self.variable(&receiver.name, receiver.span) {
+                    Some(Type::Class(class)) => (class, true),
+                    Some(ty) => {
+                        let message = format!("{} is {}, not an object", receiver.name, ty);
+                        self.error(receiver.span, message);
+                        return self.arguments(call, None);
+                    }
+                    None => return self.arguments(call, None),
+                },
+                None => {
+                    if self.analyzer.class(&receiver.name).is_none() {
+                        let message = format!("undefined class or variable {}", receiver.name);
+                        self.error(receiver.span, message);
+                        return self.arguments(call, None);
+                    }
+                    (receiver.name.clone(), false)
+                }
+            },
+        };
+
+        let Some(info) = self.analyzer.class(&class) else {
+            // The type of the variable is an undefined class, it is reported
+            // at its declaration.
+            return self.arguments(call, None);
+        };
+        let Some(signature) = info.subroutines.get(name) else {
+            let message = format!("{} has no subroutine {}", class, name);
+            self.error(call.name.span, message);
+            return self.arguments(call, None);
+        };
+
+        let full_name = format!("{}.{}", class, name);
+        match (signature.kind, &call.receiver, object) {
+            (SubroutineKind::Method, None, _) if self.in_function() => {
+                let message = format!("method {} called from a function", full_name);
+                self.error(call.name.span, message);
+            }
+            (SubroutineKind::Method, _, false) => {
+                let message = format!("{} is a method, it needs an object", full_name);
+                self.error(call.name.span, message);
+            }
+            (kind, _, true) if kind != SubroutineKind::Method => {
+                let message = format!("{} is not a method, call it as {}", name, full_name);
+                self.error(call.name.span, message);
+            }
+            _ => {}
+        }
+        if call.arguments.len() != signature.parameters.len() {
+            let message = format!(
+                "{} expects {} arguments, got {}",
+                full_name,
+                signature.parameters.len(),
+                call.arguments.len()
+            );
+            self.error(call.name.span, message);
+        }
+        let signature = signature.clone();
+        self.arguments(call, Some((&full_name, &signature)))
+    }
+
+    /// arguments checks the arguments of a call against the parameters of
+    /// the subroutine, when it is known.
+    fn arguments(
+        &mut self,
+        call: &SubroutineCall,
+        signature: Option<(&str, &Signature)>,
+    ) -> (Option<Type>, bool) {
+        for (index, argument) in call.arguments.iter().enumerate() {
+            let actual = self.expression(argument);
+            let expected = signature.and_then(|(_, signature)| signature.parameters.get(index));
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                if !assignable(expected, &actual) {
+                    let name = signature.map_or("", |(name, _)| name);
+                    let message = format!(
+                        "argument {} of {} is {}, got {}",
+                        index + 1,
+                        name,
+                        expected,
+                        actual
+                    );
+                    self.warning(argument.span(), message);
+                }
+            }
+        }
+        match signature {
+            Some((_, signature)) => (
+                signature.return_type.clone(),
+                signature.return_type.is_none(),
+            ),
+            None => (None, false),
+        }
+    }
+}
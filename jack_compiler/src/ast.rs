@@ -0,0 +1,261 @@
+use std::fmt;
+
+use crate::error::Span;
+
+/// Identifier is a name with its position in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+}
+
+/// Type of a variable, a parameter or a return value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Char,
+    Boolean,
+    /// An object of a class, e.g. `Array` or `String`.
+    Class(String),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Char => write!(f, "char"),
+            Type::Boolean => write!(f, "boolean"),
+            Type::Class(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Class is a parsed `.jack` file, the unit of compilation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Class {
+    pub name: Identifier,
+    pub variables: Vec<ClassVarDec>,
+    pub subroutines: Vec<Subroutine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassVarKind {
+    Static,
+    Field,
+}
+
+/// ClassVarDec declares static or field variables, `field int x, y;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassVarDec {
+    pub kind: ClassVarKind,
+    pub ty: Type,
+    pub type_span: Span,
+    pub names: Vec<Identifier>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubroutineKind {
+    Constructor,
+    Function,
+    Method,
+}
+
+/// Subroutine is a constructor, function or method of a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subroutine {
+    pub kind: SubroutineKind,
+    /// The return type, None for void.
+    pub return_type: Option<Type>,
+    pub type_span: Span,
+    pub name: Identifier,
+    pub parameters: Vec<Parameter>,
+    pub locals: Vec<VarDec>,
+    pub statements: Vec<Statement>,
+    /// Position of the `}` closing the body.
+    pub end: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    pub ty: Type,
+    pub type_span: Span,
+    pub name: Identifier,
+}
+
+/// VarDec declares local variables, `var int i, sum;`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarDec {
+    pub ty: Type,
+    pub type_span: Span,
+    pub names: Vec<Identifier>,
+}
+
+/// Statement of a subroutine body, the span is the one of its keyword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Let {
+        target: Identifier,
+        /// The index of an array element, `let a[i] = x;`.
+        index: Option<Expression>,
+        value: Expression,
+        span: Span,
+    },
+    If {
+        condition: Expression,
+        then: Vec<Statement>,
+        otherwise: Option<Vec<Statement>>,
+        span: Span,
+    },
+    While {
+        condition: Expression,
+        body: Vec<Statement>,
+        span: Span,
+    },
+    Do {
+        call: SubroutineCall,
+        span: Span,
+    },
+    Return {
+        value: Option<Expression>,
+        span: Span,
+    },
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Let { span, .. }
+            | Statement::If { span, .. }
+            | Statement::While { span, .. }
+            | Statement::Do { span, .. }
+            | Statement::Return { span, .. } => *span,
+        }
+    }
+}
+
+/// Expression is a term followed by operations on other terms. Jack has
+/// no operator precedence, they apply from left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    pub first: Term,
+    pub rest: Vec<(BinaryOp, Span, Term)>,
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        self.first.span()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Lt,
+    Gt,
+    Eq,
+}
+
+impl BinaryOp {
+    pub fn parse(symbol: char) -> Option<Self> {
+        match symbol {
+            '+' => Some(BinaryOp::Add),
+            '-' => Some(BinaryOp::Sub),
+            '*' => Some(BinaryOp::Mul),
+            '/' => Some(BinaryOp::Div),
+            '&' => Some(BinaryOp::And),
+            '|' => Some(BinaryOp::Or),
+            '<' => Some(BinaryOp::Lt),
+            '>' => Some(BinaryOp::Gt),
+            '=' => Some(BinaryOp::Eq),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            BinaryOp::Add => '+',
+            BinaryOp::Sub => '-',
+            BinaryOp::Mul => '*',
+            BinaryOp::Div => '/',
+            BinaryOp::And => '&',
+            BinaryOp::Or => '|',
+            BinaryOp::Lt => '<',
+            BinaryOp::Gt => '>',
+            BinaryOp::Eq => '=',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-x`
+    Neg,
+    /// `~x`
+    Not,
+}
+
+impl UnaryOp {
+    pub fn symbol(self) -> char {
+        match self {
+            UnaryOp::Neg => '-',
+            UnaryOp::Not => '~',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordConstant {
+    True,
+    False,
+    Null,
+    This,
+}
+
+/// Term is an operand of an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Integer(u16, Span),
+    String(String, Span),
+    Keyword(KeywordConstant, Span),
+    Variable(Identifier),
+    /// An array element, `a[i]`.
+    Index(Identifier, Box<Expression>),
+    Call(SubroutineCall),
+    /// An expression in parentheses, the span is the one of `(`.
+    Paren(Box<Expression>, Span),
+    Unary(UnaryOp, Box<Term>, Span),
+}
+
+impl Term {
+    pub fn span(&self) -> Span {
+        match self {
+            Term::Integer(_, span)
+            | Term::String(_, span)
+            | Term::Keyword(_, span)
+            | Term::Paren(_, span)
+            | Term::Unary(_, _, span) => *span,
+            Term::Variable(name) | Term::Index(name, _) => name.span,
+            Term::Call(call) => call.span(),
+        }
+    }
+}
+
+/// SubroutineCall calls `name(...)` on this object, or `receiver.name(...)`
+/// where the receiver is a variable or a class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubroutineCall {
+    pub receiver: Option<Identifier>,
+    pub name: Identifier,
+    pub arguments: Vec<Expression>,
+}
+
+impl SubroutineCall {
+    pub fn span(&self) -> Span {
+        self.receiver.as_ref().unwrap_or(&self.name).span
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, ValueEnum};
+
+use hack_assembler::assembler::Assembler;
+use jack_compiler::analyzer::{Analyzer, Severity};
+use jack_compiler::codegen::{compile, vm_code};
+use jack_compiler::jack_files;
+use jack_compiler::optimizer::{instructions, optimize};
+use jack_compiler::os;
+use jack_compiler::parser::parse;
+use jack_compiler::tokenizer::{tokenize, tokens_xml};
+use jack_compiler::xml::class_xml;
+use vm_translator::parser::{parse_line, Command};
+use vm_translator::source_map::Mapping;
+use vm_translator::{translate, Options, VmFile};
+
+/// Number of words of the ROM of the Hack computer.
+const ROM_SIZE: usize = 32768;
+
+/// Emit is an output of the build, the ROM is the last one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// FileT.xml, the tokens of every Jack file.
+    Tokens,
+    /// File.xml, the parse tree of every Jack file.
+    Xml,
+    /// File.vm, the VM code of every Jack file.
+    Vm,
+    /// Program.asm, the assembly code of the program with the OS.
+    Asm,
+    /// Program.hack, the ROM.
+    Hack,
+}
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+#[command(name = "hackc")]
+struct Args {
+    /// The Jack program, a directory of Jack files (e.g. projects/11/Pong) or a single file.
+    path: PathBuf,
+
+    /// The files to write, separated by commas.
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "hack")]
+    emit: Vec<Emit>,
+
+    /// Directory of the output files, the directory of the program by default.
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+
+    /// Optimizes the VM code of the whole program with the OS: inlines the
+    /// getters, folds constants and removes the unused subroutines.
+    #[arg(short = 'O', long)]
+    optimize: bool,
+}
+
+impl Args {
+    fn emits(&self, emit: Emit) -> bool {
+        self.emit.contains(&emit)
+    }
+}
+
+/// write creates an output file, named after the program or a class.
+fn write(directory: &Path, name: &str, contents: &str) -> Result<(), String> {
+    let path = directory.join(name);
+    fs::write(&path, contents).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// vm_file reads the VM code of a class, the path only names it in errors.
+fn vm_file(path: PathBuf, name: &str, code: &str) -> Result<VmFile, String> {
+    let mut commands = Vec::new();
+    for (index, line) in code.lines().enumerate() {
+        match parse_line(line) {
+            Ok(Some(command)) => commands.push((index + 1, command)),
+            Ok(None) => {}
+            Err(err) => return Err(format!("{}:{}: {}", path.display(), index + 1, err)),
+        }
+    }
+    Ok(VmFile {
+        path,
+        name: name.to_string(),
+        commands,
+    })
+}
+
+/// Program is a built program, its ROM and the VM files it is made of.
+struct Program {
+    rom: Vec<u16>,
+    files: Vec<VmFile>,
+    /// Whether each file is one of the OS.
+    os: Vec<bool>,
+    mappings: Vec<Mapping>,
+    /// The VM instructions before and after the optimizer, and the words
+    /// of ROM before it.
+    optimized: Option<(usize, usize, usize)>,
+}
+
+/// Unit is the VM code of a class of the program or of the OS.
+struct Unit {
+    path: PathBuf,
+    name: String,
+    commands: Vec<Command>,
+    os: bool,
+}
+
+/// build compiles the Jack files of a program, links them with the OS and
+/// assembles them.
+fn build(args: &Args, out_dir: &Path, name: &str) -> Result<Program, String> {
+    let files =
+        jack_files(&args.path).map_err(|err| format!("{}: {}", args.path.display(), err))?;
+    if files.is_empty() {
+        return Err(format!("no .jack files found in {}", args.path.display()));
+    }
+
+    let mut classes = Vec::new();
+    for file in &files {
+        let source =
+            fs::read_to_string(file).map_err(|err| format!("{}: {}", file.display(), err))?;
+        let located = |err| format!("{}:{}", file.display(), err);
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        if args.emits(Emit::Tokens) {
+            let tokens = tokenize(&source).map_err(located)?;
+            write(out_dir, &format!("{}T.xml", stem), &tokens_xml(&tokens))?;
+        }
+        let class = parse(&source).map_err(located)?;
+        if args.emits(Emit::Xml) {
+            write(out_dir, &format!("{}.xml", stem), &class_xml(&class))?;
+        }
+        classes.push(class);
+    }
+
+    let analyzer = Analyzer::new(&classes);
+    let mut errors = 0;
+    for (file, class) in files.iter().zip(&classes) {
+        for diagnostic in analyzer.check(class) {
+            if diagnostic.severity == Severity::Error {
+                errors += 1;
+            }
+            eprintln!("{}:{}", file.display(), diagnostic);
+        }
+    }
+    if errors > 0 {
+        return Err(format!("{} errors in {}", errors, args.path.display()));
+    }
+
+    let mut units = Vec::new();
+    for (file, class) in files.iter().zip(&classes) {
+        let commands = compile(class).map_err(|err| format!("{}:{}", file.display(), err))?;
+        units.push(Unit {
+            path: file.with_extension("vm"),
+            name: class.name.name.clone(),
+            commands,
+            os: false,
+        });
+    }
+    // The program may replace classes of the OS with its own.
+    for class in os::classes() {
+        let class_name = &class.name.name;
+        if classes.iter().all(|other| &other.name.name != class_name) {
+            let path = PathBuf::from(format!("projects/12/{}.jack", class_name));
+            let commands = compile(&class).map_err(|err| format!("{}:{}", path.display(), err))?;
+            units.push(Unit {
+                path,
+                name: class_name.clone(),
+                commands,
+                os: true,
+            });
+        }
+    }
+
+    let mut optimized = None;
+    if args.optimize {
+        // The unoptimized program is linked too, for the report.
+        let (_, _, rom) = link(&vm_files(&units)?, out_dir, name)?;
+        let count = units.iter().map(|unit| instructions(&unit.commands)).sum();
+        let mut code: Vec<_> = units.iter().map(|unit| unit.commands.clone()).collect();
+        optimize(&mut code);
+        for (unit, commands) in units.iter_mut().zip(code) {
+            unit.commands = commands;
+        }
+        let after = units.iter().map(|unit| instructions(&unit.commands)).sum();
+        optimized = Some((count, after, rom.len()));
+    }
+
+    if args.emits(Emit::Vm) {
+        for unit in units.iter().filter(|unit| !unit.os) {
+            write(
+                out_dir,
+                &format!("{}.vm", unit.name),
+                &vm_code(&unit.commands),
+            )?;
+        }
+    }
+    let files = vm_files(&units)?;
+    let (mappings, asm, rom) = link(&files, out_dir, name)?;
+    if args.emits(Emit::Asm) {
+        write(out_dir, &format!("{}.asm", name), &asm)?;
+    }
+    Ok(Program {
+        rom,
+        files,
+        os: units.iter().map(|unit| unit.os).collect(),
+        mappings,
+        optimized,
+    })
+}
+
+/// vm_files turns the code of the units into the VM files to translate.
+fn vm_files(units: &[Unit]) -> Result<Vec<VmFile>, String> {
+    units
+        .iter()
+        .map(|unit| vm_file(unit.path.clone(), &unit.name, &vm_code(&unit.commands)))
+        .collect()
+}
+
+/// link translates the VM files to assembly and assembles them, it returns
+/// the source map, the assembly code and the ROM.
+fn link(
+    files: &[VmFile],
+    out_dir: &Path,
+    name: &str,
+) -> Result<(Vec<Mapping>, String, Vec<u16>), String> {
+    // The OS starts the program with Sys.init, the code is the compact and
+    // optimized one to fit in the ROM.
+    let options = Options {
+        compact: true,
+        optimize: true,
+        bootstrap: true,
+        comments: false,
+    };
+    let mut asm = Vec::new();
+    let mappings = translate(files, &mut asm, &options)?;
+    let asm = String::from_utf8_lossy(&asm).to_string();
+
+    let mut assembler = Assembler::new(out_dir.join(format!("{}.asm", name)));
+    assembler.initialize();
+    let rom = assembler
+        .assemble(&asm)
+        .map_err(|err| format!("{}.asm: {}", name, err))?;
+    Ok((mappings, asm, rom))
+}
+
+/// report prints the size of the ROM taken by each class, the runtime is
+/// the bootstrap and the routines shared by the calls and comparisons.
+fn report(name: &str, program: &Program) {
+    let mut sizes: BTreeMap<Option<usize>, usize> = BTreeMap::new();
+    for mapping in program
+        .mappings
+        .iter()
+        .filter(|mapping| mapping.rom.is_some())
+    {
+        let file = mapping.location.as_ref().map(|location| location.file);
+        *sizes.entry(file).or_default() += 1;
+    }
+    for (file, size) in &sizes {
+        let label = match file {
+            Some(index) if program.os[*index] => format!("{} (OS)", program.files[*index].name),
+            Some(index) => program.files[*index].name.clone(),
+            None => String::from("(runtime)"),
+        };
+        println!("  {:<16} {:>6}", label, size);
+    }
+    if let Some((before, after, words)) = program.optimized {
+        println!(
+            "optimizer: {} -> {} VM instructions ({} saved), {} -> {} words of ROM ({} saved)",
+            before,
+            after,
+            before as isize - after as isize,
+            words,
+            program.rom.len(),
+            words as isize - program.rom.len() as isize
+        );
+    }
+    println!(
+        "{}: {} of {} words of ROM ({:.1}%)",
+        name,
+        program.rom.len(),
+        ROM_SIZE,
+        100.0 * program.rom.len() as f64 / ROM_SIZE as f64
+    );
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // The program is named after its directory, or its file.
+    let name = args
+        .path
+        .canonicalize()
+        .ok()
+        .and_then(|path| {
+            path.file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| String::from("out"));
+    let out_dir = match &args.out_dir {
+        Some(directory) => directory.clone(),
+        None if args.path.is_dir() => args.path.clone(),
+        None => args.path.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+    if let Err(err) = fs::create_dir_all(&out_dir) {
+        eprintln!("err: {}: {}", out_dir.display(), err);
+        std::process::exit(1);
+    }
+
+    let program = match build(&args, &out_dir, &name) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    };
+    report(&name, &program);
+    if program.rom.len() > ROM_SIZE {
+        eprintln!(
+            "err: {} takes {} words, more than the {} of the ROM",
+            name,
+            program.rom.len(),
+            ROM_SIZE
+        );
+        std::process::exit(1);
+    }
+
+    if args.emits(Emit::Hack) {
+        let code: String = program
+            .rom
+            .iter()
+            .map(|word| format!("{word:016b}\n"))
+            .collect();
+        if let Err(err) = write(&out_dir, &format!("{}.hack", name), &code) {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use serde_json::{json, Value};
+
+use jack_compiler::analyzer::{Analyzer, Severity};
+use jack_compiler::ast::{Class, SubroutineKind, Type};
+use jack_compiler::index::{
+    declaration, doc_comment, references, signature, subroutine_at, variables, Reference, Target,
+};
+use jack_compiler::parser::parse;
+use jack_compiler::symbols::Kind;
+use jack_compiler::tokenizer::Keyword;
+use jack_compiler::{jack_files, os, Error, Span};
+
+/// The kinds of completion items of the protocol.
+const METHOD: u8 = 2;
+const FUNCTION: u8 = 3;
+const CONSTRUCTOR: u8 = 4;
+const FIELD: u8 = 5;
+const VARIABLE: u8 = 6;
+const CLASS: u8 = 7;
+const KEYWORD: u8 = 14;
+
+/// The error code of an unknown request.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Args reads the command line arguments. The language server talks to the
+/// editor with the Language Server Protocol over stdin and stdout.
+#[derive(Parser, Debug)]
+#[command(name = "jack_lsp")]
+struct Args {
+    /// Uses stdin and stdout, the only transport, for the editors asking for it.
+    #[arg(long)]
+    stdio: bool,
+}
+
+/// Document is a Jack file of the program being edited, or of the OS.
+struct Document {
+    path: PathBuf,
+    source: String,
+    /// The class of the file, the last one that parsed for an open file
+    /// with a syntax error.
+    class: Option<Class>,
+    error: Option<Error>,
+    os: bool,
+}
+
+/// Server holds the files open in the editor, the others are read from the
+/// disk. A program is the directory of a file, with the OS.
+#[derive(Default)]
+struct Server {
+    open: HashMap<PathBuf, String>,
+    /// The last class of each open file that parsed.
+    parsed: HashMap<PathBuf, Class>,
+    shutdown: bool,
+}
+
+impl Server {
+    /// handle answers a message of the editor, it returns the response to a
+    /// request and the notifications to send.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                },
+                "serverInfo": { "name": "jack_lsp" },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/definition" => Some(self.definition(params).unwrap_or_default()),
+            "textDocument/references" => Some(self.references(params).unwrap_or_default()),
+            "textDocument/hover" => Some(self.hover(params).unwrap_or_default()),
+            "textDocument/completion" => Some(self.completion(params).unwrap_or_default()),
+            _ => None,
+        };
+        let response = match result {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": METHOD_NOT_FOUND,
+                    "message": format!("unknown method {}", method),
+                },
+            }),
+        };
+        vec![response]
+    }
+
+    /// notification follows the files the editor opens, changes and closes,
+    /// it returns their diagnostics.
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(path) = path_of(uri) else {
+            return Vec::new();
+        };
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // The whole text is sent, the last change is the current one.
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.open.remove(&path);
+                self.parsed.remove(&path);
+                let mut messages = vec![publish(&path, Vec::new())];
+                messages.extend(self.diagnostics(&path));
+                return messages;
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        if let Ok(class) = parse(text) {
+            self.parsed.insert(path.clone(), class);
+        }
+        self.open.insert(path.clone(), text.to_string());
+        self.diagnostics(&path)
+    }
+
+    /// program reads the files of the directory of a file, with the OS
+    /// classes the program does not replace.
+    fn program(&self, path: &Path) -> Vec<Document> {
+        let directory = path.parent().unwrap_or(Path::new("."));
+        let mut paths = jack_files(directory).unwrap_or_default();
+        for open in self.open.keys() {
+            if open.parent() == Some(directory) && !paths.contains(open) {
+                paths.push(open.clone());
+            }
+        }
+
+        let mut program = Vec::new();
+        for path in paths {
+            let source = match self.open.get(&path) {
+                Some(source) => source.clone(),
+                None => match fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(_) => continue,
+                },
+            };
+            let (class, error) = match parse(&source) {
+                Ok(class) => (Some(class), None),
+                Err(err) => (self.parsed.get(&path).cloned(), Some(err)),
+            };
+            program.push(Document {
+                path,
+                source,
+                class,
+                error,
+                os: false,
+            });
+        }
+
+        let directory = os::directory();
+        for (name, source) in os::SOURCES {
+            let defined = program.iter().any(|document| {
+                document
+                    .class
+                    .as_ref()
+                    .is_some_and(|class| class.name.name == name)
+            });
+            if !defined {
+                program.push(Document {
+                    path: directory.join(format!("{}.jack", name)),
+                    source: source.to_string(),
+                    class: parse(source).ok(),
+                    error: None,
+                    os: true,
+                });
+            }
+        }
+        program
+    }
+
+    /// diagnostics checks the open files of the program of a file, a change
+    /// of a class changes the diagnostics of the classes using it.
+    fn diagnostics(&self, path: &Path) -> Vec<Value> {
+        let program = self.program(path);
+        let classes: Vec<Class> = program
+            .iter()
+            .filter(|document| !document.os)
+            .filter_map(|document| document.class.clone())
+            .collect();
+        let analyzer = Analyzer::new(&classes);
+        program
+            .iter()
+            .filter(|document| self.open.contains_key(&document.path))
+            .map(|document| {
+                let diagnostics = match (&document.error, &document.class) {
+                    (Some(err), _) => vec![diagnostic(&document.source, err.span, 1, &err.message)],
+                    (None, Some(class)) => analyzer
+                        .check(class)
+                        .iter()
+                        .map(|issue| {
+                            let severity = match issue.severity {
+                                Severity::Error => 1,
+                                Severity::Warning => 2,
+                            };
+                            diagnostic(&document.source, issue.span, severity, &issue.message)
+                        })
+                        .collect(),
+                    (None, None) => Vec::new(),
+                };
+                publish(&document.path, diagnostics)
+            })
+            .collect()
+    }
+
+    /// target_at finds what the name at the position of a request refers
+    /// to, with the program it is in.
+    fn target_at(&self, params: &Value) -> Option<(Vec<Document>, Target)> {
+        let (path, position) = position(params)?;
+        let program = self.program(&path);
+        let document = program.iter().find(|document| document.path == path)?;
+        let reference = references(document.class.as_ref()?)
+            .into_iter()
+            .find(|reference| reference.contains(position))?;
+        Some((program, reference.target))
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (program, target) = self.target_at(params)?;
+        let (document, reference) = find(&program, &target)
+            .into_iter()
+            .find(|(_, reference)| reference.declaration)?;
+        Some(location(document, &reference))
+    }
+
+    fn references(&self, params: &Value) -> Option<Value> {
+        let (program, target) = self.target_at(params)?;
+        let declarations = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let locations: Vec<Value> = find(&program, &target)
+            .into_iter()
+            .filter(|(_, reference)| declarations || !reference.declaration)
+            .map(|(document, reference)| location(document, &reference))
+            .collect();
+        Some(json!(locations))
+    }
+
+    /// hover shows the declaration of a name and its doc comment.
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (program, target) = self.target_at(params)?;
+        let (document, reference) = find(&program, &target)
+            .into_iter()
+            .find(|(_, reference)| reference.declaration)?;
+        let class = document.class.as_ref()?;
+        let text = match &target {
+            Target::Class(name) => format!("class {}", name),
+            Target::Subroutine(_, name) => {
+                let subroutine = class
+                    .subroutines
+                    .iter()
+                    .find(|subroutine| &subroutine.name.name == name)?;
+                signature(&class.name.name, subroutine)
+            }
+            Target::Variable(_, span) => declaration(class, *span)?.description(),
+        };
+        let mut value = format!("```jack\n{}\n```", text);
+        if let Some(doc) = doc_comment(&document.source, reference.span.line) {
+            value.push_str("\n\n");
+            value.push_str(&doc);
+        }
+        Some(json!({ "contents": { "kind": "markdown", "value": value } }))
+    }
+
+    /// completion lists the subroutines of the class or of the object
+    /// before a dot, or else the variables in scope, the subroutines of the
+    /// class, the classes and the keywords.
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (path, position) = position(params)?;
+        let program = self.program(&path);
+        let document = program.iter().find(|document| document.path == path)?;
+        let line = document
+            .source
+            .lines()
+            .nth(position.line - 1)
+            .unwrap_or_default();
+        let before: String = line.chars().take(position.column - 1).collect();
+        let receiver = before
+            .trim_end_matches(is_name)
+            .strip_suffix('.')
+            .map(|before| {
+                let start = before.trim_end_matches(is_name).len();
+                &before[start..]
+            });
+        let class = document.class.as_ref();
+        let subroutine = class.and_then(|class| subroutine_at(class, position));
+        let class_of = |name: &str| {
+            program.iter().find(|document| {
+                document
+                    .class
+                    .as_ref()
+                    .is_some_and(|class| class.name.name == name)
+            })
+        };
+
+        let mut items = Vec::new();
+        if let Some(receiver) = receiver {
+            // The object of a variable has methods, a class has functions
+            // and constructors.
+            let variable = class.and_then(|class| {
+                variables(class, subroutine)
+                    .into_iter()
+                    .rev()
+                    .find(|variable| variable.name.name == receiver)
+            });
+            let (name, methods) = match variable {
+                Some(variable) => match variable.ty {
+                    Type::Class(name) => (name.as_str(), true),
+                    _ => return Some(json!([])),
+                },
+                None => (receiver, false),
+            };
+            if let Some(target) = class_of(name) {
+                let target_class = target.class.as_ref()?;
+                // The helpers of the OS classes are not offered.
+                for subroutine in &target_class.subroutines {
+                    if (subroutine.kind == SubroutineKind::Method) == methods
+                        && (!target.os || os::is_api(name, &subroutine.name.name))
+                    {
+                        items.push(item(
+                            &subroutine.name.name,
+                            subroutine_kind(subroutine.kind),
+                            signature(name, subroutine),
+                            doc_comment(&target.source, subroutine.name.span.line),
+                        ));
+                    }
+                }
+            }
+            return Some(json!(items));
+        }
+
+        if let Some(class) = class {
+            // The arguments and locals hide the class variables.
+            let mut seen = HashSet::new();
+            for variable in variables(class, subroutine).into_iter().rev() {
+                if seen.insert(&variable.name.name) {
+                    let kind = match variable.kind {
+                        Kind::Static | Kind::Field => FIELD,
+                        Kind::Argument | Kind::Local => VARIABLE,
+                    };
+                    items.push(item(
+                        &variable.name.name,
+                        kind,
+                        variable.description(),
+                        None,
+                    ));
+                }
+            }
+            for subroutine in &class.subroutines {
+                items.push(item(
+                    &subroutine.name.name,
+                    subroutine_kind(subroutine.kind),
+                    signature(&class.name.name, subroutine),
+                    doc_comment(&document.source, subroutine.name.span.line),
+                ));
+            }
+        }
+        for other in &program {
+            if let Some(class) = &other.class {
+                items.push(item(
+                    &class.name.name,
+                    CLASS,
+                    format!("class {}", class.name.name),
+                    doc_comment(&other.source, class.name.span.line),
+                ));
+            }
+        }
+        for keyword in Keyword::ALL {
+            items.push(item(keyword.as_str(), KEYWORD, String::new(), None));
+        }
+        Some(json!(items))
+    }
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn subroutine_kind(kind: SubroutineKind) -> u8 {
+    match kind {
+        SubroutineKind::Constructor => CONSTRUCTOR,
+        SubroutineKind::Function => FUNCTION,
+        SubroutineKind::Method => METHOD,
+    }
+}
+
+fn item(label: &str, kind: u8, detail: String, documentation: Option<String>) -> Value {
+    let mut item = json!({ "label": label, "kind": kind, "detail": detail });
+    if let Some(documentation) = documentation {
+        item["documentation"] = json!(documentation);
+    }
+    item
+}
+
+/// find finds the names referring to a target in a program.
+fn find<'a>(program: &'a [Document], target: &Target) -> Vec<(&'a Document, Reference)> {
+    let mut found = Vec::new();
+    for document in program {
+        let Some(class) = &document.class else {
+            continue;
+        };
+        for reference in references(class) {
+            if &reference.target == target {
+                found.push((document, reference));
+            }
+        }
+    }
+    found
+}
+
+/// position reads the file and the position of a request, the protocol
+/// counts lines and characters from 0.
+fn position(params: &Value) -> Option<(PathBuf, Span)> {
+    let path = path_of(params["textDocument"]["uri"].as_str()?)?;
+    let position = &params["position"];
+    let span = Span {
+        line: position["line"].as_u64()? as usize + 1,
+        column: position["character"].as_u64()? as usize + 1,
+    };
+    Some((path, span))
+}
+
+fn range(span: Span, length: usize) -> Value {
+    let (line, character) = (span.line.saturating_sub(1), span.column.saturating_sub(1));
+    json!({
+        "start": { "line": line, "character": character },
+        "end": { "line": line, "character": character + length },
+    })
+}
+
+fn location(document: &Document, reference: &Reference) -> Value {
+    json!({
+        "uri": uri_of(&document.path),
+        "range": range(reference.span, reference.length),
+    })
+}
+
+/// diagnostic is an issue at a span of a source, it covers the name there
+/// or a single character.
+fn diagnostic(source: &str, span: Span, severity: u8, message: &str) -> Value {
+    let line = source
+        .lines()
+        .nth(span.line.saturating_sub(1))
+        .unwrap_or_default();
+    let length = line
+        .chars()
+        .skip(span.column.saturating_sub(1))
+        .take_while(|c| is_name(*c))
+        .count();
+    json!({
+        "range": range(span, length.max(1)),
+        "severity": severity,
+        "source": "jack",
+        "message": message,
+    })
+}
+
+fn publish(path: &Path, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri_of(path), "diagnostics": diagnostics },
+    })
+}
+
+/// path_of is the path of a `file://` URI, with its `%XX` escapes decoded.
+fn path_of(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::new();
+    let mut index = 0;
+    while index < encoded.len() {
+        let escaped = encoded
+            .get(index + 1..index + 3)
+            .filter(|_| encoded[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                index += 3;
+            }
+            None => {
+                bytes.push(encoded[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// uri_of is the `file://` URI of a path.
+fn uri_of(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+/// read_message reads a message of the editor, a `Content-Length` header
+/// and a JSON body. It returns None at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() && length.is_some() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim();
+                let value = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid Content-Length: {}", value))?;
+                length = Some(value);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body).map_err(|err| err.to_string())?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| format!("invalid message: {}", err))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn main() {
+    Args::parse();
+
+    let mut server = Server::default();
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(err) => {
+                eprintln!("err: {err}");
+                std::process::exit(1);
+            }
+        };
+        // The editor asks to shut down before it exits.
+        if message["method"] == "exit" {
+            std::process::exit(if server.shutdown { 0 } else { 1 });
+        }
+        for reply in server.handle(&message) {
+            if let Err(err) = write_message(&mut output, &reply) {
+                eprintln!("err: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
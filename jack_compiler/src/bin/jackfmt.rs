@@ -0,0 +1,90 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use jack_compiler::format::format;
+use jack_compiler::jack_files;
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+#[command(name = "jackfmt")]
+struct Args {
+    /// The Jack files (.jack) or the directories of Jack files to format,
+    /// each one in place. The subdirectories are formatted too.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Only checks the files are formatted, it lists the others and fails
+    /// when there are some.
+    #[arg(long)]
+    check: bool,
+}
+
+/// sources lists the Jack files of a path and of its subdirectories, e.g.
+/// of the programs of projects/09.
+fn sources(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = jack_files(path)?;
+    if path.is_dir() {
+        let mut directories = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                directories.push(path);
+            }
+        }
+        directories.sort();
+        for directory in directories {
+            files.extend(sources(&directory)?);
+        }
+    }
+    Ok(files)
+}
+
+/// run formats the files of the arguments, it returns the ones that were
+/// not formatted.
+fn run(args: &Args) -> Result<Vec<PathBuf>, String> {
+    let mut unformatted = Vec::new();
+    for path in &args.paths {
+        let files = sources(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if files.is_empty() {
+            return Err(format!("no .jack files found in {}", path.display()));
+        }
+        for file in files {
+            let source =
+                fs::read_to_string(&file).map_err(|err| format!("{}: {}", file.display(), err))?;
+            let formatted = format(&source).map_err(|err| format!("{}:{}", file.display(), err))?;
+            if formatted == source {
+                continue;
+            }
+            if !args.check {
+                fs::write(&file, &formatted)
+                    .map_err(|err| format!("{}: {}", file.display(), err))?;
+            }
+            unformatted.push(file);
+        }
+    }
+    Ok(unformatted)
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let unformatted = match run(&args) {
+        Ok(unformatted) => unformatted,
+        Err(err) => {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    };
+    if args.check {
+        for file in &unformatted {
+            println!("{}", file.display());
+        }
+        if !unformatted.is_empty() {
+            eprintln!("err: {} files are not formatted", unformatted.len());
+            std::process::exit(1);
+        }
+    }
+}
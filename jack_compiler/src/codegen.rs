@@ -0,0 +1,317 @@
+use vm_translator::parser::{Arithmetic, Command, Segment};
+
+use crate::ast::{
+    BinaryOp, Class, Expression, Identifier, KeywordConstant, Statement, Subroutine,
+    SubroutineCall, SubroutineKind, Term, Type, UnaryOp,
+};
+use crate::error::{Error, Span};
+use crate::symbols::{Kind, SymbolTable};
+
+/// CodeGenerator compiles a class to VM commands, a subroutine at a time.
+struct CodeGenerator<'a> {
+    class: &'a Class,
+    symbols: SymbolTable,
+    commands: Vec<Command>,
+    /// Numbers of the if and while statements of the subroutine, they make
+    /// their labels unique.
+    if_count: usize,
+    while_count: usize,
+}
+
+impl<'a> CodeGenerator<'a> {
+    fn push(&mut self, segment: Segment, index: u16) {
+        self.commands.push(Command::Push(segment, index));
+    }
+
+    fn pop(&mut self, segment: Segment, index: u16) {
+        self.commands.push(Command::Pop(segment, index));
+    }
+
+    fn arithmetic(&mut self, op: Arithmetic) {
+        self.commands.push(Command::Arithmetic(op));
+    }
+
+    fn call(&mut self, name: String, arguments: u16) {
+        self.commands.push(Command::Call(name, arguments));
+    }
+
+    fn label(&mut self, label: String) {
+        self.commands.push(Command::Label(label));
+    }
+
+    fn subroutine(&mut self, subroutine: &Subroutine) -> Result<(), Error> {
+        self.symbols.start_subroutine();
+        self.if_count = 0;
+        self.while_count = 0;
+
+        if subroutine.kind == SubroutineKind::Method {
+            // The object is the hidden first argument.
+            let this = Identifier {
+                name: String::from("this"),
+                span: subroutine.name.span,
+            };
+            let ty = Type::Class(self.class.name.name.clone());
+            self.symbols.define(&this, &ty, Kind::Argument)?;
+        }
+        for parameter in &subroutine.parameters {
+            self.symbols
+                .define(&parameter.name, &parameter.ty, Kind::Argument)?;
+        }
+        for local in &subroutine.locals {
+            for name in &local.names {
+                self.symbols.define(name, &local.ty, Kind::Local)?;
+            }
+        }
+
+        let name = format!("{}.{}", self.class.name.name, subroutine.name.name);
+        let locals = self.symbols.count(Kind::Local);
+        self.commands.push(Command::Function(name, locals));
+        match subroutine.kind {
+            SubroutineKind::Constructor => {
+                let size = self.symbols.count(Kind::Field);
+                self.push(Segment::Constant, size);
+                self.call(String::from("Memory.alloc"), 1);
+                self.pop(Segment::Pointer, 0);
+            }
+            SubroutineKind::Method => {
+                self.push(Segment::Argument, 0);
+                self.pop(Segment::Pointer, 0);
+            }
+            SubroutineKind::Function => {}
+        }
+        self.statements(&subroutine.statements)
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<(), Error> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), Error> {
+        match statement {
+            Statement::Let {
+                target,
+                index: None,
+                value,
+                ..
+            } => {
+                self.expression(value)?;
+                let (segment, index) = self.variable(&target.name, target.span)?;
+                self.pop(segment, index);
+            }
+            Statement::Let {
+                target,
+                index: Some(index),
+                value,
+                ..
+            } => {
+                // The address is computed first, the value may use `that`.
+                let (segment, base) = self.variable(&target.name, target.span)?;
+                self.expression(index)?;
+                self.push(segment, base);
+                self.arithmetic(Arithmetic::Add);
+                self.expression(value)?;
+                self.pop(Segment::Temp, 0);
+                self.pop(Segment::Pointer, 1);
+                self.push(Segment::Temp, 0);
+                self.pop(Segment::That, 0);
+            }
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                let number = self.if_count;
+                self.if_count += 1;
+                self.expression(condition)?;
+                self.commands
+                    .push(Command::IfGoto(format!("IF_TRUE{}", number)));
+                self.commands
+                    .push(Command::Goto(format!("IF_FALSE{}", number)));
+                self.label(format!("IF_TRUE{}", number));
+                self.statements(then)?;
+                match otherwise {
+                    Some(otherwise) => {
+                        self.commands
+                            .push(Command::Goto(format!("IF_END{}", number)));
+                        self.label(format!("IF_FALSE{}", number));
+                        self.statements(otherwise)?;
+                        self.label(format!("IF_END{}", number));
+                    }
+                    None => self.label(format!("IF_FALSE{}", number)),
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                let number = self.while_count;
+                self.while_count += 1;
+                self.label(format!("WHILE_EXP{}", number));
+                self.expression(condition)?;
+                self.arithmetic(Arithmetic::Not);
+                self.commands
+                    .push(Command::IfGoto(format!("WHILE_END{}", number)));
+                self.statements(body)?;
+                self.commands
+                    .push(Command::Goto(format!("WHILE_EXP{}", number)));
+                self.label(format!("WHILE_END{}", number));
+            }
+            Statement::Do { call, .. } => {
+                self.subroutine_call(call)?;
+                // The returned value is dropped.
+                self.pop(Segment::Temp, 0);
+            }
+            Statement::Return { value, .. } => {
+                match value {
+                    Some(value) => self.expression(value)?,
+                    // A void subroutine returns 0, the caller drops it.
+                    None => self.push(Segment::Constant, 0),
+                }
+                self.commands.push(Command::Return);
+            }
+        }
+        Ok(())
+    }
+
+    /// variable finds the segment and index of a variable.
+    fn variable(&self, name: &str, span: Span) -> Result<(Segment, u16), Error> {
+        match self.symbols.get(name) {
+            Some(symbol) => Ok((symbol.kind.segment(), symbol.index)),
+            None => Err(Error::new(span, format!("undefined variable {}", name))),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<(), Error> {
+        self.term(&expression.first)?;
+        for (op, _, term) in &expression.rest {
+            self.term(term)?;
+            match op {
+                BinaryOp::Add => self.arithmetic(Arithmetic::Add),
+                BinaryOp::Sub => self.arithmetic(Arithmetic::Sub),
+                BinaryOp::Mul => self.call(String::from("Math.multiply"), 2),
+                BinaryOp::Div => self.call(String::from("Math.divide"), 2),
+                BinaryOp::And => self.arithmetic(Arithmetic::And),
+                BinaryOp::Or => self.arithmetic(Arithmetic::Or),
+                BinaryOp::Lt => self.arithmetic(Arithmetic::Lt),
+                BinaryOp::Gt => self.arithmetic(Arithmetic::Gt),
+                BinaryOp::Eq => self.arithmetic(Arithmetic::Eq),
+            }
+        }
+        Ok(())
+    }
+
+    fn term(&mut self, term: &Term) -> Result<(), Error> {
+        match term {
+            Term::Integer(value, _) => self.push(Segment::Constant, *value),
+            Term::String(text, span) => {
+                let length = u16::try_from(text.chars().count())
+                    .ok()
+                    .filter(|length| *length <= Segment::Constant.max_index())
+                    .ok_or_else(|| Error::new(*span, String::from("string is too long")))?;
+                self.push(Segment::Constant, length);
+                self.call(String::from("String.new"), 1);
+                for c in text.chars() {
+                    if !(' '..='~').contains(&c) {
+                        return Err(Error::new(
+                            *span,
+                            format!("character {:?} is not in the Hack character set", c),
+                        ));
+                    }
+                    self.push(Segment::Constant, c as u16);
+                    self.call(String::from("String.appendChar"), 2);
+                }
+            }
+            Term::Keyword(constant, _) => match constant {
+                KeywordConstant::True => {
+                    self.push(Segment::Constant, 0);
+                    self.arithmetic(Arithmetic::Not);
+                }
+                KeywordConstant::False | KeywordConstant::Null => self.push(Segment::Constant, 0),
+                KeywordConstant::This => self.push(Segment::Pointer, 0),
+            },
+            Term::Variable(name) => {
+                let (segment, index) = self.variable(&name.name, name.span)?;
+                self.push(segment, index);
+            }
+            Term::Index(name, index) => {
+                let (segment, base) = self.variable(&name.name, name.span)?;
+                self.expression(index)?;
+                self.push(segment, base);
+                self.arithmetic(Arithmetic::Add);
+                self.pop(Segment::Pointer, 1);
+                self.push(Segment::That, 0);
+            }
+            Term::Call(call) => self.subroutine_call(call)?,
+            Term::Paren(expression, _) => self.expression(expression)?,
+            Term::Unary(op, term, _) => {
+                self.term(term)?;
+                self.arithmetic(match op {
+                    UnaryOp::Neg => Arithmetic::Neg,
+                    UnaryOp::Not => Arithmetic::Not,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// subroutine_call pushes the object of a method before the arguments.
+    /// A receiver that is not a variable is a class, the call is a function
+    /// or a constructor call.
+    fn subroutine_call(&mut self, call: &SubroutineCall) -> Result<(), Error> {
+        let (class, object) = match &call.receiver {
+            None => {
+                self.push(Segment::Pointer, 0);
+                (self.class.name.name.clone(), true)
+            }
+            Some(receiver) => match self.symbols.get(&receiver.name) {
+                Some(symbol) => {
+                    let Type::Class(class) = &symbol.ty else {
+                        return Err(Error::new(
+                            receiver.span,
+                            format!("{} is not an object", receiver.name),
+                        ));
+                    };
+                    let class = class.clone();
+                    self.push(symbol.kind.segment(), symbol.index);
+                    (class, true)
+                }
+                None => (receiver.name.clone(), false),
+            },
+        };
+        for argument in &call.arguments {
+            self.expression(argument)?;
+        }
+        let arguments = call.arguments.len() as u16 + u16::from(object);
+        self.call(format!("{}.{}", class, call.name.name), arguments);
+        Ok(())
+    }
+}
+
+/// compile generates the VM code of a class, its functions are named
+/// `Class.subroutine`.
+pub fn compile(class: &Class) -> Result<Vec<Command>, Error> {
+    let mut generator = CodeGenerator {
+        class,
+        symbols: SymbolTable::new(&class.variables)?,
+        commands: Vec::new(),
+        if_count: 0,
+        while_count: 0,
+    };
+    for subroutine in &class.subroutines {
+        generator.subroutine(subroutine)?;
+    }
+    Ok(generator.commands)
+}
+
+/// vm_code writes VM commands as the lines of a `.vm` file.
+pub fn vm_code(commands: &[Command]) -> String {
+    let mut code = String::new();
+    for command in commands {
+        code.push_str(&command.to_string());
+        code.push('\n');
+    }
+    code
+}
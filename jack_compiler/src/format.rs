@@ -0,0 +1,390 @@
+use crate::ast::{
+    Class, ClassVarKind, Expression, Identifier, KeywordConstant, Statement, Subroutine,
+    SubroutineCall, SubroutineKind, Term,
+};
+use crate::error::{Error, Span};
+use crate::parser::parse;
+use crate::tokenizer::{lex, Comment, Token};
+
+/// Indentation of a block, in spaces.
+const INDENT: usize = 4;
+
+/// format lays out a `.jack` file in one style: a declaration or statement
+/// per line, indented by 4 spaces in each block, with the `{` at the end of
+/// the line and spaces around the binary operators. The comments are kept
+/// with the code they are next to, and a blank line is kept where the
+/// source has some. The line endings are the ones of the source.
+///
+/// A declaration or statement with a comment inside it, e.g. between the
+/// arguments of a call, is kept as written so the comment stays in place,
+/// only its indentation changes.
+pub fn format(source: &str) -> Result<String, Error> {
+    let (tokens, comments) = lex(source)?;
+    let class = parse(source)?;
+    let mut formatter = Formatter {
+        source: source.lines().collect(),
+        tokens,
+        comments,
+        next: 0,
+        lines: Vec::new(),
+        indent: 0,
+        block_start: true,
+    };
+    formatter.class(&class);
+
+    let newline = if source.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut formatted = formatter.lines.join(newline);
+    formatted.push_str(newline);
+    Ok(formatted)
+}
+
+/// Formatter writes the lines of a class, with the comments of the source
+/// in between.
+struct Formatter<'a> {
+    /// The lines of the source, for its blank lines.
+    source: Vec<&'a str>,
+    /// The tokens of the source, for the positions the parser does not keep.
+    tokens: Vec<(Token, Span)>,
+    comments: Vec<Comment>,
+    /// The first comment not written yet.
+    next: usize,
+    lines: Vec<String>,
+    indent: usize,
+    /// Whether nothing was written since the last `{`, or since the start.
+    block_start: bool,
+}
+
+impl Formatter<'_> {
+    /// previous is the span of the token before another, the keyword of a
+    /// declaration before its type.
+    fn previous(&self, span: Span) -> Span {
+        let index = self.tokens.partition_point(|(_, other)| *other < span);
+        self.tokens[index.saturating_sub(1)].1
+    }
+
+    /// end is the span of the `;` or `{` ending the declaration or statement
+    /// starting at a position.
+    fn end(&self, start: Span) -> Span {
+        self.tokens
+            .iter()
+            .find(|(token, span)| *span >= start && matches!(token, Token::Symbol(';' | '{')))
+            .map_or(start, |(_, span)| *span)
+    }
+
+    /// block_end is the span of the `}` closing the first block opened
+    /// after a position.
+    fn block_end(&self, after: Span) -> Span {
+        let mut depth = 0;
+        for (token, span) in self.tokens.iter().filter(|(_, span)| *span > after) {
+            match token {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return *span;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.tokens.last().map_or(after, |(_, span)| *span)
+    }
+
+    /// blank writes a blank line before what starts at a line of the source
+    /// when the source has one, but not at the start of a block.
+    fn blank(&mut self, line: usize) {
+        let blank = line >= 2
+            && self
+                .source
+                .get(line - 2)
+                .is_some_and(|previous| previous.trim().is_empty());
+        if blank && !self.block_start {
+            self.lines.push(String::new());
+        }
+    }
+
+    /// comments writes the comments before a position on their own lines.
+    fn comments(&mut self, before: Span) {
+        while let Some(comment) = self.comments.get(self.next).cloned() {
+            if comment.span >= before {
+                break;
+            }
+            self.next += 1;
+            self.blank(comment.span.line);
+            let mut text = comment.text.lines();
+            let first = text.next().unwrap_or_default();
+            self.lines
+                .push(format!("{}{}", " ".repeat(self.indent), first));
+            for line in text {
+                self.lines
+                    .push(shift(line, comment.span.column - 1, self.indent));
+            }
+            self.block_start = false;
+        }
+    }
+
+    /// line writes a line of code ending at a line of the source, followed
+    /// by the comments ending that line of the source.
+    fn line(&mut self, code: String, line: usize) {
+        self.append(format!("{}{}", " ".repeat(self.indent), code), line);
+    }
+
+    /// append writes an indented line of code, followed by the comments up
+    /// to the end of a line of the source.
+    fn append(&mut self, mut text: String, line: usize) {
+        let mut rest = Vec::new();
+        while let Some(comment) = self.comments.get(self.next) {
+            if comment.span.line > line {
+                break;
+            }
+            let column = text.chars().count() + 1;
+            let mut lines = comment.text.lines();
+            text.push(' ');
+            text.push_str(lines.next().unwrap_or_default());
+            rest.extend(lines.map(|line| shift(line, comment.span.column - 1, column)));
+            self.next += 1;
+        }
+        self.lines.push(text);
+        self.lines.extend(rest);
+        self.block_start = false;
+    }
+
+    /// write writes a line of code starting at a position of the source,
+    /// after the comments before it. The source is kept when there are
+    /// comments inside the code.
+    fn write(&mut self, start: Span, code: String) {
+        self.comments(start);
+        self.blank(start.line);
+        let end = self.end(start);
+        let inside = self
+            .comments
+            .get(self.next)
+            .is_some_and(|comment| comment.span < end);
+        if inside {
+            self.verbatim(start, end);
+        } else {
+            self.line(code, end.line);
+        }
+    }
+
+    /// verbatim writes the source from a position to the end of a token, the
+    /// lines after the first one keep their indentation relative to it.
+    fn verbatim(&mut self, start: Span, end: Span) {
+        while self
+            .comments
+            .get(self.next)
+            .is_some_and(|comment| comment.span < end)
+        {
+            self.next += 1;
+        }
+        let mut lines = Vec::new();
+        for line in start.line..=end.line {
+            let mut text: String = self.source[line - 1].to_string();
+            if line == end.line {
+                text = text.chars().take(end.column).collect();
+            }
+            if line == start.line {
+                let code: String = text.chars().skip(start.column - 1).collect();
+                text = format!("{}{}", " ".repeat(self.indent), code.trim_end());
+            } else {
+                text = shift(&text, start.column - 1, self.indent);
+            }
+            lines.push(text);
+        }
+        let last = lines.pop().unwrap_or_default();
+        self.lines.extend(lines);
+        self.append(last, end.line);
+    }
+
+    /// open writes a line of code ending with `{`, the start of a block.
+    fn open(&mut self, start: Span, code: String) {
+        self.write(start, code);
+        self.indent += INDENT;
+        self.block_start = true;
+    }
+
+    /// close ends a block at the position of its `}`, after the comments at
+    /// the end of the block.
+    fn close(&mut self, end: Span, code: &str) {
+        self.comments(end);
+        self.indent -= INDENT;
+        self.line(code.to_string(), end.line);
+    }
+
+    fn class(&mut self, class: &Class) {
+        let start = self.previous(class.name.span);
+        self.open(start, format!("class {} {{", class.name.name));
+        for variable in &class.variables {
+            let kind = match variable.kind {
+                ClassVarKind::Static => "static",
+                ClassVarKind::Field => "field",
+            };
+            let code = format!("{} {} {};", kind, variable.ty, names(&variable.names));
+            self.write(self.previous(variable.type_span), code);
+        }
+        for subroutine in &class.subroutines {
+            self.subroutine(subroutine);
+        }
+        self.close(self.block_end(start), "}");
+        // The comments after the class.
+        self.comments(Span {
+            line: usize::MAX,
+            column: usize::MAX,
+        });
+    }
+
+    fn subroutine(&mut self, subroutine: &Subroutine) {
+        let kind = match subroutine.kind {
+            SubroutineKind::Constructor => "constructor",
+            SubroutineKind::Function => "function",
+            SubroutineKind::Method => "method",
+        };
+        let return_type = subroutine
+            .return_type
+            .as_ref()
+            .map_or(String::from("void"), |ty| ty.to_string());
+        let parameters: Vec<_> = subroutine
+            .parameters
+            .iter()
+            .map(|parameter| format!("{} {}", parameter.ty, parameter.name.name))
+            .collect();
+        let code = format!(
+            "{} {} {}({}) {{",
+            kind,
+            return_type,
+            subroutine.name.name,
+            parameters.join(", ")
+        );
+        self.open(self.previous(subroutine.type_span), code);
+        for local in &subroutine.locals {
+            let code = format!("var {} {};", local.ty, names(&local.names));
+            self.write(self.previous(local.type_span), code);
+        }
+        self.statements(&subroutine.statements);
+        self.close(subroutine.end, "}");
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let {
+                target,
+                index,
+                value,
+                span,
+            } => {
+                let index = index
+                    .as_ref()
+                    .map_or(String::new(), |index| format!("[{}]", expression(index)));
+                let code = format!("let {}{} = {};", target.name, index, expression(value));
+                self.write(*span, code);
+            }
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+                span,
+            } => {
+                self.open(*span, format!("if ({}) {{", expression(condition)));
+                self.statements(then);
+                let end = self.block_end(*span);
+                if let Some(otherwise) = otherwise {
+                    self.close(end, "} else {");
+                    self.indent += INDENT;
+                    self.block_start = true;
+                    self.statements(otherwise);
+                    self.close(self.block_end(end), "}");
+                } else {
+                    self.close(end, "}");
+                }
+            }
+            Statement::While {
+                condition,
+                body,
+                span,
+            } => {
+                self.open(*span, format!("while ({}) {{", expression(condition)));
+                self.statements(body);
+                self.close(self.block_end(*span), "}");
+            }
+            Statement::Do { call, span } => {
+                self.write(*span, format!("do {};", subroutine_call(call)))
+            }
+            Statement::Return { value, span } => {
+                let code = match value {
+                    Some(value) => format!("return {};", expression(value)),
+                    None => String::from("return;"),
+                };
+                self.write(*span, code);
+            }
+        }
+    }
+}
+
+/// shift moves a line of a comment written at another column, keeping its
+/// indentation relative to the first line.
+fn shift(line: &str, from: usize, to: usize) -> String {
+    let text = line.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+    let indentation = line.len() - line.trim_start().len();
+    format!(
+        "{}{}",
+        " ".repeat((indentation + to).saturating_sub(from)),
+        text
+    )
+}
+
+fn names(names: &[Identifier]) -> String {
+    let names: Vec<_> = names.iter().map(|name| name.name.as_str()).collect();
+    names.join(", ")
+}
+
+fn expression(expression: &Expression) -> String {
+    let mut code = term(&expression.first);
+    for (op, _, operand) in &expression.rest {
+        code.push_str(&format!(" {} {}", op.symbol(), term(operand)));
+    }
+    code
+}
+
+fn term(value: &Term) -> String {
+    match value {
+        Term::Integer(value, _) => value.to_string(),
+        Term::String(text, _) => format!("\"{}\"", text),
+        Term::Keyword(constant, _) => String::from(match constant {
+            KeywordConstant::True => "true",
+            KeywordConstant::False => "false",
+            KeywordConstant::Null => "null",
+            KeywordConstant::This => "this",
+        }),
+        Term::Variable(name) => name.name.clone(),
+        Term::Index(name, index) => format!("{}[{}]", name.name, expression(index)),
+        Term::Call(call) => subroutine_call(call),
+        Term::Paren(inner, _) => format!("({})", expression(inner)),
+        Term::Unary(op, operand, _) => format!("{}{}", op.symbol(), term(operand)),
+    }
+}
+
+fn subroutine_call(call: &SubroutineCall) -> String {
+    let arguments: Vec<_> = call.arguments.iter().map(expression).collect();
+    match &call.receiver {
+        Some(receiver) => format!(
+            "{}.{}({})",
+            receiver.name,
+            call.name.name,
+            arguments.join(", ")
+        ),
+        None => format!("{}({})", call.name.name, arguments.join(", ")),
+    }
+}
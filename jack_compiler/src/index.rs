@@ -0,0 +1,357 @@
+use crate::ast::{
+    Class, ClassVarKind, Expression, Identifier, Statement, Subroutine, SubroutineCall,
+    SubroutineKind, Term, Type,
+};
+use crate::error::Span;
+use crate::symbols::{Kind, SymbolTable};
+
+/// Target is what a name refers to, the same for its declaration and all
+/// its uses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    Class(String),
+    /// A subroutine of a class, e.g. `Output` and `printInt`.
+    Subroutine(String, String),
+    /// A variable of a class, by the span of its declaration.
+    Variable(String, Span),
+}
+
+/// Reference is a name in a class and what it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Span,
+    /// Length of the name, in characters.
+    pub length: usize,
+    pub target: Target,
+    /// Whether the name is the one of a declaration.
+    pub declaration: bool,
+}
+
+impl Reference {
+    /// contains tells whether a position is on the name, or right after it.
+    pub fn contains(&self, position: Span) -> bool {
+        position.line == self.span.line
+            && (self.span.column..=self.span.column + self.length).contains(&position.column)
+    }
+}
+
+/// Variable is a declared variable of a class or of one of its subroutines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable<'a> {
+    pub kind: Kind,
+    pub ty: &'a Type,
+    pub name: &'a Identifier,
+}
+
+impl Variable<'_> {
+    /// description is the variable as declared, e.g. `field int x`, the
+    /// arguments and locals are named after their segment.
+    pub fn description(&self) -> String {
+        let kind = match self.kind {
+            Kind::Static => "static",
+            Kind::Field => "field",
+            Kind::Argument => "argument",
+            Kind::Local => "var",
+        };
+        format!("{} {} {}", kind, self.ty, self.name.name)
+    }
+}
+
+/// references finds the names of a class: its declarations, the uses of
+/// its variables, the class names of types and calls, and the subroutines
+/// called, sorted by position.
+pub fn references(class: &Class) -> Vec<Reference> {
+    let mut walker = Walker {
+        class: &class.name.name,
+        symbols: SymbolTable::default(),
+        references: Vec::new(),
+    };
+    walker.class(class);
+    let mut references = walker.references;
+    references.sort_by_key(|reference| reference.span);
+    references
+}
+
+/// variables lists the variables of a class, and the arguments and locals
+/// of one of its subroutines when given.
+pub fn variables<'a>(class: &'a Class, subroutine: Option<&'a Subroutine>) -> Vec<Variable<'a>> {
+    let mut variables = Vec::new();
+    for variable in &class.variables {
+        let kind = match variable.kind {
+            ClassVarKind::Static => Kind::Static,
+            ClassVarKind::Field => Kind::Field,
+        };
+        for name in &variable.names {
+            variables.push(Variable {
+                kind,
+                ty: &variable.ty,
+                name,
+            });
+        }
+    }
+    if let Some(subroutine) = subroutine {
+        for parameter in &subroutine.parameters {
+            variables.push(Variable {
+                kind: Kind::Argument,
+                ty: &parameter.ty,
+                name: &parameter.name,
+            });
+        }
+        for local in &subroutine.locals {
+            for name in &local.names {
+                variables.push(Variable {
+                    kind: Kind::Local,
+                    ty: &local.ty,
+                    name,
+                });
+            }
+        }
+    }
+    variables
+}
+
+/// declaration finds the variable of a class declared at a span.
+pub fn declaration(class: &Class, span: Span) -> Option<Variable<'_>> {
+    class
+        .subroutines
+        .iter()
+        .flat_map(|subroutine| variables(class, Some(subroutine)))
+        .find(|variable| variable.name.span == span)
+        .or_else(|| {
+            variables(class, None)
+                .into_iter()
+                .find(|variable| variable.name.span == span)
+        })
+}
+
+/// subroutine_at finds the subroutine of a class a position is in, the last
+/// one declared before it.
+pub fn subroutine_at(class: &Class, position: Span) -> Option<&Subroutine> {
+    class
+        .subroutines
+        .iter()
+        .take_while(|subroutine| subroutine.name.span.line <= position.line)
+        .last()
+}
+
+/// signature is the declaration of a subroutine of a class, e.g.
+/// `function int Math.max(int a, int b)`.
+pub fn signature(class: &str, subroutine: &Subroutine) -> String {
+    let kind = match subroutine.kind {
+        SubroutineKind::Constructor => "constructor",
+        SubroutineKind::Function => "function",
+        SubroutineKind::Method => "method",
+    };
+    let return_type = subroutine
+        .return_type
+        .as_ref()
+        .map_or(String::from("void"), |ty| ty.to_string());
+    let parameters: Vec<_> = subroutine
+        .parameters
+        .iter()
+        .map(|parameter| format!("{} {}", parameter.ty, parameter.name.name))
+        .collect();
+    format!(
+        "{} {} {}.{}({})",
+        kind,
+        return_type,
+        class,
+        subroutine.name.name,
+        parameters.join(", ")
+    )
+}
+
+/// doc_comment is the text of the `/** */` comment right before a line of
+/// a source, without its stars.
+pub fn doc_comment(source: &str, line: usize) -> Option<String> {
+    let before: Vec<&str> = source.lines().take(line.saturating_sub(1)).collect();
+    let before = before.join("\n");
+    let before = before.trim_end().strip_suffix("*/")?;
+    let start = before.rfind("/*")?;
+    let text = before[start..].strip_prefix("/**")?;
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix('*').unwrap_or(line).trim()
+        })
+        .collect();
+    let text = lines.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Walker walks a class with the variables in scope.
+struct Walker<'a> {
+    class: &'a str,
+    symbols: SymbolTable,
+    references: Vec<Reference>,
+}
+
+impl Walker<'_> {
+    fn add(&mut self, name: &Identifier, target: Target, declaration: bool) {
+        self.references.push(Reference {
+            span: name.span,
+            length: name.name.chars().count(),
+            target,
+            declaration,
+        });
+    }
+
+    fn ty(&mut self, ty: &Type, span: Span) {
+        if let Type::Class(name) = ty {
+            self.references.push(Reference {
+                span,
+                length: name.chars().count(),
+                target: Target::Class(name.clone()),
+                declaration: false,
+            });
+        }
+    }
+
+    /// declare defines a variable, a second declaration of a name is still
+    /// a declaration but nothing refers to it.
+    fn declare(&mut self, name: &Identifier, ty: &Type, kind: Kind) {
+        let _ = self.symbols.define(name, ty, kind);
+        let target = Target::Variable(self.class.to_string(), name.span);
+        self.add(name, target, true);
+    }
+
+    fn class(&mut self, class: &Class) {
+        self.add(&class.name, Target::Class(self.class.to_string()), true);
+        for variable in &class.variables {
+            self.ty(&variable.ty, variable.type_span);
+            let kind = match variable.kind {
+                ClassVarKind::Static => Kind::Static,
+                ClassVarKind::Field => Kind::Field,
+            };
+            for name in &variable.names {
+                self.declare(name, &variable.ty, kind);
+            }
+        }
+        for subroutine in &class.subroutines {
+            self.subroutine(subroutine);
+        }
+    }
+
+    fn subroutine(&mut self, subroutine: &Subroutine) {
+        self.symbols.start_subroutine();
+        if let Some(ty) = &subroutine.return_type {
+            self.ty(ty, subroutine.type_span);
+        }
+        let target = Target::Subroutine(self.class.to_string(), subroutine.name.name.clone());
+        self.add(&subroutine.name, target, true);
+        for parameter in &subroutine.parameters {
+            self.ty(&parameter.ty, parameter.type_span);
+            self.declare(&parameter.name, &parameter.ty, Kind::Argument);
+        }
+        for local in &subroutine.locals {
+            self.ty(&local.ty, local.type_span);
+            for name in &local.names {
+                self.declare(name, &local.ty, Kind::Local);
+            }
+        }
+        self.statements(&subroutine.statements);
+    }
+
+    /// variable refers to a variable in scope, it returns its type.
+    fn variable(&mut self, name: &Identifier) -> Option<Type> {
+        let symbol = self.symbols.get(&name.name)?;
+        let (ty, span) = (symbol.ty.clone(), symbol.span);
+        self.add(name, Target::Variable(self.class.to_string(), span), false);
+        Some(ty)
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Let {
+                    target,
+                    index,
+                    value,
+                    ..
+                } => {
+                    self.variable(target);
+                    if let Some(index) = index {
+                        self.expression(index);
+                    }
+                    self.expression(value);
+                }
+                Statement::If {
+                    condition,
+                    then,
+                    otherwise,
+                    ..
+                } => {
+                    self.expression(condition);
+                    self.statements(then);
+                    if let Some(otherwise) = otherwise {
+                        self.statements(otherwise);
+                    }
+                }
+                Statement::While {
+                    condition, body, ..
+                } => {
+                    self.expression(condition);
+                    self.statements(body);
+                }
+                Statement::Do { call, .. } => self.call(call),
+                Statement::Return { value, .. } => {
+                    if let Some(value) = value {
+                        self.expression(value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        self.term(&expression.first);
+        for (_, _, term) in &expression.rest {
+            self.term(term);
+        }
+    }
+
+    fn term(&mut self, term: &Term) {
+        match term {
+            Term::Integer(..) | Term::String(..) | Term::Keyword(..) => {}
+            Term::Variable(name) => {
+                self.variable(name);
+            }
+            Term::Index(name, index) => {
+                self.variable(name);
+                self.expression(index);
+            }
+            Term::Call(call) => self.call(call),
+            Term::Paren(expression, _) => self.expression(expression),
+            Term::Unary(_, term, _) => self.term(term),
+        }
+    }
+
+    /// call refers to the receiver of a call and to the subroutine, when
+    /// the class of the receiver is known.
+    fn call(&mut self, call: &SubroutineCall) {
+        let class = match &call.receiver {
+            None => Some(self.class.to_string()),
+            Some(receiver) if self.symbols.get(&receiver.name).is_some() => {
+                match self.variable(receiver) {
+                    Some(Type::Class(class)) => Some(class),
+                    _ => None,
+                }
+            }
+            Some(receiver) => {
+                self.add(receiver, Target::Class(receiver.name.clone()), false);
+                Some(receiver.name.clone())
+            }
+        };
+        if let Some(class) = class {
+            self.add(
+                &call.name,
+                Target::Subroutine(class, call.name.name.clone()),
+                false,
+            );
+        }
+        for argument in &call.arguments {
+            self.expression(argument);
+        }
+    }
+}
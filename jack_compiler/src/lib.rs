@@ -0,0 +1,42 @@
+//! Compiler of the Jack language to VM code. The tokenizer and the parser
+//! are the first stages, they can write the `<tokens>` and parse tree XML
+//! files of the course. The code generator writes the VM commands of the
+//! vm_translator. The Jack OS of `projects/12` is built in, programs are
+//! checked against it and hackc links it with them. The index of the names
+//! of a program is the base of jack_lsp, its language server, and jackfmt
+//! lays out the sources from their parse tree.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub mod analyzer;
+pub mod ast;
+pub mod codegen;
+mod error;
+pub mod format;
+pub mod index;
+pub mod optimizer;
+pub mod os;
+pub mod parser;
+pub mod symbols;
+pub mod tokenizer;
+pub mod xml;
+
+pub use error::{Error, Span};
+
+/// jack_files lists the `.jack` files to compile, in a stable order for directories.
+pub fn jack_files(input: &Path) -> io::Result<Vec<PathBuf>> {
+    if !input.is_dir() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "jack") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use jack_compiler::analyzer::{Analyzer, Severity};
+use jack_compiler::ast::Class;
+use jack_compiler::codegen::{compile, vm_code};
+use jack_compiler::jack_files;
+use jack_compiler::parser::parse;
+use jack_compiler::tokenizer::{tokenize, tokens_xml};
+use jack_compiler::xml::class_xml;
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The Jack file (.jack) or the directory of Jack files to compile, each
+    /// one to a VM file (.vm).
+    path: PathBuf,
+
+    /// Writes the tokens of every file to FileT.xml, in the XML format of the course.
+    #[arg(short, long)]
+    tokens: bool,
+
+    /// Writes the parse tree of every file to File.xml, in the XML format of the course.
+    #[arg(short = 'x', long)]
+    xml: bool,
+
+    /// Directory of the output files, the directory of each Jack file by default.
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+}
+
+/// output_path is the path of an output file of a Jack file, e.g.
+/// `Main.jack` gives `MainT.xml` for the suffix `T.xml`.
+fn output_path(args: &Args, file: &Path, suffix: &str) -> PathBuf {
+    let name = file.file_stem().unwrap_or_default().to_string_lossy();
+    let directory = match &args.out_dir {
+        Some(directory) => directory.as_path(),
+        None => file.parent().unwrap_or(Path::new("")),
+    };
+    directory.join(format!("{}{}", name, suffix))
+}
+
+/// parse_file reads the class of a Jack file, it writes its tokens and its
+/// parse tree when asked.
+fn parse_file(args: &Args, file: &Path) -> Result<Class, String> {
+    let source = fs::read_to_string(file).map_err(|err| format!("{}: {}", file.display(), err))?;
+    let tokens = tokenize(&source).map_err(|err| format!("{}:{}", file.display(), err))?;
+
+    if args.tokens {
+        let path = output_path(args, file, "T.xml");
+        fs::write(&path, tokens_xml(&tokens))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+
+    let class = parse(&source).map_err(|err| format!("{}:{}", file.display(), err))?;
+    if args.xml {
+        let path = output_path(args, file, ".xml");
+        fs::write(&path, class_xml(&class))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    Ok(class)
+}
+
+fn write_vm(args: &Args, file: &Path, class: &Class) -> Result<(), String> {
+    let commands = compile(class).map_err(|err| format!("{}:{}", file.display(), err))?;
+    let path = output_path(args, file, ".vm");
+    fs::write(&path, vm_code(&commands)).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let files = match jack_files(&args.path) {
+        Ok(files) if !files.is_empty() => files,
+        Ok(_) => {
+            eprintln!("err: no .jack files found in {}", args.path.display());
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("err: {}: {}", args.path.display(), err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(directory) = &args.out_dir {
+        if let Err(err) = fs::create_dir_all(directory) {
+            eprintln!("err: {}: {}", directory.display(), err);
+            std::process::exit(1);
+        }
+    }
+
+    let mut failed = false;
+    let mut parsed = Vec::new();
+    let mut classes = Vec::new();
+    for file in &files {
+        match parse_file(&args, file) {
+            Ok(class) => {
+                parsed.push(file);
+                classes.push(class);
+            }
+            Err(err) => {
+                eprintln!("err: {err}");
+                failed = true;
+            }
+        }
+    }
+
+    // The classes are checked together, a class calls the others.
+    let analyzer = Analyzer::new(&classes);
+    for (file, class) in parsed.into_iter().zip(&classes) {
+        let diagnostics = analyzer.check(class);
+        for diagnostic in &diagnostics {
+            eprintln!("{}:{}", file.display(), diagnostic);
+        }
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            failed = true;
+            continue;
+        }
+        if let Err(err) = write_vm(&args, file, class) {
+            eprintln!("err: {err}");
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
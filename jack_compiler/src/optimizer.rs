@@ -0,0 +1,326 @@
+use std::collections::{HashMap, HashSet};
+
+use vm_translator::parser::{Arithmetic, Command, Segment};
+
+/// Subroutines a program starts from, Sys.init calls Main.main.
+const ROOTS: [&str; 2] = ["Sys.init", "Main.main"];
+
+/// Largest power of two, as its exponent, a multiplication is turned into
+/// additions for. Every doubling takes a few commands, more of them would
+/// take more ROM than the call to Math.multiply.
+const MAX_DOUBLINGS: u32 = 3;
+
+/// Getter is the value a subroutine made of a single push returns, its
+/// calls are replaced by the push.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Getter {
+    /// A method returning a field, `push this index`.
+    Field(u16),
+    /// A function returning a constant.
+    Constant(u16),
+    /// A function returning a static variable, only its class can read it.
+    Static(u16),
+}
+
+/// optimize rewrites the VM code of a whole program, the commands of each
+/// of its classes. It inlines tiny getters, folds constant arithmetic,
+/// turns multiplications by small powers of two into additions and
+/// removes the subroutines Sys.init and Main.main never call.
+pub fn optimize(classes: &mut [Vec<Command>]) {
+    let getters = getters(classes);
+    for commands in classes.iter_mut() {
+        *commands = fold(&inline(commands, &getters));
+    }
+    remove_unreachable(classes);
+}
+
+/// instructions counts the commands that are executed, labels are not.
+pub fn instructions(commands: &[Command]) -> usize {
+    commands
+        .iter()
+        .filter(|command| !matches!(command, Command::Label(_)))
+        .count()
+}
+
+/// class is the class of a subroutine, `Main` for `Main.main`.
+fn class(name: &str) -> &str {
+    name.split_once('.').map_or(name, |(class, _)| class)
+}
+
+/// getters finds the subroutines without locals whose whole body pushes a
+/// value and returns it.
+fn getters(classes: &[Vec<Command>]) -> HashMap<String, Getter> {
+    use Command::{Function, Pop, Push, Return};
+    let mut getters = HashMap::new();
+    for commands in classes {
+        for (index, command) in commands.iter().enumerate() {
+            let Function(name, 0) = command else {
+                continue;
+            };
+            let body = &commands[index + 1..];
+            let (getter, length) = match body {
+                [Push(Segment::Argument, 0), Pop(Segment::Pointer, 0), Push(Segment::This, field), Return, ..] => {
+                    (Getter::Field(*field), 4)
+                }
+                [Push(Segment::Constant, value), Return, ..] => (Getter::Constant(*value), 2),
+                [Push(Segment::Static, index), Return, ..] => (Getter::Static(*index), 2),
+                _ => continue,
+            };
+            if matches!(body.get(length), None | Some(Function(..))) {
+                getters.insert(name.clone(), getter);
+            }
+        }
+    }
+    getters
+}
+
+/// inline replaces the calls to getters by the push of their value. The
+/// object of a method goes in THAT, the code generator sets it right
+/// before every use.
+fn inline(commands: &[Command], getters: &HashMap<String, Getter>) -> Vec<Command> {
+    let mut out = Vec::new();
+    let mut current = "";
+    for command in commands {
+        match command {
+            Command::Function(name, _) => current = class(name),
+            Command::Call(name, arguments) => match (getters.get(name), arguments) {
+                (Some(Getter::Field(field)), 1) => {
+                    // A getter of the object itself reads the field directly.
+                    if out.last() == Some(&Command::Push(Segment::Pointer, 0)) {
+                        out.pop();
+                        out.push(Command::Push(Segment::This, *field));
+                    } else {
+                        out.push(Command::Pop(Segment::Pointer, 1));
+                        out.push(Command::Push(Segment::That, *field));
+                    }
+                    continue;
+                }
+                (Some(Getter::Constant(value)), 0) => {
+                    out.push(Command::Push(Segment::Constant, *value));
+                    continue;
+                }
+                (Some(Getter::Static(index)), 0) if class(name) == current => {
+                    out.push(Command::Push(Segment::Static, *index));
+                    continue;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        out.push(command.clone());
+    }
+    out
+}
+
+/// constant_at reads the constant the commands ending at end push: a push
+/// of a constant, followed by neg or not for the values below 0.
+fn constant_at(out: &[Command], end: usize) -> Option<(i16, usize)> {
+    let constant = |index: usize| match out.get(index) {
+        Some(Command::Push(Segment::Constant, value)) => Some(*value as i16),
+        _ => None,
+    };
+    let last = end.checked_sub(1)?;
+    if let Some(value) = constant(last) {
+        return Some((value, 1));
+    }
+    let value = constant(last.checked_sub(1)?)?;
+    match out[last] {
+        Command::Arithmetic(Arithmetic::Neg) => Some((value.wrapping_neg(), 2)),
+        Command::Arithmetic(Arithmetic::Not) => Some((!value, 2)),
+        _ => None,
+    }
+}
+
+/// push_constant pushes any 16-bit value, push constant only takes 15 bits.
+fn push_constant(out: &mut Vec<Command>, value: i16) {
+    match value {
+        0.. => out.push(Command::Push(Segment::Constant, value as u16)),
+        // true, and -32768 which has no positive counterpart.
+        -1 | i16::MIN => {
+            out.push(Command::Push(Segment::Constant, !value as u16));
+            out.push(Command::Arithmetic(Arithmetic::Not));
+        }
+        _ => {
+            out.push(Command::Push(Segment::Constant, -value as u16));
+            out.push(Command::Arithmetic(Arithmetic::Neg));
+        }
+    }
+}
+
+/// fold evaluates the arithmetic on constants, multiplications and
+/// divisions included, and removes the operations with no effect.
+fn fold(commands: &[Command]) -> Vec<Command> {
+    let mut out = Vec::new();
+    for command in commands {
+        match command {
+            Command::Arithmetic(op) => arithmetic(&mut out, *op),
+            Command::Call(name, 2) if name == "Math.multiply" => multiply(&mut out),
+            Command::Call(name, 2) if name == "Math.divide" => divide(&mut out),
+            _ => out.push(command.clone()),
+        }
+    }
+    out
+}
+
+fn arithmetic(out: &mut Vec<Command>, op: Arithmetic) {
+    let end = out.len();
+    let Some((y, y_length)) = constant_at(out, end) else {
+        out.push(Command::Arithmetic(op));
+        return;
+    };
+    let (start, value) = match op {
+        Arithmetic::Neg => (end - y_length, Some(y.wrapping_neg())),
+        Arithmetic::Not => (end - y_length, Some(!y)),
+        _ => match constant_at(out, end - y_length) {
+            Some((x, x_length)) => (end - y_length - x_length, Some(binary(op, x, y))),
+            None => (end - y_length, None),
+        },
+    };
+    match value {
+        Some(value) => {
+            out.truncate(start);
+            push_constant(out, value);
+        }
+        // x + 0 and x - 0 are x.
+        None if y == 0 && matches!(op, Arithmetic::Add | Arithmetic::Sub) => out.truncate(start),
+        None => out.push(Command::Arithmetic(op)),
+    }
+}
+
+/// binary is the value of x op y, true is -1.
+fn binary(op: Arithmetic, x: i16, y: i16) -> i16 {
+    let truth = |value: bool| if value { -1 } else { 0 };
+    match op {
+        Arithmetic::Add => x.wrapping_add(y),
+        Arithmetic::Sub => x.wrapping_sub(y),
+        Arithmetic::And => x & y,
+        Arithmetic::Or => x | y,
+        Arithmetic::Eq => truth(x == y),
+        Arithmetic::Gt => truth(x > y),
+        Arithmetic::Lt => truth(x < y),
+        Arithmetic::Neg | Arithmetic::Not => unreachable!("{:?} is unary", op),
+    }
+}
+
+fn multiply(out: &mut Vec<Command>) {
+    let call = Command::Call(String::from("Math.multiply"), 2);
+    let end = out.len();
+    if let Some((y, y_length)) = constant_at(out, end) {
+        if let Some((x, x_length)) = constant_at(out, end - y_length) {
+            out.truncate(end - y_length - x_length);
+            push_constant(out, x.wrapping_mul(y));
+        } else if let Some(doublings) = doublings(y) {
+            out.truncate(end - y_length);
+            double(out, doublings);
+        } else {
+            out.push(call);
+        }
+        return;
+    }
+
+    // A constant times a push, the push is the whole second operand.
+    let doublings = match out.get(end.wrapping_sub(2)..) {
+        Some([Command::Push(Segment::Constant, x), Command::Push(_, _)]) => doublings(*x as i16),
+        _ => None,
+    };
+    match doublings {
+        Some(doublings) => {
+            out.remove(end - 2);
+            double(out, doublings);
+        }
+        None => out.push(call),
+    }
+}
+
+/// doublings is the exponent of a power of two small enough to multiply
+/// by with additions.
+fn doublings(value: i16) -> Option<u32> {
+    let exponent = value.trailing_zeros();
+    (value > 0 && value.count_ones() == 1 && exponent <= MAX_DOUBLINGS).then_some(exponent)
+}
+
+/// double doubles the value on top of the stack, adding it to itself. A
+/// value just pushed is pushed again, others are copied through temp 1,
+/// which the code generator does not use.
+fn double(out: &mut Vec<Command>, times: u32) {
+    let mut times = times;
+    if times == 0 {
+        return;
+    }
+    if let Some(Command::Push(segment, index)) = out.last().cloned() {
+        out.push(Command::Push(segment, index));
+        out.push(Command::Arithmetic(Arithmetic::Add));
+        times -= 1;
+    }
+    for _ in 0..times {
+        out.push(Command::Pop(Segment::Temp, 1));
+        out.push(Command::Push(Segment::Temp, 1));
+        out.push(Command::Push(Segment::Temp, 1));
+        out.push(Command::Arithmetic(Arithmetic::Add));
+    }
+}
+
+fn divide(out: &mut Vec<Command>) {
+    let end = out.len();
+    if let Some((y, y_length)) = constant_at(out, end) {
+        if let Some((x, x_length)) = constant_at(out, end - y_length).filter(|_| y != 0) {
+            out.truncate(end - y_length - x_length);
+            push_constant(out, x.wrapping_div(y));
+            return;
+        }
+        // x / 1 is x. Hack has no shift to the right, the other powers
+        // of two still need Math.divide.
+        if y == 1 {
+            out.truncate(end - y_length);
+            return;
+        }
+    }
+    out.push(Command::Call(String::from("Math.divide"), 2));
+}
+
+/// remove_unreachable removes the subroutines no call leads to from the
+/// roots, it keeps everything when the program has no root.
+fn remove_unreachable(classes: &mut [Vec<Command>]) {
+    let mut calls: HashMap<&str, Vec<&str>> = HashMap::new();
+    for commands in classes.iter() {
+        let mut current = None;
+        for command in commands {
+            match command {
+                Command::Function(name, _) => {
+                    current = Some(name.as_str());
+                    calls.entry(name).or_default();
+                }
+                Command::Call(name, _) => {
+                    if let Some(current) = current {
+                        calls.entry(current).or_default().push(name);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut pending: Vec<&str> = ROOTS
+        .into_iter()
+        .filter(|root| calls.contains_key(root))
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+    let mut reachable = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if reachable.insert(name.to_string()) {
+            pending.extend(calls.get(name).into_iter().flatten().copied());
+        }
+    }
+
+    for commands in classes.iter_mut() {
+        let mut keep = true;
+        commands.retain(|command| {
+            if let Command::Function(name, _) = command {
+                keep = reachable.contains(name);
+            }
+            keep
+        });
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use crate::ast::Class;
+use crate::parser::parse;
+
+/// The classes of the Jack OS, their declarations are the API available to
+/// every program and their code is linked with it by hackc.
+pub const SOURCES: [(&str, &str); 8] = [
+    ("Array", include_str!("../../projects/12/Array.jack")),
+    ("Keyboard", include_str!("../../projects/12/Keyboard.jack")),
+    ("Math", include_str!("../../projects/12/Math.jack")),
+    ("Memory", include_str!("../../projects/12/Memory.jack")),
+    ("Output", include_str!("../../projects/12/Output.jack")),
+    ("Screen", include_str!("../../projects/12/Screen.jack")),
+    ("String", include_str!("../../projects/12/String.jack")),
+    ("Sys", include_str!("../../projects/12/Sys.jack")),
+];
+
+/// The subroutines of the OS classes documented by the Jack OS API, the
+/// others are helpers of their implementation, e.g. `Output.initMap`.
+pub const API: [(&str, &[&str]); 8] = [
+    ("Array", &["new", "dispose"]),
+    (
+        "Keyboard",
+        &["keyPressed", "readChar", "readLine", "readInt"],
+    ),
+    ("Math", &["abs", "multiply", "divide", "min", "max", "sqrt"]),
+    ("Memory", &["peek", "poke", "alloc", "deAlloc"]),
+    (
+        "Output",
+        &[
+            "moveCursor",
+            "printChar",
+            "printString",
+            "printInt",
+            "println",
+            "backSpace",
+        ],
+    ),
+    (
+        "Screen",
+        &[
+            "clearScreen",
+            "setColor",
+            "drawPixel",
+            "drawLine",
+            "drawRectangle",
+            "drawCircle",
+        ],
+    ),
+    (
+        "String",
+        &[
+            "new",
+            "dispose",
+            "length",
+            "charAt",
+            "setCharAt",
+            "appendChar",
+            "eraseLastChar",
+            "intValue",
+            "setInt",
+            "backSpace",
+            "doubleQuote",
+            "newLine",
+        ],
+    ),
+    ("Sys", &["halt", "error", "wait"]),
+];
+
+/// is_api checks if a subroutine of an OS class is documented by the API.
+pub fn is_api(class: &str, subroutine: &str) -> bool {
+    API.iter()
+        .any(|(name, subroutines)| *name == class && subroutines.contains(&subroutine))
+}
+
+/// classes parses the OS classes of `projects/12`.
+pub fn classes() -> Vec<Class> {
+    SOURCES
+        .iter()
+        .map(|(name, source)| {
+            parse(source).unwrap_or_else(|err| panic!("projects/12/{}.jack:{}", name, err))
+        })
+        .collect()
+}
+
+/// directory is where the sources of the OS are, `projects/12` of the
+/// repository the compiler was built from.
+pub fn directory() -> PathBuf {
+    let directory = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../projects/12"));
+    directory.canonicalize().unwrap_or(directory)
+}
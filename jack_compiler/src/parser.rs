@@ -0,0 +1,421 @@
+use crate::ast::{
+    BinaryOp, Class, ClassVarDec, ClassVarKind, Expression, Identifier, KeywordConstant, Parameter,
+    Statement, Subroutine, SubroutineCall, SubroutineKind, Term, Type, UnaryOp, VarDec,
+};
+use crate::error::{Error, Span};
+use crate::tokenizer::{tokenize, Keyword, Token};
+
+/// Parser reads a class from the tokens of a `.jack` file, by recursive
+/// descent on the grammar of the language.
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    position: usize,
+    /// Span of the end of the file, for the errors of a truncated file.
+    end: Span,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(_, span)| *span)
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, Error> {
+        let got = match self.peek() {
+            Some(token) => format!("{:?}", token.to_string()),
+            None => String::from("the end of the file"),
+        };
+        Err(Error::new(
+            self.span(),
+            format!("expected {}, got {}", expected, got),
+        ))
+    }
+
+    fn next_is(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn next_is_keyword(&self, keyword: Keyword) -> bool {
+        self.peek() == Some(&Token::Keyword(keyword))
+    }
+
+    /// expect reads a symbol, it returns its span.
+    fn expect(&mut self, symbol: char) -> Result<Span, Error> {
+        if !self.next_is(symbol) {
+            return self.error(&format!("'{}'", symbol));
+        }
+        let span = self.span();
+        self.position += 1;
+        Ok(span)
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<Span, Error> {
+        if !self.next_is_keyword(keyword) {
+            return self.error(keyword.as_str());
+        }
+        let span = self.span();
+        self.position += 1;
+        Ok(span)
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<Identifier, Error> {
+        match self.tokens.get(self.position) {
+            Some((Token::Identifier(name), span)) => {
+                let identifier = Identifier {
+                    name: name.clone(),
+                    span: *span,
+                };
+                self.position += 1;
+                Ok(identifier)
+            }
+            _ => self.error(expected),
+        }
+    }
+
+    /// class reads `class Name { classVarDec* subroutineDec* }`.
+    fn class(&mut self) -> Result<Class, Error> {
+        self.expect_keyword(Keyword::Class)?;
+        let name = self.identifier("the class name")?;
+        self.expect('{')?;
+
+        let mut variables = Vec::new();
+        while self.next_is_keyword(Keyword::Static) || self.next_is_keyword(Keyword::Field) {
+            variables.push(self.class_var_dec()?);
+        }
+        let mut subroutines = Vec::new();
+        while !self.next_is('}') {
+            subroutines.push(self.subroutine()?);
+        }
+        self.expect('}')?;
+
+        if self.peek().is_some() {
+            return self.error("the end of the file");
+        }
+        Ok(Class {
+            name,
+            variables,
+            subroutines,
+        })
+    }
+
+    /// class_var_dec reads `static|field type name, name;`.
+    fn class_var_dec(&mut self) -> Result<ClassVarDec, Error> {
+        let kind = match self.peek() {
+            Some(Token::Keyword(Keyword::Static)) => ClassVarKind::Static,
+            _ => ClassVarKind::Field,
+        };
+        self.position += 1;
+        let (ty, type_span) = self.type_name()?;
+        let names = self.names()?;
+        Ok(ClassVarDec {
+            kind,
+            ty,
+            type_span,
+            names,
+        })
+    }
+
+    /// type_name reads `int`, `char`, `boolean` or a class name.
+    fn type_name(&mut self) -> Result<(Type, Span), Error> {
+        let span = self.span();
+        let ty = match self.peek() {
+            Some(Token::Keyword(Keyword::Int)) => Type::Int,
+            Some(Token::Keyword(Keyword::Char)) => Type::Char,
+            Some(Token::Keyword(Keyword::Boolean)) => Type::Boolean,
+            Some(Token::Identifier(name)) => Type::Class(name.clone()),
+            _ => return self.error("a type"),
+        };
+        self.position += 1;
+        Ok((ty, span))
+    }
+
+    /// names reads `name, name, name;` at the end of a declaration.
+    fn names(&mut self) -> Result<Vec<Identifier>, Error> {
+        let mut names = vec![self.identifier("a variable name")?];
+        while self.next_is(',') {
+            self.position += 1;
+            names.push(self.identifier("a variable name")?);
+        }
+        self.expect(';')?;
+        Ok(names)
+    }
+
+    /// subroutine reads `constructor|function|method void|type name
+    /// (parameterList) { varDec* statements }`.
+    fn subroutine(&mut self) -> Result<Subroutine, Error> {
+        let kind = match self.peek() {
+            Some(Token::Keyword(Keyword::Constructor)) => SubroutineKind::Constructor,
+            Some(Token::Keyword(Keyword::Function)) => SubroutineKind::Function,
+            Some(Token::Keyword(Keyword::Method)) => SubroutineKind::Method,
+            _ => return self.error("constructor, function, method or '}'"),
+        };
+        self.position += 1;
+
+        let (return_type, type_span) = if self.next_is_keyword(Keyword::Void) {
+            (None, self.expect_keyword(Keyword::Void)?)
+        } else {
+            let (ty, span) = self.type_name()?;
+            (Some(ty), span)
+        };
+        let name = self.identifier("the subroutine name")?;
+
+        self.expect('(')?;
+        let mut parameters = Vec::new();
+        if !self.next_is(')') {
+            loop {
+                let (ty, type_span) = self.type_name()?;
+                let name = self.identifier("a parameter name")?;
+                parameters.push(Parameter {
+                    ty,
+                    type_span,
+                    name,
+                });
+                if !self.next_is(',') {
+                    break;
+                }
+                self.position += 1;
+            }
+        }
+        self.expect(')')?;
+
+        self.expect('{')?;
+        let mut locals = Vec::new();
+        while self.next_is_keyword(Keyword::Var) {
+            self.position += 1;
+            let (ty, type_span) = self.type_name()?;
+            let names = self.names()?;
+            locals.push(VarDec {
+                ty,
+                type_span,
+                names,
+            });
+        }
+        let statements = self.statements()?;
+        let end = self.expect('}')?;
+
+        Ok(Subroutine {
+            kind,
+            return_type,
+            type_span,
+            name,
+            parameters,
+            locals,
+            statements,
+            end,
+        })
+    }
+
+    /// statements reads statements up to the closing `}`.
+    fn statements(&mut self) -> Result<Vec<Statement>, Error> {
+        let mut statements = Vec::new();
+        while !self.next_is('}') {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    /// block reads `{ statements }`.
+    fn block(&mut self) -> Result<Vec<Statement>, Error> {
+        self.expect('{')?;
+        let statements = self.statements()?;
+        self.expect('}')?;
+        Ok(statements)
+    }
+
+    /// condition reads `( expression )` of an if or while.
+    fn condition(&mut self) -> Result<Expression, Error> {
+        self.expect('(')?;
+        let condition = self.expression()?;
+        self.expect(')')?;
+        Ok(condition)
+    }
+
+    fn statement(&mut self) -> Result<Statement, Error> {
+        let span = self.span();
+        let keyword = match self.peek() {
+            Some(Token::Keyword(
+                keyword @ (Keyword::Let
+                | Keyword::If
+                | Keyword::While
+                | Keyword::Do
+                | Keyword::Return),
+            )) => *keyword,
+            _ => return self.error("a statement or '}'"),
+        };
+        self.position += 1;
+
+        let statement = match keyword {
+            Keyword::Let => {
+                let target = self.identifier("a variable name")?;
+                let index = if self.next_is('[') {
+                    self.position += 1;
+                    let index = self.expression()?;
+                    self.expect(']')?;
+                    Some(index)
+                } else {
+                    None
+                };
+                self.expect('=')?;
+                let value = self.expression()?;
+                self.expect(';')?;
+                Statement::Let {
+                    target,
+                    index,
+                    value,
+                    span,
+                }
+            }
+            Keyword::If => {
+                let condition = self.condition()?;
+                let then = self.block()?;
+                let otherwise = if self.next_is_keyword(Keyword::Else) {
+                    self.position += 1;
+                    Some(self.block()?)
+                } else {
+                    None
+                };
+                Statement::If {
+                    condition,
+                    then,
+                    otherwise,
+                    span,
+                }
+            }
+            Keyword::While => {
+                let condition = self.condition()?;
+                let body = self.block()?;
+                Statement::While {
+                    condition,
+                    body,
+                    span,
+                }
+            }
+            Keyword::Do => {
+                let name = self.identifier("a subroutine call")?;
+                let call = self.call(name)?;
+                self.expect(';')?;
+                Statement::Do { call, span }
+            }
+            _ => {
+                let value = if self.next_is(';') {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+                self.expect(';')?;
+                Statement::Return { value, span }
+            }
+        };
+        Ok(statement)
+    }
+
+    /// expression reads `term (op term)*`.
+    fn expression(&mut self) -> Result<Expression, Error> {
+        let first = self.term()?;
+        let mut rest = Vec::new();
+        while let Some(Token::Symbol(symbol)) = self.peek() {
+            let Some(op) = BinaryOp::parse(*symbol) else {
+                break;
+            };
+            let span = self.span();
+            self.position += 1;
+            rest.push((op, span, self.term()?));
+        }
+        Ok(Expression { first, rest })
+    }
+
+    fn term(&mut self) -> Result<Term, Error> {
+        let span = self.span();
+        let term = match self.peek() {
+            Some(Token::IntegerConstant(value)) => Term::Integer(*value, span),
+            Some(Token::StringConstant(text)) => Term::String(text.clone(), span),
+            Some(Token::Keyword(keyword)) => {
+                let constant = match keyword {
+                    Keyword::True => KeywordConstant::True,
+                    Keyword::False => KeywordConstant::False,
+                    Keyword::Null => KeywordConstant::Null,
+                    Keyword::This => KeywordConstant::This,
+                    _ => return self.error("an expression"),
+                };
+                Term::Keyword(constant, span)
+            }
+            Some(Token::Symbol('(')) => {
+                self.position += 1;
+                let expression = self.expression()?;
+                self.expect(')')?;
+                return Ok(Term::Paren(Box::new(expression), span));
+            }
+            Some(Token::Symbol(symbol @ ('-' | '~'))) => {
+                let op = match symbol {
+                    '-' => UnaryOp::Neg,
+                    _ => UnaryOp::Not,
+                };
+                self.position += 1;
+                return Ok(Term::Unary(op, Box::new(self.term()?), span));
+            }
+            Some(Token::Identifier(_)) => {
+                let name = self.identifier("a variable name")?;
+                return match self.peek() {
+                    Some(Token::Symbol('[')) => {
+                        self.position += 1;
+                        let index = self.expression()?;
+                        self.expect(']')?;
+                        Ok(Term::Index(name, Box::new(index)))
+                    }
+                    Some(Token::Symbol('(' | '.')) => Ok(Term::Call(self.call(name)?)),
+                    _ => Ok(Term::Variable(name)),
+                };
+            }
+            _ => return self.error("an expression"),
+        };
+        self.position += 1;
+        Ok(term)
+    }
+
+    /// call reads the rest of `name(arguments)` or
+    /// `receiver.name(arguments)` after the first name.
+    fn call(&mut self, first: Identifier) -> Result<SubroutineCall, Error> {
+        let (receiver, name) = if self.next_is('.') {
+            self.position += 1;
+            (Some(first), self.identifier("a subroutine name")?)
+        } else {
+            (None, first)
+        };
+
+        self.expect('(')?;
+        let mut arguments = Vec::new();
+        if !self.next_is(')') {
+            arguments.push(self.expression()?);
+            while self.next_is(',') {
+                self.position += 1;
+                arguments.push(self.expression()?);
+            }
+        }
+        self.expect(')')?;
+        Ok(SubroutineCall {
+            receiver,
+            name,
+            arguments,
+        })
+    }
+}
+
+/// parse reads the class of a `.jack` file.
+pub fn parse(source: &str) -> Result<Class, Error> {
+    let tokens = tokenize(source)?;
+    let lines = source.lines().count().max(1);
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: Span {
+            line: lines,
+            column: source.lines().last().map_or(0, |line| line.chars().count()) + 1,
+        },
+    };
+    parser.class()
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use vm_translator::parser::Segment;
+
+use crate::ast::{ClassVarDec, ClassVarKind, Identifier, Type};
+use crate::error::{Error, Span};
+
+/// Kind of a variable, it gives the segment holding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Static,
+    Field,
+    Argument,
+    Local,
+}
+
+impl Kind {
+    pub fn segment(self) -> Segment {
+        match self {
+            Kind::Static => Segment::Static,
+            Kind::Field => Segment::This,
+            Kind::Argument => Segment::Argument,
+            Kind::Local => Segment::Local,
+        }
+    }
+}
+
+/// Symbol is a declared variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub ty: Type,
+    pub kind: Kind,
+    /// Index of the variable in its segment.
+    pub index: u16,
+    /// Position of the name in its declaration.
+    pub span: Span,
+}
+
+/// SymbolTable holds the variables of a class and of the subroutine being
+/// compiled, the subroutine ones hide the class ones.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    class: HashMap<String, Symbol>,
+    subroutine: HashMap<String, Symbol>,
+    counts: HashMap<Kind, u16>,
+}
+
+impl SymbolTable {
+    /// new declares the static and field variables of a class.
+    pub fn new(variables: &[ClassVarDec]) -> Result<Self, Error> {
+        let mut table = SymbolTable::default();
+        for variable in variables {
+            let kind = match variable.kind {
+                ClassVarKind::Static => Kind::Static,
+                ClassVarKind::Field => Kind::Field,
+            };
+            for name in &variable.names {
+                table.define(name, &variable.ty, kind)?;
+            }
+        }
+        Ok(table)
+    }
+
+    /// start_subroutine forgets the arguments and locals of the previous
+    /// subroutine.
+    pub fn start_subroutine(&mut self) {
+        self.subroutine.clear();
+        self.counts.remove(&Kind::Argument);
+        self.counts.remove(&Kind::Local);
+    }
+
+    /// define declares a variable at the next index of its kind.
+    pub fn define(&mut self, name: &Identifier, ty: &Type, kind: Kind) -> Result<(), Error> {
+        let scope = match kind {
+            Kind::Static | Kind::Field => &mut self.class,
+            Kind::Argument | Kind::Local => &mut self.subroutine,
+        };
+        if let Some(previous) = scope.get(&name.name) {
+            return Err(Error::new(
+                name.span,
+                format!("{} is already declared at {}", name.name, previous.span),
+            ));
+        }
+        let count = self.counts.entry(kind).or_default();
+        scope.insert(
+            name.name.clone(),
+            Symbol {
+                ty: ty.clone(),
+                kind,
+                index: *count,
+                span: name.span,
+            },
+        );
+        *count += 1;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.subroutine.get(name).or_else(|| self.class.get(name))
+    }
+
+    /// count is the number of variables of a kind, e.g. the size of an
+    /// object for Field.
+    pub fn count(&self, kind: Kind) -> u16 {
+        self.counts.get(&kind).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+use crate::error::{Error, Span};
+
+/// Keyword is one of the reserved words of Jack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keyword {
+    Class,
+    Constructor,
+    Function,
+    Method,
+    Field,
+    Static,
+    Var,
+    Int,
+    Char,
+    Boolean,
+    Void,
+    True,
+    False,
+    Null,
+    This,
+    Let,
+    Do,
+    If,
+    Else,
+    While,
+    Return,
+}
+
+impl Keyword {
+    pub const ALL: [Keyword; 21] = [
+        Keyword::Class,
+        Keyword::Constructor,
+        Keyword::Function,
+        Keyword::Method,
+        Keyword::Field,
+        Keyword::Static,
+        Keyword::Var,
+        Keyword::Int,
+        Keyword::Char,
+        Keyword::Boolean,
+        Keyword::Void,
+        Keyword::True,
+        Keyword::False,
+        Keyword::Null,
+        Keyword::This,
+        Keyword::Let,
+        Keyword::Do,
+        Keyword::If,
+        Keyword::Else,
+        Keyword::While,
+        Keyword::Return,
+    ];
+
+    pub fn parse(word: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|keyword| keyword.as_str() == word)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Keyword::Class => "class",
+            Keyword::Constructor => "constructor",
+            Keyword::Function => "function",
+            Keyword::Method => "method",
+            Keyword::Field => "field",
+            Keyword::Static => "static",
+            Keyword::Var => "var",
+            Keyword::Int => "int",
+            Keyword::Char => "char",
+            Keyword::Boolean => "boolean",
+            Keyword::Void => "void",
+            Keyword::True => "true",
+            Keyword::False => "false",
+            Keyword::Null => "null",
+            Keyword::This => "this",
+            Keyword::Let => "let",
+            Keyword::Do => "do",
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::While => "while",
+            Keyword::Return => "return",
+        }
+    }
+}
+
+/// The symbols of Jack, each one is a token.
+pub const SYMBOLS: &str = "{}()[].,;+-*/&|<>=~";
+
+/// Largest integer constant, the 15 bits of a non-negative word.
+pub const MAX_INTEGER: u16 = 32767;
+
+/// Token is a lexical element of Jack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Keyword(Keyword),
+    Symbol(char),
+    IntegerConstant(u16),
+    /// A string without its quotes.
+    StringConstant(String),
+    Identifier(String),
+}
+
+impl Token {
+    /// xml writes the token as an element of the `<tokens>` output, e.g.
+    /// `<symbol> &lt; </symbol>`.
+    pub fn xml(&self) -> String {
+        let (tag, text) = match self {
+            Token::Keyword(keyword) => ("keyword", keyword.as_str().to_string()),
+            Token::Symbol(symbol) => ("symbol", symbol.to_string()),
+            Token::IntegerConstant(value) => ("integerConstant", value.to_string()),
+            Token::StringConstant(text) => ("stringConstant", text.clone()),
+            Token::Identifier(name) => ("identifier", name.clone()),
+        };
+        format!("<{}> {} </{}>", tag, escape(&text), tag)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Keyword(keyword) => write!(f, "{}", keyword.as_str()),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+            Token::IntegerConstant(value) => write!(f, "{}", value),
+            Token::StringConstant(text) => write!(f, "\"{}\"", text),
+            Token::Identifier(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// escape replaces the characters that are markup in XML.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Comment is a `//`, `/* */` or `/** */` comment of a `.jack` file, with
+/// its delimiters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+/// tokenize splits a `.jack` file in tokens, the `//`, `/* */` and
+/// `/** */` comments are skipped.
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Span)>, Error> {
+    lex(source).map(|(tokens, _)| tokens)
+}
+
+/// Tokens are the tokens of a `.jack` file with their positions.
+pub type Tokens = Vec<(Token, Span)>;
+
+/// lex splits a `.jack` file in tokens, and also returns its comments.
+pub fn lex(source: &str) -> Result<(Tokens, Vec<Comment>), Error> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    let mut position = 0;
+
+    while position < chars.len() {
+        let c = chars[position];
+        let span = Span { line, column };
+        let start = position;
+
+        if c == '/' && chars.get(position + 1) == Some(&'/') {
+            while position < chars.len() && chars[position] != '\n' {
+                position += 1;
+            }
+            let text: String = chars[start..position].iter().collect();
+            let text = text.trim_end().to_string();
+            comments.push(Comment { text, span });
+        } else if c == '/' && chars.get(position + 1) == Some(&'*') {
+            position += 2;
+            while position < chars.len()
+                && !(chars[position] == '*' && chars.get(position + 1) == Some(&'/'))
+            {
+                position += 1;
+            }
+            if position >= chars.len() {
+                return Err(Error::new(span, String::from("unterminated comment")));
+            }
+            position += 2;
+            let text = chars[start..position].iter().collect();
+            comments.push(Comment { text, span });
+        } else if c.is_whitespace() {
+            position += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while position < chars.len()
+                && (chars[position].is_ascii_alphanumeric() || chars[position] == '_')
+            {
+                position += 1;
+            }
+            let word: String = chars[start..position].iter().collect();
+            let token = match Keyword::parse(&word) {
+                Some(keyword) => Token::Keyword(keyword),
+                None => Token::Identifier(word),
+            };
+            tokens.push((token, span));
+        } else if c.is_ascii_digit() {
+            while position < chars.len() && chars[position].is_ascii_digit() {
+                position += 1;
+            }
+            let digits: String = chars[start..position].iter().collect();
+            let value = digits
+                .parse()
+                .ok()
+                .filter(|value| *value <= MAX_INTEGER)
+                .ok_or_else(|| {
+                    Error::new(
+                        span,
+                        format!(
+                            "integer constant out of range 0..{}: {}",
+                            MAX_INTEGER, digits
+                        ),
+                    )
+                })?;
+            tokens.push((Token::IntegerConstant(value), span));
+        } else if c == '"' {
+            position += 1;
+            while position < chars.len() && chars[position] != '"' && chars[position] != '\n' {
+                position += 1;
+            }
+            if chars.get(position) != Some(&'"') {
+                return Err(Error::new(span, String::from("unterminated string")));
+            }
+            let text: String = chars[start + 1..position].iter().collect();
+            position += 1;
+            tokens.push((Token::StringConstant(text), span));
+        } else if SYMBOLS.contains(c) {
+            position += 1;
+            tokens.push((Token::Symbol(c), span));
+        } else {
+            return Err(Error::new(span, format!("unexpected character {:?}", c)));
+        }
+
+        for c in &chars[start..position] {
+            if *c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+    }
+    Ok((tokens, comments))
+}
+
+/// tokens_xml writes the `<tokens>` file of the course, with the CRLF line
+/// endings of its compare files.
+pub fn tokens_xml(tokens: &[(Token, Span)]) -> String {
+    let mut xml = String::from("<tokens>\r\n");
+    for (token, _) in tokens {
+        xml.push_str(&token.xml());
+        xml.push_str("\r\n");
+    }
+    xml.push_str("</tokens>\r\n");
+    xml
+}
@@ -0,0 +1,286 @@
+use crate::ast::{
+    Class, ClassVarKind, Expression, KeywordConstant, Statement, SubroutineCall, SubroutineKind,
+    Term, Type,
+};
+use crate::tokenizer::{Keyword, Token};
+
+/// Writer builds the parse tree XML of the course, an element per rule of
+/// the grammar and the tokens as leaves, indented by two spaces with the
+/// CRLF line endings of its compare files.
+struct Writer {
+    xml: String,
+    depth: usize,
+}
+
+impl Writer {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.xml.push_str("  ");
+        }
+        self.xml.push_str(text);
+        self.xml.push_str("\r\n");
+    }
+
+    fn open(&mut self, tag: &str) {
+        self.line(&format!("<{}>", tag));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    fn token(&mut self, token: Token) {
+        self.line(&token.xml());
+    }
+
+    fn keyword(&mut self, keyword: Keyword) {
+        self.token(Token::Keyword(keyword));
+    }
+
+    fn symbol(&mut self, symbol: char) {
+        self.token(Token::Symbol(symbol));
+    }
+
+    fn identifier(&mut self, name: &str) {
+        self.token(Token::Identifier(name.to_string()));
+    }
+
+    fn type_name(&mut self, ty: &Type) {
+        match ty {
+            Type::Int => self.keyword(Keyword::Int),
+            Type::Char => self.keyword(Keyword::Char),
+            Type::Boolean => self.keyword(Keyword::Boolean),
+            Type::Class(name) => self.identifier(name),
+        }
+    }
+
+    /// names writes the variables of a declaration, `a, b;`.
+    fn names<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        for (index, name) in names.into_iter().enumerate() {
+            if index > 0 {
+                self.symbol(',');
+            }
+            self.identifier(name);
+        }
+        self.symbol(';');
+    }
+
+    fn class(&mut self, class: &Class) {
+        self.open("class");
+        self.keyword(Keyword::Class);
+        self.identifier(&class.name.name);
+        self.symbol('{');
+
+        for variable in &class.variables {
+            self.open("classVarDec");
+            self.keyword(match variable.kind {
+                ClassVarKind::Static => Keyword::Static,
+                ClassVarKind::Field => Keyword::Field,
+            });
+            self.type_name(&variable.ty);
+            self.names(variable.names.iter().map(|name| name.name.as_str()));
+            self.close("classVarDec");
+        }
+
+        for subroutine in &class.subroutines {
+            self.open("subroutineDec");
+            self.keyword(match subroutine.kind {
+                SubroutineKind::Constructor => Keyword::Constructor,
+                SubroutineKind::Function => Keyword::Function,
+                SubroutineKind::Method => Keyword::Method,
+            });
+            match &subroutine.return_type {
+                Some(ty) => self.type_name(ty),
+                None => self.keyword(Keyword::Void),
+            }
+            self.identifier(&subroutine.name.name);
+
+            self.symbol('(');
+            self.open("parameterList");
+            for (index, parameter) in subroutine.parameters.iter().enumerate() {
+                if index > 0 {
+                    self.symbol(',');
+                }
+                self.type_name(&parameter.ty);
+                self.identifier(&parameter.name.name);
+            }
+            self.close("parameterList");
+            self.symbol(')');
+
+            self.open("subroutineBody");
+            self.symbol('{');
+            for local in &subroutine.locals {
+                self.open("varDec");
+                self.keyword(Keyword::Var);
+                self.type_name(&local.ty);
+                self.names(local.names.iter().map(|name| name.name.as_str()));
+                self.close("varDec");
+            }
+            self.statements(&subroutine.statements);
+            self.symbol('}');
+            self.close("subroutineBody");
+            self.close("subroutineDec");
+        }
+
+        self.symbol('}');
+        self.close("class");
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        self.open("statements");
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.close("statements");
+    }
+
+    /// block writes `{ statements }`.
+    fn block(&mut self, statements: &[Statement]) {
+        self.symbol('{');
+        self.statements(statements);
+        self.symbol('}');
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let {
+                target,
+                index,
+                value,
+                ..
+            } => {
+                self.open("letStatement");
+                self.keyword(Keyword::Let);
+                self.identifier(&target.name);
+                if let Some(index) = index {
+                    self.symbol('[');
+                    self.expression(index);
+                    self.symbol(']');
+                }
+                self.symbol('=');
+                self.expression(value);
+                self.symbol(';');
+                self.close("letStatement");
+            }
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                self.open("ifStatement");
+                self.keyword(Keyword::If);
+                self.symbol('(');
+                self.expression(condition);
+                self.symbol(')');
+                self.block(then);
+                if let Some(otherwise) = otherwise {
+                    self.keyword(Keyword::Else);
+                    self.block(otherwise);
+                }
+                self.close("ifStatement");
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.open("whileStatement");
+                self.keyword(Keyword::While);
+                self.symbol('(');
+                self.expression(condition);
+                self.symbol(')');
+                self.block(body);
+                self.close("whileStatement");
+            }
+            Statement::Do { call, .. } => {
+                self.open("doStatement");
+                self.keyword(Keyword::Do);
+                self.call(call);
+                self.symbol(';');
+                self.close("doStatement");
+            }
+            Statement::Return { value, .. } => {
+                self.open("returnStatement");
+                self.keyword(Keyword::Return);
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.symbol(';');
+                self.close("returnStatement");
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        self.open("expression");
+        self.term(&expression.first);
+        for (op, _, term) in &expression.rest {
+            self.symbol(op.symbol());
+            self.term(term);
+        }
+        self.close("expression");
+    }
+
+    fn term(&mut self, term: &Term) {
+        self.open("term");
+        match term {
+            Term::Integer(value, _) => self.token(Token::IntegerConstant(*value)),
+            Term::String(text, _) => self.token(Token::StringConstant(text.clone())),
+            Term::Keyword(constant, _) => self.keyword(match constant {
+                KeywordConstant::True => Keyword::True,
+                KeywordConstant::False => Keyword::False,
+                KeywordConstant::Null => Keyword::Null,
+                KeywordConstant::This => Keyword::This,
+            }),
+            Term::Variable(name) => self.identifier(&name.name),
+            Term::Index(name, index) => {
+                self.identifier(&name.name);
+                self.symbol('[');
+                self.expression(index);
+                self.symbol(']');
+            }
+            Term::Call(call) => self.call(call),
+            Term::Paren(expression, _) => {
+                self.symbol('(');
+                self.expression(expression);
+                self.symbol(')');
+            }
+            Term::Unary(op, term, _) => {
+                self.symbol(op.symbol());
+                self.term(term);
+            }
+        }
+        self.close("term");
+    }
+
+    /// call writes the tokens of a call, they have no element of their own.
+    fn call(&mut self, call: &SubroutineCall) {
+        if let Some(receiver) = &call.receiver {
+            self.identifier(&receiver.name);
+            self.symbol('.');
+        }
+        self.identifier(&call.name.name);
+        self.symbol('(');
+        self.open("expressionList");
+        for (index, argument) in call.arguments.iter().enumerate() {
+            if index > 0 {
+                self.symbol(',');
+            }
+            self.expression(argument);
+        }
+        self.close("expressionList");
+        self.symbol(')');
+    }
+}
+
+/// class_xml writes the parse tree of a class, as the `Main.xml` files of
+/// the course.
+pub fn class_xml(class: &Class) -> String {
+    let mut writer = Writer {
+        xml: String::new(),
+        depth: 0,
+    };
+    writer.class(class);
+    writer.xml
+}
@@ -0,0 +1,199 @@
+//! The semantic checks, on small classes, on the programs of projects/11 and
+//! on the OS of projects/12.
+use std::fs;
+use std::path::PathBuf;
+
+use jack_compiler::analyzer::Analyzer;
+use jack_compiler::jack_files;
+use jack_compiler::os;
+use jack_compiler::parser::parse;
+
+/// diagnostics checks the last of the classes, they are sources of `.jack` files.
+fn diagnostics(sources: &[&str]) -> Vec<String> {
+    let classes: Vec<_> = sources
+        .iter()
+        .map(|source| parse(source).unwrap())
+        .collect();
+    let analyzer = Analyzer::new(&classes);
+    analyzer
+        .check(classes.last().unwrap())
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+#[test]
+fn programs_are_clean() {
+    let projects = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../projects/11");
+    for program in [
+        "Average",
+        "ComplexArrays",
+        "ConvertToBin",
+        "Pong",
+        "Seven",
+        "Square",
+    ] {
+        let files = jack_files(&projects.join(program)).unwrap();
+        let classes: Vec<_> = files
+            .iter()
+            .map(|file| parse(&fs::read_to_string(file).unwrap()).unwrap())
+            .collect();
+        let analyzer = Analyzer::new(&classes);
+        for (file, class) in files.iter().zip(&classes) {
+            let diagnostics = analyzer.check(class);
+            assert!(
+                diagnostics.is_empty(),
+                "{}: {:?}",
+                file.display(),
+                diagnostics
+            );
+        }
+    }
+}
+
+#[test]
+fn os_is_clean() {
+    // Sys.init calls Main.main, the OS needs a program.
+    let main = parse("class Main { function void main() { return; } }").unwrap();
+    let analyzer = Analyzer::new(&[main]);
+    for class in os::classes() {
+        let diagnostics = analyzer.check(&class);
+        assert!(
+            diagnostics.is_empty(),
+            "{}: {:?}",
+            class.name.name,
+            diagnostics
+        );
+    }
+}
+
+#[test]
+fn undefined_names() {
+    let source = "class Main {
+  function void main() {
+    var Foo f;
+    let x = 1;
+    do Main.run();
+    do Outpt.printInt(1);
+    do Output.printLn();
+    return;
+  }
+}";
+    assert_eq!(
+        diagnostics(&[source]),
+        [
+            "3:9: error: undefined class Foo",
+            "3:13: warning: unused variable f",
+            "4:9: error: undefined variable x",
+            "5:13: error: Main has no subroutine run",
+            "6:8: error: undefined class or variable Outpt",
+            "7:15: error: Output has no subroutine printLn",
+        ]
+    );
+}
+
+#[test]
+fn calls() {
+    let game = "class Game {
+  field int score;
+  constructor Game new() { let score = 0; return this; }
+  method void play(int rounds) { return; }
+  function int best() { return score; }
+}";
+    let main = "class Main {
+  function void main() {
+    var Game game;
+    var int n;
+    let game = Game.new(1);
+    do Game.play(3);
+    do game.best();
+    do game.play();
+    do play(1);
+    do n.play(1);
+    return;
+  }
+}";
+    assert_eq!(
+        diagnostics(&[game]),
+        ["5:32: error: field score used in a function"]
+    );
+    assert_eq!(
+        diagnostics(&[game, main]),
+        [
+            "5:21: error: Game.new expects 0 arguments, got 1",
+            "6:13: error: Game.play is a method, it needs an object",
+            "7:13: error: best is not a method, call it as Game.best",
+            "8:13: error: Game.play expects 1 arguments, got 0",
+            "9:8: error: Main has no subroutine play",
+            "10:8: error: n is int, not an object",
+        ]
+    );
+}
+
+#[test]
+fn methods_from_functions() {
+    let source = "class Main {
+  method void draw() { return; }
+  function void main() {
+    do draw();
+    do Output.printInt(this);
+    return;
+  }
+}";
+    assert_eq!(
+        diagnostics(&[source]),
+        [
+            "4:8: error: method Main.draw called from a function",
+            "5:24: error: this used in a function",
+        ]
+    );
+}
+
+#[test]
+fn returns() {
+    let source = "class Main {
+  constructor int new() { return 0; }
+  function int size() { return; }
+  function void run() { return 1; }
+  function int last() { if (true) { return 1; } }
+  function int first() { return 1; let x = 2; }
+}";
+    assert_eq!(
+        diagnostics(&[source]),
+        [
+            "2:15: error: constructor new must return Main",
+            "2:34: warning: constructor new should return this",
+            "3:25: error: size must return a value of type int",
+            "4:32: error: run is void, it cannot return a value",
+            "5:49: warning: missing return at the end of last",
+            "6:36: warning: unreachable statement",
+            "6:40: error: undefined variable x",
+        ]
+    );
+}
+
+#[test]
+fn types() {
+    let source = "class Main {
+  function String name(boolean b) {
+    var String s;
+    var Array a;
+    let s = true;
+    let a = s;
+    let s = 1000;
+    do Main.name(\"yes\");
+    do Output.printInt(Main.done());
+    return false;
+  }
+  function void done() { return; }
+}";
+    assert_eq!(
+        diagnostics(&[source]),
+        [
+            "5:13: warning: s is String, it cannot be assigned boolean",
+            "8:18: warning: argument 1 of Main.name is boolean, got String",
+            "9:29: warning: done returns void, it has no value",
+            "10:12: warning: name returns String, not boolean",
+        ]
+    );
+}
@@ -0,0 +1,112 @@
+//! The formatter, on a class in a loose style and on the programs of
+//! projects/09 and projects/11.
+use std::fs;
+use std::path::PathBuf;
+
+use jack_compiler::codegen::{compile, vm_code};
+use jack_compiler::format::format;
+use jack_compiler::jack_files;
+use jack_compiler::parser::parse;
+
+#[test]
+fn formats_a_class() {
+    let source = "\
+// Counts.
+class Counter
+{
+  field int count;   // the count
+
+
+  /** Adds n,
+   *  at most 10. */
+  method void add(int n) { if (n>10) { let n=10; }
+    else {let n = -n+1;}   // negated
+    let count=count+(n*2);
+    // done
+  return; }
+}
+";
+    let expected = "\
+// Counts.
+class Counter {
+    field int count; // the count
+
+    /** Adds n,
+     *  at most 10. */
+    method void add(int n) {
+        if (n > 10) {
+            let n = 10;
+        } else {
+            let n = -n + 1; // negated
+        }
+        let count = count + (n * 2);
+        // done
+        return;
+    }
+}
+";
+    assert_eq!(format(source).unwrap(), expected);
+    assert_eq!(format(expected).unwrap(), expected);
+}
+
+#[test]
+fn keeps_the_comments_inside_the_code() {
+    let source = "\
+class Main {
+  function void main(int first, /* second */ int second) {
+      let x = Foo.bar(1, // one
+            2); // two
+      do Output.printInt(x);   // printed
+  if (x /* big */ > 1) {
+  return; }
+  return;
+  }
+}
+";
+    let expected = "\
+class Main {
+    function void main(int first, /* second */ int second) {
+        let x = Foo.bar(1, // one
+              2); // two
+        do Output.printInt(x); // printed
+        if (x /* big */ > 1) {
+            return;
+        }
+        return;
+    }
+}
+";
+    assert_eq!(format(source).unwrap(), expected);
+    assert_eq!(format(expected).unwrap(), expected);
+}
+
+#[test]
+fn keeps_the_line_endings() {
+    let source = "class Main {\r\n  function void main() { return; }\r\n}\r\n";
+    let expected =
+        "class Main {\r\n    function void main() {\r\n        return;\r\n    }\r\n}\r\n";
+    assert_eq!(format(source).unwrap(), expected);
+}
+
+#[test]
+fn keeps_the_code_of_programs() {
+    let projects = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../projects");
+    for program in [
+        "09/Average",
+        "09/Fraction",
+        "09/List",
+        "09/Square",
+        "11/ComplexArrays",
+        "11/ConvertToBin",
+        "11/Pong",
+        "11/Square",
+    ] {
+        for file in jack_files(&projects.join(program)).unwrap() {
+            let source = fs::read_to_string(&file).unwrap();
+            let formatted = format(&source).unwrap();
+            assert_eq!(format(&formatted).unwrap(), formatted, "{}", file.display());
+            let code = |source: &str| vm_code(&compile(&parse(source).unwrap()).unwrap());
+            assert_eq!(code(&formatted), code(&source), "{}", file.display());
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! The hackc linker on programs written to a temporary directory.
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// hackc writes the classes of a program to its own directory and builds
+/// it, the directory is removed afterwards.
+fn hackc(name: &str, classes: &[(&str, String)], args: &[&str]) -> Output {
+    let directory: PathBuf =
+        std::env::temp_dir().join(format!("hackc-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for (class, source) in classes {
+        fs::write(directory.join(format!("{}.jack", class)), source).unwrap();
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_hackc"))
+        .arg(&directory)
+        .args(args)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    output
+}
+
+/// statics is a Main class using a number of static variables.
+fn statics(count: usize) -> String {
+    let names: Vec<String> = (0..count).map(|index| format!("s{}", index)).collect();
+    let lets: Vec<String> = names
+        .iter()
+        .map(|name| format!("        let {} = 1;\n", name))
+        .collect();
+    format!(
+        "class Main {{\n    static int {};\n    function void main() {{\n{}        return;\n    }}\n}}\n",
+        names.join(", "),
+        lets.concat()
+    )
+}
+
+#[test]
+fn builds_a_program() {
+    for args in [&[][..], &["-O"]] {
+        let output = hackc("builds", &[("Main", statics(200))], args);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn fails_when_the_statics_overflow_with_the_os() {
+    // The OS classes use 12 static variables.
+    for args in [&[][..], &["-O"]] {
+        let output = hackc("statics", &[("Main", statics(235))], args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "{:?}", args);
+        assert_eq!(
+            stderr,
+            "err: Too many static variables: 247 are used but only 240 fit in RAM 16 - 255\n"
+        );
+    }
+}
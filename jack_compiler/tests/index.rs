@@ -0,0 +1,93 @@
+//! The names of classes and what they refer to, for the language server.
+use jack_compiler::index::{declaration, doc_comment, references, signature, Target};
+use jack_compiler::parser::parse;
+use jack_compiler::Span;
+
+const LIST: &str = "\
+/** A list of integers. */
+class List {
+    field int data;
+    field List next;
+
+    /** Makes a list. */
+    constructor List new(int car, List cdr) {
+        let data = car;
+        let next = cdr;
+        return this;
+    }
+
+    method int sum() {
+        var int total;
+        let total = data;
+        if (~(next = null)) {
+            let total = total + next.sum();
+        }
+        return total;
+    }
+}
+";
+
+fn at(line: usize, column: usize) -> Span {
+    Span { line, column }
+}
+
+#[test]
+fn finds_what_names_refer_to() {
+    let class = parse(LIST).unwrap();
+    let references = references(&class);
+    let target = |span| {
+        references
+            .iter()
+            .find(|reference| reference.contains(span))
+            .map(|reference| reference.target.clone())
+    };
+    let list = || String::from("List");
+    assert_eq!(target(at(2, 7)), Some(Target::Class(list())));
+    assert_eq!(target(at(4, 11)), Some(Target::Class(list())));
+    assert_eq!(target(at(8, 13)), Some(Target::Variable(list(), at(3, 15))));
+    assert_eq!(target(at(8, 20)), Some(Target::Variable(list(), at(7, 30))));
+    assert_eq!(
+        target(at(17, 38)),
+        Some(Target::Subroutine(list(), String::from("sum")))
+    );
+    assert_eq!(
+        target(at(17, 33)),
+        Some(Target::Variable(list(), at(4, 16)))
+    );
+
+    let uses = references
+        .iter()
+        .filter(|reference| reference.target == Target::Variable(list(), at(14, 17)))
+        .map(|reference| (reference.span, reference.declaration))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        uses,
+        [
+            (at(14, 17), true),
+            (at(15, 13), false),
+            (at(17, 17), false),
+            (at(17, 25), false),
+            (at(19, 16), false)
+        ]
+    );
+}
+
+#[test]
+fn describes_declarations() {
+    let class = parse(LIST).unwrap();
+    assert_eq!(
+        signature("List", &class.subroutines[0]),
+        "constructor List List.new(int car, List cdr)"
+    );
+    assert_eq!(
+        declaration(&class, at(7, 30)).unwrap().description(),
+        "argument int car"
+    );
+    assert_eq!(
+        declaration(&class, at(14, 17)).unwrap().description(),
+        "var int total"
+    );
+    assert_eq!(doc_comment(LIST, 2).as_deref(), Some("A list of integers."));
+    assert_eq!(doc_comment(LIST, 7).as_deref(), Some("Makes a list."));
+    assert_eq!(doc_comment(LIST, 13), None);
+}
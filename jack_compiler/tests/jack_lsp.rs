@@ -0,0 +1,101 @@
+//! The language server on a program written to a temporary directory, the
+//! messages are piped to the binary.
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use serde_json::{json, Value};
+
+const MAIN: &str = "class Main {
+    function void main() {
+        do Output.
+        do String.
+        return;
+    }
+}
+";
+
+/// complete opens Main.jack and returns the labels of the completions at
+/// each position, the lines and characters count from 0.
+fn complete(positions: &[(u64, u64)]) -> Vec<Vec<String>> {
+    let directory = std::env::temp_dir().join(format!("jack_compiler-lsp-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("Main.jack");
+    fs::write(&path, MAIN).unwrap();
+    let uri = format!("file://{}", path.display());
+
+    let mut messages = vec![
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": MAIN } },
+        }),
+    ];
+    for (id, (line, character)) in positions.iter().enumerate() {
+        messages.push(json!({
+            "jsonrpc": "2.0",
+            "id": id + 1,
+            "method": "textDocument/completion",
+            "params": {
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+            },
+        }));
+    }
+    messages.push(json!({ "jsonrpc": "2.0", "id": 99, "method": "shutdown" }));
+    messages.push(json!({ "jsonrpc": "2.0", "method": "exit" }));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jack_lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for message in &messages {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let replies: Vec<Value> = stdout
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|message| serde_json::from_str(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+    (1..=positions.len())
+        .map(|id| {
+            let reply = replies.iter().find(|reply| reply["id"] == id).unwrap();
+            reply["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["label"].as_str().unwrap().to_string())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn completes_the_os_api_only() {
+    let completions = complete(&[(2, 18), (3, 18)]);
+    assert_eq!(
+        completions[0],
+        [
+            "moveCursor",
+            "printChar",
+            "printString",
+            "printInt",
+            "println",
+            "backSpace"
+        ]
+    );
+    assert_eq!(
+        completions[1],
+        ["new", "newLine", "backSpace", "doubleQuote"]
+    );
+}
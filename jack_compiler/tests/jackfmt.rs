@@ -0,0 +1,50 @@
+//! The jackfmt command in check mode.
+use std::path::PathBuf;
+use std::process::Command;
+
+/// jackfmt checks a path, it returns the success, the files listed and the
+/// errors with the path replaced by PATH.
+fn jackfmt(path: &str) -> (bool, String, String) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path);
+    let output = Command::new(env!("CARGO_BIN_EXE_jackfmt"))
+        .arg("--check")
+        .arg(&path)
+        .output()
+        .unwrap();
+    let replace = |output: &[u8]| {
+        String::from_utf8_lossy(output).replace(&path.display().to_string(), "PATH")
+    };
+    (
+        output.status.success(),
+        replace(&output.stdout),
+        replace(&output.stderr),
+    )
+}
+
+#[test]
+fn fails_without_jack_files() {
+    let (success, _, stderr) = jackfmt("../projects/09/BitmapEditor");
+    assert!(!success);
+    assert_eq!(stderr, "err: no .jack files found in PATH\n");
+}
+
+#[test]
+fn checks_the_subdirectories() {
+    // BitmapEditor has no Jack files and Square/Main.jack is formatted.
+    let (success, stdout, stderr) = jackfmt("../projects/09");
+    assert!(!success);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "PATH/Average/Main.jack",
+            "PATH/Fraction/Fraction.jack",
+            "PATH/Fraction/Main.jack",
+            "PATH/HelloWorld/Main.jack",
+            "PATH/List/List.jack",
+            "PATH/List/Main.jack",
+            "PATH/Square/Square.jack",
+            "PATH/Square/SquareGame.jack",
+        ]
+    );
+    assert_eq!(stderr, "err: 8 files are not formatted\n");
+}
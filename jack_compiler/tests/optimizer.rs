@@ -0,0 +1,120 @@
+//! The optimizer on small programs.
+use jack_compiler::codegen::{compile, vm_code};
+use jack_compiler::optimizer::optimize;
+use jack_compiler::parser::parse;
+
+fn optimized(sources: &[&str]) -> Vec<String> {
+    let mut classes: Vec<_> = sources
+        .iter()
+        .map(|source| compile(&parse(source).unwrap()).unwrap())
+        .collect();
+    optimize(&mut classes);
+    classes.iter().map(|commands| vm_code(commands)).collect()
+}
+
+#[test]
+fn folds_constants() {
+    let source = "class Main { function void main() {
+        do Output.printInt(1 + (2 * 3));
+        do Output.printInt(-5 - 3);
+        do Output.printInt((7 / 2) & 1);
+        return;
+    } }";
+    let expected = "\
+function Main.main 0
+push constant 7
+call Output.printInt 1
+pop temp 0
+push constant 8
+neg
+call Output.printInt 1
+pop temp 0
+push constant 1
+call Output.printInt 1
+pop temp 0
+push constant 0
+return
+";
+    assert_eq!(optimized(&[source]), [expected]);
+}
+
+#[test]
+fn multiplies_by_additions() {
+    let source = "class Main { function int main(int x) {
+        return (x * 8) + (4 * x) + (x * 16);
+    } }";
+    let expected = "\
+function Main.main 0
+push argument 0
+push argument 0
+add
+pop temp 1
+push temp 1
+push temp 1
+add
+pop temp 1
+push temp 1
+push temp 1
+add
+push argument 0
+push argument 0
+add
+pop temp 1
+push temp 1
+push temp 1
+add
+add
+push argument 0
+push constant 16
+call Math.multiply 2
+add
+return
+";
+    assert_eq!(optimized(&[source]), [expected]);
+}
+
+#[test]
+fn inlines_getters() {
+    let point = "class Point {
+        field int x;
+        static int count;
+        constructor Point new() { let x = 3; let count = count + 1; return this; }
+        method int getX() { return x; }
+        method int twice() { return getX() + getX(); }
+        function int count() { return count; }
+        function int origin() { return 0; }
+    }";
+    let main = "class Main { function int main() {
+        var Point p;
+        let p = Point.new();
+        return p.getX() + p.twice() + Point.origin() + Point.count();
+    } }";
+    let optimized = optimized(&[point, main]);
+    assert!(optimized[0].contains("function Point.twice 0\npush argument 0\npop pointer 0\npush this 0\npush this 0\nadd\nreturn\n"));
+    // Static variables stay private to their class.
+    assert!(optimized[1]
+        .contains("push local 0\npop pointer 1\npush that 0\npush local 0\ncall Point.twice 1\nadd\ncall Point.count 0\nadd\n"));
+}
+
+#[test]
+fn removes_unreachable_subroutines() {
+    let source = "class Main {
+        function void main() { do Main.used(); return; }
+        function void used() { do Output.printChar(65); return; }
+        function void unused() { do Main.used(); return; }
+    }";
+    let expected = "\
+function Main.main 0
+call Main.used 0
+pop temp 0
+push constant 0
+return
+function Main.used 0
+push constant 65
+call Output.printChar 1
+pop temp 0
+push constant 0
+return
+";
+    assert_eq!(optimized(&[source]), [expected]);
+}
@@ -0,0 +1,163 @@
+//! The Jack OS of projects/12 with the test programs of its directories.
+//! A test program is copied with the classes of the OS, like hackc links
+//! them.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use hack_cpu::{Cpu, Status, SCREEN};
+use hack_test::{script, Runner, VmSimulator};
+
+const OS: [&str; 8] = [
+    "Array", "Keyboard", "Math", "Memory", "Output", "Screen", "String", "Sys",
+];
+
+fn projects12() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../projects/12")
+}
+
+/// copy copies a test directory and the classes of the OS to a temporary
+/// directory of the same name.
+fn copy(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir()
+        .join(format!("jack_compiler-os-{}", std::process::id()))
+        .join(test);
+    fs::create_dir_all(&directory).unwrap();
+    for entry in fs::read_dir(projects12().join(test)).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() {
+            fs::copy(&path, directory.join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    for class in OS {
+        let file = format!("{}.jack", class);
+        fs::copy(projects12().join(&file), directory.join(file)).unwrap();
+    }
+    directory
+}
+
+fn run(program: &str, directory: &Path) {
+    let output = Command::new(program).arg(directory).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn passes_the_test_scripts() {
+    for test in ["ArrayTest", "MathTest", "MemoryTest"] {
+        let directory = copy(test);
+        run(env!("CARGO_BIN_EXE_jack_compiler"), &directory);
+
+        let script = directory.join(format!("{}.tst", test));
+        let statements = script::parse(&fs::read_to_string(&script).unwrap()).unwrap();
+        let report = Runner::new(VmSimulator::new(), &directory)
+            .run(&statements)
+            .unwrap_or_else(|err| panic!("{}: {}", script.display(), err));
+        // The header and the line of values.
+        assert_eq!(report.compared, Some(2), "{}", test);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
+
+/// screen builds a test program with hackc and runs it on the CPU until
+/// it halts, the tests of String, Output and Screen are pictures of it.
+fn screen(test: &str) -> Cpu {
+    let directory = copy(test);
+    run(env!("CARGO_BIN_EXE_hackc"), &directory);
+    let program = hack_cpu::load_hack(&directory.join(format!("{}.hack", test))).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    let mut cpu = Cpu::new(&program).unwrap();
+    assert_eq!(cpu.run(100_000_000).1, Status::Halted, "{}", test);
+    cpu
+}
+
+/// text reads the 23 lines of 64 characters of the screen back, with the
+/// font that Output.jack creates. An unknown character is read as `?`.
+fn text(cpu: &Cpu) -> Vec<String> {
+    let source = fs::read_to_string(projects12().join("Output.jack")).unwrap();
+    let mut font = HashMap::new();
+    for line in source.lines() {
+        let Some(arguments) = line.trim().strip_prefix("do Output.create(") else {
+            continue;
+        };
+        let arguments = &arguments[..arguments.find(')').unwrap()];
+        let values: Vec<u16> = arguments.split(',').map(|v| v.parse().unwrap()).collect();
+        font.insert(values[1..].to_vec(), char::from(values[0] as u8));
+    }
+
+    (0..23)
+        .map(|row| {
+            let line: String = (0..64)
+                .map(|column| {
+                    let glyph: Vec<u16> = (0..11)
+                        .map(|y| {
+                            let word = cpu.peek(SCREEN + (row * 11 + y) * 32 + column / 2);
+                            word >> (8 * (column % 2)) & 0xFF
+                        })
+                        .collect();
+                    font.get(&glyph).copied().unwrap_or('?')
+                })
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn prints_the_strings() {
+    let expected = [
+        "new,appendChar: abcde",
+        "setInt: 12345",
+        "setInt: -32767",
+        "length: 5",
+        "charAt[2]: 99",
+        "setCharAt(2,'-'): ab-de",
+        "eraseLastChar: ab-d",
+        "intValue: 456",
+        "intValue: -32123",
+        "backSpace: 129",
+        "doubleQuote: 34",
+        "newLine: 128",
+    ];
+    let text = text(&screen("StringTest"));
+    assert_eq!(text[..expected.len()], expected);
+    assert!(text[expected.len()..].iter().all(|line| line.is_empty()));
+}
+
+#[test]
+fn prints_the_characters() {
+    let text = text(&screen("OutputTest"));
+    let mut expected = vec![String::new(); 23];
+    // A is printed after the last column of the last line, it wraps around.
+    expected[0] = format!("A{}B", " ".repeat(62));
+    expected[2] = String::from("0123456789");
+    expected[3] = String::from("ABCDEFGHIJKLMNOPQRSTUVWXYZ abcdefghijklmnopqrstuvwxyz");
+    expected[4] = String::from("!#$%&'()*+,-./:;<=>?@[\\]^_`{|}~\"");
+    expected[5] = String::from("-12346789");
+    expected[22] = format!("C{}D", " ".repeat(62));
+    assert_eq!(text, expected);
+}
+
+#[test]
+fn draws_the_picture() {
+    let cpu = screen("ScreenTest");
+    let pixel = |x: usize, y: usize| cpu.peek(SCREEN + y * 32 + x / 16) >> (x % 16) & 1 == 1;
+
+    // The base line and the sun are black, the door and the window of the
+    // house are cut out of it.
+    assert!((0..512).all(|x| pixel(x, 220)));
+    assert!(!pixel(0, 219) && !pixel(0, 221));
+    assert!((280..=410).all(|x| pixel(x, 100)));
+    assert!(!pixel(279, 100) && !pixel(411, 100));
+    assert!((350..=390).all(|x| !pixel(x, 200) || x == 360));
+    assert!(!pixel(300, 130) && pixel(300, 160));
+    assert!((111..=169).all(|x| pixel(x, 60)));
+    assert!(!pixel(140, 60 - 31) && pixel(140, 6));
+    // The roof.
+    assert!(pixel(280, 90) && pixel(345, 35) && pixel(410, 90));
+}
@@ -0,0 +1,83 @@
+//! The syntax analyzer against the compare files of projects/10.
+use std::fs;
+use std::path::PathBuf;
+
+use jack_compiler::jack_files;
+use jack_compiler::parser::parse;
+use jack_compiler::tokenizer::{tokenize, tokens_xml};
+use jack_compiler::xml::class_xml;
+
+fn project(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects/10")
+        .join(name)
+}
+
+/// check compares the outputs for every Jack file of a project with its
+/// FileT.xml and File.xml.
+fn check(name: &str) {
+    let files = jack_files(&project(name)).unwrap();
+    assert!(!files.is_empty(), "no .jack files in {}", name);
+    for file in files {
+        let source = fs::read_to_string(&file).unwrap();
+        let stem = file.with_extension("");
+        let stem = stem.display();
+
+        let tokens = tokenize(&source).unwrap();
+        let expected = fs::read_to_string(format!("{}T.xml", stem)).unwrap();
+        assert!(tokens_xml(&tokens) == expected, "{}T.xml differs", stem);
+
+        let class = parse(&source).unwrap_or_else(|err| panic!("{}:{}", file.display(), err));
+        let expected = fs::read_to_string(format!("{}.xml", stem)).unwrap();
+        assert!(class_xml(&class) == expected, "{}.xml differs", stem);
+    }
+}
+
+#[test]
+fn array_test() {
+    check("ArrayTest");
+}
+
+#[test]
+fn expression_less_square() {
+    check("ExpressionLessSquare");
+}
+
+#[test]
+fn square() {
+    check("Square");
+}
+
+#[test]
+fn syntax_errors() {
+    let cases = [
+        (
+            "class Main { function void main() { let x = 1 } }",
+            "1:47: expected ';', got \"}\"",
+        ),
+        (
+            "class Main { function void main() { do Output; } }",
+            "1:46: expected '(', got \";\"",
+        ),
+        (
+            "class Main { function void main() { return",
+            "1:43: expected an expression, got the end of the file",
+        ),
+        (
+            "class Main { var int x; }",
+            "1:14: expected constructor, function, method or '}', got \"var\"",
+        ),
+        (
+            "class Main { } class",
+            "1:16: expected the end of the file, got \"class\"",
+        ),
+    ];
+    for (source, message) in cases {
+        assert_eq!(
+            parse(source).unwrap_err().to_string(),
+            message,
+            "{}",
+            source
+        );
+    }
+}
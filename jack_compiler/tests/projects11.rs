@@ -0,0 +1,78 @@
+//! The code generator on the programs of projects/11.
+use std::fs;
+use std::path::PathBuf;
+
+use jack_compiler::codegen::{compile, vm_code};
+use jack_compiler::jack_files;
+use jack_compiler::parser::parse;
+
+fn compile_file(path: &PathBuf) -> String {
+    let source = fs::read_to_string(path).unwrap();
+    let class = parse(&source).unwrap_or_else(|err| panic!("{}:{}", path.display(), err));
+    let commands = compile(&class).unwrap_or_else(|err| panic!("{}:{}", path.display(), err));
+    vm_code(&commands)
+}
+
+#[test]
+fn compiles_every_program() {
+    let projects = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../projects/11");
+    for program in [
+        "Average",
+        "ComplexArrays",
+        "ConvertToBin",
+        "Pong",
+        "Seven",
+        "Square",
+    ] {
+        let files = jack_files(&projects.join(program)).unwrap();
+        assert!(!files.is_empty(), "no .jack files in {}", program);
+        for file in &files {
+            compile_file(file);
+        }
+    }
+}
+
+#[test]
+fn seven() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../projects/11/Seven/Main.jack");
+    let expected = "\
+function Main.main 0
+push constant 1
+push constant 2
+push constant 3
+call Math.multiply 2
+add
+call Output.printInt 1
+pop temp 0
+push constant 0
+return
+";
+    assert_eq!(compile_file(&path), expected);
+}
+
+#[test]
+fn semantic_errors() {
+    let cases = [
+        (
+            "class Main { function void main() { let x = 1; return; } }",
+            "1:41: undefined variable x",
+        ),
+        (
+            "class Main { function void main() { var int x, x; return; } }",
+            "1:48: x is already declared at 1:45",
+        ),
+        (
+            "class Main { function void main() { var int x; do x.run(); return; } }",
+            "1:51: x is not an object",
+        ),
+    ];
+    for (source, message) in cases {
+        let class = parse(source).unwrap();
+        assert_eq!(
+            compile(&class).unwrap_err().to_string(),
+            message,
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Emulator of the VM language, it executes the commands of `.vm` files
+//! directly on the Hack RAM, with the same memory layout as the code of
+//! the vm_translator. The OS can run natively, in Rust.
+
+mod os;
+mod vm;
+
+pub use os::OS_CLASSES;
+pub use vm::{Status, Vm, RAM_SIZE};
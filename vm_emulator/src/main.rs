@@ -0,0 +1,123 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use vm_emulator::{Status, Vm, OS_CLASSES, RAM_SIZE};
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The VM file (.vm) or the directory of VM files to run.
+    path: PathBuf,
+
+    /// Maximum number of VM commands to run, the program stops earlier when it halts.
+    #[arg(short = 'n', long, default_value_t = 1_000_000)]
+    steps: u64,
+
+    /// Sets a RAM word before running, as ADDRESS=VALUE (e.g. 0=256). Can be repeated.
+    #[arg(short, long, value_parser = parse_assignment)]
+    set: Vec<(usize, i16)>,
+
+    /// RAM range to print after running, as START-END or a single ADDRESS. Can be repeated.
+    #[arg(short, long, value_parser = parse_range)]
+    dump: Vec<RangeInclusive<usize>>,
+
+    /// Classes of the OS to run natively even if the program defines them,
+    /// separated by commas, or all. The classes it does not define always are.
+    #[arg(long, value_delimiter = ',')]
+    native: Vec<String>,
+}
+
+fn parse_address(value: &str) -> Result<usize, String> {
+    let address: usize = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid address {value:?}"))?;
+    if address >= RAM_SIZE {
+        return Err(format!("address {address} is outside of the RAM"));
+    }
+    Ok(address)
+}
+
+fn parse_assignment(value: &str) -> Result<(usize, i16), String> {
+    let (address, word) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected ADDRESS=VALUE, got {value:?}"))?;
+    let word = word
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value {word:?}"))?;
+    Ok((parse_address(address)?, word))
+}
+
+fn parse_range(value: &str) -> Result<RangeInclusive<usize>, String> {
+    match value.split_once('-') {
+        Some((start, end)) => Ok(parse_address(start)?..=parse_address(end)?),
+        None => {
+            let address = parse_address(value)?;
+            Ok(address..=address)
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut vm = match Vm::load(&args.path) {
+        Ok(vm) => vm,
+        Err(err) => {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    for class in &args.native {
+        let classes = match class.as_str() {
+            "all" => OS_CLASSES.to_vec(),
+            class => vec![class],
+        };
+        for class in classes {
+            if let Err(err) = vm.native(class) {
+                eprintln!("err: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // A program with Sys.init starts like the translated one, otherwise
+    // the stack and the segments are set up with --set.
+    if vm.bootstrap().is_ok() {
+        println!("bootstrap: SP = 256, call Sys.init");
+    }
+
+    for (address, value) in &args.set {
+        vm.poke(*address, *value as u16);
+    }
+
+    let (steps, status) = match vm.run(args.steps) {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("err: {err}");
+            std::process::exit(1);
+        }
+    };
+    match status {
+        Status::Halted => println!("halted after {steps} steps"),
+        Status::Running => println!("stopped after {steps} steps"),
+    }
+    println!(
+        "SP = {}, LCL = {}, ARG = {}, THIS = {}, THAT = {}",
+        vm.peek(0),
+        vm.peek(1),
+        vm.peek(2),
+        vm.peek(3),
+        vm.peek(4)
+    );
+
+    for range in &args.dump {
+        for address in range.clone() {
+            println!("RAM[{address}] = {}", vm.peek(address) as i16);
+        }
+    }
+}
@@ -0,0 +1,808 @@
+use crate::vm::Vm;
+use crate::RAM_SIZE;
+
+/// The classes of the Jack OS, each one can run natively.
+pub const OS_CLASSES: [&str; 8] = [
+    "Array", "Keyboard", "Math", "Memory", "Output", "Screen", "String", "Sys",
+];
+
+// Memory map of the Hack computer.
+const HEAP: u16 = 2048;
+const HEAP_SIZE: u16 = 14336;
+const SCREEN: u16 = 16384;
+const KBD: u16 = 24576;
+
+// Characters of the keyboard and of String.
+const NEW_LINE: u16 = 128;
+const BACKSPACE: u16 = 129;
+
+// Fields of a String object.
+const CHARS: u16 = 0;
+const SIZE: u16 = 1;
+const CAPACITY: u16 = 2;
+
+/// Stop is why a native function returns no value.
+pub(crate) enum Stop {
+    /// The program halted, in Sys.halt or in Sys.error.
+    Halt,
+    /// Keyboard waits for a key, the call is made again at the next step.
+    Wait,
+    Error(String),
+}
+
+/// Os is the state the classes of the OS keep in static variables, the
+/// rest lives in the RAM like with the Jack OS: the heap, the objects and
+/// the screen.
+#[derive(Default)]
+pub(crate) struct Os {
+    free_list: u16,
+    color: u16,
+    row: u16,
+    column: u16,
+    // The String of printInt and the Array of the character maps.
+    number: u16,
+    char_maps: u16,
+    // Keyboard.readChar shows its cursor once, then waits for a key to be
+    // pressed and released. Keyboard.readLine keeps its line across waits.
+    cursor: bool,
+    key: Option<u16>,
+    line: Option<u16>,
+}
+
+/// init initializes the classes of the OS, in the order of Sys.init.
+pub(crate) fn init(vm: &mut Vm) -> Result<(), Stop> {
+    for class in ["Memory", "Math", "Screen", "Output", "Keyboard"] {
+        vm.invoke(&format!("{}.init", class), &[])?;
+    }
+    Ok(())
+}
+
+/// call runs a function of the OS natively, it has the same effects on
+/// the RAM as the Jack OS of projects/12. The OS functions it calls may
+/// run natively or not.
+pub(crate) fn call(vm: &mut Vm, name: &str, args: &[u16]) -> Result<u16, Stop> {
+    match (name, args) {
+        ("Array.new", &[size]) => array_new(vm, size),
+        ("Array.dispose", &[this]) => vm.invoke("Memory.deAlloc", &[this]).map(|_| 0),
+
+        ("Keyboard.init", &[]) => Ok(0),
+        ("Keyboard.keyPressed", &[]) => read(vm, KBD),
+        ("Keyboard.readChar", &[]) => read_char(vm),
+        ("Keyboard.readLine", &[message]) => read_line(vm, message),
+        ("Keyboard.readInt", &[message]) => read_int(vm, message),
+
+        ("Math.init", &[]) => math_init(vm),
+        ("Math.abs", &[x]) => Ok(int(x).wrapping_abs() as u16),
+        ("Math.multiply", &[x, y]) => Ok(int(x).wrapping_mul(int(y)) as u16),
+        ("Math.divide", &[x, y]) => divide(vm, x, y),
+        ("Math.sqrt", &[x]) => sqrt(vm, x),
+        ("Math.max", &[a, b]) => Ok(int(a).max(int(b)) as u16),
+        ("Math.min", &[a, b]) => Ok(int(a).min(int(b)) as u16),
+
+        ("Memory.init", &[]) => memory_init(vm),
+        ("Memory.peek", &[address]) => read(vm, address),
+        ("Memory.poke", &[address, value]) => write(vm, address, value).map(|_| 0),
+        ("Memory.alloc", &[size]) => alloc(vm, size),
+        ("Memory.deAlloc", &[o]) => de_alloc(vm, o),
+
+        ("Output.init", &[]) => output_init(vm),
+        ("Output.moveCursor", &[i, j]) => move_cursor(vm, i, j),
+        ("Output.printChar", &[c]) => print_char(vm, c),
+        ("Output.printString", &[s]) => print_string(vm, s),
+        ("Output.printInt", &[i]) => print_int(vm, i),
+        ("Output.println", &[]) => Ok(println(vm)),
+        ("Output.backSpace", &[]) => Ok(back_space(vm)),
+
+        ("Screen.init", &[]) => screen_init(vm),
+        ("Screen.clearScreen", &[]) => clear_screen(vm),
+        ("Screen.setColor", &[b]) => {
+            vm.os.color = b;
+            Ok(0)
+        }
+        ("Screen.drawPixel", &[x, y]) => draw_pixel(vm, int(x), int(y)),
+        ("Screen.drawLine", &[x1, y1, x2, y2]) => draw_line(vm, int(x1), int(y1), int(x2), int(y2)),
+        ("Screen.drawRectangle", &[x1, y1, x2, y2]) => {
+            draw_rectangle(vm, int(x1), int(y1), int(x2), int(y2))
+        }
+        ("Screen.drawCircle", &[x, y, r]) => draw_circle(vm, int(x), int(y), int(r)),
+
+        ("String.new", &[max_length]) => string_new(vm, max_length),
+        ("String.dispose", &[this]) => string_dispose(vm, this),
+        ("String.length", &[this]) => read(vm, this.wrapping_add(SIZE)),
+        ("String.charAt", &[this, j]) => char_at(vm, this, j),
+        ("String.setCharAt", &[this, j, c]) => set_char_at(vm, this, j, c),
+        ("String.appendChar", &[this, c]) => append_char(vm, this, c),
+        ("String.eraseLastChar", &[this]) => erase_last_char(vm, this),
+        ("String.intValue", &[this]) => int_value(vm, this),
+        ("String.setInt", &[this, val]) => set_int(vm, this, val),
+        ("String.newLine", &[]) => Ok(NEW_LINE),
+        ("String.backSpace", &[]) => Ok(BACKSPACE),
+        ("String.doubleQuote", &[]) => Ok(34),
+
+        ("Sys.init", &[]) => {
+            init(vm)?;
+            vm.invoke("Main.main", &[])?;
+            Err(Stop::Halt)
+        }
+        ("Sys.halt", &[]) => Err(Stop::Halt),
+        ("Sys.wait", &[duration]) => {
+            // Waiting has no effect on the RAM, it is skipped.
+            if int(duration) < 0 {
+                return error(vm, 1);
+            }
+            Ok(0)
+        }
+        ("Sys.error", &[code]) => {
+            let message = string(vm, "ERR")?;
+            vm.invoke("Output.printString", &[message])?;
+            vm.invoke("Output.printInt", &[code])?;
+            Err(Stop::Halt)
+        }
+
+        _ => Err(Stop::Error(format!(
+            "No native function {} with {} arguments",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+/// int is the signed value of a word.
+fn int(word: u16) -> i16 {
+    word as i16
+}
+
+fn read(vm: &Vm, address: u16) -> Result<u16, Stop> {
+    if address as usize >= RAM_SIZE {
+        return Err(Stop::Error(format!(
+            "address {} is outside of the RAM",
+            address
+        )));
+    }
+    Ok(vm.peek(address as usize))
+}
+
+fn write(vm: &mut Vm, address: u16, value: u16) -> Result<(), Stop> {
+    if address as usize >= RAM_SIZE {
+        return Err(Stop::Error(format!(
+            "address {} is outside of the RAM",
+            address
+        )));
+    }
+    vm.poke(address as usize, value);
+    Ok(())
+}
+
+/// error calls Sys.error, which halts the program.
+fn error(vm: &mut Vm, code: u16) -> Result<u16, Stop> {
+    vm.invoke("Sys.error", &[code])?;
+    Err(Stop::Halt)
+}
+
+/// string creates a String like the code of a string constant does.
+fn string(vm: &mut Vm, text: &str) -> Result<u16, Stop> {
+    let s = vm.invoke("String.new", &[text.len() as u16])?;
+    for c in text.bytes() {
+        vm.invoke("String.appendChar", &[s, c as u16])?;
+    }
+    Ok(s)
+}
+
+fn array_new(vm: &mut Vm, size: u16) -> Result<u16, Stop> {
+    if int(size) < 1 {
+        return error(vm, 2);
+    }
+    vm.invoke("Memory.alloc", &[size])
+}
+
+fn read_char(vm: &mut Vm) -> Result<u16, Stop> {
+    if !vm.os.cursor {
+        vm.invoke("Output.printChar", &[0])?;
+        vm.invoke("Output.backSpace", &[])?;
+        vm.os.cursor = true;
+    }
+    let key = read(vm, KBD)?;
+    let c = match vm.os.key {
+        Some(c) => c,
+        None if key == 0 => return Err(Stop::Wait),
+        None => *vm.os.key.insert(key),
+    };
+    if key != 0 {
+        return Err(Stop::Wait);
+    }
+    vm.os.key = None;
+    vm.os.cursor = false;
+    if c == NEW_LINE || c == BACKSPACE {
+        // The cursor is erased, printChar moves for these keys.
+        vm.invoke("Output.printChar", &[32])?;
+        vm.invoke("Output.backSpace", &[])?;
+    }
+    vm.invoke("Output.printChar", &[c])?;
+    Ok(c)
+}
+
+fn read_line(vm: &mut Vm, message: u16) -> Result<u16, Stop> {
+    let line = match vm.os.line {
+        Some(line) => line,
+        None => {
+            vm.invoke("Output.printString", &[message])?;
+            let line = vm.invoke("String.new", &[64])?;
+            vm.os.line = Some(line);
+            line
+        }
+    };
+    loop {
+        let c = read_char(vm)?;
+        if c == NEW_LINE {
+            vm.os.line = None;
+            return Ok(line);
+        }
+        let length = int(vm.invoke("String.length", &[line])?);
+        if c == BACKSPACE {
+            if length > 0 {
+                vm.invoke("String.eraseLastChar", &[line])?;
+            }
+        } else if length < 64 {
+            vm.invoke("String.appendChar", &[line, c])?;
+        }
+    }
+}
+
+fn read_int(vm: &mut Vm, message: u16) -> Result<u16, Stop> {
+    let line = read_line(vm, message)?;
+    let value = vm.invoke("String.intValue", &[line])?;
+    vm.invoke("String.dispose", &[line])?;
+    Ok(value)
+}
+
+fn math_init(vm: &mut Vm) -> Result<u16, Stop> {
+    let two_to_the = vm.invoke("Array.new", &[16])?;
+    for j in 0..16 {
+        write(vm, two_to_the.wrapping_add(j), 1 << j)?;
+    }
+    Ok(0)
+}
+
+fn divide(vm: &mut Vm, x: u16, y: u16) -> Result<u16, Stop> {
+    if y == 0 {
+        return error(vm, 3);
+    }
+    Ok(int(x).wrapping_div(int(y)) as u16)
+}
+
+fn sqrt(vm: &mut Vm, x: u16) -> Result<u16, Stop> {
+    if int(x) < 0 {
+        return error(vm, 4);
+    }
+    let mut y: u32 = 0;
+    while (y + 1) * (y + 1) <= x as u32 {
+        y += 1;
+    }
+    Ok(y as u16)
+}
+
+fn memory_init(vm: &mut Vm) -> Result<u16, Stop> {
+    vm.os.free_list = HEAP;
+    write(vm, HEAP, HEAP_SIZE)?;
+    write(vm, HEAP + 1, 0)?;
+    Ok(0)
+}
+
+/// alloc is the first fit of Memory.jack: the free segments hold their
+/// size and the next segment, a block holds its size before its address.
+fn alloc(vm: &mut Vm, size: u16) -> Result<u16, Stop> {
+    if int(size) < 1 {
+        return error(vm, 5);
+    }
+    let needed = size.wrapping_add(1);
+    let mut previous: u16 = 0;
+    let mut segment = vm.os.free_list;
+    while segment != 0 {
+        let length = read(vm, segment)?;
+        if int(length) > int(needed.wrapping_add(1)) {
+            let rest = length.wrapping_sub(needed);
+            write(vm, segment, rest)?;
+            let block = segment.wrapping_add(rest);
+            write(vm, block, needed)?;
+            return Ok(block.wrapping_add(1));
+        }
+        if int(length) >= int(needed) {
+            let next = read(vm, segment.wrapping_add(1))?;
+            if previous == 0 {
+                vm.os.free_list = next;
+            } else {
+                write(vm, previous.wrapping_add(1), next)?;
+            }
+            return Ok(segment.wrapping_add(1));
+        }
+        previous = segment;
+        segment = read(vm, segment.wrapping_add(1))?;
+    }
+    error(vm, 6)
+}
+
+fn de_alloc(vm: &mut Vm, o: u16) -> Result<u16, Stop> {
+    let block = o.wrapping_sub(1);
+    let mut previous: u16 = 0;
+    let mut next = vm.os.free_list;
+    while next != 0 && int(next) < int(block) {
+        previous = next;
+        next = read(vm, next.wrapping_add(1))?;
+    }
+    let length = read(vm, block)?;
+    if next != 0 && block.wrapping_add(length) == next {
+        let merged = length.wrapping_add(read(vm, next)?);
+        write(vm, block, merged)?;
+        let after = read(vm, next.wrapping_add(1))?;
+        write(vm, block.wrapping_add(1), after)?;
+    } else {
+        write(vm, block.wrapping_add(1), next)?;
+    }
+    if previous == 0 {
+        vm.os.free_list = block;
+        return Ok(0);
+    }
+    let previous_length = read(vm, previous)?;
+    if previous.wrapping_add(previous_length) == block {
+        let merged = previous_length.wrapping_add(read(vm, block)?);
+        write(vm, previous, merged)?;
+        let after = read(vm, block.wrapping_add(1))?;
+        write(vm, previous.wrapping_add(1), after)?;
+    } else {
+        write(vm, previous.wrapping_add(1), block)?;
+    }
+    Ok(0)
+}
+
+fn output_init(vm: &mut Vm) -> Result<u16, Stop> {
+    vm.os.row = 0;
+    vm.os.column = 0;
+    vm.os.number = vm.invoke("String.new", &[6])?;
+    vm.os.char_maps = vm.invoke("Array.new", &[127])?;
+    for (index, rows) in FONT {
+        let map = vm.invoke("Array.new", &[11])?;
+        write(vm, vm.os.char_maps.wrapping_add(index), map)?;
+        for (i, bits) in (0..).zip(rows) {
+            write(vm, map.wrapping_add(i), bits)?;
+        }
+    }
+    Ok(0)
+}
+
+fn move_cursor(vm: &mut Vm, i: u16, j: u16) -> Result<u16, Stop> {
+    if !(0..23).contains(&int(i)) || !(0..64).contains(&int(j)) {
+        return error(vm, 20);
+    }
+    vm.os.row = i;
+    vm.os.column = j;
+    draw_char(vm, 32)
+}
+
+fn print_char(vm: &mut Vm, c: u16) -> Result<u16, Stop> {
+    if c == NEW_LINE {
+        return Ok(println(vm));
+    }
+    if c == BACKSPACE {
+        back_space(vm);
+        return draw_char(vm, 32);
+    }
+    draw_char(vm, c)?;
+    vm.os.column += 1;
+    if vm.os.column == 64 {
+        println(vm);
+    }
+    Ok(0)
+}
+
+fn print_string(vm: &mut Vm, s: u16) -> Result<u16, Stop> {
+    let length = int(vm.invoke("String.length", &[s])?);
+    for i in 0..length.max(0) {
+        let c = vm.invoke("String.charAt", &[s, i as u16])?;
+        print_char(vm, c)?;
+    }
+    Ok(0)
+}
+
+fn print_int(vm: &mut Vm, i: u16) -> Result<u16, Stop> {
+    let number = vm.os.number;
+    vm.invoke("String.setInt", &[number, i])?;
+    print_string(vm, number)
+}
+
+fn println(vm: &mut Vm) -> u16 {
+    vm.os.column = 0;
+    vm.os.row += 1;
+    if vm.os.row == 23 {
+        vm.os.row = 0;
+    }
+    0
+}
+
+fn back_space(vm: &mut Vm) -> u16 {
+    if vm.os.column > 0 {
+        vm.os.column -= 1;
+    } else if vm.os.row > 0 {
+        vm.os.row -= 1;
+        vm.os.column = 63;
+    }
+    0
+}
+
+/// draw_char draws a character at the cursor, two columns share a word of
+/// the screen and the odd one is in the high byte.
+fn draw_char(vm: &mut Vm, c: u16) -> Result<u16, Stop> {
+    let c = if (32..=126).contains(&int(c)) { c } else { 0 };
+    let map = read(vm, vm.os.char_maps.wrapping_add(c))?;
+    let mut address = SCREEN + vm.os.row * 352 + vm.os.column / 2;
+    for i in 0..11 {
+        let bits = read(vm, map.wrapping_add(i))?;
+        let word = read(vm, address)?;
+        let word = if vm.os.column & 1 == 1 {
+            (word & 0x00FF) | bits.wrapping_mul(256)
+        } else {
+            (word & 0xFF00) | bits
+        };
+        write(vm, address, word)?;
+        address += 32;
+    }
+    Ok(0)
+}
+
+fn screen_init(vm: &mut Vm) -> Result<u16, Stop> {
+    let masks = vm.invoke("Array.new", &[16])?;
+    for i in 0..16 {
+        write(vm, masks.wrapping_add(i), 1 << i)?;
+    }
+    vm.os.color = 0xFFFF;
+    Ok(0)
+}
+
+fn clear_screen(vm: &mut Vm) -> Result<u16, Stop> {
+    for address in SCREEN..KBD {
+        write(vm, address, 0)?;
+    }
+    Ok(0)
+}
+
+fn on_screen(x: i16, y: i16) -> bool {
+    (0..512).contains(&x) && (0..256).contains(&y)
+}
+
+/// word is the address of the screen word holding pixel (x,y).
+fn word(x: i16, y: i16) -> u16 {
+    SCREEN + (y as u16) * 32 + (x as u16) / 16
+}
+
+/// paint sets the bits of mask in a word of the screen to the color, like
+/// the Jack OS any color but 0 is black.
+fn paint(vm: &mut Vm, address: u16, mask: u16) -> Result<(), Stop> {
+    let word = read(vm, address)?;
+    let word = if vm.os.color != 0 {
+        word | mask
+    } else {
+        word & !mask
+    };
+    write(vm, address, word)
+}
+
+fn draw_pixel(vm: &mut Vm, x: i16, y: i16) -> Result<u16, Stop> {
+    if !on_screen(x, y) {
+        return error(vm, 7);
+    }
+    paint(vm, word(x, y), 1 << (x & 15))?;
+    Ok(0)
+}
+
+/// draw_line draws the same pixels as Screen.jack: lines go from left to
+/// right, the diagonal ones step in x or in y after the sign of diff.
+fn draw_line(vm: &mut Vm, x1: i16, y1: i16, x2: i16, y2: i16) -> Result<u16, Stop> {
+    if !on_screen(x1, y1) || !on_screen(x2, y2) {
+        return error(vm, 8);
+    }
+    let ((x1, y1), (x2, y2)) = if x1 > x2 {
+        ((x2, y2), (x1, y1))
+    } else {
+        ((x1, y1), (x2, y2))
+    };
+    if y1 == y2 {
+        return draw_horizontal(vm, x1, x2, y1);
+    }
+    let step = if y1 > y2 { -1 } else { 1 };
+    if x1 == x2 {
+        let mask = 1 << (x1 & 15);
+        let mut y = y1;
+        loop {
+            paint(vm, word(x1, y), mask)?;
+            if y == y2 {
+                return Ok(0);
+            }
+            y += step;
+        }
+    }
+    let dx = x2 - x1;
+    let dy = (y2 - y1).abs();
+    let (mut x, mut y, mut diff) = (x1, y1, 0);
+    while x <= x2 && y - y2 != step {
+        draw_pixel(vm, x, y)?;
+        if diff < 0 {
+            y += step;
+            diff += dx;
+        } else {
+            x += 1;
+            diff -= dy;
+        }
+    }
+    Ok(0)
+}
+
+fn draw_rectangle(vm: &mut Vm, x1: i16, y1: i16, x2: i16, y2: i16) -> Result<u16, Stop> {
+    if x1 < 0 || x2 > 511 || y1 < 0 || y2 > 255 || x1 > x2 || y1 > y2 {
+        return error(vm, 9);
+    }
+    for y in y1..=y2 {
+        draw_horizontal(vm, x1, x2, y)?;
+    }
+    Ok(0)
+}
+
+fn draw_circle(vm: &mut Vm, x: i16, y: i16, r: i16) -> Result<u16, Stop> {
+    if !on_screen(x, y) {
+        return error(vm, 12);
+    }
+    if !(0..=181).contains(&r) {
+        return error(vm, 13);
+    }
+    // Rows outside the screen are skipped, the others are clipped.
+    for dy in -r..=r {
+        if (0..256).contains(&(y + dy)) {
+            let half = sqrt(vm, (r * r - dy * dy) as u16)? as i16;
+            draw_horizontal(vm, (x - half).max(0), (x + half).min(511), y + dy)?;
+        }
+    }
+    Ok(0)
+}
+
+/// draw_horizontal draws the pixels x1 to x2 of row y, x1 <= x2, a word at
+/// a time.
+fn draw_horizontal(vm: &mut Vm, x1: i16, x2: i16, y: i16) -> Result<u16, Stop> {
+    let first = word(x1, y);
+    let last = word(x2, y);
+    let left = !((1u16 << (x1 & 15)) - 1);
+    let right = (1u16 << (x2 & 15)).wrapping_sub(1) | (1 << (x2 & 15));
+    if first == last {
+        paint(vm, first, left & right)?;
+        return Ok(0);
+    }
+    paint(vm, first, left)?;
+    for address in first + 1..last {
+        write(vm, address, vm.os.color)?;
+    }
+    paint(vm, last, right)?;
+    Ok(0)
+}
+
+fn string_new(vm: &mut Vm, max_length: u16) -> Result<u16, Stop> {
+    // The constructor allocates the object first, Array.new refuses an
+    // empty array.
+    let this = vm.invoke("Memory.alloc", &[3])?;
+    if int(max_length) < 0 {
+        return error(vm, 14);
+    }
+    if int(max_length) > 0 {
+        let chars = vm.invoke("Array.new", &[max_length])?;
+        write(vm, this.wrapping_add(CHARS), chars)?;
+    }
+    write(vm, this.wrapping_add(CAPACITY), max_length)?;
+    write(vm, this.wrapping_add(SIZE), 0)?;
+    Ok(this)
+}
+
+fn string_dispose(vm: &mut Vm, this: u16) -> Result<u16, Stop> {
+    if int(read(vm, this.wrapping_add(CAPACITY))?) > 0 {
+        let chars = read(vm, this.wrapping_add(CHARS))?;
+        vm.invoke("Array.dispose", &[chars])?;
+    }
+    vm.invoke("Memory.deAlloc", &[this])?;
+    Ok(0)
+}
+
+/// fields reads the characters, size and capacity of a String.
+fn fields(vm: &Vm, this: u16) -> Result<(u16, u16, u16), Stop> {
+    Ok((
+        read(vm, this.wrapping_add(CHARS))?,
+        read(vm, this.wrapping_add(SIZE))?,
+        read(vm, this.wrapping_add(CAPACITY))?,
+    ))
+}
+
+fn char_at(vm: &mut Vm, this: u16, j: u16) -> Result<u16, Stop> {
+    let (chars, size, _) = fields(vm, this)?;
+    if int(j) < 0 || int(j) >= int(size) {
+        return error(vm, 15);
+    }
+    read(vm, chars.wrapping_add(j))
+}
+
+fn set_char_at(vm: &mut Vm, this: u16, j: u16, c: u16) -> Result<u16, Stop> {
+    let (chars, size, _) = fields(vm, this)?;
+    if int(j) < 0 || int(j) >= int(size) {
+        return error(vm, 16);
+    }
+    write(vm, chars.wrapping_add(j), c)?;
+    Ok(0)
+}
+
+fn append_char(vm: &mut Vm, this: u16, c: u16) -> Result<u16, Stop> {
+    let (chars, size, capacity) = fields(vm, this)?;
+    if size == capacity {
+        return error(vm, 17);
+    }
+    write(vm, chars.wrapping_add(size), c)?;
+    write(vm, this.wrapping_add(SIZE), size.wrapping_add(1))?;
+    Ok(this)
+}
+
+fn erase_last_char(vm: &mut Vm, this: u16) -> Result<u16, Stop> {
+    let size = read(vm, this.wrapping_add(SIZE))?;
+    if size == 0 {
+        return error(vm, 18);
+    }
+    write(vm, this.wrapping_add(SIZE), size - 1)?;
+    Ok(0)
+}
+
+fn int_value(vm: &mut Vm, this: u16) -> Result<u16, Stop> {
+    let (chars, size, _) = fields(vm, this)?;
+    let size = int(size);
+    let mut i = 0;
+    let negative = size > 0 && read(vm, chars)? == b'-' as u16;
+    if negative {
+        i = 1;
+    }
+    let mut value: i16 = 0;
+    while i < size {
+        let c = read(vm, chars.wrapping_add(i as u16))?;
+        if !(b'0' as u16..=b'9' as u16).contains(&c) {
+            break;
+        }
+        value = value
+            .wrapping_mul(10)
+            .wrapping_add((c - b'0' as u16) as i16);
+        i += 1;
+    }
+    if negative {
+        value = value.wrapping_neg();
+    }
+    Ok(value as u16)
+}
+
+fn set_int(vm: &mut Vm, this: u16, val: u16) -> Result<u16, Stop> {
+    write(vm, this.wrapping_add(SIZE), 0)?;
+    let mut val = int(val);
+    if val < 0 {
+        append_char(vm, this, b'-' as u16)?;
+        if val == i16::MIN {
+            // -32768 has no positive counterpart.
+            append_digits(vm, this, 3276)?;
+            append_char(vm, this, b'8' as u16)?;
+            return Ok(0);
+        }
+        val = -val;
+    }
+    append_digits(vm, this, val)?;
+    Ok(0)
+}
+
+/// append_digits appends the decimal digits of n >= 0, a full String is
+/// error 19 rather than the 17 of appendChar.
+fn append_digits(vm: &mut Vm, this: u16, n: i16) -> Result<u16, Stop> {
+    for digit in n.to_string().bytes() {
+        let (_, size, capacity) = fields(vm, this)?;
+        if size == capacity {
+            return error(vm, 19);
+        }
+        append_char(vm, this, digit as u16)?;
+    }
+    Ok(0)
+}
+
+/// FONT is the bitmap of the characters of Output, in the order the
+/// Output class of projects/12 creates them.
+const FONT: [(u16, [u16; 11]); 96] = [
+    (0, [63, 63, 63, 63, 63, 63, 63, 63, 63, 0, 0]),
+    (32, [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+    (33, [12, 30, 30, 30, 12, 12, 0, 12, 12, 0, 0]),
+    (34, [54, 54, 20, 0, 0, 0, 0, 0, 0, 0, 0]),
+    (35, [0, 18, 18, 63, 18, 18, 63, 18, 18, 0, 0]),
+    (36, [12, 30, 51, 3, 30, 48, 51, 30, 12, 12, 0]),
+    (37, [0, 0, 35, 51, 24, 12, 6, 51, 49, 0, 0]),
+    (38, [12, 30, 30, 12, 54, 27, 27, 27, 54, 0, 0]),
+    (39, [12, 12, 6, 0, 0, 0, 0, 0, 0, 0, 0]),
+    (40, [24, 12, 6, 6, 6, 6, 6, 12, 24, 0, 0]),
+    (41, [6, 12, 24, 24, 24, 24, 24, 12, 6, 0, 0]),
+    (42, [0, 0, 0, 51, 30, 63, 30, 51, 0, 0, 0]),
+    (43, [0, 0, 0, 12, 12, 63, 12, 12, 0, 0, 0]),
+    (44, [0, 0, 0, 0, 0, 0, 0, 12, 12, 6, 0]),
+    (45, [0, 0, 0, 0, 0, 63, 0, 0, 0, 0, 0]),
+    (46, [0, 0, 0, 0, 0, 0, 0, 12, 12, 0, 0]),
+    (47, [0, 0, 32, 48, 24, 12, 6, 3, 1, 0, 0]),
+    (48, [12, 30, 51, 51, 51, 51, 51, 30, 12, 0, 0]),
+    (49, [12, 14, 15, 12, 12, 12, 12, 12, 63, 0, 0]),
+    (50, [30, 51, 48, 24, 12, 6, 3, 51, 63, 0, 0]),
+    (51, [30, 51, 48, 48, 28, 48, 48, 51, 30, 0, 0]),
+    (52, [16, 24, 28, 26, 25, 63, 24, 24, 60, 0, 0]),
+    (53, [63, 3, 3, 31, 48, 48, 48, 51, 30, 0, 0]),
+    (54, [28, 6, 3, 3, 31, 51, 51, 51, 30, 0, 0]),
+    (55, [63, 49, 48, 48, 24, 12, 12, 12, 12, 0, 0]),
+    (56, [30, 51, 51, 51, 30, 51, 51, 51, 30, 0, 0]),
+    (57, [30, 51, 51, 51, 62, 48, 48, 24, 14, 0, 0]),
+    (58, [0, 0, 12, 12, 0, 0, 12, 12, 0, 0, 0]),
+    (59, [0, 0, 12, 12, 0, 0, 12, 12, 6, 0, 0]),
+    (60, [0, 0, 24, 12, 6, 3, 6, 12, 24, 0, 0]),
+    (61, [0, 0, 0, 63, 0, 0, 63, 0, 0, 0, 0]),
+    (62, [0, 0, 3, 6, 12, 24, 12, 6, 3, 0, 0]),
+    (64, [30, 51, 51, 59, 59, 59, 27, 3, 30, 0, 0]),
+    (63, [30, 51, 51, 24, 12, 12, 0, 12, 12, 0, 0]),
+    (65, [12, 30, 51, 51, 63, 51, 51, 51, 51, 0, 0]),
+    (66, [31, 51, 51, 51, 31, 51, 51, 51, 31, 0, 0]),
+    (67, [28, 54, 35, 3, 3, 3, 35, 54, 28, 0, 0]),
+    (68, [15, 27, 51, 51, 51, 51, 51, 27, 15, 0, 0]),
+    (69, [63, 51, 35, 11, 15, 11, 35, 51, 63, 0, 0]),
+    (70, [63, 51, 35, 11, 15, 11, 3, 3, 3, 0, 0]),
+    (71, [28, 54, 35, 3, 59, 51, 51, 54, 44, 0, 0]),
+    (72, [51, 51, 51, 51, 63, 51, 51, 51, 51, 0, 0]),
+    (73, [30, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0]),
+    (74, [60, 24, 24, 24, 24, 24, 27, 27, 14, 0, 0]),
+    (75, [51, 51, 51, 27, 15, 27, 51, 51, 51, 0, 0]),
+    (76, [3, 3, 3, 3, 3, 3, 35, 51, 63, 0, 0]),
+    (77, [33, 51, 63, 63, 51, 51, 51, 51, 51, 0, 0]),
+    (78, [51, 51, 55, 55, 63, 59, 59, 51, 51, 0, 0]),
+    (79, [30, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0]),
+    (80, [31, 51, 51, 51, 31, 3, 3, 3, 3, 0, 0]),
+    (81, [30, 51, 51, 51, 51, 51, 63, 59, 30, 48, 0]),
+    (82, [31, 51, 51, 51, 31, 27, 51, 51, 51, 0, 0]),
+    (83, [30, 51, 51, 6, 28, 48, 51, 51, 30, 0, 0]),
+    (84, [63, 63, 45, 12, 12, 12, 12, 12, 30, 0, 0]),
+    (85, [51, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0]),
+    (86, [51, 51, 51, 51, 51, 30, 30, 12, 12, 0, 0]),
+    (87, [51, 51, 51, 51, 51, 63, 63, 63, 18, 0, 0]),
+    (88, [51, 51, 30, 30, 12, 30, 30, 51, 51, 0, 0]),
+    (89, [51, 51, 51, 51, 30, 12, 12, 12, 30, 0, 0]),
+    (90, [63, 51, 49, 24, 12, 6, 35, 51, 63, 0, 0]),
+    (91, [30, 6, 6, 6, 6, 6, 6, 6, 30, 0, 0]),
+    (92, [0, 0, 1, 3, 6, 12, 24, 48, 32, 0, 0]),
+    (93, [30, 24, 24, 24, 24, 24, 24, 24, 30, 0, 0]),
+    (94, [8, 28, 54, 0, 0, 0, 0, 0, 0, 0, 0]),
+    (95, [0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 0]),
+    (96, [6, 12, 24, 0, 0, 0, 0, 0, 0, 0, 0]),
+    (97, [0, 0, 0, 14, 24, 30, 27, 27, 54, 0, 0]),
+    (98, [3, 3, 3, 15, 27, 51, 51, 51, 30, 0, 0]),
+    (99, [0, 0, 0, 30, 51, 3, 3, 51, 30, 0, 0]),
+    (100, [48, 48, 48, 60, 54, 51, 51, 51, 30, 0, 0]),
+    (101, [0, 0, 0, 30, 51, 63, 3, 51, 30, 0, 0]),
+    (102, [28, 54, 38, 6, 15, 6, 6, 6, 15, 0, 0]),
+    (103, [0, 0, 30, 51, 51, 51, 62, 48, 51, 30, 0]),
+    (104, [3, 3, 3, 27, 55, 51, 51, 51, 51, 0, 0]),
+    (105, [12, 12, 0, 14, 12, 12, 12, 12, 30, 0, 0]),
+    (106, [48, 48, 0, 56, 48, 48, 48, 48, 51, 30, 0]),
+    (107, [3, 3, 3, 51, 27, 15, 15, 27, 51, 0, 0]),
+    (108, [14, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0]),
+    (109, [0, 0, 0, 29, 63, 43, 43, 43, 43, 0, 0]),
+    (110, [0, 0, 0, 29, 51, 51, 51, 51, 51, 0, 0]),
+    (111, [0, 0, 0, 30, 51, 51, 51, 51, 30, 0, 0]),
+    (112, [0, 0, 0, 30, 51, 51, 51, 31, 3, 3, 0]),
+    (113, [0, 0, 0, 30, 51, 51, 51, 62, 48, 48, 0]),
+    (114, [0, 0, 0, 29, 55, 51, 3, 3, 7, 0, 0]),
+    (115, [0, 0, 0, 30, 51, 6, 24, 51, 30, 0, 0]),
+    (116, [4, 6, 6, 15, 6, 6, 6, 54, 28, 0, 0]),
+    (117, [0, 0, 0, 27, 27, 27, 27, 27, 54, 0, 0]),
+    (118, [0, 0, 0, 51, 51, 51, 51, 30, 12, 0, 0]),
+    (119, [0, 0, 0, 51, 51, 51, 63, 63, 18, 0, 0]),
+    (120, [0, 0, 0, 51, 30, 12, 12, 30, 51, 0, 0]),
+    (121, [0, 0, 0, 51, 51, 51, 62, 48, 24, 15, 0]),
+    (122, [0, 0, 0, 63, 27, 12, 6, 51, 63, 0, 0]),
+    (123, [56, 12, 12, 12, 7, 12, 12, 12, 56, 0, 0]),
+    (124, [12, 12, 12, 12, 12, 12, 12, 12, 12, 0, 0]),
+    (125, [7, 12, 12, 12, 56, 12, 12, 12, 7, 0, 0]),
+    (126, [38, 45, 25, 0, 0, 0, 0, 0, 0, 0, 0]),
+];
@@ -0,0 +1,578 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use vm_translator::parser::{Arithmetic, Command, Segment, STATIC_AREA_SIZE};
+use vm_translator::VmFile;
+
+use crate::os::{self, Os, Stop, OS_CLASSES};
+
+/// Size in words of the Hack RAM the VM runs on.
+pub const RAM_SIZE: usize = 32768;
+
+// Addresses of the VM registers and segments in the RAM.
+const SP: usize = 0;
+const LCL: usize = 1;
+const ARG: usize = 2;
+const THIS: usize = 3;
+const THAT: usize = 4;
+const TEMP: usize = 5;
+const STATIC: usize = 16;
+const KBD: usize = 24576;
+
+/// State of the VM after a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    /// The program ran past its last command or is stuck in a loop that
+    /// can't change any state, like the `label END goto END` at the end of
+    /// Sys.init or the `while (true) {}` of Sys.halt.
+    Halted,
+}
+
+/// Loop is a backward jump seen by the halt detection, with the state of the
+/// VM when it was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Loop {
+    target: usize,
+    sp: u16,
+    changes: u64,
+}
+
+/// Instruction is a VM command with where it comes from, labels are
+/// resolved to the index of the command following them.
+struct Instruction {
+    command: Command,
+    file: usize,
+    line: usize,
+    function: String,
+    /// Index of the command a goto, if-goto or call jumps to.
+    target: Option<usize>,
+}
+
+/// Vm executes VM commands one at a time. The stack, the segments and the
+/// call frames live in the RAM like in the translated code: SP, LCL, ARG,
+/// THIS and THAT in RAM[0-4], temp in RAM[5-12] and the static variables
+/// from RAM[16].
+///
+/// Return addresses pushed by call are indexes in the list of commands,
+/// labels are not commands.
+///
+/// Calls to the native classes of the OS run in Rust, see the os module.
+pub struct Vm {
+    program: Vec<Instruction>,
+    files: Vec<String>,
+    functions: HashMap<String, usize>,
+    // Static variables get their address in order of first use, the same
+    // order the assembler allocates them in the translated code.
+    statics: HashMap<(usize, u16), usize>,
+    ram: Vec<u16>,
+    pc: usize,
+    steps: u64,
+    native: HashSet<&'static str>,
+    pub(crate) os: Os,
+    // Number of VM calls made by native functions still running.
+    depth: usize,
+    halted: bool,
+    // Number of changes of the RAM, native calls and keyboard reads, the
+    // words a push changes are tracked by `pushed` instead.
+    changes: u64,
+    // Lowest address a push changed since the last backward jump, the words
+    // above SP are not part of the state.
+    pushed: usize,
+    last_loop: Option<Loop>,
+}
+
+impl Vm {
+    /// Creates a new Vm running the commands of the files, in order. It
+    /// starts at Sys.init if the program has one, at the first command
+    /// otherwise. The RAM is set to 0.
+    ///
+    /// Like the VM emulator of the course, the classes of the OS the
+    /// program does not define run natively.
+    pub fn new(files: &[VmFile]) -> Result<Self, String> {
+        let mut program = Vec::new();
+        let mut labels = HashMap::new();
+        let mut functions = HashMap::new();
+        let mut statics = HashMap::new();
+
+        for (file_index, file) in files.iter().enumerate() {
+            let mut function = String::new();
+            for (line, command) in &file.commands {
+                let located = |err: String| format!("{}:{}: {}", file.path.display(), line, err);
+                match command {
+                    Command::Label(label) => {
+                        let key = (function.clone(), label.clone());
+                        if labels.insert(key, program.len()).is_some() {
+                            return Err(located(format!("Duplicate label: {}", label)));
+                        }
+                        continue;
+                    }
+                    Command::Function(name, _) => {
+                        if functions.insert(name.clone(), program.len()).is_some() {
+                            return Err(located(format!("Duplicate function: {}", name)));
+                        }
+                        function = name.clone();
+                    }
+                    Command::Push(Segment::Static, index)
+                    | Command::Pop(Segment::Static, index) => {
+                        let next = STATIC + statics.len();
+                        statics.entry((file_index, *index)).or_insert(next);
+                    }
+                    _ => {}
+                }
+
+                program.push(Instruction {
+                    command: command.clone(),
+                    file: file_index,
+                    line: *line,
+                    function: function.clone(),
+                    target: None,
+                });
+            }
+        }
+
+        if statics.len() > STATIC_AREA_SIZE {
+            return Err(format!(
+                "Too many static variables: {} are used but only {} fit in RAM 16 - 255",
+                statics.len(),
+                STATIC_AREA_SIZE
+            ));
+        }
+
+        // Jumps are resolved once all the labels are known. Calls to
+        // functions that are not part of the program fail when executed.
+        for instruction in &mut program {
+            instruction.target = match &instruction.command {
+                Command::Goto(label) | Command::IfGoto(label) => {
+                    let key = (instruction.function.clone(), label.clone());
+                    let Some(target) = labels.get(&key) else {
+                        return Err(format!(
+                            "{}:{}: Unknown label: {}",
+                            files[instruction.file].path.display(),
+                            instruction.line,
+                            label
+                        ));
+                    };
+                    Some(*target)
+                }
+                Command::Call(name, _) => functions.get(name).copied(),
+                _ => None,
+            };
+        }
+
+        let native = OS_CLASSES
+            .into_iter()
+            .filter(|class| {
+                let prefix = format!("{}.", class);
+                !functions.keys().any(|name| name.starts_with(&prefix))
+            })
+            .collect();
+        let pc = functions.get("Sys.init").copied().unwrap_or(0);
+        Ok(Vm {
+            program,
+            files: files
+                .iter()
+                .map(|file| file.path.display().to_string())
+                .collect(),
+            functions,
+            statics,
+            ram: vec![0; RAM_SIZE],
+            pc,
+            steps: 0,
+            native,
+            os: Os::default(),
+            depth: 0,
+            halted: false,
+            changes: 0,
+            pushed: usize::MAX,
+            last_loop: None,
+        })
+    }
+
+    /// load reads a `.vm` file or all the `.vm` files of a directory, see new.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let paths =
+            vm_translator::vm_files(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        if paths.is_empty() {
+            return Err(format!("No .vm files found in: {}", path.display()));
+        }
+        let files = paths
+            .iter()
+            .map(|path| vm_translator::parse_file(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(&files)
+    }
+
+    /// native makes the calls to a class of the OS run natively, even if
+    /// the program defines it.
+    pub fn native(&mut self, class: &str) -> Result<(), String> {
+        let Some(class) = OS_CLASSES.into_iter().find(|known| *known == class) else {
+            return Err(format!("{} is not a class of the OS", class));
+        };
+        self.native.insert(class);
+        Ok(())
+    }
+
+    /// is_native tells whether calls to the function run natively.
+    pub fn is_native(&self, function: &str) -> bool {
+        function
+            .split_once('.')
+            .is_some_and(|(class, _)| self.native.contains(class))
+    }
+
+    /// bootstrap sets SP to 256 and calls Sys.init, like the bootstrap
+    /// code of the translated program.
+    pub fn bootstrap(&mut self) -> Result<(), String> {
+        let end = self.program.len();
+        if !self.is_native("Sys.init") {
+            let Some(target) = self.functions.get("Sys.init").copied() else {
+                return Err(String::from("Unknown function: Sys.init"));
+            };
+            self.ram[SP] = 256;
+            // Returning from Sys.init runs past the end of the program.
+            self.call(0, end)?;
+            self.pc = target;
+            return Ok(());
+        }
+
+        // The native Sys.init initializes the OS and calls Main.main from
+        // its frame, the program halts when Main.main returns.
+        let Some(main) = self.functions.get("Main.main").copied() else {
+            return Err(String::from("Unknown function: Main.main"));
+        };
+        self.ram[SP] = 256;
+        self.call(0, end)?;
+        match os::init(self) {
+            Ok(()) => {}
+            Err(Stop::Halt) => {
+                self.halted = true;
+                return Ok(());
+            }
+            Err(Stop::Wait) => return Err(String::from("Sys.init cannot wait for a key")),
+            Err(Stop::Error(err)) => return Err(err),
+        }
+        self.call(0, end)?;
+        self.pc = main;
+        Ok(())
+    }
+
+    /// Number of commands executed since the Vm was created.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Index of the next command to execute.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// current_function is the name of the function of the next command,
+    /// empty for code outside of functions.
+    pub fn current_function(&self) -> &str {
+        self.program
+            .get(self.pc)
+            .map_or("", |instruction| instruction.function.as_str())
+    }
+
+    pub fn ram(&self) -> &[u16] {
+        &self.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u16] {
+        &mut self.ram
+    }
+
+    pub fn peek(&self, address: usize) -> u16 {
+        self.ram[address]
+    }
+
+    pub fn poke(&mut self, address: usize, value: u16) {
+        self.store(address, value);
+    }
+
+    /// store writes a word of the RAM, counting it as a change of the state
+    /// when its value differs.
+    fn store(&mut self, address: usize, value: u16) {
+        if self.ram[address] != value {
+            self.ram[address] = value;
+            self.changes += 1;
+        }
+    }
+
+    /// address is the RAM address of segment[index], constants have none.
+    pub fn address(&self, segment: Segment, index: u16) -> Option<usize> {
+        let index = index as usize;
+        let address = match segment {
+            Segment::Local => self.ram[LCL] as usize + index,
+            Segment::Argument => self.ram[ARG] as usize + index,
+            Segment::This => self.ram[THIS] as usize + index,
+            Segment::That => self.ram[THAT] as usize + index,
+            Segment::Pointer => THIS + index,
+            Segment::Temp => TEMP + index,
+            Segment::Static | Segment::Constant => return None,
+        };
+        Some(address).filter(|address| *address < RAM_SIZE)
+    }
+
+    /// step executes the next command.
+    pub fn step(&mut self) -> Result<Status, String> {
+        if self.halted {
+            return Ok(Status::Halted);
+        }
+        let Some(instruction) = self.program.get(self.pc) else {
+            return Ok(Status::Halted);
+        };
+        let command = instruction.command.clone();
+        let target = instruction.target;
+        let next = self.execute(&command, target).map_err(|err| {
+            let instruction = &self.program[self.pc];
+            format!(
+                "{}:{}: {}",
+                self.files[instruction.file], instruction.line, err
+            )
+        })?;
+
+        self.steps += 1;
+        let mut halted = self.halted || next == self.pc && matches!(command, Command::Goto(_));
+        if next <= self.pc && matches!(command, Command::Goto(_) | Command::IfGoto(_)) {
+            halted |= self.is_stuck(next);
+        }
+        self.pc = next;
+        if halted {
+            return Ok(Status::Halted);
+        }
+        Ok(Status::Running)
+    }
+
+    /// is_stuck checks a backward jump to target, the program can never
+    /// leave the loop if nothing changed since the last time the same jump
+    /// was taken.
+    fn is_stuck(&mut self, target: usize) -> bool {
+        let sp = self.ram[SP];
+        let current = Loop {
+            target,
+            sp,
+            changes: self.changes,
+        };
+        let stuck = self.last_loop == Some(current) && self.pushed >= sp as usize;
+        self.last_loop = Some(current);
+        self.pushed = usize::MAX;
+        stuck
+    }
+
+    /// run executes up to max_steps commands, it stops early when the
+    /// program halts. It returns the number of steps run and the status.
+    pub fn run(&mut self, max_steps: u64) -> Result<(u64, Status), String> {
+        let start = self.steps;
+        for _ in 0..max_steps {
+            if self.step()? == Status::Halted {
+                return Ok((self.steps - start, Status::Halted));
+            }
+        }
+        Ok((max_steps, Status::Running))
+    }
+
+    /// execute runs a command and returns the index of the next one.
+    fn execute(&mut self, command: &Command, target: Option<usize>) -> Result<usize, String> {
+        let next = self.pc + 1;
+        let resolved = || target.expect("labels are resolved when loading");
+        match command {
+            Command::Arithmetic(op) => self.arithmetic(*op)?,
+            Command::Push(segment, index) => {
+                let value = match segment {
+                    Segment::Constant => *index,
+                    _ => {
+                        let address = self.segment_address(*segment, *index)?;
+                        // The key pressed may change while waiting for it.
+                        if address == KBD {
+                            self.changes += 1;
+                        }
+                        self.ram[address]
+                    }
+                };
+                self.push(value)?;
+            }
+            Command::Pop(segment, index) => {
+                let address = self.segment_address(*segment, *index)?;
+                let value = self.pop()?;
+                self.store(address, value);
+            }
+            Command::Label(_) => {}
+            Command::Goto(_) => return Ok(resolved()),
+            Command::IfGoto(_) => {
+                if self.pop()? != 0 {
+                    return Ok(resolved());
+                }
+            }
+            Command::Function(_, locals) => {
+                for _ in 0..*locals {
+                    self.push(0)?;
+                }
+            }
+            Command::Call(name, args) if self.is_native(name) => {
+                return self.call_native(name, *args, next);
+            }
+            Command::Call(name, args) => {
+                let Some(target) = target else {
+                    return Err(format!("Unknown function: {}", name));
+                };
+                self.call(*args, next)?;
+                return Ok(target);
+            }
+            Command::Return => return self.ret(),
+        }
+        Ok(next)
+    }
+
+    /// call_native runs a native function with the arguments on the stack
+    /// and replaces them with its return value. A function waiting for a
+    /// key is called again by the next step.
+    fn call_native(&mut self, name: &str, args: u16, next: usize) -> Result<usize, String> {
+        let sp = self.ram[SP] as usize;
+        let Some(start) = sp.checked_sub(args as usize).filter(|_| sp <= RAM_SIZE) else {
+            return Err(String::from("Stack underflow"));
+        };
+        let values = self.ram[start..sp].to_vec();
+        // The state of the native OS is not in the RAM.
+        self.changes += 1;
+        match os::call(self, name, &values) {
+            Ok(value) => {
+                self.ram[SP] = start as u16;
+                self.push(value)?;
+                Ok(next)
+            }
+            Err(Stop::Wait) if self.depth == 0 => Ok(self.pc),
+            Err(Stop::Wait) => Err(format!(
+                "{} waits for a key, it cannot be called by a native function",
+                name
+            )),
+            Err(Stop::Halt) => {
+                self.halted = true;
+                Ok(self.pc)
+            }
+            Err(Stop::Error(err)) => Err(err),
+        }
+    }
+
+    /// invoke calls a function for a native one and returns its value, a
+    /// function of the program runs until it returns.
+    pub(crate) fn invoke(&mut self, name: &str, args: &[u16]) -> Result<u16, Stop> {
+        if self.is_native(name) {
+            return os::call(self, name, args);
+        }
+        let Some(target) = self.functions.get(name).copied() else {
+            return Err(Stop::Error(format!("Unknown function: {}", name)));
+        };
+        for arg in args {
+            self.push(*arg).map_err(Stop::Error)?;
+        }
+        // The function returns past the end of the program, where it
+        // stops like the program does after Sys.init.
+        let pc = self.pc;
+        let end = self.program.len();
+        self.call(args.len() as u16, end).map_err(Stop::Error)?;
+        self.pc = target;
+        self.depth += 1;
+        let result = loop {
+            if self.pc == end {
+                break Ok(());
+            }
+            match self.step() {
+                Ok(Status::Running) => {}
+                Ok(Status::Halted) => break Err(Stop::Halt),
+                Err(err) => break Err(Stop::Error(err)),
+            }
+        };
+        self.depth -= 1;
+        self.pc = pc;
+        result?;
+        self.pop().map_err(Stop::Error)
+    }
+
+    fn segment_address(&self, segment: Segment, index: u16) -> Result<usize, String> {
+        if segment == Segment::Static {
+            let file = self.program[self.pc].file;
+            return Ok(self.statics[&(file, index)]);
+        }
+        self.address(segment, index)
+            .ok_or_else(|| format!("{} {} is outside of the RAM", segment, index))
+    }
+
+    fn push(&mut self, value: u16) -> Result<(), String> {
+        let sp = self.ram[SP] as usize;
+        if sp >= RAM_SIZE {
+            return Err(String::from("Stack overflow"));
+        }
+        if self.ram[sp] != value {
+            self.ram[sp] = value;
+            self.pushed = self.pushed.min(sp);
+        }
+        self.ram[SP] += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u16, String> {
+        let sp = self.ram[SP] as usize;
+        if sp == 0 || sp > RAM_SIZE {
+            return Err(String::from("Stack underflow"));
+        }
+        self.ram[SP] -= 1;
+        Ok(self.ram[sp - 1])
+    }
+
+    fn arithmetic(&mut self, op: Arithmetic) -> Result<(), String> {
+        let truth = |value: bool| if value { 0xFFFF } else { 0 };
+        let y = self.pop()?;
+        let value = match op {
+            Arithmetic::Neg => y.wrapping_neg(),
+            Arithmetic::Not => !y,
+            _ => {
+                let x = self.pop()?;
+                match op {
+                    Arithmetic::Add => x.wrapping_add(y),
+                    Arithmetic::Sub => x.wrapping_sub(y),
+                    Arithmetic::And => x & y,
+                    Arithmetic::Or => x | y,
+                    Arithmetic::Eq => truth(x == y),
+                    Arithmetic::Gt => truth((x as i16) > (y as i16)),
+                    Arithmetic::Lt => truth((x as i16) < (y as i16)),
+                    Arithmetic::Neg | Arithmetic::Not => unreachable!(),
+                }
+            }
+        };
+        self.push(value)
+    }
+
+    /// call saves the frame of the caller and sets up the one of the callee.
+    fn call(&mut self, args: u16, return_address: usize) -> Result<(), String> {
+        self.push(return_address as u16)?;
+        for register in [LCL, ARG, THIS, THAT] {
+            self.push(self.ram[register])?;
+        }
+        let sp = self.ram[SP];
+        self.store(ARG, sp.wrapping_sub(5 + args));
+        self.store(LCL, sp);
+        Ok(())
+    }
+
+    /// ret puts the return value in place of the arguments, restores the
+    /// frame of the caller and returns the return address.
+    fn ret(&mut self) -> Result<usize, String> {
+        let frame = self.ram[LCL] as usize;
+        if !(5..RAM_SIZE).contains(&frame) {
+            return Err(String::from("Invalid frame, LCL is below 5"));
+        }
+        let return_address = self.ram[frame - 5];
+        let value = self.pop()?;
+        let arg = self.ram[ARG] as usize;
+        if arg >= RAM_SIZE {
+            return Err(String::from("ARG is outside of the RAM"));
+        }
+        self.store(arg, value);
+        self.ram[SP] = arg as u16 + 1;
+        for (offset, register) in [THAT, THIS, ARG, LCL].into_iter().enumerate() {
+            self.store(register, self.ram[frame - 1 - offset]);
+        }
+        Ok(return_address as usize)
+    }
+}
@@ -0,0 +1,77 @@
+//! The halt detection of the Vm on small programs.
+use std::path::PathBuf;
+
+use vm_emulator::{Status, Vm};
+use vm_translator::parser::parse_line;
+use vm_translator::VmFile;
+
+/// run runs the VM code of a Sys.vm file from Sys.init.
+fn run(code: &str, max_steps: u64) -> (u64, Status) {
+    let commands = code
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| parse_line(text).unwrap().map(|command| (line + 1, command)))
+        .collect();
+    let file = VmFile {
+        path: PathBuf::from("Sys.vm"),
+        name: String::from("Sys"),
+        commands,
+    };
+    let mut vm = Vm::new(&[file]).unwrap();
+    vm.bootstrap().unwrap();
+    vm.run(max_steps).unwrap()
+}
+
+#[test]
+fn halts_in_a_goto_to_itself() {
+    let code = "function Sys.init 0\nlabel END\ngoto END\n";
+    assert_eq!(run(code, 100), (2, Status::Halted));
+}
+
+#[test]
+fn halts_in_a_loop_without_changes() {
+    // while (true) {} of the Jack Sys.halt, the stack changes in the loop
+    // but not below SP.
+    let code = "\
+function Sys.init 0
+label WHILE_EXP0
+push constant 0
+not
+not
+if-goto WHILE_END0
+goto WHILE_EXP0
+label WHILE_END0
+push constant 0
+return
+";
+    assert_eq!(run(code, 1000).1, Status::Halted);
+}
+
+#[test]
+fn runs_loops_that_change_the_state() {
+    // A counter on the stack and a static variable set to the same value.
+    let code = "\
+function Sys.init 0
+push constant 0
+label LOOP
+push constant 1
+add
+push constant 7
+pop static 0
+goto LOOP
+";
+    assert_eq!(run(code, 1000), (1000, Status::Running));
+
+    // A loop waiting for a key.
+    let code = "\
+function Sys.init 0
+push constant 24576
+pop pointer 1
+label WAIT
+push that 0
+push constant 0
+eq
+if-goto WAIT
+";
+    assert_eq!(run(code, 1000), (1000, Status::Running));
+}
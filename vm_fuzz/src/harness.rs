@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use hack_assembler::assembler::Assembler;
+use hack_cpu::{Cpu, Status};
+use vm_emulator::Vm;
+use vm_translator::{Options, VmFile};
+
+use crate::program::{SEGMENT_WORDS, THAT_BASE, THIS_BASE};
+
+/// Limits of a run, generated programs need far less.
+const MAX_STEPS: u64 = 200_000;
+const MAX_CYCLES: u64 = 20_000_000;
+
+/// Stack base and the return address Sys.init gets from the bootstrap, it
+/// is a command index in the VM and a ROM address on the CPU.
+const STACK: usize = 256;
+const RETURN_ADDRESS: usize = STACK;
+
+/// Difference is a RAM word with different values after both runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub address: usize,
+    pub vm: i16,
+    pub cpu: i16,
+}
+
+/// Outcome of running a program both ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Same,
+    /// The RAM states diverge at the listed words.
+    Diverged(Vec<Difference>),
+    /// The translated program failed to translate, assemble or halt.
+    Failed(String),
+    /// The VM interpreter rejected the program, it is not a valid test.
+    Invalid(String),
+}
+
+/// run_vm interprets the program and returns the RAM once it halts.
+pub fn run_vm(files: &[VmFile]) -> Result<Vec<u16>, String> {
+    let mut vm = Vm::new(files)?;
+    vm.bootstrap()?;
+    match vm.run(MAX_STEPS)? {
+        (_, vm_emulator::Status::Halted) => Ok(vm.ram().to_vec()),
+        (steps, _) => Err(format!("did not halt after {} steps", steps)),
+    }
+}
+
+/// run_cpu translates, assembles and runs the program on the CPU emulator
+/// and returns the RAM once it halts.
+pub fn run_cpu(files: &[VmFile], options: &Options) -> Result<Vec<u16>, String> {
+    let mut asm = Vec::new();
+    vm_translator::translate(files, &mut asm, options)?;
+    let asm = String::from_utf8(asm).map_err(|err| err.to_string())?;
+
+    let mut assembler = Assembler::new(PathBuf::from("fuzz.asm"));
+    assembler.initialize();
+    let program = assembler.assemble(&asm).map_err(|err| err.to_string())?;
+
+    let mut cpu = Cpu::new(&program).map_err(|err| err.to_string())?;
+    match cpu.run(MAX_CYCLES) {
+        (_, Status::Halted) => Ok(cpu.ram().to_vec()),
+        (cycles, _) => Err(format!("did not halt after {} cycles", cycles)),
+    }
+}
+
+/// compared_addresses lists the RAM words both runs must agree on: the
+/// pointers, temp, statics, the frame of Sys.init and the this and that
+/// words. R13-R15 are scratch registers of the translated code and the
+/// stack above SP holds leftovers of returned calls.
+fn compared_addresses(sp: usize) -> Vec<usize> {
+    let mut addresses: Vec<usize> = (0..=12).collect();
+    addresses.extend(16..STACK);
+    addresses
+        .extend((STACK..sp.min(hack_cpu::RAM_SIZE)).filter(|address| *address != RETURN_ADDRESS));
+    for base in [THIS_BASE, THAT_BASE] {
+        addresses.extend(base as usize..(base + SEGMENT_WORDS) as usize);
+    }
+    addresses
+}
+
+/// compare runs the program both ways with the translator options.
+pub fn compare(files: &[VmFile], options: &Options) -> Outcome {
+    let vm = match run_vm(files) {
+        Ok(ram) => ram,
+        Err(err) => return Outcome::Invalid(err),
+    };
+    let cpu = match run_cpu(files, options) {
+        Ok(ram) => ram,
+        Err(err) => return Outcome::Failed(err),
+    };
+
+    let differences: Vec<Difference> = compared_addresses(vm[0] as usize)
+        .into_iter()
+        .filter(|address| vm[*address] != cpu[*address])
+        .map(|address| Difference {
+            address,
+            vm: vm[address] as i16,
+            cpu: cpu[address] as i16,
+        })
+        .collect();
+    if differences.is_empty() {
+        Outcome::Same
+    } else {
+        Outcome::Diverged(differences)
+    }
+}
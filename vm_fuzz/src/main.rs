@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Parser;
+
+use vm_translator::Options;
+
+mod harness;
+mod program;
+mod rng;
+
+use harness::Outcome;
+use program::{Generator, Program};
+use rng::Rng;
+
+/// Args reads the command line arguments.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Seed of the first program, taken from the clock by default.
+    #[arg(short, long)]
+    seed: Option<u64>,
+
+    /// Number of random programs to test.
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    iterations: u64,
+
+    /// Directory to write the .vm files of the minimal diverging program to.
+    #[arg(long)]
+    save: Option<PathBuf>,
+}
+
+/// The translator options every program is tested with.
+const MODES: [(&str, Options); 4] = [
+    ("default", mode(false, false)),
+    ("--optimize", mode(false, true)),
+    ("--compact", mode(true, false)),
+    ("--compact --optimize", mode(true, true)),
+];
+
+const fn mode(compact: bool, optimize: bool) -> Options {
+    Options {
+        compact,
+        optimize,
+        bootstrap: true,
+        comments: false,
+    }
+}
+
+/// shrink simplifies a failing program one step at a time for as long as
+/// it keeps failing the same way, it returns the smallest one found.
+fn shrink(mut program: Program, options: &Options, outcome: Outcome) -> (Program, Outcome) {
+    let same_kind = |outcome: &Outcome, expected: &Outcome| {
+        std::mem::discriminant(outcome) == std::mem::discriminant(expected)
+    };
+
+    let mut outcome = outcome;
+    loop {
+        let mut candidates = program.simplifications();
+        // Try the smallest programs first, they shrink the fastest.
+        candidates.sort_by_key(Program::size);
+
+        let simpler = candidates.into_iter().find_map(|candidate| {
+            let result = harness::compare(&candidate.files(), options);
+            same_kind(&result, &outcome).then_some((candidate, result))
+        });
+        match simpler {
+            Some((candidate, result)) => {
+                program = candidate;
+                outcome = result;
+            }
+            None => return (program, outcome),
+        }
+    }
+}
+
+fn report(seed: u64, mode: &str, program: &Program, outcome: &Outcome) {
+    println!("seed {} diverges with vm_translator {}", seed, mode);
+    match outcome {
+        Outcome::Diverged(differences) => {
+            for difference in differences {
+                println!(
+                    "  RAM[{}]: vm_emulator {}, translated {}",
+                    difference.address, difference.vm, difference.cpu
+                );
+            }
+        }
+        Outcome::Failed(err) => println!("  translated program failed: {}", err),
+        Outcome::Same | Outcome::Invalid(_) => {}
+    }
+
+    for file in program.files() {
+        println!("\n// {}", file.path.display());
+        for (_, command) in &file.commands {
+            println!("{}", command);
+        }
+    }
+}
+
+fn save(directory: &PathBuf, program: &Program) -> std::io::Result<()> {
+    fs::create_dir_all(directory)?;
+    for file in program.files() {
+        let code: String = file
+            .commands
+            .iter()
+            .map(|(_, command)| format!("{}\n", command))
+            .collect();
+        fs::write(directory.join(&file.path), code)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::parse();
+    let first_seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs())
+    });
+
+    for seed in first_seed..first_seed + args.iterations {
+        let program = Generator::generate(&mut Rng::new(seed));
+        let files = program.files();
+
+        for (name, options) in &MODES {
+            let outcome = harness::compare(&files, options);
+            match outcome {
+                Outcome::Same => continue,
+                Outcome::Invalid(err) => {
+                    // The generator only makes programs the interpreter accepts.
+                    eprintln!("err: seed {}: generated an invalid program: {}", seed, err);
+                    std::process::exit(2);
+                }
+                Outcome::Diverged(_) | Outcome::Failed(_) => {}
+            }
+
+            let (program, outcome) = shrink(program, options, outcome);
+            report(seed, name, &program, &outcome);
+            if let Some(directory) = &args.save {
+                if let Err(err) = save(directory, &program) {
+                    eprintln!("err: {}: {}", directory.display(), err);
+                }
+            }
+            std::process::exit(1);
+        }
+    }
+
+    println!(
+        "{} programs from seed {}, no divergence",
+        args.iterations, first_seed
+    );
+}
@@ -0,0 +1,618 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use vm_translator::parser::{Arithmetic, Command, Segment};
+use vm_translator::VmFile;
+
+use crate::rng::Rng;
+
+/// Base addresses the generated programs give to the this and that
+/// segments, far from the stack.
+pub const THIS_BASE: u16 = 3000;
+pub const THAT_BASE: u16 = 3500;
+/// Number of words of the this, that and static segments programs use.
+pub const SEGMENT_WORDS: u16 = 8;
+
+const BINARY: [Arithmetic; 7] = [
+    Arithmetic::Add,
+    Arithmetic::Sub,
+    Arithmetic::And,
+    Arithmetic::Or,
+    Arithmetic::Eq,
+    Arithmetic::Gt,
+    Arithmetic::Lt,
+];
+const COMPARE: [Arithmetic; 3] = [Arithmetic::Eq, Arithmetic::Gt, Arithmetic::Lt];
+const UNARY: [Arithmetic; 2] = [Arithmetic::Neg, Arithmetic::Not];
+/// Constants around the edges of the 16-bit range, where overflows happen.
+const EDGE_CONSTANTS: [u16; 6] = [0, 1, 255, 16384, 32766, 32767];
+
+/// Expr is VM code that pushes exactly one value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Push(Segment, u16),
+    Unary(Arithmetic, Box<Expr>),
+    Binary(Arithmetic, Box<Expr>, Box<Expr>),
+    /// Call of the function at the index, with one expression per argument.
+    Call(usize, Vec<Expr>),
+}
+
+/// Statement is VM code that leaves the stack as it found it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    Pop(Segment, u16, Expr),
+    /// Runs the body when the condition is not 0, with `not; if-goto`.
+    If(Expr, Vec<Statement>),
+    /// Runs the body count times, the counter is a local of its own.
+    Loop(u16, Vec<Statement>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub class: String,
+    pub name: String,
+    pub args: u16,
+    /// Locals the body reads and writes, loop counters come after them.
+    pub locals: u16,
+    pub body: Vec<Statement>,
+    /// The returned value, None for Sys.init which never returns.
+    pub result: Option<Expr>,
+}
+
+/// Program is a random VM program, the first function is Sys.init and
+/// functions only call functions after them so the program always ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+/// Generator holds the limits of the generated programs.
+pub struct Generator<'a> {
+    rng: &'a mut Rng,
+    functions: Vec<(u16, u16)>,
+}
+
+impl<'a> Generator<'a> {
+    /// generate creates a program of up to 4 functions spread over the
+    /// Sys, Main and Util classes.
+    pub fn generate(rng: &'a mut Rng) -> Program {
+        let count = 1 + rng.below(4) as usize;
+        let functions: Vec<(u16, u16)> = (0..count)
+            .map(|index| {
+                let args = if index == 0 { 0 } else { rng.below(4) as u16 };
+                (args, rng.below(4) as u16)
+            })
+            .collect();
+        let mut generator = Generator { rng, functions };
+
+        let functions = (0..count).map(|index| generator.function(index)).collect();
+        Program { functions }
+    }
+
+    fn function(&mut self, index: usize) -> Function {
+        let (args, locals) = self.functions[index];
+        let statements = 1 + self.rng.below(6) as usize;
+        let body = (0..statements).map(|_| self.statement(index, 2)).collect();
+
+        let (class, name) = if index == 0 {
+            (String::from("Sys"), String::from("init"))
+        } else {
+            let class = *self.rng.pick(&["Main", "Util"]);
+            (class.to_string(), format!("f{}", index))
+        };
+        Function {
+            class,
+            name,
+            args,
+            locals,
+            body,
+            result: (index != 0).then(|| self.expr(index, 3)),
+        }
+    }
+
+    fn statement(&mut self, function: usize, depth: u32) -> Statement {
+        let roll = self.rng.below(10);
+        if depth > 0 && roll == 0 {
+            let count = 1 + self.rng.below(3) as u16;
+            return Statement::Loop(count, self.block(function, depth - 1));
+        }
+        if depth > 0 && roll <= 2 {
+            let condition = if self.rng.chance(60) {
+                let op = *self.rng.pick(&COMPARE);
+                Expr::Binary(
+                    op,
+                    Box::new(self.expr(function, 1)),
+                    Box::new(self.expr(function, 1)),
+                )
+            } else {
+                self.expr(function, 2)
+            };
+            return Statement::If(condition, self.block(function, depth - 1));
+        }
+
+        let (args, locals) = self.functions[function];
+        let mut targets = vec![Segment::Static, Segment::Temp, Segment::This, Segment::That];
+        if args > 0 {
+            targets.push(Segment::Argument);
+        }
+        if locals > 0 {
+            targets.push(Segment::Local);
+        }
+        let segment = *self.rng.pick(&targets);
+        let index = self.index(segment, args, locals);
+        Statement::Pop(segment, index, self.expr(function, 3))
+    }
+
+    fn block(&mut self, function: usize, depth: u32) -> Vec<Statement> {
+        let statements = 1 + self.rng.below(3) as usize;
+        (0..statements)
+            .map(|_| self.statement(function, depth))
+            .collect()
+    }
+
+    fn expr(&mut self, function: usize, depth: u32) -> Expr {
+        let roll = self.rng.below(10);
+        if depth > 0 && roll < 4 {
+            let op = *self.rng.pick(&BINARY);
+            return Expr::Binary(
+                op,
+                Box::new(self.expr(function, depth - 1)),
+                Box::new(self.expr(function, depth - 1)),
+            );
+        }
+        if depth > 0 && roll < 5 {
+            let op = *self.rng.pick(&UNARY);
+            return Expr::Unary(op, Box::new(self.expr(function, depth - 1)));
+        }
+        if depth > 0 && roll < 6 && function + 1 < self.functions.len() {
+            let callee = function
+                + 1
+                + self.rng.below((self.functions.len() - function - 1) as u64) as usize;
+            let args = (0..self.functions[callee].0)
+                .map(|_| self.expr(function, depth - 1))
+                .collect();
+            return Expr::Call(callee, args);
+        }
+
+        let (args, locals) = self.functions[function];
+        let mut sources = vec![
+            Segment::Constant,
+            Segment::Constant,
+            Segment::Static,
+            Segment::Temp,
+            Segment::This,
+            Segment::That,
+            Segment::Pointer,
+        ];
+        if args > 0 {
+            sources.push(Segment::Argument);
+        }
+        if locals > 0 {
+            sources.push(Segment::Local);
+        }
+        let segment = *self.rng.pick(&sources);
+        if segment == Segment::Constant {
+            return Expr::Push(segment, self.constant());
+        }
+        let index = self.index(segment, args, locals);
+        Expr::Push(segment, index)
+    }
+
+    fn index(&mut self, segment: Segment, args: u16, locals: u16) -> u16 {
+        let words = match segment {
+            Segment::Argument => args,
+            Segment::Local => locals,
+            Segment::Pointer => 2,
+            _ => SEGMENT_WORDS,
+        };
+        self.rng.below(words as u64) as u16
+    }
+
+    fn constant(&mut self) -> u16 {
+        match self.rng.below(10) {
+            0..=4 => self.rng.below(16) as u16,
+            5..=7 => self.rng.below(1000) as u16,
+            _ if self.rng.chance(50) => *self.rng.pick(&EDGE_CONSTANTS),
+            _ => self.rng.below(32768) as u16,
+        }
+    }
+}
+
+impl Program {
+    /// files emits the VM code of the program, one file per class.
+    pub fn files(&self) -> Vec<VmFile> {
+        let mut classes: BTreeMap<&str, Vec<Command>> = BTreeMap::new();
+        for function in &self.functions {
+            let commands = classes.entry(&function.class).or_default();
+            Emitter::new(self, function, commands).function();
+        }
+
+        classes
+            .into_iter()
+            .map(|(class, commands)| VmFile {
+                path: PathBuf::from(format!("{}.vm", class)),
+                name: class.to_string(),
+                commands: commands
+                    .into_iter()
+                    .enumerate()
+                    .map(|(line, command)| (line + 1, command))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// size counts the nodes of the program, shrinking makes it smaller.
+    pub fn size(&self) -> usize {
+        self.functions
+            .iter()
+            .map(|function| {
+                1 + statements_size(&function.body) + function.result.as_ref().map_or(0, expr_size)
+            })
+            .sum()
+    }
+
+    /// simplifications lists the programs one step simpler than this one:
+    /// a statement removed or inlined, an expression replaced by 0 or by one
+    /// of its operands, a smaller constant, index or loop count.
+    pub fn simplifications(&self) -> Vec<Program> {
+        let mut programs = Vec::new();
+        for (index, function) in self.functions.iter().enumerate() {
+            for body in statements_variants(&function.body) {
+                let mut program = self.clone();
+                program.functions[index].body = body;
+                programs.push(program);
+            }
+            if let Some(result) = &function.result {
+                for result in expr_variants(result) {
+                    let mut program = self.clone();
+                    program.functions[index].result = Some(result);
+                    programs.push(program);
+                }
+            }
+        }
+        programs.iter_mut().for_each(Program::remove_uncalled);
+        programs
+    }
+
+    /// remove_uncalled drops the functions Sys.init no longer reaches.
+    fn remove_uncalled(&mut self) {
+        let mut reached = vec![false; self.functions.len()];
+        reached[0] = true;
+        // Calls only go forward, one pass in order finds all the reached functions.
+        for index in 0..self.functions.len() {
+            if reached[index] {
+                let mut callees = Vec::new();
+                let function = &self.functions[index];
+                collect_calls(&function.body, function.result.as_ref(), &mut callees);
+                callees
+                    .into_iter()
+                    .for_each(|callee| reached[callee] = true);
+            }
+        }
+
+        let mut renumber = Vec::new();
+        let mut next = 0;
+        for reached in &reached {
+            renumber.push(next);
+            next += *reached as usize;
+        }
+        let mut index = 0;
+        self.functions.retain(|_| {
+            index += 1;
+            reached[index - 1]
+        });
+        for function in &mut self.functions {
+            function
+                .body
+                .iter_mut()
+                .for_each(|statement| renumber_statement(statement, &renumber));
+            if let Some(result) = &mut function.result {
+                renumber_expr(result, &renumber);
+            }
+        }
+    }
+}
+
+/// Emitter writes the commands of one function.
+struct Emitter<'a> {
+    program: &'a Program,
+    function: &'a Function,
+    commands: &'a mut Vec<Command>,
+    labels: usize,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(program: &'a Program, function: &'a Function, commands: &'a mut Vec<Command>) -> Self {
+        Emitter {
+            program,
+            function,
+            commands,
+            labels: 0,
+        }
+    }
+
+    fn function(&mut self) {
+        let function = self.function;
+        let counters = loop_depth(&function.body);
+        self.commands.push(Command::Function(
+            qualified_name(function),
+            function.locals + counters,
+        ));
+
+        if function.result.is_none() {
+            // Sys.init points this and that away from the stack and the statics.
+            for (base, pointer) in [(THIS_BASE, 0), (THAT_BASE, 1)] {
+                self.commands.push(Command::Push(Segment::Constant, base));
+                self.commands.push(Command::Pop(Segment::Pointer, pointer));
+            }
+        }
+
+        for statement in &function.body {
+            self.statement(statement, 0);
+        }
+
+        match &function.result {
+            Some(result) => {
+                self.expr(result);
+                self.commands.push(Command::Return);
+            }
+            None => {
+                self.commands.push(Command::Label(String::from("END")));
+                self.commands.push(Command::Goto(String::from("END")));
+            }
+        }
+    }
+
+    fn label(&mut self, name: &str) -> String {
+        self.labels += 1;
+        format!("{}_{}", name, self.labels)
+    }
+
+    fn statement(&mut self, statement: &Statement, depth: u16) {
+        match statement {
+            Statement::Pop(segment, index, value) => {
+                self.expr(value);
+                self.commands.push(Command::Pop(*segment, *index));
+            }
+            Statement::If(condition, body) => {
+                let skip = self.label("SKIP");
+                self.expr(condition);
+                self.commands.push(Command::Arithmetic(Arithmetic::Not));
+                self.commands.push(Command::IfGoto(skip.clone()));
+                for statement in body {
+                    self.statement(statement, depth);
+                }
+                self.commands.push(Command::Label(skip));
+            }
+            Statement::Loop(count, body) => {
+                let start = self.label("LOOP");
+                let counter = self.function.locals + depth;
+                self.commands.push(Command::Push(Segment::Constant, *count));
+                self.commands.push(Command::Pop(Segment::Local, counter));
+                self.commands.push(Command::Label(start.clone()));
+                for statement in body {
+                    self.statement(statement, depth + 1);
+                }
+                self.commands.extend([
+                    Command::Push(Segment::Local, counter),
+                    Command::Push(Segment::Constant, 1),
+                    Command::Arithmetic(Arithmetic::Sub),
+                    Command::Pop(Segment::Local, counter),
+                    Command::Push(Segment::Local, counter),
+                    Command::IfGoto(start),
+                ]);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Push(segment, index) => self.commands.push(Command::Push(*segment, *index)),
+            Expr::Unary(op, value) => {
+                self.expr(value);
+                self.commands.push(Command::Arithmetic(*op));
+            }
+            Expr::Binary(op, x, y) => {
+                self.expr(x);
+                self.expr(y);
+                self.commands.push(Command::Arithmetic(*op));
+            }
+            Expr::Call(callee, args) => {
+                args.iter().for_each(|arg| self.expr(arg));
+                let callee = &self.program.functions[*callee];
+                self.commands
+                    .push(Command::Call(qualified_name(callee), callee.args));
+            }
+        }
+    }
+}
+
+fn qualified_name(function: &Function) -> String {
+    format!("{}.{}", function.class, function.name)
+}
+
+/// loop_depth is the deepest nesting of loops, one counter per level.
+fn loop_depth(statements: &[Statement]) -> u16 {
+    statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::Pop(..) => 0,
+            Statement::If(_, body) => loop_depth(body),
+            Statement::Loop(_, body) => 1 + loop_depth(body),
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn statements_size(statements: &[Statement]) -> usize {
+    statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::Pop(_, _, value) => 1 + expr_size(value),
+            Statement::If(condition, body) => 1 + expr_size(condition) + statements_size(body),
+            Statement::Loop(count, body) => 1 + *count as usize + statements_size(body),
+        })
+        .sum()
+}
+
+fn expr_size(expr: &Expr) -> usize {
+    match expr {
+        // Bigger constants and indexes count as bigger so they get shrunk too.
+        Expr::Push(segment, index) => 1 + (*segment != Segment::Constant || *index != 0) as usize,
+        Expr::Unary(_, value) => 1 + expr_size(value),
+        Expr::Binary(_, x, y) => 1 + expr_size(x) + expr_size(y),
+        Expr::Call(_, args) => 1 + args.iter().map(expr_size).sum::<usize>(),
+    }
+}
+
+fn statements_variants(statements: &[Statement]) -> Vec<Vec<Statement>> {
+    let mut variants = Vec::new();
+    for (index, statement) in statements.iter().enumerate() {
+        let mut removed = statements.to_vec();
+        removed.remove(index);
+        variants.push(removed);
+
+        for replacement in statement_variants(statement) {
+            let mut replaced = statements[..index].to_vec();
+            replaced.extend(replacement);
+            replaced.extend_from_slice(&statements[index + 1..]);
+            variants.push(replaced);
+        }
+    }
+    variants
+}
+
+fn statement_variants(statement: &Statement) -> Vec<Vec<Statement>> {
+    let mut variants = Vec::new();
+    match statement {
+        Statement::Pop(segment, index, value) => {
+            if *index > 0 {
+                variants.push(vec![Statement::Pop(*segment, 0, value.clone())]);
+            }
+            for value in expr_variants(value) {
+                variants.push(vec![Statement::Pop(*segment, *index, value)]);
+            }
+        }
+        Statement::If(condition, body) => {
+            variants.push(body.clone());
+            for condition in expr_variants(condition) {
+                variants.push(vec![Statement::If(condition, body.clone())]);
+            }
+            for body in statements_variants(body) {
+                variants.push(vec![Statement::If(condition.clone(), body)]);
+            }
+        }
+        Statement::Loop(count, body) => {
+            variants.push(body.clone());
+            if *count > 1 {
+                variants.push(vec![Statement::Loop(count - 1, body.clone())]);
+            }
+            for body in statements_variants(body) {
+                variants.push(vec![Statement::Loop(*count, body)]);
+            }
+        }
+    }
+    variants
+}
+
+fn expr_variants(expr: &Expr) -> Vec<Expr> {
+    let zero = Expr::Push(Segment::Constant, 0);
+    let mut variants = Vec::new();
+    if *expr != zero {
+        variants.push(zero);
+    }
+
+    match expr {
+        Expr::Push(Segment::Constant, value) if *value > 1 => {
+            variants.push(Expr::Push(Segment::Constant, value / 2));
+        }
+        Expr::Push(segment, index) if *index > 0 => variants.push(Expr::Push(*segment, 0)),
+        Expr::Push(..) => {}
+        Expr::Unary(op, value) => {
+            variants.push((**value).clone());
+            for value in expr_variants(value) {
+                variants.push(Expr::Unary(*op, Box::new(value)));
+            }
+        }
+        Expr::Binary(op, x, y) => {
+            variants.push((**x).clone());
+            variants.push((**y).clone());
+            for x in expr_variants(x) {
+                variants.push(Expr::Binary(*op, Box::new(x), y.clone()));
+            }
+            for y in expr_variants(y) {
+                variants.push(Expr::Binary(*op, x.clone(), Box::new(y)));
+            }
+        }
+        Expr::Call(callee, args) => {
+            for (index, arg) in args.iter().enumerate() {
+                for arg in expr_variants(arg) {
+                    let mut args = args.clone();
+                    args[index] = arg;
+                    variants.push(Expr::Call(*callee, args));
+                }
+            }
+        }
+    }
+    variants
+}
+
+fn collect_calls(statements: &[Statement], result: Option<&Expr>, callees: &mut Vec<usize>) {
+    fn expr_calls(expr: &Expr, callees: &mut Vec<usize>) {
+        match expr {
+            Expr::Push(..) => {}
+            Expr::Unary(_, value) => expr_calls(value, callees),
+            Expr::Binary(_, x, y) => {
+                expr_calls(x, callees);
+                expr_calls(y, callees);
+            }
+            Expr::Call(callee, args) => {
+                callees.push(*callee);
+                args.iter().for_each(|arg| expr_calls(arg, callees));
+            }
+        }
+    }
+
+    for statement in statements {
+        match statement {
+            Statement::Pop(_, _, value) => expr_calls(value, callees),
+            Statement::If(condition, body) => {
+                expr_calls(condition, callees);
+                collect_calls(body, None, callees);
+            }
+            Statement::Loop(_, body) => collect_calls(body, None, callees),
+        }
+    }
+    if let Some(result) = result {
+        expr_calls(result, callees);
+    }
+}
+
+fn renumber_statement(statement: &mut Statement, renumber: &[usize]) {
+    match statement {
+        Statement::Pop(_, _, value) => renumber_expr(value, renumber),
+        Statement::If(condition, body) => {
+            renumber_expr(condition, renumber);
+            body.iter_mut()
+                .for_each(|statement| renumber_statement(statement, renumber));
+        }
+        Statement::Loop(_, body) => body
+            .iter_mut()
+            .for_each(|statement| renumber_statement(statement, renumber)),
+    }
+}
+
+fn renumber_expr(expr: &mut Expr, renumber: &[usize]) {
+    match expr {
+        Expr::Push(..) => {}
+        Expr::Unary(_, value) => renumber_expr(value, renumber),
+        Expr::Binary(_, x, y) => {
+            renumber_expr(x, renumber);
+            renumber_expr(y, renumber);
+        }
+        Expr::Call(callee, args) => {
+            *callee = renumber[*callee];
+            args.iter_mut().for_each(|arg| renumber_expr(arg, renumber));
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! The fuzzer on a fixed range of seeds, in all the translator modes. The
+//! range is large enough to find an overflow in the signed comparisons.
+use std::process::Command;
+
+#[test]
+fn finds_no_divergence() {
+    let output = Command::new(env!("CARGO_BIN_EXE_vm_fuzz"))
+        .args(["--seed", "1", "--iterations", "500"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(stdout, "500 programs from seed 1, no divergence\n");
+}
@@ -0,0 +1,1231 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::optimizer::{Condition, Op, Operand, Sink, Step};
+use crate::parser::{Arithmetic, Command, Segment};
+use crate::source_map::{Location, Mapping, SourceTracker};
+
+/// LABEL_COUNTER is used to keep track of the label count.
+/// AtomicUsize ensures that the counter can be safely accessed and modified across
+/// multiple threads if running program is multithreaded.
+static LABEL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// next_label_id is a placeholder for a function that generates unique label IDs.
+// Label IDs are used in assembly languages to create unique labels for jumps and branching.
+fn next_label_id() -> usize {
+    LABEL_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Labels of the shared runtime subroutines emitted in compact mode.
+const EQ_ROUTINE: &str = "__VM_EQ";
+const GT_ROUTINE: &str = "__VM_GT";
+const LT_ROUTINE: &str = "__VM_LT";
+const COMPARE_RETURN: &str = "__VM_COMPARE_RETURN";
+const CALL_ROUTINE: &str = "__VM_CALL";
+const RETURN_ROUTINE: &str = "__VM_RETURN";
+
+/// SharedRoutines records which of the shared runtime subroutines have been
+/// jumped to, so only those are emitted at the end of the output.
+#[derive(Default)]
+struct SharedRoutines {
+    compare: bool,
+    call: bool,
+    ret: bool,
+}
+
+/// CodeWriter translates parsed VM commands into Hack assembly code.
+///
+/// In compact mode eq/gt/lt, call and return jump to one shared copy of
+/// their code (emitted after the program) instead of being expanded inline
+/// at every site, trading a few cycles for a much smaller ROM footprint.
+pub struct CodeWriter<W: Write> {
+    out: SourceTracker<W>,
+    // Used for the "FileName.index" static variable symbols.
+    file_name: String,
+    // The function currently being translated, labels are scoped to it.
+    function_name: String,
+    // Index of the current file and line of the VM command, for the source map.
+    file_index: Option<usize>,
+    line: usize,
+    compact: bool,
+    // Write the VM commands as comments before their code.
+    comments: bool,
+    routines: SharedRoutines,
+}
+
+impl<W: Write> CodeWriter<W> {
+    pub fn new(out: W, compact: bool, comments: bool) -> Self {
+        CodeWriter {
+            out: SourceTracker::new(out),
+            file_name: String::new(),
+            function_name: String::new(),
+            file_index: None,
+            line: 0,
+            compact,
+            comments,
+            routines: SharedRoutines::default(),
+        }
+    }
+
+    /// set_file_name informs the writer that the translation of a new VM file
+    /// has started, static variables are named after it.
+    pub fn set_file_name(&mut self, file_name: &str) {
+        self.file_name = file_name.to_string();
+        self.function_name.clear();
+        self.file_index = Some(self.file_index.map_or(0, |index| index + 1));
+    }
+
+    /// set_line informs the writer of the line number of the VM command
+    /// written next, it is recorded in the source map.
+    pub fn set_line(&mut self, line: usize) {
+        self.line = line;
+        self.update_location();
+    }
+
+    fn update_location(&mut self) {
+        let location = self.file_index.map(|file| Location {
+            file,
+            line: self.line,
+            function: self.function_name.clone(),
+        });
+        self.out.set_location(location);
+    }
+
+    /// write_bootstrap writes the code that initializes the stack pointer
+    /// to 256 and calls Sys.init, it must be the first code in the output.
+    pub fn write_bootstrap(&mut self) -> io::Result<()> {
+        self.out.set_location(None);
+        self.write_comment("bootstrap")?;
+        write!(
+            self.out,
+            "\t@256\n\
+            \tD=A\n\
+            \t@SP\n\
+            \tM=D\n"
+        )?;
+        self.function_name = String::from("BOOTSTRAP");
+        self.write_call("Sys.init", 0)
+    }
+
+    /// write_command writes the assembly code of a single VM command,
+    /// preceded by the command as a comment.
+    pub fn write_command(&mut self, command: &Command) -> io::Result<()> {
+        // The code of a function declaration already belongs to the function.
+        if let Command::Function(name, _) = command {
+            self.function_name = name.clone();
+            self.update_location();
+        }
+        self.write_comment(&command.to_string())?;
+
+        match command {
+            Command::Arithmetic(op) => self.write_arithmetic(*op),
+            Command::Push(segment, index) => self.write_push(*segment, *index),
+            Command::Pop(segment, index) => self.write_pop(*segment, *index),
+            Command::Label(label) => {
+                let label = self.scoped_label(label);
+                writeln!(self.out, "({})", label)
+            }
+            Command::Goto(label) => {
+                let label = self.scoped_label(label);
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \t0;JMP\n",
+                    label
+                )
+            }
+            Command::IfGoto(label) => {
+                // Pop the top of the stack and jump if it is not false (0).
+                let label = self.scoped_label(label);
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n\
+                    \t@{}\n\
+                    \tD;JNE\n",
+                    label
+                )
+            }
+            Command::Function(name, locals) => self.write_function(name, *locals),
+            Command::Call(name, args) => self.write_call(name, *args),
+            Command::Return => self.write_return(),
+        }
+    }
+
+    /// write_op writes the assembly code of an op produced by the optimizer.
+    pub fn write_op(&mut self, op: &Op) -> io::Result<()> {
+        match op {
+            Op::Command(command) => self.write_command(command),
+            Op::Expression {
+                value,
+                steps,
+                sink,
+                source,
+            } => {
+                let source: Vec<String> =
+                    source.iter().map(|command| command.to_string()).collect();
+                self.write_comment(&source.join(" / "))?;
+                self.write_expression(value, steps, sink)
+            }
+        }
+    }
+
+    /// finish ends the program with an infinite loop, followed by the shared
+    /// runtime subroutines used in compact mode.
+    pub fn finish(mut self) -> io::Result<Vec<Mapping>> {
+        self.out.set_location(None);
+
+        // It is recommended to end each program with an infinite loop.
+        write!(
+            self.out,
+            "(INFINITE_LOOP)\n\
+            \t@INFINITE_LOOP\n\
+            \t0;JMP\n"
+        )?;
+
+        if self.routines.compare {
+            self.write_compare_routines()?;
+        }
+        if self.routines.call {
+            self.write_call_routine()?;
+        }
+        if self.routines.ret {
+            self.write_return_routine()?;
+        }
+
+        self.out.flush()?;
+        Ok(self.out.into_mappings())
+    }
+
+    fn write_comment(&mut self, comment: &str) -> io::Result<()> {
+        if self.comments {
+            writeln!(self.out, "// {}", comment)?;
+        }
+        Ok(())
+    }
+
+    /// Labels declared inside a function are scoped to it as "functionName$label".
+    fn scoped_label(&self, label: &str) -> String {
+        if self.function_name.is_empty() {
+            label.to_string()
+        } else {
+            format!("{}${}", self.function_name, label)
+        }
+    }
+
+    fn write_arithmetic(&mut self, op: Arithmetic) -> io::Result<()> {
+        match op {
+            Arithmetic::Add | Arithmetic::Sub => {
+                /*
+                @SP: This line sets the A-register to the address pointed to by the Stack Pointer (SP). It's essentially telling the computer to access the value at the top of the stack.
+
+                AM=M-1: This is a combination of two operations. A=M-1 sets the A-register to the address immediately below the current top of the stack. M=M-1 then decrements the value at the top of the stack. This is a common pattern for accessing and modifying the top value while leaving the Stack Pointer in the correct position for further operations.
+
+                D=M: This line copies the value from the memory location pointed to by the A-register (which was set to the second-to-top value on the stack) into the D-register. This value is temporarily stored in the D-register for later use.
+
+                A=A-1: This line decrements the A-register to point to the address immediately below the current top of the stack. Now, the A-register is pointing to the destination where we want to store the result of the addition.
+
+                M=D+M: Finally, this line adds the value in the D-register (which holds the second value from the top of the stack) to the value in the memory location pointed to by the A-register (which is the top value of the stack). The result is stored back into the memory location pointed to by the A-register. In essence, this line replaces the two top values with their sum.
+                */
+                // x - y is M-D, as M holds x (second from the top) and D holds y.
+                let comp = if op == Arithmetic::Add { "D+M" } else { "M-D" };
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n\
+                    \tA=A-1\n\
+                    \tM={}\n",
+                    comp
+                )
+            }
+            Arithmetic::And | Arithmetic::Or => {
+                let logical_command = if op == Arithmetic::And { "&" } else { "|" };
+
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n\
+                    \tA=A-1\n\
+                    \tM=D{}M\n",
+                    logical_command
+                )
+            }
+            Arithmetic::Not => {
+                // Decrement the Stack Pointer (SP) and access the value at the top of the stack
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n"
+                )?;
+
+                // Perform bitwise NOT operation on the value in the D register
+                writeln!(self.out, "\tD=!D")?;
+
+                // Increment the Stack Pointer and store the result on the stack
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n"
+                )
+            }
+            Arithmetic::Neg => {
+                // Pop the value from the stack into the D register
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n"
+                )?;
+
+                // Negate the value in the D register
+                write!(
+                    self.out,
+                    "\t@0\n\
+                    \tD=A-D\n"
+                )?;
+
+                // Push the negated value back onto the stack
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n"
+                )
+            }
+            Arithmetic::Eq | Arithmetic::Gt | Arithmetic::Lt => {
+                if self.compact {
+                    return self.write_compare_jump(op);
+                }
+
+                let jump_instruction = match op {
+                    Arithmetic::Eq => "JEQ",
+                    Arithmetic::Gt => "JGT",
+                    _ => "JLT",
+                };
+
+                let label_id = next_label_id();
+
+                // Decrement SP and compare the top two stack values.
+                self.write_pop_compare(op)?;
+
+                // Set the result to true (-1) if the values are equal; otherwise, set it to false (0).
+                //We then perform the jump based on the comparison result, setting the
+                // top of the stack accordingly (0 or -1) and jumping to the appropriate labels.
+                write!(
+                    self.out,
+                    "\t@TRUE.{}\n\
+                    \tD;{}\n\
+                    \t@SP\n\
+                    \tA=M-1\n\
+                    \tM=0\n\
+                    \t@CONTINUE.{}\n\
+                    \t0;JMP\n\
+                    (TRUE.{})\n\
+                    \t@SP\n\
+                    \tA=M-1\n\
+                    \tM=-1\n\
+                    (CONTINUE.{})\n",
+                    label_id, jump_instruction, label_id, label_id, label_id
+                )
+            }
+        }
+    }
+
+    /// write_push writes [push segment index]. Push the value of segment[index] onto the stack.
+    fn write_push(&mut self, segment: Segment, index: u16) -> io::Result<()> {
+        match segment {
+            Segment::Argument => {
+                /*
+                Load the base address of the "argument" segment (which is stored in the ARG register) into the D register.
+
+                Add the index to the base address (loaded into the A register) to calculate the target address within the "argument" segment.
+                The value at the calculated target address is loaded into the D register.
+
+                Finally, the value from the D register is stored onto the stack, and the Stack Pointer (SP) is incremented to point to the next empty slot in the stack.
+                 */
+                write!(
+                    self.out,
+                    "\t@ARG\n\
+                    \tD=M\n\
+                    \t@{}\n\
+                    \tA=D+A\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    index
+                )
+            }
+            Segment::Local => {
+                /*
+                Load the base address of the "local" segment (which is stored in the LCL register) into the D register.
+
+                Add the index to the base address to calculate the target address within the "local" segment.
+                The value at the calculated target address is loaded into the D register.
+
+                Finally, the value from the D register is stored onto the stack, and the Stack Pointer (SP) is incremented to point to the next empty slot in the stack.
+                 */
+                write!(
+                    self.out,
+                    "\t@LCL\n\
+                    \tD=M\n\
+                    \t@{}\n\
+                    \tA=D+A\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    index
+                )
+            }
+            Segment::Static => {
+                /*
+                Each reference to "static index" will be translated to assembly symbol "FileName.index"
+
+                This label is used to access static variables in the assembly code.
+                It loads the value from the memory location pointed to by the label (which represents the static variable) into the D register.
+                The value from the D register is then stored onto the stack, and the Stack Pointer (SP) is incremented to point to the next empty slot in the stack.
+                */
+                let static_label = format!("{}.{}", self.file_name, index);
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    static_label
+                )
+            }
+            Segment::Constant => {
+                /*
+                Uses the provided index value directly as the constant to be pushed onto the stack.
+                It loads the constant value into the D register.
+                The value from the D register is then stored onto the stack.
+                */
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tD=A\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    index
+                )
+            }
+            Segment::This => {
+                /*
+                Loads the base address of the "this" segment (which is stored in the THIS register) into the D register.
+                It then adds the index to the base address to calculate the target address within the "this" segment.
+                The value at the calculated target address is loaded into the D register.
+                Finally, the value from the D register is stored onto the stack
+                */
+                write!(
+                    self.out,
+                    "\t@THIS\n\
+                    \tD=M\n\
+                    \t@{}\n\
+                    \tA=D+A\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    index
+                )
+            }
+            Segment::That => {
+                /*
+                Loads the base address of the "that" segment (which is stored in the THAT register) into the D register.
+                It then adds the index to the base address to calculate the target address within the "this" segment.
+                The value at the calculated target address is loaded into the D register.
+                Finally, the value from the D register is stored onto the stack.
+                */
+                write!(
+                    self.out,
+                    "\t@THAT\n\
+                    \tD=M\n\
+                    \t@{}\n\
+                    \tA=D+A\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    index
+                )
+            }
+            Segment::Pointer => {
+                /*
+                Access to pointer 0 should result in accessing the THIS pointer and any access
+                to pointer 1 should result in accessing the THAT pointer. The pointer segment
+                contains exactly two values and is mapped directly to RAM locations 3 and 4,
+                these RAM locations are also called THIS and THAT respectively.
+                */
+                let pointer = if index == 0 { "THIS" } else { "THAT" };
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    pointer
+                )
+            }
+            Segment::Temp => {
+                /*
+                It is a fixed 8-word segment that is mapped directly to RAM locations 5 - 12, index
+                varies from 0 to 7.
+                Push from the temp segment (R5-R12)
+                */
+                let temp_current_address = temp_address(index);
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tD=M\n\
+                    \t@SP\n\
+                    \tA=M\n\
+                    \tM=D\n\
+                    \t@SP\n\
+                    \tM=M+1\n",
+                    temp_current_address
+                )
+            }
+        }
+    }
+
+    /// write_pop writes [pop segment index]. Pop the top of the stack into segment[index].
+    fn write_pop(&mut self, segment: Segment, index: u16) -> io::Result<()> {
+        match segment {
+            Segment::Argument => {
+                /*
+                Calculate the target address within the "argument" segment in a single step by adding the index to the value stored in the ARG register and storing it in the D register as the target address.
+
+                Use @R13 as a temporary register (variable) to store the target address.
+
+                Next, we use AM=M-1 to decrement the Stack Pointer (SP) and access the value at the top of the stack, storing it in the D register.
+
+                Finally, we use the stored target address in @R13 to store the value from the D register into the target address within the "argument" segment.
+                */
+                self.write_pop_to_base("ARG", index)
+            }
+            // Logic is similar to "argument" segment above with a
+            // difference of the base memory address LOCAL.
+            Segment::Local => self.write_pop_to_base("LCL", index),
+            Segment::Static => {
+                let static_label = format!("{}.{}", self.file_name, index);
+                // Pop the value from the stack into the D register.
+                // Decrement the Stack Pointer (SP) and access the value at
+                // the top of the stack. The value is then stored in the D register.
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n"
+                )?;
+
+                // Store the popped value to the static address location.
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tM=D\n",
+                    static_label
+                )
+            }
+            Segment::Constant => {
+                /*
+                The constant segment in VM is read-only, meaning you can only push values onto the stack using it. It doesn't support the pop operation because it doesn't represent a writable memory location. The parser rejects it.
+                */
+                unreachable!("pop constant is rejected by the parser")
+            }
+            Segment::This => {
+                /*
+                Calculate the target address within the "this" segment in a single step by adding the index to the value stored in the THIS register and storing it in the D register as the target address.
+
+                Use @R13 as a temporary register (variable) to store the target address.
+
+                Next, we use AM=M-1 to decrement the Stack Pointer (SP) and access the value at the top of the stack, storing it in the D register.
+
+                Finally, we use the stored target address in @R13 to store the value from the D register into the target address within the "this" segment.
+                */
+                self.write_pop_to_base("THIS", index)
+            }
+            // Logic is similar to this segment above.
+            Segment::That => self.write_pop_to_base("THAT", index),
+            Segment::Pointer => {
+                let pointer = if index == 0 { "THIS" } else { "THAT" };
+
+                // Pop the value from the stack into the D register.
+                // Decrement the Stack Pointer (SP) and access the value at
+                // the top of the stack. The value is then stored in the D register.
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n"
+                )?;
+
+                // Store the popped value in the D-register into the THIS or THAT pointer.
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tM=D\n",
+                    pointer
+                )
+            }
+            Segment::Temp => {
+                let temp_current_address = temp_address(index);
+
+                // Pop the value from the stack into the D register.
+                // Decrement the Stack Pointer (SP) and access the value at
+                // the top of the stack. The value is then stored in the D register.
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tAM=M-1\n\
+                    \tD=M\n"
+                )?;
+
+                // Store the popped value in the D-register into the temp_current_address.
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tM=D\n",
+                    temp_current_address
+                )
+            }
+        }
+    }
+
+    /// write_pop_to_base pops the top of the stack into base[index], where base is
+    /// the register (ARG, LCL, THIS or THAT) holding the segment base address.
+    fn write_pop_to_base(&mut self, base: &str, index: u16) -> io::Result<()> {
+        // Calculate the target address within the segment.
+        write!(
+            self.out,
+            "\t@{}\n\
+            \tD=M\n\
+            \t@{}\n\
+            \tA=D+A\n\
+            \tD=A\n", // D = Target Address
+            base, index
+        )?;
+
+        // Pop the value from the stack into the target address.
+        write!(
+            self.out,
+            "\t@R13\n\
+            \tM=D\n\
+            \t@SP\n\
+            \tAM=M-1\n\
+            \tD=M\n\
+            \t@R13\n\
+            \tA=M\n\
+            \tM=D\n"
+        )
+    }
+
+    /// write_function declares the function entry label and initializes
+    /// its local variables to 0.
+    fn write_function(&mut self, name: &str, locals: u16) -> io::Result<()> {
+        writeln!(self.out, "({})", name)?;
+        if locals == 0 {
+            return Ok(());
+        }
+
+        // Zero the locals in place and move SP past them once.
+        write!(
+            self.out,
+            "\t@SP\n\
+            \tA=M\n\
+            \tM=0\n"
+        )?;
+        for _ in 1..locals {
+            write!(
+                self.out,
+                "\tA=A+1\n\
+                \tM=0\n"
+            )?;
+        }
+        write!(
+            self.out,
+            "\tD=A+1\n\
+            \t@SP\n\
+            \tM=D\n"
+        )
+    }
+
+    /// write_call saves the frame of the caller (return address, LCL, ARG, THIS, THAT)
+    /// on the stack, repositions ARG and LCL for the callee and jumps to it.
+    fn write_call(&mut self, name: &str, args: u16) -> io::Result<()> {
+        let return_label = format!("{}$ret.{}", self.function_name, next_label_id());
+
+        if self.compact {
+            // R14 = nArgs, R13 = callee address, D = return address.
+            self.routines.call = true;
+            return write!(
+                self.out,
+                "\t@{}\n\
+                \tD=A\n\
+                \t@R14\n\
+                \tM=D\n\
+                \t@{}\n\
+                \tD=A\n\
+                \t@R13\n\
+                \tM=D\n\
+                \t@{}\n\
+                \tD=A\n\
+                \t@{}\n\
+                \t0;JMP\n\
+                ({})\n",
+                args, name, return_label, CALL_ROUTINE, return_label
+            );
+        }
+
+        // Push the return address.
+        write!(
+            self.out,
+            "\t@{}\n\
+            \tD=A\n",
+            return_label
+        )?;
+        self.write_push_d()?;
+
+        // Push the LCL, ARG, THIS and THAT pointers of the caller.
+        for pointer in ["LCL", "ARG", "THIS", "THAT"] {
+            write!(
+                self.out,
+                "\t@{}\n\
+                \tD=M\n",
+                pointer
+            )?;
+            self.write_push_d()?;
+        }
+
+        // ARG = SP - 5 - nArgs, LCL = SP, then goto the callee.
+        write!(
+            self.out,
+            "\t@SP\n\
+            \tD=M\n\
+            \t@{}\n\
+            \tD=D-A\n\
+            \t@ARG\n\
+            \tM=D\n\
+            \t@SP\n\
+            \tD=M\n\
+            \t@LCL\n\
+            \tM=D\n\
+            \t@{}\n\
+            \t0;JMP\n\
+            ({})\n",
+            5 + u32::from(args),
+            name,
+            return_label
+        )
+    }
+
+    /// write_return copies the return value to the caller's stack, restores the
+    /// caller's frame and jumps back to the return address.
+    fn write_return(&mut self) -> io::Result<()> {
+        if self.compact {
+            self.routines.ret = true;
+            return write!(
+                self.out,
+                "\t@{}\n\
+                \t0;JMP\n",
+                RETURN_ROUTINE
+            );
+        }
+
+        self.write_return_body()
+    }
+
+    fn write_return_body(&mut self) -> io::Result<()> {
+        // R13 = FRAME = LCL, R14 = RET = *(FRAME - 5).
+        // The return address is saved first as *ARG may overwrite it when nArgs == 0.
+        write!(
+            self.out,
+            "\t@LCL\n\
+            \tD=M\n\
+            \t@R13\n\
+            \tM=D\n\
+            \t@5\n\
+            \tA=D-A\n\
+            \tD=M\n\
+            \t@R14\n\
+            \tM=D\n"
+        )?;
+
+        // *ARG = pop(), SP = ARG + 1.
+        write!(
+            self.out,
+            "\t@SP\n\
+            \tAM=M-1\n\
+            \tD=M\n\
+            \t@ARG\n\
+            \tA=M\n\
+            \tM=D\n\
+            \t@ARG\n\
+            \tD=M+1\n\
+            \t@SP\n\
+            \tM=D\n"
+        )?;
+
+        // Restore THAT, THIS, ARG and LCL from the frame.
+        for pointer in ["THAT", "THIS", "ARG", "LCL"] {
+            write!(
+                self.out,
+                "\t@R13\n\
+                \tAM=M-1\n\
+                \tD=M\n\
+                \t@{}\n\
+                \tM=D\n",
+                pointer
+            )?;
+        }
+
+        write!(
+            self.out,
+            "\t@R14\n\
+            \tA=M\n\
+            \t0;JMP\n"
+        )
+    }
+
+    /// write_expression computes an expression in the D register and writes
+    /// the result to its sink, the stack is only touched by the sink.
+    fn write_expression(&mut self, value: &Operand, steps: &[Step], sink: &Sink) -> io::Result<()> {
+        // x; push constant 0; cmp; if-goto -> jump on x directly.
+        if let (Operand::Constant(0), true, Sink::JumpIfCompare(condition, label)) =
+            (value, steps.is_empty(), sink)
+        {
+            let label = self.scoped_label(label);
+            return write!(
+                self.out,
+                "\t@SP\n\
+                \tAM=M-1\n\
+                \tD=M\n\
+                \t@{}\n\
+                \tD;{}\n",
+                label,
+                condition.mnemonic()
+            );
+        }
+
+        self.write_load_d(value)?;
+        for step in steps {
+            match step {
+                Step::Binary(op, operand) => {
+                    let register = self.write_select(operand)?;
+                    let comp = match op {
+                        Arithmetic::Add => format!("D+{}", register),
+                        Arithmetic::Sub => format!("D-{}", register),
+                        Arithmetic::And => format!("D&{}", register),
+                        Arithmetic::Or => format!("D|{}", register),
+                        _ => return Err(invalid_input(format!("Unexpected step: {}", op))),
+                    };
+                    writeln!(self.out, "\tD={}", comp)?;
+                }
+                Step::Unary(Arithmetic::Neg) => writeln!(self.out, "\tD=-D")?,
+                Step::Unary(Arithmetic::Not) => writeln!(self.out, "\tD=!D")?,
+                Step::Unary(op) => return Err(invalid_input(format!("Unexpected step: {}", op))),
+            }
+        }
+
+        match sink {
+            Sink::Push => self.write_push_d(),
+            Sink::Pop(segment, index) => self.write_store_d(*segment, *index),
+            Sink::ApplyTop(op) => {
+                let comp = match op {
+                    Arithmetic::Add => "D+M",
+                    Arithmetic::Sub => "M-D",
+                    Arithmetic::And => "D&M",
+                    Arithmetic::Or => "D|M",
+                    _ => return Err(invalid_input(format!("Unexpected operation: {}", op))),
+                };
+                write!(
+                    self.out,
+                    "\t@SP\n\
+                    \tA=M-1\n\
+                    \tM={}\n",
+                    comp
+                )
+            }
+            Sink::JumpIf(condition, label) => {
+                let label = self.scoped_label(label);
+                self.write_jump(condition, &label)
+            }
+            Sink::JumpIfCompare(condition, label) => {
+                let label = self.scoped_label(label);
+                if matches!(condition, Condition::Eq | Condition::Ne) {
+                    write!(
+                        self.out,
+                        "\t@SP\n\
+                        \tAM=M-1\n\
+                        \tD=M-D\n"
+                    )?;
+                } else {
+                    write!(
+                        self.out,
+                        "\t@R14\n\
+                        \tM=D\n\
+                        \t@SP\n\
+                        \tAM=M-1\n\
+                        \tD=M\n"
+                    )?;
+                    self.write_signed_compare()?;
+                }
+                self.write_jump(condition, &label)
+            }
+        }
+    }
+
+    fn write_jump(&mut self, condition: &Condition, label: &str) -> io::Result<()> {
+        write!(
+            self.out,
+            "\t@{}\n\
+            \tD;{}\n",
+            label,
+            condition.mnemonic()
+        )
+    }
+
+    /// write_load_d loads the value of the operand into the D register.
+    fn write_load_d(&mut self, operand: &Operand) -> io::Result<()> {
+        match operand {
+            Operand::Constant(value @ (-1..=1)) => writeln!(self.out, "\tD={}", value),
+            Operand::Constant(value @ 0..) => write!(
+                self.out,
+                "\t@{}\n\
+                \tD=A\n",
+                value
+            ),
+            // A negative constant can't be an A-instruction, load its complement instead.
+            Operand::Constant(value) => write!(
+                self.out,
+                "\t@{}\n\
+                \tD=!A\n",
+                !value
+            ),
+            Operand::Memory(segment, index) if operand.is_direct() => {
+                self.write_select_address(*segment, *index)?;
+                writeln!(self.out, "\tD=M")
+            }
+            Operand::Memory(segment, index) => {
+                write!(
+                    self.out,
+                    "\t@{}\n\
+                    \tD=M\n\
+                    \t@{}\n\
+                    \tA=D+A\n\
+                    \tD=M\n",
+                    base_register(*segment)?,
+                    index
+                )
+            }
+        }
+    }
+
+    /// write_select points the A register at a direct operand without
+    /// touching D, it returns the register holding the operand value.
+    fn write_select(&mut self, operand: &Operand) -> io::Result<&'static str> {
+        match operand {
+            Operand::Constant(value @ 0..) => {
+                writeln!(self.out, "\t@{}", value)?;
+                Ok("A")
+            }
+            Operand::Memory(segment, index) if operand.is_direct() => {
+                self.write_select_address(*segment, *index)?;
+                Ok("M")
+            }
+            _ => Err(invalid_input(String::from(
+                "Operand can't be read without using D",
+            ))),
+        }
+    }
+
+    /// write_select_address sets the A register to the address of a direct
+    /// segment[index], base segments use A=M+1 for small indexes.
+    fn write_select_address(&mut self, segment: Segment, index: u16) -> io::Result<()> {
+        match segment {
+            Segment::Static => writeln!(self.out, "\t@{}.{}", self.file_name, index),
+            Segment::Temp => writeln!(self.out, "\t@{}", temp_address(index)),
+            Segment::Pointer => {
+                writeln!(self.out, "\t@{}", if index == 0 { "THIS" } else { "THAT" })
+            }
+            _ => {
+                writeln!(self.out, "\t@{}", base_register(segment)?)?;
+                match index {
+                    0 => writeln!(self.out, "\tA=M"),
+                    1 => writeln!(self.out, "\tA=M+1"),
+                    _ => write!(
+                        self.out,
+                        "\tA=M+1\n\
+                        \tA=A+1\n"
+                    ),
+                }
+            }
+        }
+    }
+
+    /// write_store_d stores the D register in segment[index].
+    fn write_store_d(&mut self, segment: Segment, index: u16) -> io::Result<()> {
+        let direct = Operand::Memory(segment, index);
+        if segment != Segment::Constant && direct.is_direct() {
+            self.write_select_address(segment, index)?;
+            return writeln!(self.out, "\tM=D");
+        }
+
+        // R14 = value, R13 = target address.
+        write!(
+            self.out,
+            "\t@R14\n\
+            \tM=D\n\
+            \t@{}\n\
+            \tD=M\n\
+            \t@{}\n\
+            \tD=D+A\n\
+            \t@R13\n\
+            \tM=D\n\
+            \t@R14\n\
+            \tD=M\n\
+            \t@R13\n\
+            \tA=M\n\
+            \tM=D\n",
+            base_register(segment)?,
+            index
+        )
+    }
+
+    /// write_push_d pushes the value of the D register onto the stack.
+    fn write_push_d(&mut self) -> io::Result<()> {
+        write!(
+            self.out,
+            "\t@SP\n\
+            \tA=M\n\
+            \tM=D\n\
+            \t@SP\n\
+            \tM=M+1\n"
+        )
+    }
+
+    /// write_pop_compare pops y and leaves D with the sign of the comparison
+    /// of x and y, x stays below SP. x - y decides eq, gt and lt also need
+    /// it to not overflow.
+    fn write_pop_compare(&mut self, op: Arithmetic) -> io::Result<()> {
+        if op == Arithmetic::Eq {
+            // Make a subtraction, value will be 0 if they are equal.
+            return write!(
+                self.out,
+                "\t@SP\n\
+                \tAM=M-1\n\
+                \tD=M\n\
+                \tA=A-1\n\
+                \tD=M-D\n"
+            );
+        }
+
+        write!(
+            self.out,
+            "\t@SP\n\
+            \tAM=M-1\n\
+            \tD=M\n\
+            \t@R14\n\
+            \tM=D\n\
+            \t@SP\n\
+            \tA=M-1\n\
+            \tD=M\n"
+        )?;
+        self.write_signed_compare()
+    }
+
+    /// write_signed_compare expects x in D and y in R14, it leaves D with the
+    /// sign of the signed comparison of x and y. x - y overflows when the
+    /// signs differ, then the sign of x decides and D is x | 1 so it isn't 0.
+    fn write_signed_compare(&mut self) -> io::Result<()> {
+        let label_id = next_label_id();
+        write!(
+            self.out,
+            "\t@R13\n\
+            \tM=D\n\
+            \t@COMPARE_X_NEG.{id}\n\
+            \tD;JLT\n\
+            \t@R14\n\
+            \tD=M\n\
+            \t@COMPARE_SIGNS_DIFFER.{id}\n\
+            \tD;JLT\n\
+            \t@COMPARE_SAME_SIGN.{id}\n\
+            \t0;JMP\n\
+            (COMPARE_X_NEG.{id})\n\
+            \t@R14\n\
+            \tD=M\n\
+            \t@COMPARE_SIGNS_DIFFER.{id}\n\
+            \tD;JGE\n\
+            (COMPARE_SAME_SIGN.{id})\n\
+            \t@R14\n\
+            \tD=M\n\
+            \t@R13\n\
+            \tD=M-D\n\
+            \t@COMPARE_END.{id}\n\
+            \t0;JMP\n\
+            (COMPARE_SIGNS_DIFFER.{id})\n\
+            \t@R13\n\
+            \tD=M\n\
+            \t@1\n\
+            \tD=D|A\n\
+            (COMPARE_END.{id})\n",
+            id = label_id
+        )
+    }
+
+    /// write_compare_jump stores the return address in R15 and jumps to the
+    /// shared routine of the comparison.
+    fn write_compare_jump(&mut self, op: Arithmetic) -> io::Result<()> {
+        self.routines.compare = true;
+        let routine = match op {
+            Arithmetic::Eq => EQ_ROUTINE,
+            Arithmetic::Gt => GT_ROUTINE,
+            _ => LT_ROUTINE,
+        };
+        let return_label = format!("__VM_COMPARE_RET.{}", next_label_id());
+
+        write!(
+            self.out,
+            "\t@{}\n\
+            \tD=A\n\
+            \t@R15\n\
+            \tM=D\n\
+            \t@{}\n\
+            \t0;JMP\n\
+            ({})\n",
+            return_label, routine, return_label
+        )
+    }
+
+    /// write_compare_routines writes the shared eq/gt/lt routine. Each entry
+    /// point optimistically stores true (-1) and overwrites it with false (0)
+    /// when the jump is not taken, they share the return back to R15.
+    fn write_compare_routines(&mut self) -> io::Result<()> {
+        self.write_comment("shared comparison routine")?;
+        for (routine, op, jump_instruction) in [
+            (EQ_ROUTINE, Arithmetic::Eq, "JEQ"),
+            (GT_ROUTINE, Arithmetic::Gt, "JGT"),
+            (LT_ROUTINE, Arithmetic::Lt, "JLT"),
+        ] {
+            writeln!(self.out, "({})", routine)?;
+            self.write_pop_compare(op)?;
+            write!(
+                self.out,
+                "\t@SP\n\
+                \tA=M-1\n\
+                \tM=-1\n\
+                \t@{}\n\
+                \tD;{}\n\
+                \t@SP\n\
+                \tA=M-1\n\
+                \tM=0\n\
+                \t@{}\n\
+                \t0;JMP\n",
+                COMPARE_RETURN, jump_instruction, COMPARE_RETURN
+            )?;
+        }
+        write!(
+            self.out,
+            "({})\n\
+            \t@R15\n\
+            \tA=M\n\
+            \t0;JMP\n",
+            COMPARE_RETURN
+        )
+    }
+
+    /// write_call_routine writes the shared call routine, it expects the callee
+    /// address in R13, the number of arguments in R14 and the return address in D.
+    fn write_call_routine(&mut self) -> io::Result<()> {
+        self.write_comment("shared call routine")?;
+        writeln!(self.out, "({})", CALL_ROUTINE)?;
+        self.write_push_d()?;
+        for pointer in ["LCL", "ARG", "THIS", "THAT"] {
+            write!(
+                self.out,
+                "\t@{}\n\
+                \tD=M\n",
+                pointer
+            )?;
+            self.write_push_d()?;
+        }
+
+        // ARG = SP - 5 - nArgs, LCL = SP, then goto the callee.
+        write!(
+            self.out,
+            "\t@R14\n\
+            \tD=M\n\
+            \t@5\n\
+            \tD=D+A\n\
+            \t@SP\n\
+            \tD=M-D\n\
+            \t@ARG\n\
+            \tM=D\n\
+            \t@SP\n\
+            \tD=M\n\
+            \t@LCL\n\
+            \tM=D\n\
+            \t@R13\n\
+            \tA=M\n\
+            \t0;JMP\n"
+        )
+    }
+
+    fn write_return_routine(&mut self) -> io::Result<()> {
+        self.write_comment("shared return routine")?;
+        writeln!(self.out, "({})", RETURN_ROUTINE)?;
+        self.write_return_body()
+    }
+}
+
+/// temp_address maps a temp segment index to its RAM address. It is a fixed
+/// 8-word segment that is mapped directly to RAM locations 5 - 12.
+fn temp_address(index: u16) -> u16 {
+    let temp_base_address = 5;
+    temp_base_address + index
+}
+
+/// base_register is the register holding the base address of a segment.
+fn base_register(segment: Segment) -> io::Result<&'static str> {
+    match segment {
+        Segment::Argument => Ok("ARG"),
+        Segment::Local => Ok("LCL"),
+        Segment::This => Ok("THIS"),
+        Segment::That => Ok("THAT"),
+        _ => Err(invalid_input(format!(
+            "Segment {} has no base address",
+            segment
+        ))),
+    }
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
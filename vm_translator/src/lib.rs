@@ -0,0 +1,147 @@
+//! Translator of the VM language to Hack assembly code. The parser is shared
+//! with the tools that read VM code, like the vm_emulator.
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+pub mod code_writer;
+pub mod optimizer;
+pub mod parser;
+pub mod source_map;
+
+use code_writer::CodeWriter;
+use parser::{Command, Segment, STATIC_AREA_SIZE};
+use source_map::Mapping;
+
+/// A parsed VM file, its commands are paired with their line number.
+pub struct VmFile {
+    pub path: PathBuf,
+    /// The file name without extension, it names the static variables.
+    pub name: String,
+    pub commands: Vec<(usize, Command)>,
+}
+
+/// Options controlling the generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub compact: bool,
+    pub optimize: bool,
+    pub bootstrap: bool,
+    pub comments: bool,
+}
+
+/// vm_files lists the `.vm` files to translate, in a stable order for directories.
+pub fn vm_files(input: &Path) -> io::Result<Vec<PathBuf>> {
+    if !input.is_dir() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "vm") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// parse_file reads and parses all the commands of a VM file.
+pub fn parse_file(path: &Path) -> Result<VmFile, String> {
+    // Get the filename without extension.
+    let Some(static_identifier) = path.file_stem() else {
+        return Err(format!("No filename in path: {}", path.display()));
+    };
+
+    // Open the input VM file for reading.
+    let input_file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let reader = BufReader::new(input_file);
+
+    let mut commands = Vec::new();
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| format!("{}: {}", path.display(), err))?;
+        match parser::parse_line(&line) {
+            Ok(Some(command)) => commands.push((line_number + 1, command)),
+            Ok(None) => {}
+            Err(err) => return Err(format!("{}:{}: {}", path.display(), line_number + 1, err)),
+        }
+    }
+
+    Ok(VmFile {
+        path: path.to_path_buf(),
+        name: static_identifier.to_string_lossy().to_string(),
+        commands,
+    })
+}
+
+/// static_variables counts the distinct static variables of all the files,
+/// each "FileName.index" symbol takes one word of the static area.
+fn static_variables(files: &[VmFile]) -> usize {
+    let mut variables = HashSet::new();
+    for file in files {
+        for (_, command) in &file.commands {
+            if let Command::Push(Segment::Static, index) | Command::Pop(Segment::Static, index) =
+                command
+            {
+                variables.insert((file.name.as_str(), *index));
+            }
+        }
+    }
+    variables.len()
+}
+
+/// translate writes the Hack assembly code of all the VM files to out, it
+/// returns the source map of the generated lines. The static variables of
+/// all the files must fit in the static area.
+pub fn translate<W: Write>(
+    files: &[VmFile],
+    out: W,
+    options: &Options,
+) -> Result<Vec<Mapping>, String> {
+    let statics = static_variables(files);
+    if statics > STATIC_AREA_SIZE {
+        return Err(format!(
+            "Too many static variables: {} are used but only {} fit in RAM 16 - 255",
+            statics, STATIC_AREA_SIZE
+        ));
+    }
+
+    let mut writer = CodeWriter::new(out, options.compact, options.comments);
+
+    // A program made of several files starts with the bootstrap code that calls Sys.init.
+    if options.bootstrap {
+        writer.write_bootstrap().map_err(output_error)?;
+    }
+
+    for file in files {
+        writer.set_file_name(&file.name);
+        // Only the commands that can't be translated are errors of the VM
+        // file, the others come from writing the output.
+        let located = |line: usize, err: io::Error| match err.kind() {
+            io::ErrorKind::InvalidInput => format!("{}:{}: {}", file.path.display(), line, err),
+            _ => output_error(err),
+        };
+
+        if options.optimize {
+            for (line, op) in optimizer::optimize(&file.commands) {
+                writer.set_line(line);
+                writer.write_op(&op).map_err(|err| located(line, err))?;
+            }
+        } else {
+            for (line, command) in &file.commands {
+                writer.set_line(*line);
+                writer
+                    .write_command(command)
+                    .map_err(|err| located(*line, err))?;
+            }
+        }
+    }
+
+    writer.finish().map_err(output_error)
+}
+
+fn output_error(err: io::Error) -> String {
+    format!("Unable to write the output: {}", err)
+}
@@ -0,0 +1,348 @@
+use crate::parser::{Arithmetic, Command, Segment};
+
+/// Operand is a value a push command puts on the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// A constant, after folding it may be any 16-bit value.
+    Constant(i16),
+    Memory(Segment, u16),
+}
+
+impl Operand {
+    /// from_push returns the operand of [push segment index], the parser
+    /// ensures constants fit in 15 bits.
+    fn from_push(segment: Segment, index: u16) -> Self {
+        match segment {
+            Segment::Constant => Operand::Constant(index as i16),
+            _ => Operand::Memory(segment, index),
+        }
+    }
+
+    pub fn is_constant(&self) -> bool {
+        matches!(self, Operand::Constant(_))
+    }
+
+    /// is_direct reports if the operand can be read without clobbering the D
+    /// register, i.e. its address is known or takes at most two A instructions.
+    pub fn is_direct(&self) -> bool {
+        match self {
+            Operand::Constant(_) => true,
+            Operand::Memory(Segment::Static | Segment::Temp | Segment::Pointer, _) => true,
+            Operand::Memory(_, index) => *index <= 2,
+        }
+    }
+}
+
+/// Condition of a conditional jump, compared against 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Condition {
+    fn from_compare(op: Arithmetic) -> Option<Self> {
+        match op {
+            Arithmetic::Eq => Some(Condition::Eq),
+            Arithmetic::Gt => Some(Condition::Gt),
+            Arithmetic::Lt => Some(Condition::Lt),
+            _ => None,
+        }
+    }
+
+    fn negate(self) -> Self {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Gt => Condition::Le,
+            Condition::Ge => Condition::Lt,
+            Condition::Lt => Condition::Ge,
+            Condition::Le => Condition::Gt,
+        }
+    }
+
+    fn holds(self, value: i16) -> bool {
+        match self {
+            Condition::Eq => value == 0,
+            Condition::Ne => value != 0,
+            Condition::Gt => value > 0,
+            Condition::Ge => value >= 0,
+            Condition::Lt => value < 0,
+            Condition::Le => value <= 0,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Condition::Eq => "JEQ",
+            Condition::Ne => "JNE",
+            Condition::Gt => "JGT",
+            Condition::Ge => "JGE",
+            Condition::Lt => "JLT",
+            Condition::Le => "JLE",
+        }
+    }
+}
+
+/// Step is applied to the value held in the D register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// D = D op operand, op is add, sub, and or or and the operand is direct.
+    Binary(Arithmetic, Operand),
+    /// D = op D, op is neg or not.
+    Unary(Arithmetic),
+}
+
+/// Sink is where the value computed in the D register ends up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sink {
+    /// Push D onto the stack.
+    Push,
+    /// Store D in segment[index] without going through the stack.
+    Pop(Segment, u16),
+    /// Replace the top of the stack x with x op D.
+    ApplyTop(Arithmetic),
+    /// Jump to the label if D satisfies the condition.
+    JumpIf(Condition, String),
+    /// Pop x and jump to the label if the comparison of x and D satisfies
+    /// the condition.
+    JumpIfCompare(Condition, String),
+}
+
+/// Op is a unit of code produced by the optimizer for the code writer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// A command translated as is.
+    Command(Command),
+    /// A value computed in the D register from a push and the commands
+    /// following it, with the top of the stack kept in D until the sink.
+    Expression {
+        value: Operand,
+        steps: Vec<Step>,
+        sink: Sink,
+        /// The VM commands the expression replaces, written as a comment.
+        source: Vec<Command>,
+    },
+}
+
+/// optimize rewrites a list of (line number, command) into ops. It folds
+/// constants, fuses push-then-pop into direct memory moves, turns comparisons
+/// followed by if-goto into direct jumps and keeps the top of the stack in
+/// the D register across runs of push and arithmetic commands.
+///
+/// Expressions never span labels, calls or function boundaries, so the
+/// stack is in the same state as unoptimized code at every jump target.
+pub fn optimize(commands: &[(usize, Command)]) -> Vec<(usize, Op)> {
+    let mut ops = Vec::new();
+    let mut position = 0;
+
+    while position < commands.len() {
+        let (line, command) = &commands[position];
+        let expression = match command {
+            Command::Push(segment, index) => Some(expression(
+                Operand::from_push(*segment, *index),
+                &commands[position + 1..],
+            )),
+            _ => None,
+        };
+
+        match expression {
+            Some((op, consumed)) => {
+                let consumed = consumed + 1;
+                let source = commands[position..position + consumed]
+                    .iter()
+                    .map(|(_, command)| command.clone())
+                    .collect();
+                if let Some(op) = finish_expression(op, source) {
+                    ops.push((*line, op));
+                }
+                position += consumed;
+            }
+            None => {
+                ops.push((*line, Op::Command(command.clone())));
+                position += 1;
+            }
+        }
+    }
+
+    ops
+}
+
+/// Partially built expression, before constant folding.
+struct Pending {
+    value: Operand,
+    steps: Vec<Step>,
+    sink: Sink,
+}
+
+/// expression consumes the commands following a push of value that can be
+/// computed in the D register, it returns the expression and how many of
+/// the following commands it replaces.
+fn expression(value: Operand, rest: &[(usize, Command)]) -> (Pending, usize) {
+    let command = |position: usize| rest.get(position).map(|(_, command)| command);
+    let mut steps = Vec::new();
+    let mut position = 0;
+
+    let sink = loop {
+        match command(position) {
+            Some(Command::Push(segment, index)) => {
+                let operand = Operand::from_push(*segment, *index);
+                if !operand.is_direct() {
+                    break Sink::Push;
+                }
+
+                match command(position + 1) {
+                    Some(Command::Arithmetic(
+                        op @ (Arithmetic::Add | Arithmetic::Sub | Arithmetic::And | Arithmetic::Or),
+                    )) => {
+                        steps.push(Step::Binary(*op, operand));
+                        position += 2;
+                    }
+                    Some(Command::Arithmetic(op)) => match compare_jump(*op, &rest[position + 2..])
+                    {
+                        // x; push y; cmp; if-goto -> D = x - y and jump on D,
+                        // unless x - y may overflow and flip the order.
+                        Some((condition, label, consumed))
+                            if matches!(condition, Condition::Eq | Condition::Ne)
+                                || operand == Operand::Constant(0) =>
+                        {
+                            steps.push(Step::Binary(Arithmetic::Sub, operand));
+                            position += 2 + consumed;
+                            break Sink::JumpIf(condition, label);
+                        }
+                        // Comparisons of constants are only kept to be folded.
+                        _ if Condition::from_compare(*op).is_some()
+                            && is_constant(&value, &steps)
+                            && operand.is_constant() =>
+                        {
+                            steps.push(Step::Binary(*op, operand));
+                            position += 2;
+                        }
+                        _ => break Sink::Push,
+                    },
+                    _ => break Sink::Push,
+                }
+            }
+            Some(Command::Arithmetic(op @ (Arithmetic::Neg | Arithmetic::Not))) => {
+                steps.push(Step::Unary(*op));
+                position += 1;
+            }
+            Some(Command::Arithmetic(
+                op @ (Arithmetic::Add | Arithmetic::Sub | Arithmetic::And | Arithmetic::Or),
+            )) => {
+                position += 1;
+                break Sink::ApplyTop(*op);
+            }
+            Some(Command::Arithmetic(op)) => match compare_jump(*op, &rest[position + 1..]) {
+                Some((condition, label, consumed)) => {
+                    position += 1 + consumed;
+                    break Sink::JumpIfCompare(condition, label);
+                }
+                None => break Sink::Push,
+            },
+            Some(Command::Pop(segment, index)) => {
+                position += 1;
+                break Sink::Pop(*segment, *index);
+            }
+            Some(Command::IfGoto(label)) => {
+                position += 1;
+                break Sink::JumpIf(Condition::Ne, label.clone());
+            }
+            _ => break Sink::Push,
+        }
+    };
+
+    (Pending { value, steps, sink }, position)
+}
+
+/// is_constant reports if value and steps fold into a single constant.
+fn is_constant(value: &Operand, steps: &[Step]) -> bool {
+    value.is_constant()
+        && steps.iter().all(|step| match step {
+            Step::Binary(_, operand) => operand.is_constant(),
+            Step::Unary(_) => true,
+        })
+}
+
+/// compare_jump matches the `[not] if-goto label` following a comparison,
+/// returning the jump condition, the label and how many commands it used.
+fn compare_jump(op: Arithmetic, rest: &[(usize, Command)]) -> Option<(Condition, String, usize)> {
+    let condition = Condition::from_compare(op)?;
+    match (rest.first(), rest.get(1)) {
+        (Some((_, Command::IfGoto(label))), _) => Some((condition, label.clone(), 1)),
+        (Some((_, Command::Arithmetic(Arithmetic::Not))), Some((_, Command::IfGoto(label)))) => {
+            Some((condition.negate(), label.clone(), 2))
+        }
+        _ => None,
+    }
+}
+
+/// finish_expression folds the constant prefix of the expression and
+/// simplifies it, it returns None when nothing is left to do at runtime.
+fn finish_expression(pending: Pending, source: Vec<Command>) -> Option<Op> {
+    let Pending {
+        mut value,
+        mut steps,
+        sink,
+    } = pending;
+
+    // Fold constant steps into a constant value.
+    while let Operand::Constant(constant) = value {
+        let folded = match steps.first() {
+            Some(Step::Unary(op)) => apply(*op, constant, 0),
+            Some(Step::Binary(op, Operand::Constant(operand))) => apply(*op, constant, *operand),
+            _ => break,
+        };
+        value = Operand::Constant(folded);
+        steps.remove(0);
+    }
+
+    let sink = match (&value, steps.is_empty(), sink) {
+        // A constant condition is either an unconditional jump or no jump at all.
+        (Operand::Constant(constant), true, Sink::JumpIf(condition, label)) => {
+            if !condition.holds(*constant) {
+                return None;
+            }
+            return Some(Op::Command(Command::Goto(label)));
+        }
+        // A lone push is translated as the command itself.
+        (Operand::Memory(segment, index), true, Sink::Push) => {
+            return Some(Op::Command(Command::Push(*segment, *index)));
+        }
+        (Operand::Constant(constant @ 0..), true, Sink::Push) => {
+            return Some(Op::Command(Command::Push(
+                Segment::Constant,
+                *constant as u16,
+            )));
+        }
+        (_, _, sink) => sink,
+    };
+
+    Some(Op::Expression {
+        value,
+        steps,
+        sink,
+        source,
+    })
+}
+
+/// apply evaluates op on constants with the same 16-bit wrapping semantics
+/// as the generated code, comparisons are signed.
+fn apply(op: Arithmetic, x: i16, y: i16) -> i16 {
+    let truth = |value: bool| if value { -1 } else { 0 };
+    match op {
+        Arithmetic::Add => x.wrapping_add(y),
+        Arithmetic::Sub => x.wrapping_sub(y),
+        Arithmetic::Neg => x.wrapping_neg(),
+        Arithmetic::Not => !x,
+        Arithmetic::And => x & y,
+        Arithmetic::Or => x | y,
+        Arithmetic::Eq => truth(x == y),
+        Arithmetic::Gt => truth(x > y),
+        Arithmetic::Lt => truth(x < y),
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+
+/// Segment is one of the eight virtual memory segments a push/pop
+/// command can operate on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Argument,
+    Local,
+    Static,
+    Constant,
+    This,
+    That,
+    Pointer,
+    Temp,
+}
+
+/// Number of words in the static area (RAM 16 - 255) shared by the
+/// static variables of all the files of a program.
+pub const STATIC_AREA_SIZE: usize = 240;
+
+impl Segment {
+    /// max_index is the largest valid index of the segment. Constants and
+    /// base segment offsets are limited by the 15-bit A-instruction value.
+    pub fn max_index(self) -> u16 {
+        match self {
+            Segment::Pointer => 1,
+            Segment::Temp => 7,
+            Segment::Static => (STATIC_AREA_SIZE - 1) as u16,
+            _ => 32767,
+        }
+    }
+
+    fn parse(segment: &str) -> Option<Self> {
+        match segment {
+            "argument" => Some(Segment::Argument),
+            "local" => Some(Segment::Local),
+            "static" => Some(Segment::Static),
+            "constant" => Some(Segment::Constant),
+            "this" => Some(Segment::This),
+            "that" => Some(Segment::That),
+            "pointer" => Some(Segment::Pointer),
+            "temp" => Some(Segment::Temp),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Segment::Argument => "argument",
+            Segment::Local => "local",
+            Segment::Static => "static",
+            Segment::Constant => "constant",
+            Segment::This => "this",
+            Segment::That => "that",
+            Segment::Pointer => "pointer",
+            Segment::Temp => "temp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Arithmetic covers the arithmetic-logical commands, they all operate
+/// on the top one (neg, not) or two (the rest) values of the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arithmetic {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+}
+
+impl Arithmetic {
+    fn parse(command: &str) -> Option<Self> {
+        match command {
+            "add" => Some(Arithmetic::Add),
+            "sub" => Some(Arithmetic::Sub),
+            "neg" => Some(Arithmetic::Neg),
+            "eq" => Some(Arithmetic::Eq),
+            "gt" => Some(Arithmetic::Gt),
+            "lt" => Some(Arithmetic::Lt),
+            "and" => Some(Arithmetic::And),
+            "or" => Some(Arithmetic::Or),
+            "not" => Some(Arithmetic::Not),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Arithmetic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Arithmetic::Add => "add",
+            Arithmetic::Sub => "sub",
+            Arithmetic::Neg => "neg",
+            Arithmetic::Eq => "eq",
+            Arithmetic::Gt => "gt",
+            Arithmetic::Lt => "lt",
+            Arithmetic::And => "and",
+            Arithmetic::Or => "or",
+            Arithmetic::Not => "not",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Command is a single parsed line of VM code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Arithmetic(Arithmetic),
+    Push(Segment, u16),
+    Pop(Segment, u16),
+    Label(String),
+    Goto(String),
+    IfGoto(String),
+    Function(String, u16),
+    Call(String, u16),
+    Return,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Arithmetic(op) => write!(f, "{}", op),
+            Command::Push(segment, index) => write!(f, "push {} {}", segment, index),
+            Command::Pop(segment, index) => write!(f, "pop {} {}", segment, index),
+            Command::Label(label) => write!(f, "label {}", label),
+            Command::Goto(label) => write!(f, "goto {}", label),
+            Command::IfGoto(label) => write!(f, "if-goto {}", label),
+            Command::Function(name, locals) => write!(f, "function {} {}", name, locals),
+            Command::Call(name, args) => write!(f, "call {} {}", name, args),
+            Command::Return => write!(f, "return"),
+        }
+    }
+}
+
+/// parse_line breaks a line of VM code into a Command, it returns Ok(None)
+/// for empty lines and comments. In-line comments "//" are ignored.
+pub fn parse_line(line: &str) -> Result<Option<Command>, String> {
+    let code = match line.split_once("//") {
+        Some((code, _)) => code,
+        None => line,
+    };
+    let parts: Vec<&str> = code.split_whitespace().collect();
+    if parts.is_empty() {
+        // Skip empty lines or ignore comments.
+        return Ok(None);
+    }
+
+    let command = parts[0];
+    let arg1 = parts.get(1).cloned();
+    let arg2 = parts.get(2).cloned();
+
+    let parsed = match command {
+        "push" | "pop" => {
+            // [push/pop segment index] where segment is argument, local, static,
+            // constant, this, that, pointer, or temp and index is a positive integer.
+            let segment_name = arg1.ok_or("Missing segment argument")?;
+            let index = arg2.ok_or("Missing index argument")?;
+            let Some(segment) = Segment::parse(segment_name) else {
+                return Err(format!("Unsupported {} segment: {}", command, segment_name));
+            };
+
+            let index = index
+                .parse::<u16>()
+                .ok()
+                .filter(|index| *index <= segment.max_index())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid {} index: {}, expected a number between 0 - {}",
+                        segment,
+                        index,
+                        segment.max_index()
+                    )
+                })?;
+
+            if command == "push" {
+                Command::Push(segment, index)
+            } else if segment == Segment::Constant {
+                // The constant segment is read-only, it doesn't represent a writable memory location.
+                return Err(String::from("Unexpected pop operation: constant"));
+            } else {
+                Command::Pop(segment, index)
+            }
+        }
+        "label" | "goto" | "if-goto" => {
+            let label = arg1.ok_or("Missing label argument")?.to_string();
+            match command {
+                "label" => Command::Label(label),
+                "goto" => Command::Goto(label),
+                _ => Command::IfGoto(label),
+            }
+        }
+        "function" | "call" => {
+            let name = arg1.ok_or("Missing function name argument")?.to_string();
+            let count = arg2
+                .ok_or("Missing count argument")?
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid count argument: {}", arg2.unwrap_or_default()))?;
+            if command == "function" {
+                Command::Function(name, count)
+            } else {
+                Command::Call(name, count)
+            }
+        }
+        "return" => Command::Return,
+        _ => match Arithmetic::parse(command) {
+            Some(op) => Command::Arithmetic(op),
+            None => return Err(format!("Unsupported VM command: {}", command)),
+        },
+    };
+
+    Ok(Some(parsed))
+}
@@ -0,0 +1,170 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Location of a VM command in the translated program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// Index of the VM file in the list of translated files.
+    pub file: usize,
+    /// 1-based line number in the VM file.
+    pub line: usize,
+    /// The function the command belongs to, empty outside of functions.
+    pub function: String,
+}
+
+/// Mapping of a single generated assembly line back to its VM source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    /// 1-based line number in the generated assembly code.
+    pub asm_line: usize,
+    /// ROM address of the instruction on the line, None for labels and comments.
+    pub rom: Option<usize>,
+    /// None for code that has no VM source (bootstrap, end loop, shared routines).
+    pub location: Option<Location>,
+}
+
+/// SourceTracker wraps the output of the code writer and records, for every
+/// line written, the ROM address it assembles to and the current VM location.
+pub struct SourceTracker<W: Write> {
+    inner: W,
+    // The line being written, until its newline is seen.
+    line: Vec<u8>,
+    asm_line: usize,
+    rom: usize,
+    location: Option<Location>,
+    mappings: Vec<Mapping>,
+}
+
+impl<W: Write> SourceTracker<W> {
+    pub fn new(inner: W) -> Self {
+        SourceTracker {
+            inner,
+            line: Vec::new(),
+            asm_line: 0,
+            rom: 0,
+            location: None,
+            mappings: Vec::new(),
+        }
+    }
+
+    /// set_location sets the VM location of the lines written next.
+    pub fn set_location(&mut self, location: Option<Location>) {
+        self.location = location;
+    }
+
+    pub fn into_mappings(self) -> Vec<Mapping> {
+        self.mappings
+    }
+
+    fn end_line(&mut self) {
+        self.asm_line += 1;
+        let code = String::from_utf8_lossy(&self.line);
+        let code = code.trim();
+
+        // Labels and comments do not take space in the ROM.
+        let rom = if code.is_empty() || code.starts_with("//") || code.starts_with('(') {
+            None
+        } else {
+            self.rom += 1;
+            Some(self.rom - 1)
+        };
+
+        self.mappings.push(Mapping {
+            asm_line: self.asm_line,
+            rom,
+            location: self.location.clone(),
+        });
+        self.line.clear();
+    }
+}
+
+impl<W: Write> Write for SourceTracker<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        for byte in &buf[..written] {
+            if *byte == b'\n' {
+                self.end_line();
+            } else {
+                self.line.push(*byte);
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// write_json writes the source map as JSON, mapping every line of the
+/// generated assembly code back to the VM file, line and function.
+pub fn write_json<W: Write>(
+    mut out: W,
+    asm_file: &str,
+    vm_files: &[PathBuf],
+    mappings: &[Mapping],
+) -> io::Result<()> {
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"version\": 1,")?;
+    writeln!(out, "  \"file\": {},", json_string(asm_file))?;
+
+    let files: Vec<String> = vm_files
+        .iter()
+        .map(|path| json_string(&path.to_string_lossy()))
+        .collect();
+    writeln!(out, "  \"sources\": [{}],", files.join(", "))?;
+
+    writeln!(out, "  \"mappings\": [")?;
+    for (position, mapping) in mappings.iter().enumerate() {
+        let rom = match mapping.rom {
+            Some(rom) => rom.to_string(),
+            None => String::from("null"),
+        };
+        let (source, line, function) = match &mapping.location {
+            Some(location) => (
+                location.file.to_string(),
+                location.line.to_string(),
+                if location.function.is_empty() {
+                    String::from("null")
+                } else {
+                    json_string(&location.function)
+                },
+            ),
+            None => (
+                String::from("null"),
+                String::from("null"),
+                String::from("null"),
+            ),
+        };
+        let separator = if position + 1 == mappings.len() {
+            ""
+        } else {
+            ","
+        };
+        writeln!(
+            out,
+            "    {{\"asm_line\": {}, \"rom\": {}, \"source\": {}, \"line\": {}, \"function\": {}}}{}",
+            mapping.asm_line, rom, source, line, function, separator
+        )?;
+    }
+    writeln!(out, "  ]")?;
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
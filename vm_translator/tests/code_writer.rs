@@ -0,0 +1,42 @@
+//! The code of the comparisons on the CPU, x - y overflows when the signs
+//! of x and y differ.
+mod common;
+
+use common::{run, MODES};
+
+#[test]
+fn compares_constants_of_different_signs() {
+    let cases = [
+        ("push constant 32767\npush constant 3000\nneg\nlt\n", 0),
+        ("push constant 32767\npush constant 3000\nneg\ngt\n", -1),
+        ("push constant 3000\nneg\npush constant 32767\nlt\n", -1),
+        ("push constant 3000\nneg\npush constant 32767\ngt\n", 0),
+        ("push constant 32767\npush constant 3000\nneg\neq\n", 0),
+    ];
+    for (code, expected) in cases {
+        for options in &MODES {
+            let ram = run(code, &[], options);
+            assert_eq!((ram[0], ram[256]), (257, expected), "{}{:?}", code, options);
+        }
+    }
+}
+
+#[test]
+fn compares_values_of_different_signs() {
+    // Values from memory are not folded by the optimizer.
+    let code = "push local 0\npush local 1\nlt\npush local 0\npush local 1\ngt\n";
+    for (x, y) in [(32767, -3000), (-3000, 32767), (-32768, 1), (1, -32768)] {
+        for options in &MODES {
+            let ram = run(code, &[(1, 300), (300, x), (301, y)], options);
+            let expected = (258, -((x < y) as i16), -((x > y) as i16));
+            assert_eq!(
+                (ram[0], ram[256], ram[257]),
+                expected,
+                "{} {} {:?}",
+                x,
+                y,
+                options
+            );
+        }
+    }
+}
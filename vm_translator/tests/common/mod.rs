@@ -0,0 +1,60 @@
+//! Runs translated VM code on the CPU emulator.
+#![allow(dead_code)]
+use std::path::PathBuf;
+
+use hack_assembler::assembler::Assembler;
+use hack_cpu::{Cpu, Status};
+use vm_translator::parser::{parse_line, Command};
+use vm_translator::{translate, Options, VmFile};
+
+/// The translator options the code is run with.
+pub const MODES: [Options; 4] = [
+    mode(false, false),
+    mode(false, true),
+    mode(true, false),
+    mode(true, true),
+];
+
+const fn mode(compact: bool, optimize: bool) -> Options {
+    Options {
+        compact,
+        optimize,
+        bootstrap: false,
+        comments: false,
+    }
+}
+
+/// commands parses VM code, one command per line.
+pub fn commands(code: &str) -> Vec<(usize, Command)> {
+    code.lines()
+        .enumerate()
+        .filter_map(|(line, text)| parse_line(text).unwrap().map(|command| (line + 1, command)))
+        .collect()
+}
+
+/// run translates the VM code of a Test.vm file, runs it on the CPU with
+/// the stack at 256 and the words of ram set, and returns the RAM once
+/// it halts.
+pub fn run(code: &str, ram: &[(usize, i16)], options: &Options) -> Vec<i16> {
+    let file = VmFile {
+        path: PathBuf::from("Test.vm"),
+        name: String::from("Test"),
+        commands: commands(code),
+    };
+    let mut asm = Vec::new();
+    translate(&[file], &mut asm, options).unwrap();
+
+    let mut assembler = Assembler::new(PathBuf::from("Test.asm"));
+    assembler.initialize();
+    let program = assembler
+        .assemble(&String::from_utf8(asm).unwrap())
+        .unwrap();
+
+    let mut cpu = Cpu::new(&program).unwrap();
+    cpu.poke(0, 256);
+    for (address, value) in ram {
+        cpu.poke(*address, *value as u16);
+    }
+    assert_eq!(cpu.run(100_000).1, Status::Halted, "{:?}", options);
+    cpu.ram().iter().map(|word| *word as i16).collect()
+}
@@ -0,0 +1,59 @@
+//! The errors of the binary, the errors of a VM file name its line and the
+//! errors of the output don't.
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// translate writes a VM file to its own directory and translates it.
+fn translate(name: &str, vm: &str, args: &[&str]) -> Output {
+    let dir: PathBuf = std::env::temp_dir().join(format!(
+        "vm_translator-errors-{}-{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.vm", name));
+    fs::write(&path, vm).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_vm_translator"))
+        .arg(&path)
+        .args(args)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(!output.status.success());
+    output
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+#[test]
+fn reports_the_line_of_invalid_commands() {
+    let output = translate("Pointer", "push constant 1\npop pointer 5\n", &[]);
+    assert!(stderr(&output)
+        .ends_with("Pointer.vm:2: Invalid pointer index: 5, expected a number between 0 - 1\n"));
+}
+
+#[test]
+fn reports_output_errors_without_a_line() {
+    let vm = "push constant 7\npush constant 8\nadd\n";
+    let output = translate("Full", vm, &["-o", "/dev/full"]);
+    assert_eq!(
+        stderr(&output),
+        "Unable to write the output: No space left on device (os error 28)\n"
+    );
+
+    let output = translate("Missing", vm, &["-o", "/nowhere/Missing.asm"]);
+    assert_eq!(
+        stderr(&output),
+        "Unable to create output file /nowhere/Missing.asm: No such file or directory (os error 2)\n"
+    );
+
+    let output = translate("Map", vm, &["-o", "-", "--source-map", "/dev/full"]);
+    assert_eq!(
+        stderr(&output),
+        "Unable to write source map /dev/full: No space left on device (os error 28)\n"
+    );
+}
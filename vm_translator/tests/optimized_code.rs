@@ -0,0 +1,112 @@
+//! The code of the optimizer, written by the vm_translator binary.
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// vm_translator writes a program to a temporary .vm file and translates
+/// it with the arguments.
+fn vm_translator(name: &str, code: &str, args: &[&str]) -> Output {
+    let path =
+        std::env::temp_dir().join(format!("vm_translator-{}-{}.vm", name, std::process::id()));
+    fs::write(&path, code).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_vm_translator"))
+        .arg(&path)
+        .args(args)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn optimized(name: &str, code: &str) -> String {
+    let output = vm_translator(name, code, &["--optimize", "-o", "-"]);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn folds_constants_and_fuses_push_and_pop() {
+    let code = "\
+push constant 7
+push constant 8
+add
+pop temp 0
+push local 0
+pop that 1
+";
+    let expected = "\
+// push constant 7 / push constant 8 / add / pop temp 0
+\t@15
+\tD=A
+\t@5
+\tM=D
+// push local 0 / pop that 1
+\t@LCL
+\tA=M
+\tD=M
+\t@THAT
+\tA=M+1
+\tM=D
+(INFINITE_LOOP)
+\t@INFINITE_LOOP
+\t0;JMP
+";
+    assert_eq!(optimized("fold", code), expected);
+}
+
+#[test]
+fn jumps_on_comparisons_with_zero() {
+    let code = "\
+push local 0
+push constant 0
+eq
+if-goto ZERO
+push constant 1
+pop temp 0
+label ZERO
+";
+    let expected = "\
+// push local 0 / push constant 0 / eq / if-goto ZERO
+\t@LCL
+\tA=M
+\tD=M
+\t@0
+\tD=D-A
+\t@ZERO
+\tD;JEQ
+// push constant 1 / pop temp 0
+\tD=1
+\t@5
+\tM=D
+// label ZERO
+(ZERO)
+(INFINITE_LOOP)
+\t@INFINITE_LOOP
+\t0;JMP
+";
+    assert_eq!(optimized("jump", code), expected);
+}
+
+#[test]
+fn counts_the_instructions_saved() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../projects/07/StackArithmetic/StackTest/StackTest.vm");
+    let code = fs::read_to_string(path).unwrap();
+    let output = vm_translator("stats", &code, &["-o", "-", "--stats"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    let counts: Vec<usize> = stderr
+        .strip_prefix("Instructions: ")
+        .unwrap()
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|word| !word.is_empty())
+        .take(2)
+        .map(|count| count.parse().unwrap())
+        .collect();
+    assert!(counts[1] < counts[0], "{}", stderr);
+    assert!(stderr.ends_with("% smaller)\n"), "{}", stderr);
+}
@@ -0,0 +1,260 @@
+//! The optimizer on small programs, and the code of its ops on the CPU.
+mod common;
+
+use common::{commands, run, MODES};
+use vm_translator::optimizer::{optimize, Condition, Op, Operand, Sink, Step};
+use vm_translator::parser::{Arithmetic, Command, Segment};
+
+fn optimized(code: &str) -> Vec<Op> {
+    optimize(&commands(code))
+        .into_iter()
+        .map(|(_, op)| op)
+        .collect()
+}
+
+fn expression(value: Operand, steps: Vec<Step>, sink: Sink, code: &str) -> Op {
+    Op::Expression {
+        value,
+        steps,
+        sink,
+        source: commands(code)
+            .into_iter()
+            .map(|(_, command)| command)
+            .collect(),
+    }
+}
+
+#[test]
+fn folds_constants() {
+    let code = "\
+push constant 32767
+push constant 1
+add
+pop temp 0
+push constant 0
+push constant 32767
+sub
+push constant 1
+sub
+push constant 5
+push constant 3
+and
+not
+pop temp 2
+";
+    let wrapped = "push constant 32767\npush constant 1\nadd\npop temp 0";
+    let lowest = "push constant 0\npush constant 32767\nsub\npush constant 1\nsub";
+    let and = "push constant 5\npush constant 3\nand\nnot\npop temp 2";
+    assert_eq!(
+        optimized(code),
+        [
+            expression(
+                Operand::Constant(-32768),
+                vec![],
+                Sink::Pop(Segment::Temp, 0),
+                wrapped
+            ),
+            expression(Operand::Constant(-32768), vec![], Sink::Push, lowest),
+            expression(
+                Operand::Constant(-2),
+                vec![],
+                Sink::Pop(Segment::Temp, 2),
+                and
+            ),
+        ]
+    );
+
+    for options in &MODES {
+        let ram = run(code, &[], options);
+        assert_eq!(
+            (ram[5], ram[256], ram[7]),
+            (-32768, -32768, -2),
+            "{:?}",
+            options
+        );
+    }
+}
+
+#[test]
+fn folds_constant_jumps() {
+    let code = "\
+push constant 1
+push constant 2
+lt
+if-goto TAKEN
+push constant 2
+push constant 1
+lt
+if-goto NEVER
+label TAKEN
+label NEVER
+";
+    assert_eq!(
+        optimized(code),
+        [
+            Op::Command(Command::Goto(String::from("TAKEN"))),
+            Op::Command(Command::Label(String::from("TAKEN"))),
+            Op::Command(Command::Label(String::from("NEVER"))),
+        ]
+    );
+}
+
+#[test]
+fn fuses_push_and_pop() {
+    let code = "\
+push local 1
+pop temp 3
+push argument 4
+pop that 5
+push static 0
+pop local 0
+";
+    assert_eq!(
+        optimized(code),
+        [
+            expression(
+                Operand::Memory(Segment::Local, 1),
+                vec![],
+                Sink::Pop(Segment::Temp, 3),
+                "push local 1\npop temp 3"
+            ),
+            expression(
+                Operand::Memory(Segment::Argument, 4),
+                vec![],
+                Sink::Pop(Segment::That, 5),
+                "push argument 4\npop that 5"
+            ),
+            expression(
+                Operand::Memory(Segment::Static, 0),
+                vec![],
+                Sink::Pop(Segment::Local, 0),
+                "push static 0\npop local 0"
+            ),
+        ]
+    );
+
+    // LCL 300, ARG 400 and THAT 500.
+    let ram = [(1, 300), (2, 400), (4, 500), (301, 11), (404, 22), (16, 33)];
+    for options in &MODES {
+        let ram = run(code, &ram, options);
+        assert_eq!((ram[8], ram[505], ram[300]), (11, 22, 33), "{:?}", options);
+        assert_eq!(ram[0], 256, "{:?}", options);
+    }
+}
+
+#[test]
+fn jumps_on_comparisons_with_zero() {
+    let code = "\
+push local 0
+push constant 0
+eq
+if-goto ZERO
+push local 0
+push local 5
+add
+push constant 0
+eq
+if-goto SUM
+push constant 1
+pop temp 0
+goto END
+label ZERO
+push constant 2
+pop temp 0
+goto END
+label SUM
+push constant 3
+pop temp 0
+label END
+";
+    let ops = optimized(code);
+    assert_eq!(
+        ops[0],
+        expression(
+            Operand::Memory(Segment::Local, 0),
+            vec![Step::Binary(Arithmetic::Sub, Operand::Constant(0))],
+            Sink::JumpIf(Condition::Eq, String::from("ZERO")),
+            "push local 0\npush constant 0\neq\nif-goto ZERO"
+        )
+    );
+    // The sum stays on the stack, it is compared with 0 after it is popped.
+    assert_eq!(
+        ops[3],
+        expression(
+            Operand::Constant(0),
+            vec![],
+            Sink::JumpIfCompare(Condition::Eq, String::from("SUM")),
+            "push constant 0\neq\nif-goto SUM"
+        )
+    );
+
+    for (local, other, expected) in [(0, 0, 2), (7, -7, 3), (7, 7, 1), (-32768, 0, 1)] {
+        let ram = [(1, 300), (300, local), (305, other)];
+        for options in &MODES {
+            let ram = run(code, &ram, options);
+            assert_eq!(ram[5], expected, "{} {} {:?}", local, other, options);
+            assert_eq!(ram[0], 256, "{:?}", options);
+        }
+    }
+}
+
+#[test]
+fn compares_operands_of_different_signs() {
+    // Both operands are read from memory so that nothing is folded, x - y
+    // overflows for most of them.
+    for op in ["lt", "gt"] {
+        let code = format!(
+            "\
+push local 3
+push local 4
+{op}
+pop temp 1
+push local 3
+push local 4
+{op}
+if-goto TRUE
+push constant 0
+pop temp 0
+goto END
+label TRUE
+push constant 1
+pop temp 0
+label END
+"
+        );
+        assert!(optimized(&code).contains(&expression(
+            Operand::Memory(Segment::Local, 4),
+            vec![],
+            Sink::JumpIfCompare(
+                if op == "lt" {
+                    Condition::Lt
+                } else {
+                    Condition::Gt
+                },
+                String::from("TRUE")
+            ),
+            &format!("push local 4\n{op}\nif-goto TRUE")
+        )));
+
+        let pairs = [
+            (32767, -3000),
+            (-3000, 32767),
+            (32767, -32768),
+            (-32768, 32767),
+            (-32768, 1),
+            (1, -32768),
+            (-1, 0),
+            (5, 5),
+        ];
+        for (x, y) in pairs {
+            let expected = if op == "lt" { x < y } else { x > y };
+            let ram = [(1, 300), (303, x), (304, y)];
+            for options in &MODES {
+                let ram = run(&code, &ram, options);
+                let message = format!("{} {} {} {:?}", x, op, y, options);
+                assert_eq!(ram[6], -(expected as i16), "{}", message);
+                assert_eq!(ram[5], expected as i16, "{}", message);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! The source map of projects/08/FunctionCalls/SimpleFunction, with and
+//! without the comments.
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde_json::Value;
+
+/// translate translates a copy of SimpleFunction.vm with a source map and
+/// returns the lines of the assembly code and the source map.
+fn translate(name: &str, args: &[&str]) -> (Vec<String>, Value) {
+    let dir = std::env::temp_dir().join(format!("vm_translator-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let vm = dir.join("SimpleFunction.vm");
+    fs::copy(
+        "../projects/08/FunctionCalls/SimpleFunction/SimpleFunction.vm",
+        &vm,
+    )
+    .unwrap();
+
+    let map = dir.join("SimpleFunction.json");
+    let status = Command::new(env!("CARGO_BIN_EXE_vm_translator"))
+        .arg(&vm)
+        .args(args)
+        .arg("--source-map")
+        .arg(&map)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let asm = fs::read_to_string(dir.join("SimpleFunction.asm")).unwrap();
+    let map = serde_json::from_str(&fs::read_to_string(&map).unwrap()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    (asm.lines().map(String::from).collect(), map)
+}
+
+/// lines gives the asm lines mapped to a line of the .vm file.
+fn lines(map: &Value, line: u64) -> Vec<u64> {
+    map["mappings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|mapping| mapping["line"] == line)
+        .map(|mapping| mapping["asm_line"].as_u64().unwrap())
+        .collect()
+}
+
+#[test]
+fn maps_asm_lines_to_vm_lines() {
+    let (asm, map) = translate("comments", &[]);
+    assert_eq!(map["version"], 1);
+    let file = PathBuf::from(map["file"].as_str().unwrap());
+    assert!(file.ends_with("SimpleFunction.asm"));
+    let sources = map["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    assert!(sources[0].as_str().unwrap().ends_with("SimpleFunction.vm"));
+
+    // Every asm line is mapped once, in order, and the ROM addresses count
+    // the instructions only.
+    let mappings = map["mappings"].as_array().unwrap();
+    assert_eq!(mappings.len(), asm.len());
+    let mut rom = 0;
+    for (index, (mapping, code)) in mappings.iter().zip(&asm).enumerate() {
+        assert_eq!(mapping["asm_line"], index + 1);
+        if code.starts_with("//") || code.starts_with('(') {
+            assert!(mapping["rom"].is_null(), "{}", code);
+        } else {
+            assert_eq!(mapping["rom"], rom, "{}", code);
+            rom += 1;
+        }
+        // The end loop has no VM source.
+        if index < asm.len() - 3 {
+            assert_eq!(mapping["source"], 0);
+            assert_eq!(mapping["function"], "SimpleFunction.test");
+        } else {
+            assert!(mapping["source"].is_null() && mapping["line"].is_null());
+        }
+    }
+    assert_eq!(asm[asm.len() - 3], "(INFINITE_LOOP)");
+
+    // The code of each command starts with the command as a comment.
+    let vm = fs::read_to_string("../projects/08/FunctionCalls/SimpleFunction/SimpleFunction.vm")
+        .unwrap();
+    for (line, command) in vm.lines().enumerate().skip(6) {
+        let lines = lines(&map, line as u64 + 1);
+        assert_eq!(asm[lines[0] as usize - 1], format!("// {}", command));
+        // The lines of a command follow each other.
+        assert_eq!(
+            lines,
+            (lines[0]..lines[0] + lines.len() as u64).collect::<Vec<_>>()
+        );
+    }
+    assert_eq!(lines(&map, 7)[..3], [1, 2, 3]);
+    assert_eq!(asm[1], "(SimpleFunction.test)");
+    assert_eq!(mappings[2]["rom"], 0);
+}
+
+#[test]
+fn maps_the_code_without_comments() {
+    let (with_comments, map_with_comments) = translate("with", &[]);
+    let (asm, map) = translate("without", &["--no-comments"]);
+    assert!(asm.iter().all(|line| !line.trim_start().starts_with("//")));
+
+    // Only the comments are gone, every instruction keeps its VM line and
+    // its ROM address.
+    let instructions = |asm: &[String], map: &Value| -> Vec<(String, Value, Value)> {
+        asm.iter()
+            .zip(map["mappings"].as_array().unwrap())
+            .filter(|(code, _)| !code.starts_with("//"))
+            .map(|(code, mapping)| {
+                (
+                    code.clone(),
+                    mapping["line"].clone(),
+                    mapping["rom"].clone(),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(
+        instructions(&asm, &map),
+        instructions(&with_comments, &map_with_comments)
+    );
+    assert_eq!(lines(&map, 8)[0], lines(&map, 7).last().unwrap() + 1);
+}